@@ -0,0 +1,95 @@
+use crate::app::time::{local_date, local_day_start_ms, week_start};
+use crate::parser::models::{ActivityWeek, BossActivity};
+use chrono::{Duration, Local};
+use rusqlite::{params, Connection};
+
+pub const MAX_ACTIVITY_WEEKS: i32 = 52;
+
+// one bucket per week (monday to sunday, local time) ending with the current
+// week, weeks without encounters are kept empty so the chart has no gaps
+pub fn character_activity(
+    conn: &Connection,
+    name: &str,
+    weeks: i32,
+) -> Result<Vec<ActivityWeek>, rusqlite::Error> {
+    let weeks = weeks.clamp(1, MAX_ACTIVITY_WEEKS) as i64;
+    let current_week = week_start(local_date(Local::now().timestamp_millis()));
+    let first_week = current_week - Duration::weeks(weeks - 1);
+
+    let mut buckets: Vec<ActivityWeek> = (0..weeks)
+        .map(|week| ActivityWeek {
+            week_start: (first_week + Duration::weeks(week)).to_string(),
+            ..Default::default()
+        })
+        .collect();
+    // running dps totals, turned into averages at the end
+    let mut dps_totals = vec![0_i64; buckets.len()];
+
+    // entity_index can repeat a name in one encounter, count the encounter once
+    let mut stmt = conn.prepare_cached(
+        "
+    SELECT ep.fight_start, ep.current_boss, ep.difficulty, ep.duration, ep.cleared, MAX(en.dps)
+    FROM entity en
+    JOIN encounter_preview ep ON ep.id = en.encounter_id
+    WHERE en.name = ? AND en.entity_type = 'PLAYER' AND ep.fight_start >= ?
+    GROUP BY ep.id
+    ",
+    )?;
+    let rows = stmt.query_map(params![name, local_day_start_ms(first_week)], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            row.get::<_, i64>(3)?,
+            row.get::<_, Option<bool>>(4)?.unwrap_or_default(),
+            row.get::<_, Option<i64>>(5)?.unwrap_or_default(),
+        ))
+    })?;
+
+    for row in rows {
+        let (fight_start, boss, difficulty, duration, cleared, dps) = row?;
+        let week = (week_start(local_date(fight_start)) - first_week).num_weeks();
+        let Some(bucket) = usize::try_from(week).ok().and_then(|w| buckets.get_mut(w)) else {
+            continue;
+        };
+
+        bucket.encounters += 1;
+        if cleared {
+            bucket.clears += 1;
+        } else {
+            bucket.wipes += 1;
+        }
+        bucket.combat_time += duration;
+        dps_totals[week as usize] += dps;
+
+        match bucket
+            .raids
+            .iter_mut()
+            .find(|raid| raid.boss == boss && raid.difficulty == difficulty)
+        {
+            Some(raid) => {
+                raid.encounters += 1;
+                raid.clears += cleared as i64;
+            }
+            None => bucket.raids.push(BossActivity {
+                boss,
+                difficulty,
+                encounters: 1,
+                clears: cleared as i64,
+            }),
+        }
+    }
+
+    for (bucket, dps_total) in buckets.iter_mut().zip(dps_totals) {
+        if bucket.encounters > 0 {
+            bucket.average_dps = dps_total / bucket.encounters;
+        }
+        bucket.raids.sort_by(|a, b| {
+            b.encounters
+                .cmp(&a.encounters)
+                .then_with(|| a.boss.cmp(&b.boss))
+        });
+    }
+
+    Ok(buckets)
+}
@@ -0,0 +1,241 @@
+use crate::parser::models::RestoreResult;
+use log::{info, warn};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// bump when the archive layout changes, older layouts must stay readable
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DB_ENTRY: &str = "encounters.db";
+const SETTINGS_ENTRY: &str = "settings.json";
+// file name used by tauri-plugin-window-state in the app config dir
+pub const WINDOW_STATE_FILE: &str = ".window-state";
+
+const DB_FILE: &str = "encounters.db";
+// a restored database waits here until the next start, the open connections
+// would keep writing to the old one
+const STAGED_DB_FILE: &str = "encounters.db.restore";
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+const ZIP_HEADER: &[u8] = b"PK\x03\x04";
+
+// set once config files were restored so the running app doesn't write its
+// own settings and window positions over them before restarting
+static CONFIG_RESTORED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifest {
+    version: u32,
+    app_version: String,
+    created_at: i64,
+    files: Vec<String>,
+}
+
+// config files that go into a backup, and where they are restored to
+pub struct ConfigPaths {
+    pub settings: PathBuf,
+    pub window_state: PathBuf,
+}
+
+pub fn config_restored() -> bool {
+    CONFIG_RESTORED.load(Ordering::Relaxed)
+}
+
+// a plain database copy, or a zip with the database, config files and a
+// manifest when config is given
+pub fn backup(
+    conn: &Connection,
+    target: &Path,
+    config: Option<&ConfigPaths>,
+    app_version: &str,
+) -> Result<(), String> {
+    let snapshot = with_suffix(target, ".snapshot");
+    fs::remove_file(&snapshot).ok();
+    // consistent copy even while the meter keeps saving
+    conn.execute("VACUUM INTO ?", [snapshot.to_string_lossy().to_string()])
+        .map_err(|e| format!("failed to snapshot database: {}", e))?;
+
+    let result = match config {
+        None => fs::rename(&snapshot, target).map_err(|e| e.to_string()),
+        Some(config) => write_archive(target, &snapshot, config, app_version),
+    };
+    fs::remove_file(&snapshot).ok();
+    if result.is_ok() {
+        info!("backed up database to {}", target.display());
+    }
+    result
+}
+
+fn write_archive(
+    target: &Path,
+    snapshot: &Path,
+    config: &ConfigPaths,
+    app_version: &str,
+) -> Result<(), String> {
+    let mut files = vec![(DB_ENTRY, snapshot.to_path_buf())];
+    for (entry, path) in [
+        (SETTINGS_ENTRY, &config.settings),
+        (WINDOW_STATE_FILE, &config.window_state),
+    ] {
+        if path.exists() {
+            files.push((entry, path.clone()));
+        }
+    }
+    let manifest = BackupManifest {
+        version: BACKUP_FORMAT_VERSION,
+        app_version: app_version.to_string(),
+        created_at: chrono::Utc::now().timestamp_millis(),
+        files: files.iter().map(|(entry, _)| entry.to_string()).collect(),
+    };
+
+    let file = File::create(target).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    zip.start_file(MANIFEST_ENTRY, options)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+    for (entry, path) in files {
+        zip.start_file(entry, options).map_err(|e| e.to_string())?;
+        let mut source = File::open(&path).map_err(|e| e.to_string())?;
+        io::copy(&mut source, &mut zip).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// checks the backup and stages its database for the next start. config
+// files are only restored once the user agrees, through restore_config
+pub fn stage_restore(source: &Path, data_dir: &Path) -> Result<RestoreResult, String> {
+    let staged = data_dir.join(STAGED_DB_FILE);
+    let result = if is_archive(source)? {
+        let mut archive = open_archive(source)?;
+        let manifest = read_manifest(&mut archive)?;
+        let mut entry = archive
+            .by_name(DB_ENTRY)
+            .map_err(|_| "backup has no database".to_string())?;
+        let mut file = File::create(&staged).map_err(|e| e.to_string())?;
+        io::copy(&mut entry, &mut file).map_err(|e| e.to_string())?;
+        RestoreResult {
+            restart_required: true,
+            config_available: manifest
+                .files
+                .iter()
+                .any(|file| file == SETTINGS_ENTRY || file == WINDOW_STATE_FILE),
+            backup_version: Some(manifest.version),
+            app_version: Some(manifest.app_version),
+        }
+    } else {
+        fs::copy(source, &staged).map_err(|e| e.to_string())?;
+        RestoreResult {
+            restart_required: true,
+            ..Default::default()
+        }
+    };
+
+    if let Err(e) = check_database(&staged) {
+        fs::remove_file(&staged).ok();
+        return Err(e);
+    }
+    info!("staged database restore from {}", source.display());
+    Ok(result)
+}
+
+pub fn restore_config(source: &Path, config: &ConfigPaths) -> Result<(), String> {
+    let mut archive = open_archive(source)?;
+    let manifest = read_manifest(&mut archive)?;
+    for (entry, path) in [
+        (SETTINGS_ENTRY, &config.settings),
+        (WINDOW_STATE_FILE, &config.window_state),
+    ] {
+        if !manifest.files.iter().any(|file| file == entry) {
+            continue;
+        }
+        let mut contents = archive.by_name(entry).map_err(|e| e.to_string())?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut file = File::create(path).map_err(|e| e.to_string())?;
+        io::copy(&mut contents, &mut file).map_err(|e| e.to_string())?;
+        info!("restored {} from backup", entry);
+    }
+    CONFIG_RESTORED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+// runs before the database is opened. the old wal belongs to the replaced
+// database and would corrupt the restored one
+pub fn apply_staged_restore(data_dir: &Path) {
+    let staged = data_dir.join(STAGED_DB_FILE);
+    if !staged.exists() {
+        return;
+    }
+    let db = data_dir.join(DB_FILE);
+    for suffix in ["-wal", "-shm"] {
+        fs::remove_file(with_suffix(&db, suffix)).ok();
+    }
+    match fs::rename(&staged, &db) {
+        Ok(()) => info!("restored database from backup"),
+        Err(e) => warn!("failed to apply database restore: {}", e),
+    }
+}
+
+fn is_archive(path: &Path) -> Result<bool, String> {
+    let mut header = [0u8; 16];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .map_err(|e| e.to_string())?;
+    let header = &header[..read];
+    if header.starts_with(ZIP_HEADER) {
+        Ok(true)
+    } else if header.starts_with(SQLITE_HEADER) {
+        Ok(false)
+    } else {
+        Err("not a database or backup file".to_string())
+    }
+}
+
+fn open_archive(path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    ZipArchive::new(file).map_err(|e| format!("backup is not readable: {}", e))
+}
+
+fn read_manifest(archive: &mut ZipArchive<File>) -> Result<BackupManifest, String> {
+    let entry = archive
+        .by_name(MANIFEST_ENTRY)
+        .map_err(|_| "backup has no manifest".to_string())?;
+    let manifest: BackupManifest =
+        serde_json::from_reader(entry).map_err(|e| format!("invalid backup manifest: {}", e))?;
+    if manifest.version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "backup was made by a newer version of the app (format {})",
+            manifest.version
+        ));
+    }
+    Ok(manifest)
+}
+
+fn check_database(path: &Path) -> Result<(), String> {
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    let result: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if result != "ok" {
+        return Err(format!("backup database is damaged: {}", result));
+    }
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
@@ -0,0 +1,51 @@
+use crate::parser::models::BossNote;
+use rusqlite::{params, Connection, OptionalExtension};
+
+pub const MAX_NOTE_BYTES: usize = 4096;
+
+pub fn get(conn: &Connection, boss: &str) -> Result<Option<BossNote>, rusqlite::Error> {
+    conn.prepare_cached("SELECT boss, note, updated_at FROM boss_note WHERE boss = ?")?
+        .query_row([boss], |row| {
+            Ok(BossNote {
+                boss: row.get(0)?,
+                note: row.get(1)?,
+                updated_at: row.get(2)?,
+            })
+        })
+        .optional()
+}
+
+// an empty note deletes the row
+pub fn set(conn: &Connection, boss: &str, note: &str) -> Result<(), String> {
+    let note = note.trim();
+    if note.len() > MAX_NOTE_BYTES {
+        return Err(format!(
+            "note is {} bytes, the limit is {}",
+            note.len(),
+            MAX_NOTE_BYTES
+        ));
+    }
+    let result = if note.is_empty() {
+        conn.execute("DELETE FROM boss_note WHERE boss = ?", [boss])
+    } else {
+        conn.execute(
+            "INSERT INTO boss_note (boss, note, updated_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT(boss) DO UPDATE SET note = excluded.note, updated_at = excluded.updated_at",
+            params![boss, note, chrono::Utc::now().timestamp_millis()],
+        )
+    };
+    result.map(|_| ()).map_err(|e| e.to_string())
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<BossNote>, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare_cached("SELECT boss, note, updated_at FROM boss_note ORDER BY boss")?;
+    let notes = stmt.query_map([], |row| {
+        Ok(BossNote {
+            boss: row.get(0)?,
+            note: row.get(1)?,
+            updated_at: row.get(2)?,
+        })
+    })?;
+    notes.collect()
+}
@@ -0,0 +1,763 @@
+use crate::parser::models::{
+    CleanupEstimate, DeathCause, EncounterPreview, EncountersOverview, PreviewPlayer, SearchFilter,
+};
+use hashbrown::HashMap;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection};
+
+pub const MAX_PAGE_SIZE: i32 = 500;
+// difficulty filter value for encounters saved without a difficulty
+const UNKNOWN_DIFFICULTY: &str = "Unknown";
+// shortest term the trigram tokenizer can match
+const MIN_FTS_TERM_CHARS: usize = 3;
+const DELETE_CHUNK_SIZE: usize = 500;
+// columns the encounter list can be sorted by, anything else sorts by id
+const SORT_COLUMNS: [&str; 5] = ["id", "fight_start", "duration", "my_dps", "my_dps_alive"];
+
+// accepts both the legacy class:name and the current class:name:dps formats
+pub fn parse_preview_players(players: &str) -> Vec<PreviewPlayer> {
+    players
+        .split(',')
+        .map(|s| {
+            let info: Vec<&str> = s.split(':').collect();
+            if info.len() != 2 && info.len() != 3 {
+                return PreviewPlayer {
+                    name: "Unknown".to_string(),
+                    class_id: 101,
+                    dps: 0,
+                };
+            }
+            PreviewPlayer {
+                name: info[1].to_string(),
+                class_id: info[0].parse::<i32>().unwrap_or(101),
+                dps: info
+                    .get(2)
+                    .and_then(|dps| dps.parse::<i64>().ok())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+// each word becomes an fts5 string, quotes inside it are doubled
+fn fts_query(words: &[&str]) -> String {
+    words
+        .iter()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape_like(word: &str) -> String {
+    word.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// arguments of load_encounters_preview
+pub struct PreviewQuery {
+    pub page: i32,
+    pub page_size: i32,
+    pub search: String,
+    pub filter: SearchFilter,
+}
+
+// one page of the encounter list, names are not masked here
+pub fn load_previews(
+    conn: &Connection,
+    query: PreviewQuery,
+) -> Result<EncountersOverview, rusqlite::Error> {
+    let PreviewQuery {
+        page,
+        page_size,
+        search,
+        filter,
+    } = query;
+    // page 0 or a negative size would give a negative offset
+    let page = page.max(1);
+    let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+
+    let mut params: Vec<SqlValue> = vec![];
+
+    // the trigram index can't match terms shorter than 3 characters,
+    // those are matched with LIKE instead
+    let (search_words, short_words): (Vec<&str>, Vec<&str>) = search
+        .split_whitespace()
+        .partition(|word| word.chars().count() >= MIN_FTS_TERM_CHARS);
+
+    let join_clause = if !search_words.is_empty() {
+        params.push(SqlValue::Text(fts_query(&search_words)));
+        "JOIN encounter_search(?) ON encounter_search.rowid = e.id"
+    } else {
+        ""
+    };
+
+    params.push(SqlValue::Integer(filter.min_duration as i64 * 1000));
+
+    let short_search_filter = short_words
+        .iter()
+        .map(|word| {
+            let pattern = format!("%{}%", escape_like(word));
+            params.push(SqlValue::Text(pattern.clone()));
+            params.push(SqlValue::Text(pattern));
+            "AND (e.players LIKE ? ESCAPE '\\' OR e.current_boss LIKE ? ESCAPE '\\')"
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let boss_filter = if !filter.bosses.is_empty() {
+        let mut placeholders = "?,".repeat(filter.bosses.len());
+        placeholders.pop(); // remove trailing comma
+        params.extend(filter.bosses.into_iter().map(SqlValue::Text));
+        format!("AND e.current_boss IN ({})", placeholders)
+    } else {
+        "".to_string()
+    };
+
+    let raid_clear_filter = if filter.cleared {
+        "AND cleared = 1"
+    } else {
+        ""
+    };
+
+    let favorite_filter = if filter.favorite {
+        "AND favorite = 1"
+    } else {
+        ""
+    };
+
+    let boss_only_damage_filter = if filter.boss_only_damage {
+        "AND boss_only_damage = 1"
+    } else {
+        ""
+    };
+
+    let difficulty_filter = if !filter.difficulties.is_empty() {
+        let include_unknown = filter.difficulties.iter().any(|d| d == UNKNOWN_DIFFICULTY);
+        let difficulties = filter
+            .difficulties
+            .into_iter()
+            .filter(|d| d != UNKNOWN_DIFFICULTY)
+            .collect::<Vec<_>>();
+        let mut conditions = vec![];
+        if !difficulties.is_empty() {
+            let mut placeholders = "?,".repeat(difficulties.len());
+            placeholders.pop(); // remove trailing comma
+            params.extend(difficulties.into_iter().map(SqlValue::Text));
+            conditions.push(format!("difficulty IN ({})", placeholders));
+        }
+        if include_unknown {
+            conditions.push("difficulty IS NULL OR difficulty = ''".to_string());
+        }
+        format!("AND ({})", conditions.join(" OR "))
+    } else {
+        "".to_string()
+    };
+
+    let region_filter = if let Some(region) = filter.region.filter(|r| !r.is_empty()) {
+        params.push(SqlValue::Text(region));
+        "AND region = ?"
+    } else {
+        ""
+    };
+
+    let top_rank_filter = if filter.top_rank_only {
+        "AND local_rank = 1"
+    } else {
+        ""
+    };
+
+    let order = if filter.order == 1 { "ASC" } else { "DESC" };
+    // the sort is spliced into the query, so it has to be a known column
+    let sort = SORT_COLUMNS
+        .iter()
+        .find(|column| **column == filter.sort)
+        .map_or("e.id".to_string(), |column| format!("e.{}", column));
+
+    let count_params = params.clone();
+
+    let query = format!(
+        "SELECT
+    e.id,
+    e.fight_start,
+    e.current_boss,
+    e.duration,
+    e.difficulty,
+    e.favorite,
+    e.cleared,
+    e.local_player,
+    e.my_dps,
+    e.players,
+    e.my_dps_alive,
+    e.local_rank,
+    e.party_size,
+    e.dungeon
+    FROM encounter_preview e {}
+    WHERE e.duration > ? {} {}
+    {} {} {} {} {} {}
+    ORDER BY {} {}
+    LIMIT ?
+    OFFSET ?",
+        join_clause,
+        short_search_filter,
+        boss_filter,
+        raid_clear_filter,
+        favorite_filter,
+        difficulty_filter,
+        region_filter,
+        top_rank_filter,
+        boss_only_damage_filter,
+        sort,
+        order
+    );
+
+    let mut stmt = conn.prepare_cached(&query)?;
+
+    let offset = (page as i64 - 1) * page_size as i64;
+
+    params.push(SqlValue::Integer(page_size as i64));
+    params.push(SqlValue::Integer(offset));
+
+    let encounter_iter = stmt.query_map(params_from_iter(params), |row| {
+        let players: String = row.get(9).unwrap_or_default();
+
+        let players_detail = parse_preview_players(&players);
+        let (classes, names) = players_detail
+            .iter()
+            .map(|p| (p.class_id, p.name.clone()))
+            .unzip();
+
+        Ok(EncounterPreview {
+            id: row.get(0)?,
+            fight_start: row.get(1)?,
+            boss_name: row.get(2)?,
+            duration: row.get(3)?,
+            classes,
+            names,
+            players_detail,
+            difficulty: row.get(4)?,
+            favorite: row.get(5)?,
+            cleared: row.get(6)?,
+            local_player: row.get(7)?,
+            my_dps: row.get(8).unwrap_or(0),
+            my_dps_alive: row.get(10).unwrap_or(0),
+            local_rank: row.get(11).unwrap_or_default(),
+            party_size: row.get(12).unwrap_or_default(),
+            dungeon: row.get(13).unwrap_or_default(),
+        })
+    })?;
+    let encounters: Vec<EncounterPreview> = encounter_iter.collect::<Result<_, _>>()?;
+
+    let query = format!(
+        "
+        SELECT COUNT(*)
+        FROM encounter_preview e {}
+        WHERE duration > ? {} {}
+        {} {} {} {} {} {}
+        ",
+        join_clause,
+        short_search_filter,
+        boss_filter,
+        raid_clear_filter,
+        favorite_filter,
+        difficulty_filter,
+        region_filter,
+        top_rank_filter,
+        boss_only_damage_filter
+    );
+
+    let count: i32 =
+        conn.query_row_and_then(&query, params_from_iter(count_params), |row| row.get(0))?;
+
+    Ok(EncountersOverview {
+        encounters,
+        total_encounters: count,
+        page,
+        page_size,
+    })
+}
+
+// sqlite limits a statement to 999 host parameters, so large selections are
+// deleted in chunks. entities and the preview go with the encounter through
+// foreign keys, which every pooled connection has turned on
+pub fn delete_encounters(conn: &mut Connection, ids: &[i64]) -> Result<usize, rusqlite::Error> {
+    let tx = conn.transaction()?;
+    let mut deleted = 0;
+    for chunk in ids.chunks(DELETE_CHUNK_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let sql = format!("DELETE FROM encounter WHERE id IN ({})", placeholders);
+        deleted += tx.prepare_cached(&sql)?.execute(params_from_iter(chunk))?;
+    }
+    tx.commit()?;
+    Ok(deleted)
+}
+
+// counts the encounters a filter on encounter_preview matches, and deletes
+// them unless this is a dry run
+pub fn delete_previews_where(
+    conn: &Connection,
+    filter: &str,
+    params: &[&dyn rusqlite::ToSql],
+    dry_run: bool,
+) -> Result<usize, rusqlite::Error> {
+    if dry_run {
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM encounter_preview WHERE {}", filter),
+            params,
+            |row| row.get(0),
+        )?;
+        return Ok(count as usize);
+    }
+    let deleted = conn.execute(
+        &format!(
+            "DELETE FROM encounter
+            WHERE id IN (
+                SELECT id
+                FROM encounter_preview
+                WHERE {}
+            )",
+            filter
+        ),
+        params,
+    )?;
+    conn.execute("VACUUM", [])?;
+    Ok(deleted)
+}
+
+// where clause on encounter_preview for the cleanup options, a min_duration
+// of 0 and no older_than match every encounter
+pub fn cleanup_filter(
+    min_duration: i64,
+    older_than: Option<i64>,
+    uncleared_only: bool,
+    keep_favorites: bool,
+) -> (String, Vec<i64>) {
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    if min_duration > 0 {
+        conditions.push("duration < ?");
+        params.push(min_duration * 1000);
+    }
+    if let Some(older_than) = older_than {
+        conditions.push("fight_start < ?");
+        params.push(older_than);
+    }
+    if uncleared_only {
+        conditions.push("cleared = 0");
+    }
+    if keep_favorites {
+        conditions.push("favorite = 0");
+    }
+    if conditions.is_empty() {
+        return ("1".to_string(), params);
+    }
+    (conditions.join(" AND "), params)
+}
+
+// adds up the killer skills saved with each wipe on a boss since the given
+// time. encounters counts the wipes a skill killed in, so a skill that wiped
+// the raid once ranks below one that keeps killing a player or two
+pub fn death_causes(
+    conn: &Connection,
+    boss: &str,
+    difficulty: Option<&str>,
+    since: i64,
+) -> Result<Vec<DeathCause>, rusqlite::Error> {
+    let mut sql = "SELECT json_extract(e.misc, '$.topKiller.causes')
+        FROM encounter e JOIN encounter_preview ep ON e.id = ep.id
+        WHERE ep.current_boss = ? AND ep.cleared = 0 AND ep.fight_start >= ?
+            AND json_extract(e.misc, '$.topKiller.causes') IS NOT NULL"
+        .to_string();
+    let mut params = vec![SqlValue::Text(boss.to_string()), SqlValue::Integer(since)];
+    match difficulty {
+        Some(UNKNOWN_DIFFICULTY) => {
+            sql.push_str(" AND (ep.difficulty IS NULL OR ep.difficulty = '')")
+        }
+        Some(difficulty) => {
+            sql.push_str(" AND ep.difficulty = ?");
+            params.push(SqlValue::Text(difficulty.to_string()));
+        }
+        None => {}
+    }
+
+    let mut totals: HashMap<String, (i64, i64)> = HashMap::new();
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(params), |row| row.get::<_, String>(0))?;
+    for causes in rows {
+        let causes: HashMap<String, i64> = serde_json::from_str(&causes?).unwrap_or_default();
+        for (skill, deaths) in causes {
+            let total = totals.entry(skill).or_default();
+            total.0 += deaths;
+            total.1 += 1;
+        }
+    }
+
+    let mut causes: Vec<DeathCause> = totals
+        .into_iter()
+        .map(|(skill, (deaths, encounters))| DeathCause {
+            skill,
+            deaths,
+            encounters,
+        })
+        .collect();
+    causes.sort_by(|a, b| {
+        b.deaths
+            .cmp(&a.deaths)
+            .then_with(|| b.encounters.cmp(&a.encounters))
+            .then_with(|| a.skill.cmp(&b.skill))
+    });
+    Ok(causes)
+}
+
+// sums the stored json of the encounters a filter matches. sqlite only hands
+// freed pages back to the filesystem on VACUUM, so this is how much smaller
+// the file gets once it's compacted, not right after deleting
+pub fn estimate_cleanup(
+    conn: &Connection,
+    filter: &str,
+    params: &[i64],
+    mut on_progress: impl FnMut(&CleanupEstimate),
+) -> Result<CleanupEstimate, rusqlite::Error> {
+    let ids = conn
+        .prepare(&format!(
+            "SELECT id FROM encounter_preview WHERE {}",
+            filter
+        ))?
+        .query_map(params_from_iter(params), |row| row.get(0))?
+        .collect::<Result<Vec<i64>, _>>()?;
+
+    let mut estimate = CleanupEstimate {
+        encounters: ids.len(),
+        ..Default::default()
+    };
+    for chunk in ids.chunks(DELETE_CHUNK_SIZE) {
+        let placeholders = (1..=chunk.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            "SELECT
+                (SELECT IFNULL(SUM(IFNULL(length(buffs), 0) + IFNULL(length(debuffs), 0)
+                    + IFNULL(length(applied_shield_buffs), 0) + IFNULL(length(misc), 0)
+                    + IFNULL(length(boss_hp_log), 0)), 0)
+                FROM encounter WHERE id IN ({0}))
+                + (SELECT IFNULL(SUM(IFNULL(length(skills), 0) + IFNULL(length(damage_stats), 0)
+                    + IFNULL(length(skill_stats), 0) + IFNULL(length(engravings), 0)), 0)
+                FROM entity WHERE encounter_id IN ({0}))",
+            placeholders
+        );
+        let bytes: i64 = conn.query_row(&sql, params_from_iter(chunk), |row| row.get(0))?;
+        estimate.reclaimable_bytes += bytes;
+        estimate.scanned += chunk.len();
+        on_progress(&estimate);
+    }
+    Ok(estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_db;
+
+    fn insert_encounters(conn: &Connection, count: i64) {
+        for id in 1..=count {
+            conn.execute("INSERT INTO encounter (id) VALUES (?)", [id])
+                .unwrap();
+            conn.execute(
+                "INSERT INTO encounter_preview (id, fight_start, current_boss, duration)
+                VALUES (?, ?, 'Boss', 60000)",
+                [id, id * 1000],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO entity (name, encounter_id) VALUES ('Player', ?)",
+                [id],
+            )
+            .unwrap();
+        }
+    }
+
+    fn insert_preview(conn: &Connection, id: i64, boss: &str, players: &str) {
+        conn.execute("INSERT INTO encounter (id) VALUES (?)", [id])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO encounter_preview (id, fight_start, current_boss, duration, players)
+            VALUES (?, ?, ?, 60000, ?)",
+            rusqlite::params![id, id * 1000, boss, players],
+        )
+        .unwrap();
+    }
+
+    fn search(conn: &Connection, search: &str) -> Vec<i64> {
+        let query = PreviewQuery {
+            page: 1,
+            page_size: 10,
+            search: search.to_string(),
+            filter: SearchFilter {
+                sort: "fight_start".to_string(),
+                ..Default::default()
+            },
+        };
+        load_previews(conn, query)
+            .unwrap()
+            .encounters
+            .into_iter()
+            .map(|preview| preview.id)
+            .collect()
+    }
+
+    fn set(conn: &Connection, id: i64, columns: &str) {
+        conn.execute(
+            &format!("UPDATE encounter_preview SET {} WHERE id = ?", columns),
+            [id],
+        )
+        .unwrap();
+    }
+
+    fn query(conn: &Connection, filter: SearchFilter) -> Vec<i64> {
+        let query = PreviewQuery {
+            page: 1,
+            page_size: 10,
+            search: String::new(),
+            filter,
+        };
+        load_previews(conn, query)
+            .unwrap()
+            .encounters
+            .into_iter()
+            .map(|preview| preview.id)
+            .collect()
+    }
+
+    fn sorted_by(sort: &str, order: u8) -> SearchFilter {
+        SearchFilter {
+            sort: sort.to_string(),
+            order,
+            ..Default::default()
+        }
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn delete_encounters_spans_several_chunks() {
+        let mut conn = test_db();
+        insert_encounters(&conn, 1200);
+
+        let ids: Vec<i64> = (1..=1100).collect();
+        assert_eq!(delete_encounters(&mut conn, &ids).unwrap(), 1100);
+
+        assert_eq!(count(&conn, "encounter"), 100);
+        assert_eq!(count(&conn, "encounter_preview"), 100);
+        assert_eq!(count(&conn, "entity"), 100);
+        let lowest: i64 = conn
+            .query_row("SELECT MIN(id) FROM encounter", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(lowest, 1101);
+    }
+
+    #[test]
+    fn delete_encounters_ignores_missing_ids() {
+        let mut conn = test_db();
+        insert_encounters(&conn, 3);
+
+        assert_eq!(delete_encounters(&mut conn, &[2, 40, 41]).unwrap(), 1);
+        assert_eq!(delete_encounters(&mut conn, &[]).unwrap(), 0);
+        assert_eq!(count(&conn, "encounter"), 2);
+    }
+
+    #[test]
+    fn fts_words_are_quoted() {
+        assert_eq!(
+            fts_query(&["Thaemine", "Al\"ice"]),
+            "\"Thaemine\" \"Al\"\"ice\""
+        );
+        assert_eq!(escape_like("5%_a\\"), "5\\%\\_a\\\\");
+    }
+
+    #[test]
+    fn search_matches_short_and_long_words() {
+        let conn = test_db();
+        insert_preview(&conn, 1, "Thaemine", "102:Alice:100,204:Bo:50");
+        insert_preview(&conn, 2, "Echidna", "102:Carol:100");
+
+        assert_eq!(search(&conn, ""), vec![2, 1]);
+        assert_eq!(search(&conn, "Carol"), vec![2]);
+        assert_eq!(search(&conn, "Bo"), vec![1]);
+        assert_eq!(search(&conn, "Ech Ca"), vec![2]);
+        assert_eq!(search(&conn, "Thaemine Carol"), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn search_terms_are_not_syntax() {
+        let conn = test_db();
+        insert_preview(&conn, 1, "Thaemine", "102:Alice:100");
+
+        assert!(search(&conn, "Al\"ice").is_empty());
+        assert!(search(&conn, "\"\" OR").is_empty());
+        assert!(search(&conn, "%").is_empty());
+        assert!(search(&conn, "_").is_empty());
+    }
+
+    #[test]
+    fn unknown_sort_columns_sort_by_id() {
+        let conn = test_db();
+        insert_encounters(&conn, 3);
+        set(&conn, 1, "fight_start = 9000");
+
+        assert_eq!(query(&conn, sorted_by("fight_start", 0)), vec![1, 3, 2]);
+        assert_eq!(
+            query(&conn, sorted_by("id; DROP TABLE encounter", 0)),
+            vec![3, 2, 1]
+        );
+        assert_eq!(query(&conn, sorted_by("players", 1)), vec![1, 2, 3]);
+        assert_eq!(query(&conn, sorted_by("", 0)), vec![3, 2, 1]);
+        assert_eq!(count(&conn, "encounter"), 3);
+    }
+
+    #[test]
+    fn previews_sort_by_dps_and_duration() {
+        let conn = test_db();
+        insert_encounters(&conn, 3);
+        set(&conn, 1, "my_dps = 300, duration = 70000");
+        set(&conn, 2, "my_dps = 100, duration = 90000");
+        set(&conn, 3, "my_dps = 200, duration = 80000");
+
+        assert_eq!(query(&conn, sorted_by("my_dps", 0)), vec![1, 3, 2]);
+        assert_eq!(query(&conn, sorted_by("my_dps", 1)), vec![2, 3, 1]);
+        assert_eq!(query(&conn, sorted_by("duration", 0)), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn previews_are_paged() {
+        let conn = test_db();
+        insert_encounters(&conn, 25);
+
+        let page = |page, page_size| {
+            let query = PreviewQuery {
+                page,
+                page_size,
+                search: String::new(),
+                filter: sorted_by("id", 1),
+            };
+            load_previews(&conn, query).unwrap()
+        };
+
+        let overview = page(3, 10);
+        assert_eq!(overview.total_encounters, 25);
+        assert_eq!(
+            overview.encounters.iter().map(|e| e.id).collect::<Vec<_>>(),
+            (21..=25).collect::<Vec<_>>()
+        );
+        // out of range pages and sizes are clamped
+        let overview = page(0, 0);
+        assert_eq!((overview.page, overview.page_size), (1, 1));
+        assert_eq!(overview.encounters[0].id, 1);
+        assert_eq!(page(1, 10_000).page_size, MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn previews_are_filtered() {
+        let conn = test_db();
+        insert_encounters(&conn, 4);
+        set(&conn, 1, "cleared = 1, difficulty = 'Hard', region = 'EUC'");
+        set(
+            &conn,
+            2,
+            "favorite = 1, difficulty = 'Normal', local_rank = 1",
+        );
+        set(
+            &conn,
+            3,
+            "current_boss = 'Other', difficulty = '', duration = 20000",
+        );
+        set(&conn, 4, "boss_only_damage = 1, local_rank = 2");
+        let filter = |filter: SearchFilter| query(&conn, SearchFilter { order: 1, ..filter });
+
+        assert_eq!(filter(SearchFilter::default()), vec![1, 2, 3, 4]);
+        assert_eq!(
+            filter(SearchFilter {
+                cleared: true,
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                favorite: true,
+                ..Default::default()
+            }),
+            vec![2]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                boss_only_damage: true,
+                ..Default::default()
+            }),
+            vec![4]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                top_rank_only: true,
+                ..Default::default()
+            }),
+            vec![2]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                region: Some("EUC".to_string()),
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                region: Some(String::new()),
+                ..Default::default()
+            }),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                bosses: vec!["Other".to_string()],
+                ..Default::default()
+            }),
+            vec![3]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                min_duration: 30,
+                ..Default::default()
+            }),
+            vec![1, 2, 4]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                difficulties: vec!["Hard".to_string(), "Normal".to_string()],
+                ..Default::default()
+            }),
+            vec![1, 2]
+        );
+        // no difficulty at all or an empty one
+        assert_eq!(
+            filter(SearchFilter {
+                difficulties: vec![UNKNOWN_DIFFICULTY.to_string()],
+                ..Default::default()
+            }),
+            vec![3, 4]
+        );
+        assert_eq!(
+            filter(SearchFilter {
+                difficulties: vec!["Hard".to_string(), UNKNOWN_DIFFICULTY.to_string()],
+                cleared: true,
+                ..Default::default()
+            }),
+            vec![1]
+        );
+    }
+}
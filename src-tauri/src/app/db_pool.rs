@@ -0,0 +1,140 @@
+use rusqlite::{ffi, Connection};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use crate::app::query_stats;
+
+const READER_COUNT: usize = 2;
+
+// set once on every pooled connection instead of per command
+const CONNECTION_PRAGMAS: &str = "
+    PRAGMA foreign_keys = ON;
+    PRAGMA journal_mode = WAL;
+    PRAGMA busy_timeout = 5000;
+    PRAGMA cache_size = -16000;
+";
+
+// long lived connections to encounters.db, kept in tauri managed state.
+// reads are spread over a couple of connections, all writes go through one
+// writer so they never wait on each other's locks. connections are opened
+// on first use so a missing or broken db doesn't stop the app from starting
+pub struct DbPool {
+    path: PathBuf,
+    readers: Vec<Mutex<Option<Connection>>>,
+    writer: Mutex<Option<Connection>>,
+    // schema version of a database made by a newer app, 0 if it's ours
+    newer_version: AtomicI32,
+}
+
+// timed from checkout until it's dropped, queries run meanwhile are
+// attributed to the command that checked it out
+pub struct PooledConnection<'a> {
+    guard: MutexGuard<'a, Option<Connection>>,
+    command: &'static str,
+    checked_out: Instant,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.guard.as_ref().expect("pooled connection is open")
+    }
+}
+
+impl DerefMut for PooledConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.guard.as_mut().expect("pooled connection is open")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        query_stats::set_current_command(None);
+        query_stats::record_command(self.command, self.checked_out.elapsed());
+    }
+}
+
+impl DbPool {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            readers: (0..READER_COUNT).map(|_| Mutex::new(None)).collect(),
+            writer: Mutex::new(None),
+            newer_version: AtomicI32::new(0),
+        }
+    }
+
+    // a newer app may have changed tables in ways this one doesn't know,
+    // so writes are refused and reads are best effort
+    pub fn set_newer_version(&self, version: i32) {
+        self.newer_version.store(version, Ordering::Relaxed);
+    }
+
+    pub fn newer_version(&self) -> Option<i32> {
+        match self.newer_version.load(Ordering::Relaxed) {
+            0 => None,
+            version => Some(version),
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.newer_version().is_some()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // takes the first idle reader, or waits on the first one if all are busy
+    pub fn read(&self, command: &'static str) -> Result<PooledConnection<'_>, rusqlite::Error> {
+        let guard = self
+            .readers
+            .iter()
+            .find_map(|reader| reader.try_lock().ok())
+            .unwrap_or_else(|| {
+                self.readers[0]
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+            });
+        self.checkout(guard, command)
+    }
+
+    pub fn write(&self, command: &'static str) -> Result<PooledConnection<'_>, rusqlite::Error> {
+        if let Some(version) = self.newer_version() {
+            return Err(rusqlite::Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_READONLY),
+                Some(format!(
+                    "database was created by a newer version of the app (schema {})",
+                    version
+                )),
+            ));
+        }
+        // a command that panicked mid query leaves the connection usable,
+        // its transaction was rolled back when it was dropped
+        let guard = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        self.checkout(guard, command)
+    }
+
+    fn checkout<'a>(
+        &self,
+        mut guard: MutexGuard<'a, Option<Connection>>,
+        command: &'static str,
+    ) -> Result<PooledConnection<'a>, rusqlite::Error> {
+        if guard.is_none() {
+            let mut conn = Connection::open(&self.path)?;
+            conn.execute_batch(CONNECTION_PRAGMAS)?;
+            conn.profile(Some(query_stats::record_query));
+            *guard = Some(conn);
+        }
+        query_stats::set_current_command(Some(command));
+        Ok(PooledConnection {
+            guard,
+            command,
+            checked_out: Instant::now(),
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use log::{info, warn};
+use tauri::api::process::Command;
+
+pub const SCHEME: &str = "loameter";
+const SCHEME_KEY: &str = r"HKCU\Software\Classes\loameter";
+
+#[derive(Debug, PartialEq)]
+pub enum DeepLink {
+    Encounter(i64),
+    Search(String),
+}
+
+pub fn encounter_url(id: i64) -> String {
+    format!("{}://encounter/{}", SCHEME, id)
+}
+
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.strip_prefix(SCHEME)
+        .is_some_and(|rest| rest.starts_with("://"))
+}
+
+// loameter://encounter/<id> or loameter://search?boss=<name>
+pub fn parse(url: &str) -> Result<DeepLink, String> {
+    let rest = url
+        .strip_prefix(SCHEME)
+        .and_then(|rest| rest.strip_prefix("://"))
+        .ok_or_else(|| format!("not a {} link: {}", SCHEME, url))?;
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let path = path.trim_end_matches('/');
+
+    if let Some(id) = path.strip_prefix("encounter/") {
+        return id
+            .parse::<i64>()
+            .ok()
+            .filter(|&id| id > 0)
+            .map(DeepLink::Encounter)
+            .ok_or_else(|| format!("invalid encounter id in link: {}", id));
+    }
+    if path == "search" {
+        return query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == "boss")
+            .map(|(_, value)| percent_decode(value))
+            .filter(|boss| !boss.trim().is_empty())
+            .map(DeepLink::Search)
+            .ok_or_else(|| "search link has no boss".to_string());
+    }
+    Err(format!("unknown link: {}", url))
+}
+
+// links can come from discord or a browser, so both %20 and + are spaces
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push((high << 4) | low);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn hex(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+// points the scheme at this exe for the current user, redone on every start
+// so the link keeps working after the app was moved or updated
+pub fn register() {
+    if !cfg!(windows) {
+        return;
+    }
+    let Ok(app_path) = std::env::current_exe() else {
+        return;
+    };
+    let command = format!("\"{}\" \"%1\"", app_path.to_string_lossy());
+    let command_key = format!(r"{}\shell\open\command", SCHEME_KEY);
+    let entries: [(&str, Option<&str>, &str); 3] = [
+        (SCHEME_KEY, None, "URL:LOA Logs"),
+        (SCHEME_KEY, Some("URL Protocol"), ""),
+        (&command_key, None, &command),
+    ];
+    for (key, name, data) in entries {
+        let mut args = vec!["add", key];
+        match name {
+            Some(name) => args.extend(["/v", name]),
+            None => args.push("/ve"),
+        }
+        args.extend(["/d", data, "/f"]);
+        match Command::new("reg").args(args).output() {
+            Ok(output) if output.status.success() => (),
+            Ok(output) => {
+                warn!(
+                    "failed to register {} links: {}",
+                    SCHEME,
+                    output.stderr.trim()
+                );
+                return;
+            }
+            Err(e) => {
+                warn!("failed to register {} links: {}", SCHEME, e);
+                return;
+            }
+        }
+    }
+    info!("registered {} links", SCHEME);
+}
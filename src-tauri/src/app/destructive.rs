@@ -0,0 +1,38 @@
+use hashbrown::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+// one time tokens the frontend has to ask for before a mass delete, so a
+// single stray ipc call can't wipe the database
+#[derive(Default)]
+pub struct DestructiveTokens {
+    // token -> action it was issued for
+    tokens: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl DestructiveTokens {
+    pub fn issue(&self, action: &str) -> String {
+        let token = Uuid::new_v4().simple().to_string();
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, (_, issued)| issued.elapsed() < TOKEN_LIFETIME);
+        tokens.insert(token.clone(), (action.to_string(), Instant::now()));
+        token
+    }
+
+    // a token is used up by the first call that presents it, even one for a
+    // different action
+    pub fn consume(&self, action: &str, token: Option<&str>) -> Result<(), String> {
+        let issued = token.and_then(|token| self.tokens.lock().unwrap().remove(token));
+        match issued {
+            Some((issued_for, issued))
+                if issued_for == action && issued.elapsed() < TOKEN_LIFETIME =>
+            {
+                Ok(())
+            }
+            _ => Err(format!("{} needs a valid confirmation token", action)),
+        }
+    }
+}
@@ -1,10 +1,43 @@
+pub mod activity;
+pub mod backup;
+pub mod boss_notes;
+pub mod db;
+pub mod db_pool;
+pub mod deep_link;
+pub mod destructive;
+pub mod network;
+pub mod number_format;
+pub mod opacity;
+pub mod payload_guard;
+pub mod peek;
+pub mod pipe_server;
+pub mod query_stats;
+pub mod search_suggestions;
+pub mod secrets;
+pub mod session;
+pub mod skill_records;
+pub mod storage;
+pub mod time;
+pub mod tray;
+pub mod window_geometry;
+
 use flexi_logger::{
     Cleanup, Criterion, DeferredNow, Duplicate, FileSpec, Logger, LoggerHandle, Naming, WriteMode,
 };
 
-use log::{error, info, warn, Record};
+use hashbrown::HashMap;
+use log::{error, info, log, warn, Level, Record};
 
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// identical frontend messages within this window are dropped
+const FRONTEND_LOG_DEDUP: Duration = Duration::from_secs(5);
+
+// clickthrough state chosen by the user, restored after automatic changes
+static CLICKTHROUGH: AtomicBool = AtomicBool::new(false);
 
 fn get_app_state() -> &'static Mutex<AppState> {
     static APP_STATE: OnceLock<Mutex<AppState>> = OnceLock::new();
@@ -22,23 +55,19 @@ impl AppState {
         }
     }
 
-    pub fn init_logger(&mut self) {
+    pub fn init_logger(&mut self, console: bool) {
         if self.logger_handle.clone().is_some() {
             error!("AppState logger already inited");
             return;
         }
 
-        let mut resource_directory =
-            std::env::current_exe().expect("Can't find path to executable");
-        resource_directory.pop();
-
         let mut logger = Logger::try_with_str("info, tao=off")
             .unwrap()
             .log_to_file(
                 FileSpec::default()
                     .suppress_timestamp()
                     .basename("loa_logs")
-                    .directory(resource_directory),
+                    .directory(storage::log_dir()),
             )
             .use_utc()
             .write_mode(WriteMode::BufferAndFlush)
@@ -55,6 +84,16 @@ impl AppState {
             logger = logger.duplicate_to_stdout(Duplicate::All);
         }
 
+        if console {
+            // release builds have no console of their own, use the one we were started from
+            #[cfg(windows)]
+            unsafe {
+                use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+                AttachConsole(ATTACH_PARENT_PROCESS);
+            }
+            logger = logger.duplicate_to_stdout(Duplicate::All);
+        }
+
         self.logger_handle = Some(logger.start().unwrap());
     }
 
@@ -78,8 +117,8 @@ impl AppState {
     }
 }
 
-pub fn init() {
-    get_app_state().lock().unwrap().init_logger();
+pub fn init(console: bool) {
+    get_app_state().lock().unwrap().init_logger(console);
 }
 
 pub fn get_logger() -> Result<LoggerHandle, String> {
@@ -88,3 +127,63 @@ pub fn get_logger() -> Result<LoggerHandle, String> {
     }
     Err("AppState logger not present".to_string())
 }
+
+pub fn is_clickthrough() -> bool {
+    CLICKTHROUGH.load(Ordering::Relaxed)
+}
+
+pub fn set_clickthrough(set: bool) {
+    CLICKTHROUGH.store(set, Ordering::Relaxed);
+}
+
+pub fn parse_log_level(level: &str) -> Level {
+    match level.to_ascii_lowercase().as_str() {
+        "error" => Level::Error,
+        "warn" | "warning" => Level::Warn,
+        "debug" => Level::Debug,
+        _ => Level::Info,
+    }
+}
+
+// writes a message from the webview into the app log, tagged with the window
+// it came from. repeats are rate limited so a failing render loop can't flood
+// the log file
+pub fn log_frontend(label: &str, level: Level, message: &str, context: Option<&str>) {
+    if is_recent_frontend_log(label, level, message) {
+        return;
+    }
+    match context {
+        Some(context) => log!(level, "[{}] {} | {}", label, message, context),
+        None => log!(level, "[{}] {}", label, message),
+    }
+}
+
+fn is_recent_frontend_log(label: &str, level: Level, message: &str) -> bool {
+    static RECENT: OnceLock<Mutex<HashMap<u64, Instant>>> = OnceLock::new();
+    let mut hasher = DefaultHasher::new();
+    (label, level, message).hash(&mut hasher);
+    let key = hasher.finish();
+
+    let now = Instant::now();
+    let mut recent = RECENT
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    recent.retain(|_, logged| now.duration_since(*logged) < FRONTEND_LOG_DEDUP);
+    if recent.contains_key(&key) {
+        return true;
+    }
+    recent.insert(key, now);
+    false
+}
+
+// called by the webview's global error handlers for uncaught exceptions
+#[tauri::command]
+pub fn frontend_panic(window: tauri::Window, message: String, stack: Option<String>) {
+    log_frontend(
+        window.label(),
+        Level::Error,
+        &format!("uncaught error: {}", message),
+        stack.as_deref(),
+    );
+}
@@ -0,0 +1,231 @@
+use crate::parser::models::GeneralSettings;
+use hashbrown::HashMap;
+use log::{info, warn};
+use serde::Serialize;
+use std::thread;
+use std::time::Duration;
+use sysinfo::Networks;
+
+// how long traffic is sampled to tell which adapters are in use
+const TRAFFIC_SAMPLE: Duration = Duration::from_millis(250);
+// the app can start before the network is (docking, vpn reconnects)
+const NETWORK_WAIT_ATTEMPTS: u32 = 5;
+const NETWORK_WAIT_DELAY: Duration = Duration::from_secs(2);
+// lowercase name fragments of adapters created by hypervisors and vpn clients
+const VIRTUAL_ADAPTER_HINTS: [&str; 12] = [
+    "hyper-v",
+    "vethernet",
+    "vmware",
+    "virtualbox",
+    "wsl",
+    "tap-",
+    "tap windows",
+    "loopback",
+    "tailscale",
+    "zerotier",
+    "wireguard",
+    "docker",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterface {
+    pub name: String,
+    pub ips: Vec<String>,
+    pub up: bool,
+    pub is_virtual: bool,
+    pub has_traffic: bool,
+    // npf device name, \Device\NPF_{guid}, stays the same when the adapter's
+    // addresses or localized description change
+    pub device_id: Option<String>,
+    pub description: Option<String>,
+}
+
+// lists network adapters with real ones first, then by current traffic.
+// blocks for TRAFFIC_SAMPLE, call it off the main thread
+pub fn list_interfaces() -> Vec<NetworkInterface> {
+    let mut networks = Networks::new_with_refreshed_list();
+    thread::sleep(TRAFFIC_SAMPLE);
+    networks.refresh();
+    let mut adapters = adapter_info();
+
+    let mut interfaces: Vec<NetworkInterface> = networks
+        .iter()
+        .map(|(name, data)| {
+            let ips: Vec<String> = data
+                .ip_networks()
+                .iter()
+                .map(|network| network.addr.to_string())
+                .collect();
+            // sysinfo doesn't report link state, an adapter with a routable
+            // address is treated as up
+            let up = data
+                .ip_networks()
+                .iter()
+                .any(|network| !network.addr.is_loopback() && !is_link_local(&network.addr));
+            let (device_id, description) = adapters.remove(name).unzip();
+            NetworkInterface {
+                name: name.clone(),
+                ips,
+                up,
+                is_virtual: is_virtual_adapter(name),
+                has_traffic: data.received() > 0 || data.transmitted() > 0,
+                device_id,
+                description,
+            }
+        })
+        .collect();
+
+    interfaces.sort_by(|a, b| {
+        a.is_virtual
+            .cmp(&b.is_virtual)
+            .then(b.up.cmp(&a.up))
+            .then(b.has_traffic.cmp(&a.has_traffic))
+            .then(a.name.cmp(&b.name))
+    });
+    interfaces
+}
+
+// waits for an adapter with a usable address before live capture starts,
+// the found adapters are logged so bug reports show what the capture sees
+pub fn wait_for_network() -> Option<Vec<NetworkInterface>> {
+    for attempt in 1..=NETWORK_WAIT_ATTEMPTS {
+        let interfaces = list_interfaces();
+        if interfaces.iter().any(|interface| interface.up) {
+            log_interfaces(&interfaces);
+            return Some(interfaces);
+        }
+        warn!(
+            "no network adapter is up (attempt {}/{})",
+            attempt, NETWORK_WAIT_ATTEMPTS
+        );
+        if attempt < NETWORK_WAIT_ATTEMPTS {
+            thread::sleep(NETWORK_WAIT_DELAY);
+        }
+    }
+    None
+}
+
+// finds the adapter picked in the settings. the device id is tried first,
+// descriptions are localized and addresses can be shared by two adapters,
+// so those are only the fallback for settings saved without one
+pub fn resolve_interface<'a>(
+    interfaces: &'a [NetworkInterface],
+    settings: &GeneralSettings,
+) -> Option<&'a NetworkInterface> {
+    if let Some(device_id) = settings.device_id.as_deref().filter(|id| !id.is_empty()) {
+        if let Some(interface) = interfaces
+            .iter()
+            .find(|interface| interface.device_id.as_deref() == Some(device_id))
+        {
+            info!("capture adapter {} matched by device id", interface.name);
+            return Some(interface);
+        }
+        warn!("saved capture adapter {} no longer exists", device_id);
+    }
+    if !settings.if_desc.is_empty() {
+        if let Some(interface) = interfaces.iter().find(|interface| {
+            interface.description.as_deref() == Some(settings.if_desc.as_str())
+                || interface.name == settings.if_desc
+        }) {
+            info!("capture adapter {} matched by description", interface.name);
+            return Some(interface);
+        }
+    }
+    if !settings.ip.is_empty() {
+        if let Some(interface) = interfaces
+            .iter()
+            .find(|interface| interface.ips.contains(&settings.ip))
+        {
+            info!("capture adapter {} matched by ip", interface.name);
+            return Some(interface);
+        }
+    }
+    warn!("no network adapter matches the capture settings");
+    None
+}
+
+fn log_interfaces(interfaces: &[NetworkInterface]) {
+    for interface in interfaces.iter() {
+        info!(
+            "network adapter {}: ips {:?}, up: {}, virtual: {}, traffic: {}",
+            interface.name,
+            interface.ips,
+            interface.up,
+            interface.is_virtual,
+            interface.has_traffic
+        );
+    }
+    if !interfaces
+        .iter()
+        .any(|interface| interface.up && !interface.is_virtual)
+    {
+        warn!("no active physical network adapter found, game traffic may be missed");
+    }
+}
+
+// device id and description by adapter name. sysinfo names adapters by
+// their alias, which GetAdaptersAddresses reports as the friendly name
+#[cfg(windows)]
+fn adapter_info() -> HashMap<String, (String, String)> {
+    use windows::Win32::Foundation::{ERROR_BUFFER_OVERFLOW, ERROR_SUCCESS};
+    use windows::Win32::NetworkManagement::IpHelper::{
+        GetAdaptersAddresses, GAA_FLAG_SKIP_DNS_SERVER, IP_ADAPTER_ADDRESSES_LH,
+    };
+    use windows::Win32::Networking::WinSock::AF_UNSPEC;
+
+    let mut adapters = HashMap::new();
+    let mut size: u32 = 16 * 1024;
+    // u64 so the buffer is aligned for the adapter structs
+    let mut buffer: Vec<u64>;
+    loop {
+        buffer = vec![0; (size as usize).div_ceil(8)];
+        let result = unsafe {
+            GetAdaptersAddresses(
+                AF_UNSPEC.0 as u32,
+                GAA_FLAG_SKIP_DNS_SERVER,
+                None,
+                Some(buffer.as_mut_ptr() as *mut IP_ADAPTER_ADDRESSES_LH),
+                &mut size,
+            )
+        };
+        if result == ERROR_BUFFER_OVERFLOW.0 {
+            continue;
+        }
+        if result != ERROR_SUCCESS.0 {
+            warn!("failed to read network adapters: {}", result);
+            return adapters;
+        }
+        break;
+    }
+
+    let mut adapter = buffer.as_ptr() as *const IP_ADAPTER_ADDRESSES_LH;
+    while !adapter.is_null() {
+        let current = unsafe { &*adapter };
+        let name = unsafe { current.FriendlyName.to_string() }.unwrap_or_default();
+        let guid = unsafe { current.AdapterName.to_string() }.unwrap_or_default();
+        let description = unsafe { current.Description.to_string() }.unwrap_or_default();
+        if !name.is_empty() && !guid.is_empty() {
+            adapters.insert(name, (format!("\\Device\\NPF_{}", guid), description));
+        }
+        adapter = current.Next;
+    }
+    adapters
+}
+
+#[cfg(not(windows))]
+fn adapter_info() -> HashMap<String, (String, String)> {
+    HashMap::new()
+}
+
+fn is_virtual_adapter(name: &str) -> bool {
+    let name = name.to_lowercase();
+    VIRTUAL_ADAPTER_HINTS.iter().any(|hint| name.contains(hint))
+}
+
+fn is_link_local(addr: &std::net::IpAddr) -> bool {
+    match addr {
+        std::net::IpAddr::V4(addr) => addr.is_link_local(),
+        std::net::IpAddr::V6(addr) => (addr.segments()[0] & 0xffc0) == 0xfe80,
+    }
+}
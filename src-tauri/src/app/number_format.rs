@@ -0,0 +1,164 @@
+use crate::parser::models::{NumberFormat, NumberFormatExample};
+use std::sync::{Mutex, OnceLock};
+
+pub const MAX_DECIMALS: u8 = 3;
+
+const UNITS: [(u64, &str); 4] = [
+    (1_000_000_000_000, "t"),
+    (1_000_000_000, "b"),
+    (1_000_000, "m"),
+    (1_000, "k"),
+];
+
+const PREVIEW_VALUES: [i64; 7] = [0, 999, 1_000, 123_456, 1_000_000, 9_876_543_210, i64::MAX];
+
+// backend side counterpart of the frontend's abbreviateNumber, so anything
+// the app writes out itself uses the same style as the meter
+pub fn format_number(value: i64, format: &NumberFormat) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let n = value.unsigned_abs();
+    if !format.abbreviate {
+        return format!("{}{}", sign, group(n, &format.separator));
+    }
+
+    let decimals = format.decimals.min(MAX_DECIMALS) as u32;
+    let scale = 10u128.pow(decimals);
+    for (i, &(unit, suffix)) in UNITS.iter().enumerate() {
+        if n < unit {
+            continue;
+        }
+        // rounded in fixed point, 999_960 with one decimal is 1m and not 1000.0k
+        let scaled = (n as u128 * scale + unit as u128 / 2) / unit as u128;
+        if i > 0 && scaled >= 1000 * scale {
+            let (next_unit, next_suffix) = UNITS[i - 1];
+            let scaled = (n as u128 * scale + next_unit as u128 / 2) / next_unit as u128;
+            return format!("{}{}{}", sign, fixed(scaled, scale, format), next_suffix);
+        }
+        return format!("{}{}{}", sign, fixed(scaled, scale, format), suffix);
+    }
+    format!("{}{}", sign, n)
+}
+
+fn current() -> &'static Mutex<NumberFormat> {
+    static FORMAT: OnceLock<Mutex<NumberFormat>> = OnceLock::new();
+    FORMAT.get_or_init(|| Mutex::new(NumberFormat::default()))
+}
+
+// from Settings::ui::number_format
+pub fn set_format(format: &NumberFormat) {
+    *current().lock().unwrap() = format.clone();
+}
+
+// formatted the way the settings ask for
+pub fn format(value: i64) -> String {
+    let format = current().lock().unwrap().clone();
+    format_number(value, &format)
+}
+
+pub fn preview(format: &NumberFormat) -> Vec<NumberFormatExample> {
+    PREVIEW_VALUES
+        .iter()
+        .map(|&value| NumberFormatExample {
+            value,
+            formatted: format_number(value, format),
+        })
+        .collect()
+}
+
+// trailing zeros are dropped, 1.50k reads as 1.5k
+fn fixed(scaled: u128, scale: u128, format: &NumberFormat) -> String {
+    let whole = group((scaled / scale) as u64, &format.separator);
+    let fraction = scaled % scale;
+    if fraction == 0 {
+        return whole;
+    }
+    let width = scale.ilog10() as usize;
+    let fraction = format!("{:0width$}", fraction, width = width);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+fn group(n: u64, separator: &str) -> String {
+    let digits = n.to_string();
+    if separator.is_empty() {
+        return digits;
+    }
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abbreviated(decimals: u8) -> NumberFormat {
+        NumberFormat {
+            decimals,
+            ..Default::default()
+        }
+    }
+
+    fn grouped(separator: &str) -> NumberFormat {
+        NumberFormat {
+            abbreviate: false,
+            separator: separator.to_string(),
+            decimals: 0,
+        }
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(format_number(0, &abbreviated(1)), "0");
+        assert_eq!(format_number(0, &grouped(",")), "0");
+    }
+
+    #[test]
+    fn thousand_boundary() {
+        let format = abbreviated(1);
+        assert_eq!(format_number(999, &format), "999");
+        assert_eq!(format_number(1_000, &format), "1k");
+        assert_eq!(format_number(1_050, &format), "1.1k");
+        assert_eq!(format_number(-1_500, &format), "-1.5k");
+        assert_eq!(format_number(1_000, &grouped(",")), "1,000");
+        assert_eq!(format_number(1_000, &grouped("")), "1000");
+    }
+
+    #[test]
+    fn million_boundary() {
+        let format = abbreviated(1);
+        assert_eq!(format_number(999_949, &format), "999.9k");
+        // rounds up into the next unit instead of 1000k
+        assert_eq!(format_number(999_950, &format), "1m");
+        assert_eq!(format_number(1_000_000, &format), "1m");
+        assert_eq!(format_number(1_234_567, &abbreviated(3)), "1.235m");
+        assert_eq!(format_number(1_234_567, &abbreviated(0)), "1m");
+        assert_eq!(format_number(1_000_000, &grouped(" ")), "1 000 000");
+    }
+
+    #[test]
+    fn extremes() {
+        assert_eq!(format_number(i64::MAX, &abbreviated(1)), "9,223,372t");
+        assert_eq!(format_number(i64::MIN, &abbreviated(1)), "-9,223,372t");
+        assert_eq!(
+            format_number(i64::MAX, &grouped(",")),
+            "9,223,372,036,854,775,807"
+        );
+        assert_eq!(
+            format_number(i64::MIN, &grouped(",")),
+            "-9,223,372,036,854,775,808"
+        );
+    }
+
+    #[test]
+    fn decimals_are_capped() {
+        assert_eq!(
+            format_number(1_234_567, &abbreviated(9)),
+            format_number(1_234_567, &abbreviated(MAX_DECIMALS))
+        );
+    }
+}
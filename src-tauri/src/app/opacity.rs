@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Runtime, Window};
+
+// set when the user picks an opacity through the command, the idle fade
+// leaves the window alone until its next combat transition
+static MANUAL_OPACITY: AtomicBool = AtomicBool::new(false);
+
+pub fn is_manual() -> bool {
+    MANUAL_OPACITY.load(Ordering::Relaxed)
+}
+
+pub fn set_manual(manual: bool) {
+    MANUAL_OPACITY.store(manual, Ordering::Relaxed);
+}
+
+// applies opacity to the native window, values are clamped to 0.1..=1.0
+// so a bad setting can't make the meter disappear entirely
+#[cfg(windows)]
+pub fn set_window_opacity<R: Runtime>(window: &Window<R>, opacity: f64) {
+    use windows::Win32::Foundation::{COLORREF, HWND};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetWindowLongPtrW, SetLayeredWindowAttributes, SetWindowLongPtrW, GWL_EXSTYLE, LWA_ALPHA,
+        WS_EX_LAYERED,
+    };
+
+    let Ok(hwnd) = window.hwnd() else {
+        return;
+    };
+    let hwnd = HWND(hwnd.0);
+    let alpha = (opacity.clamp(0.1, 1.0) * 255.0).round() as u8;
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        if style & WS_EX_LAYERED.0 as isize == 0 {
+            SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED.0 as isize);
+        }
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn set_window_opacity<R: Runtime>(_window: &Window<R>, _opacity: f64) {}
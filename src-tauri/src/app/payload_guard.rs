@@ -0,0 +1,97 @@
+use crate::parser::models::{Encounter, EncounterEntity, Skill};
+use log::warn;
+use serde::Serialize;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const DEFAULT_PAYLOAD_LIMIT_MB: u64 = 30;
+
+static PAYLOAD_LIMIT: AtomicU64 = AtomicU64::new(DEFAULT_PAYLOAD_LIMIT_MB * 1024 * 1024);
+
+// 0 turns the guard off
+pub fn set_limit_mb(limit_mb: u64) {
+    PAYLOAD_LIMIT.store(limit_mb.saturating_mul(1024 * 1024), Ordering::Relaxed);
+}
+
+// very long raids can serialize to hundreds of mb, which freezes the webview.
+// the biggest entities lose their skill breakdown until the response fits,
+// the ui loads them one at a time with load_entity_details
+pub fn guard_encounter(encounter: &mut Encounter, id: i64) {
+    let limit = PAYLOAD_LIMIT.load(Ordering::Relaxed);
+    if limit == 0 {
+        return;
+    }
+    let size = serialized_size(encounter);
+    if size <= limit {
+        return;
+    }
+
+    let mut entities: Vec<(String, u64)> = encounter
+        .entities
+        .iter()
+        .map(|(name, entity)| (name.clone(), serialized_size(&entity.skills)))
+        .collect();
+    entities.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut remaining = size;
+    for (name, skills_size) in entities {
+        if remaining <= limit {
+            break;
+        }
+        let Some(entity) = encounter.entities.get_mut(&name) else {
+            continue;
+        };
+        summarize_skills(entity);
+        remaining = remaining.saturating_sub(skills_size) + serialized_size(&entity.skills);
+        encounter.truncated_entities.push(name);
+    }
+    encounter.truncated = true;
+
+    warn!(
+        "encounter {} is {} bytes, over the {} byte ipc limit, omitted skill details for {} entities",
+        id,
+        size,
+        limit,
+        encounter.truncated_entities.len()
+    );
+}
+
+// keeps the totals the meter tables need, drops buff maps and cast logs
+fn summarize_skills(entity: &mut EncounterEntity) {
+    for skill in entity.skills.values_mut() {
+        *skill = Skill {
+            id: skill.id,
+            name: std::mem::take(&mut skill.name),
+            icon: std::mem::take(&mut skill.icon),
+            total_damage: skill.total_damage,
+            max_damage: skill.max_damage,
+            casts: skill.casts,
+            hits: skill.hits,
+            crits: skill.crits,
+            dps: skill.dps,
+            max_hit: skill.max_hit.take(),
+            avg_hit: skill.avg_hit,
+            avg_crit_hit: skill.avg_crit_hit,
+            ..Default::default()
+        };
+    }
+}
+
+fn serialized_size<T: Serialize>(value: &T) -> u64 {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).ok();
+    counter.0
+}
+
+struct ByteCounter(u64);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
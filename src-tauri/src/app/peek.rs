@@ -0,0 +1,120 @@
+use log::{info, warn};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::Window;
+
+use crate::app;
+use crate::parser::models::Shortcut;
+
+// short enough that letting go of the key feels immediate
+const POLL_INTERVAL: Duration = Duration::from_millis(30);
+
+const VK_SHIFT: u16 = 0x10;
+const VK_CONTROL: u16 = 0x11;
+const VK_MENU: u16 = 0x12;
+const VK_LEFT: u16 = 0x25;
+const VK_UP: u16 = 0x26;
+const VK_RIGHT: u16 = 0x27;
+const VK_DOWN: u16 = 0x28;
+const VK_F1: u16 = 0x70;
+
+// virtual key codes that all have to be held, empty while no shortcut is set
+static PEEK_KEYS: Mutex<Vec<u16>> = Mutex::new(Vec::new());
+
+pub fn set_shortcut(shortcut: &Shortcut) {
+    let mut keys = PEEK_KEYS.lock().unwrap();
+    keys.clear();
+    if shortcut.modifier.is_empty() || shortcut.key.is_empty() {
+        return;
+    }
+    match virtual_keys(shortcut) {
+        Some(codes) => *keys = codes,
+        None => warn!(
+            "unsupported peek shortcut {}+{}",
+            shortcut.modifier, shortcut.key
+        ),
+    }
+}
+
+// shows the meter and lets it take the mouse while the peek shortcut is held,
+// then puts it back the way it was. global shortcuts only report the press,
+// so the keys are polled to notice the release
+pub fn start(meter: Window) {
+    if !cfg!(windows) {
+        return;
+    }
+    thread::spawn(move || {
+        // whether the meter was hidden before the current peek
+        let mut peeking: Option<bool> = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let held = {
+                let keys = PEEK_KEYS.lock().unwrap();
+                !keys.is_empty() && keys.iter().all(|&key| is_down(key))
+            };
+            match (held, peeking) {
+                (true, None) => {
+                    let hidden = !meter.is_visible().unwrap_or(false);
+                    info!("peeking meter");
+                    app::tray::show_meter(&meter);
+                    meter.set_ignore_cursor_events(false).ok();
+                    peeking = Some(hidden);
+                }
+                (false, Some(hidden)) => {
+                    if hidden {
+                        app::tray::hide_meter(&meter);
+                    }
+                    meter.set_ignore_cursor_events(app::is_clickthrough()).ok();
+                    peeking = None;
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+fn virtual_keys(shortcut: &Shortcut) -> Option<Vec<u16>> {
+    let mut keys = Vec::new();
+    for modifier in shortcut.modifier.split('+') {
+        keys.push(match modifier {
+            "Ctrl" => VK_CONTROL,
+            "Alt" => VK_MENU,
+            "Shift" => VK_SHIFT,
+            _ => return None,
+        });
+    }
+    keys.push(key_code(&shortcut.key)?);
+    Some(keys)
+}
+
+// covers the keys the shortcut settings offer
+fn key_code(key: &str) -> Option<u16> {
+    match key {
+        "ArrowLeft" => return Some(VK_LEFT),
+        "ArrowUp" => return Some(VK_UP),
+        "ArrowRight" => return Some(VK_RIGHT),
+        "ArrowDown" => return Some(VK_DOWN),
+        _ => {}
+    }
+    if let Some(n) = key.strip_prefix('F').and_then(|n| n.parse::<u16>().ok()) {
+        return (1..=12).contains(&n).then_some(VK_F1 + n - 1);
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase() as u16),
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+fn is_down(key: u16) -> bool {
+    use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+    // the high bit is set while the key is down
+    unsafe { GetAsyncKeyState(key as i32) < 0 }
+}
+
+#[cfg(not(windows))]
+fn is_down(_key: u16) -> bool {
+    false
+}
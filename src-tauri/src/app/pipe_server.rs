@@ -0,0 +1,189 @@
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::{broadcast, watch};
+
+use crate::parser::models::{Encounter, LiveUpdate};
+
+pub const PIPE_NAME: &str = r"\\.\pipe\loa-meter";
+// subscribers that fall this many updates behind skip ahead
+const UPDATE_BACKLOG: usize = 64;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// bumped on every toggle, servers and clients of an older generation shut down
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn get_updates() -> &'static broadcast::Sender<String> {
+    static UPDATES: OnceLock<broadcast::Sender<String>> = OnceLock::new();
+    UPDATES.get_or_init(|| broadcast::channel(UPDATE_BACKLOG).0)
+}
+
+fn get_shutdown() -> &'static watch::Sender<u64> {
+    static SHUTDOWN: OnceLock<watch::Sender<u64>> = OnceLock::new();
+    SHUTDOWN.get_or_init(|| watch::channel(0).0)
+}
+
+// last full encounter when live deltas are off, live_delta only tracks delta mode
+fn get_last_live() -> &'static Mutex<Option<Encounter>> {
+    static LAST_LIVE: OnceLock<Mutex<Option<Encounter>>> = OnceLock::new();
+    LAST_LIVE.get_or_init(|| Mutex::new(None))
+}
+
+pub fn set_enabled(enabled: bool) {
+    if ENABLED.swap(enabled, Ordering::Relaxed) == enabled {
+        return;
+    }
+    let generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    get_shutdown().send_replace(generation);
+    if enabled {
+        tauri::async_runtime::spawn(serve(generation));
+    } else {
+        info!("stopping pipe server");
+        *get_last_live().lock().unwrap() = None;
+    }
+}
+
+// forwards an event to subscribed clients with the same payload the frontend gets
+pub fn publish<T: Serialize>(event: &str, payload: &T) {
+    if !ENABLED.load(Ordering::Relaxed) || get_updates().receiver_count() == 0 {
+        return;
+    }
+    get_updates().send(message(event, payload)).ok();
+}
+
+pub fn publish_live_update(update: &LiveUpdate) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    *get_last_live().lock().unwrap() = Some(update.encounter.clone());
+    publish("live-update", update);
+}
+
+fn message<T: Serialize>(event: &str, payload: &T) -> String {
+    let mut line = json!({ "event": event, "payload": payload }).to_string();
+    line.push('\n');
+    line
+}
+
+// answers a single request line, subscribing or unsubscribing the client
+#[cfg(windows)]
+fn handle_request(line: &str, updates: &mut Option<broadcast::Receiver<String>>) -> String {
+    use crate::parser::capture_status::get_capture_status;
+    use crate::parser::live_delta::get_live_encounter;
+    use serde_json::Value;
+
+    let cmd = serde_json::from_str::<Value>(line)
+        .ok()
+        .and_then(|request| request.get("cmd")?.as_str().map(str::to_string));
+    match cmd.as_deref() {
+        Some("live") => {
+            let encounter =
+                get_live_encounter().or_else(|| get_last_live().lock().unwrap().clone());
+            message("live", &encounter)
+        }
+        Some("status") => message("status", &get_capture_status()),
+        Some("subscribe") => {
+            *updates = Some(get_updates().subscribe());
+            message("subscribed", &Value::Null)
+        }
+        Some("unsubscribe") => {
+            *updates = None;
+            message("unsubscribed", &Value::Null)
+        }
+        Some(cmd) => message("error", &format!("unknown command: {}", cmd)),
+        None => message("error", &"expected {\"cmd\": ...}"),
+    }
+}
+
+#[cfg(windows)]
+async fn next_update(updates: &mut Option<broadcast::Receiver<String>>) -> String {
+    loop {
+        match updates.as_mut() {
+            Some(receiver) => match receiver.recv().await {
+                Ok(update) => return update,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => *updates = None,
+            },
+            None => std::future::pending::<()>().await,
+        }
+    }
+}
+
+#[cfg(windows)]
+async fn wait_for_shutdown(shutdown: &mut watch::Receiver<u64>, generation: u64) {
+    while *shutdown.borrow_and_update() == generation {
+        if shutdown.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(windows)]
+async fn serve(generation: u64) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    info!("starting pipe server on {}", PIPE_NAME);
+    let mut shutdown = get_shutdown().subscribe();
+    loop {
+        // a fresh instance is created for every client so several can be connected at once
+        let server = match ServerOptions::new().create(PIPE_NAME) {
+            Ok(server) => server,
+            Err(e) => {
+                warn!("failed to create pipe {}: {}", PIPE_NAME, e);
+                return;
+            }
+        };
+        tokio::select! {
+            connected = server.connect() => match connected {
+                Ok(()) => {
+                    tauri::async_runtime::spawn(handle_client(server, generation));
+                }
+                Err(e) => warn!("pipe client failed to connect: {}", e),
+            },
+            _ = wait_for_shutdown(&mut shutdown, generation) => break,
+        }
+    }
+    info!("pipe server stopped");
+}
+
+#[cfg(not(windows))]
+async fn serve(_generation: u64) {
+    warn!("pipe server is only supported on windows");
+}
+
+#[cfg(windows)]
+async fn handle_client(pipe: tokio::net::windows::named_pipe::NamedPipeServer, generation: u64) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    enum Event {
+        Request(String),
+        Update(String),
+        Closed,
+    }
+
+    let (reader, mut writer) = tokio::io::split(pipe);
+    let mut lines = BufReader::new(reader).lines();
+    let mut updates: Option<broadcast::Receiver<String>> = None;
+    let mut shutdown = get_shutdown().subscribe();
+    loop {
+        let event = tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => Event::Request(line),
+                _ => Event::Closed,
+            },
+            update = next_update(&mut updates) => Event::Update(update),
+            _ = wait_for_shutdown(&mut shutdown, generation) => Event::Closed,
+        };
+        let message = match event {
+            Event::Request(line) if line.trim().is_empty() => continue,
+            Event::Request(line) => handle_request(&line, &mut updates),
+            Event::Update(update) => update,
+            Event::Closed => break,
+        };
+        if writer.write_all(message.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
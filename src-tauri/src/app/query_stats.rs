@@ -0,0 +1,168 @@
+use log::warn;
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// commands or single queries slower than this are logged
+const SLOW_THRESHOLD: Duration = Duration::from_millis(500);
+const HISTORY_SIZE: usize = 1000;
+const REPORT_SIZE: usize = 20;
+
+thread_local! {
+    // command holding a pooled connection on this thread, labels the profiled queries
+    static CURRENT_COMMAND: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+#[derive(Debug, Clone)]
+struct Sample {
+    command: &'static str,
+    sql: Option<String>,
+    duration: Duration,
+}
+
+fn get_history() -> &'static Mutex<VecDeque<Sample>> {
+    static HISTORY: OnceLock<Mutex<VecDeque<Sample>>> = OnceLock::new();
+    HISTORY.get_or_init(|| Mutex::new(VecDeque::with_capacity(HISTORY_SIZE)))
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryStat {
+    pub command: String,
+    // None for the whole command, including time spent outside of sqlite
+    pub sql: Option<String>,
+    pub count: usize,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+pub fn set_current_command(command: Option<&'static str>) {
+    CURRENT_COMMAND.with(|current| current.set(command));
+}
+
+pub fn record_command(command: &'static str, duration: Duration) {
+    if duration > SLOW_THRESHOLD {
+        warn!(
+            "slow db command {} took {}ms",
+            command,
+            duration.as_millis()
+        );
+    }
+    push(Sample {
+        command,
+        sql: None,
+        duration,
+    });
+}
+
+// rusqlite profile callback, called after every statement with its original text
+pub fn record_query(sql: &str, duration: Duration) {
+    let command = CURRENT_COMMAND.with(Cell::get).unwrap_or("unknown");
+    let sql = sanitize_sql(sql);
+    if duration > SLOW_THRESHOLD {
+        warn!(
+            "slow query in {} took {}ms: {}",
+            command,
+            duration.as_millis(),
+            sql
+        );
+    }
+    push(Sample {
+        command,
+        sql: Some(sql),
+        duration,
+    });
+}
+
+fn push(sample: Sample) {
+    let mut history = get_history().lock().unwrap();
+    if history.len() == HISTORY_SIZE {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
+
+// slowest recent commands and queries by p95
+pub fn get_query_stats() -> Vec<QueryStat> {
+    let history = get_history().lock().unwrap().clone();
+    let mut groups: Vec<(&'static str, Option<String>, Vec<Duration>)> = Vec::new();
+    for sample in history {
+        match groups
+            .iter_mut()
+            .find(|(command, sql, _)| *command == sample.command && *sql == sample.sql)
+        {
+            Some((_, _, durations)) => durations.push(sample.duration),
+            None => groups.push((sample.command, sample.sql, vec![sample.duration])),
+        }
+    }
+
+    let mut stats: Vec<QueryStat> = groups
+        .into_iter()
+        .map(|(command, sql, mut durations)| {
+            durations.sort_unstable();
+            let p95_index = ((durations.len() as f64 * 0.95).ceil() as usize).max(1) - 1;
+            QueryStat {
+                command: command.to_string(),
+                sql,
+                count: durations.len(),
+                p95_ms: durations[p95_index].as_secs_f64() * 1000.0,
+                max_ms: durations.last().unwrap().as_secs_f64() * 1000.0,
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+    stats.truncate(REPORT_SIZE);
+    stats
+}
+
+// collapses whitespace and replaces literals and placeholder lists so
+// queries that only differ in their values group together
+fn sanitize_sql(sql: &str) -> String {
+    let mut shape = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                shape.push('?');
+            }
+            c if c.is_ascii_digit()
+                && !shape.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '?') =>
+            {
+                while chars
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    chars.next();
+                }
+                shape.push('?');
+            }
+            c if c.is_whitespace() => {
+                if !shape.is_empty() && !shape.ends_with(' ') {
+                    shape.push(' ');
+                }
+            }
+            c => shape.push(c),
+        }
+    }
+
+    // IN (?, ?, ?) with any number of values
+    let mut collapsed = shape.trim_end().to_string();
+    while let Some(start) = collapsed.find("?, ?").or_else(|| collapsed.find("?,?")) {
+        let end = collapsed[start..]
+            .find(|c: char| c != '?' && c != ',' && c != ' ')
+            .map_or(collapsed.len(), |end| start + end);
+        collapsed.replace_range(start..end, "?...");
+    }
+    collapsed
+}
@@ -0,0 +1,97 @@
+use crate::parser::models::SearchSuggestion;
+use hashbrown::{HashMap, HashSet};
+use rusqlite::Connection;
+use std::sync::Mutex;
+
+pub const MAX_SUGGESTIONS: i32 = 50;
+
+// distinct boss and player names with their encounter counts, built on first
+// use and dropped whenever encounters are saved or deleted
+#[derive(Default)]
+pub struct SuggestionCache {
+    entries: Mutex<Option<Vec<SearchSuggestion>>>,
+}
+
+impl SuggestionCache {
+    pub fn invalidate(&self) {
+        *self.entries.lock().unwrap() = None;
+    }
+
+    // case insensitive prefix match, most frequent first
+    pub fn suggest(
+        &self,
+        conn: &Connection,
+        prefix: &str,
+        limit: i32,
+    ) -> Result<Vec<SearchSuggestion>, rusqlite::Error> {
+        let prefix = prefix.trim().to_lowercase();
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.is_none() {
+            *entries = Some(load_suggestions(conn)?);
+        }
+
+        Ok(entries
+            .iter()
+            .flatten()
+            .filter(|s| s.value.to_lowercase().starts_with(&prefix))
+            .take(limit.clamp(1, MAX_SUGGESTIONS) as usize)
+            .cloned()
+            .collect())
+    }
+}
+
+fn load_suggestions(conn: &Connection) -> Result<Vec<SearchSuggestion>, rusqlite::Error> {
+    let mut suggestions = Vec::new();
+
+    let mut stmt = conn.prepare_cached(
+        "
+    SELECT current_boss, COUNT(*)
+    FROM encounter_preview
+    WHERE current_boss IS NOT NULL AND current_boss <> ''
+    GROUP BY current_boss
+    ",
+    )?;
+    let bosses = stmt.query_map([], |row| {
+        Ok(SearchSuggestion {
+            value: row.get(0)?,
+            kind: "boss".to_string(),
+            count: row.get(1)?,
+        })
+    })?;
+    for boss in bosses {
+        suggestions.push(boss?);
+    }
+
+    // players are only stored as "class_id:name:dps" lists on the preview
+    let mut player_counts: HashMap<String, i64> = HashMap::new();
+    let mut stmt = conn.prepare_cached("SELECT players FROM encounter_preview")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let players: Option<String> = row.get(0)?;
+        let names = players
+            .iter()
+            .flat_map(|players| players.split(','))
+            .filter_map(|player| player.split(':').nth(1))
+            .filter(|name| !name.is_empty())
+            .collect::<HashSet<_>>();
+        for name in names {
+            *player_counts.entry(name.to_string()).or_default() += 1;
+        }
+    }
+    suggestions.extend(
+        player_counts
+            .into_iter()
+            .map(|(name, count)| SearchSuggestion {
+                value: name,
+                kind: "player".to_string(),
+                count,
+            }),
+    );
+
+    suggestions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    Ok(suggestions)
+}
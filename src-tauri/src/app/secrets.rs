@@ -0,0 +1,115 @@
+// tokens are kept in the windows credential manager instead of settings.json
+
+const TARGET_PREFIX: &str = "LOA Logs/";
+
+pub const SYNC_ACCESS_TOKEN: &str = "sync_access_token";
+
+#[cfg(windows)]
+fn target_name(name: &str) -> Vec<u16> {
+    format!("{}{}", TARGET_PREFIX, name)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+#[cfg(windows)]
+pub fn set_secret(name: &str, value: &str) -> Result<(), String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Security::Credentials::{
+        CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+    };
+
+    let mut target = target_name(name);
+    let mut blob = value.as_bytes().to_vec();
+    let credential = CREDENTIALW {
+        Type: CRED_TYPE_GENERIC,
+        TargetName: PWSTR(target.as_mut_ptr()),
+        CredentialBlobSize: blob.len() as u32,
+        CredentialBlob: blob.as_mut_ptr(),
+        Persist: CRED_PERSIST_LOCAL_MACHINE,
+        ..Default::default()
+    };
+    unsafe {
+        if CredWriteW(&credential, 0).as_bool() {
+            Ok(())
+        } else {
+            Err(format!(
+                "failed to store secret {}: {}",
+                name,
+                windows::core::Error::from_win32()
+            ))
+        }
+    }
+}
+
+#[cfg(windows)]
+pub fn get_secret(name: &str) -> Result<Option<String>, String> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::ERROR_NOT_FOUND;
+    use windows::Win32::Security::Credentials::{
+        CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC,
+    };
+
+    let target = target_name(name);
+    let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+    unsafe {
+        if !CredReadW(
+            PCWSTR(target.as_ptr()),
+            CRED_TYPE_GENERIC,
+            0,
+            &mut credential,
+        )
+        .as_bool()
+        {
+            let error = windows::core::Error::from_win32();
+            if error.code() == ERROR_NOT_FOUND.to_hresult() {
+                return Ok(None);
+            }
+            return Err(format!("failed to read secret {}: {}", name, error));
+        }
+        let blob = std::slice::from_raw_parts(
+            (*credential).CredentialBlob,
+            (*credential).CredentialBlobSize as usize,
+        );
+        let value = String::from_utf8_lossy(blob).to_string();
+        CredFree(credential as *const _);
+        Ok(Some(value))
+    }
+}
+
+#[cfg(windows)]
+pub fn delete_secret(name: &str) -> Result<(), String> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::ERROR_NOT_FOUND;
+    use windows::Win32::Security::Credentials::{CredDeleteW, CRED_TYPE_GENERIC};
+
+    let target = target_name(name);
+    unsafe {
+        if CredDeleteW(PCWSTR(target.as_ptr()), CRED_TYPE_GENERIC, 0).as_bool() {
+            return Ok(());
+        }
+        let error = windows::core::Error::from_win32();
+        if error.code() == ERROR_NOT_FOUND.to_hresult() {
+            return Ok(());
+        }
+        Err(format!("failed to delete secret {}: {}", name, error))
+    }
+}
+
+#[cfg(not(windows))]
+pub fn set_secret(name: &str, _value: &str) -> Result<(), String> {
+    Err(format!(
+        "can't store {}{}, no credential store",
+        TARGET_PREFIX, name
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn get_secret(_name: &str) -> Result<Option<String>, String> {
+    Ok(None)
+}
+
+#[cfg(not(windows))]
+pub fn delete_secret(_name: &str) -> Result<(), String> {
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::parser::models::AppSession;
+use rusqlite::{params, Connection};
+use std::sync::atomic::{AtomicI64, Ordering};
+
+// id of this run's row in the session table, 0 until it was written
+static SESSION_ID: AtomicI64 = AtomicI64::new(0);
+
+pub fn current_id() -> Option<i64> {
+    match SESSION_ID.load(Ordering::Relaxed) {
+        0 => None,
+        id => Some(id),
+    }
+}
+
+pub fn start(
+    conn: &Connection,
+    version: &str,
+    capture_mode: &str,
+    interface_ip: &str,
+) -> Result<i64, rusqlite::Error> {
+    let id = conn.query_row(
+        "INSERT INTO session (start_time, version, capture_mode, interface_ip)
+        VALUES (?, ?, ?, ?)
+        RETURNING id",
+        params![
+            chrono::Utc::now().timestamp_millis(),
+            version,
+            capture_mode,
+            interface_ip
+        ],
+        |row| row.get(0),
+    )?;
+    SESSION_ID.store(id, Ordering::Relaxed);
+    Ok(id)
+}
+
+// only written on a clean shutdown, a session without an end time crashed
+// or was killed
+pub fn end(conn: &Connection) -> Result<(), rusqlite::Error> {
+    if let Some(id) = current_id() {
+        conn.execute(
+            "UPDATE session SET end_time = ? WHERE id = ?",
+            params![chrono::Utc::now().timestamp_millis(), id],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn record_encounter(conn: &Connection) -> Result<(), rusqlite::Error> {
+    if let Some(id) = current_id() {
+        conn.execute(
+            "UPDATE session SET encounters_saved = encounters_saved + 1 WHERE id = ?",
+            [id],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn list(conn: &Connection, limit: i32) -> Result<Vec<AppSession>, rusqlite::Error> {
+    let current = current_id();
+    let mut stmt = conn.prepare_cached(
+        "
+    SELECT id, start_time, end_time, version, capture_mode, interface_ip, encounters_saved
+    FROM session
+    ORDER BY start_time DESC
+    LIMIT ?
+    ",
+    )?;
+    let sessions = stmt.query_map([limit], |row| {
+        let id: i64 = row.get(0)?;
+        let end_time: Option<i64> = row.get(2)?;
+        Ok(AppSession {
+            id,
+            start_time: row.get(1)?,
+            end_time,
+            version: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            capture_mode: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            interface_ip: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+            encounters_saved: row.get(6)?,
+            current: current == Some(id),
+            crashed: end_time.is_none() && current != Some(id),
+        })
+    })?;
+    sessions.collect()
+}
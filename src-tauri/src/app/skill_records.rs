@@ -0,0 +1,32 @@
+use hashbrown::HashMap;
+use rusqlite::{params, Connection, Transaction};
+
+// the biggest hit of every skill a character landed in saved live fights,
+// what the meter compares max hits against for a new personal record
+pub fn get(conn: &Connection, player: &str) -> Result<HashMap<u32, i64>, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare_cached("SELECT skill_id, max_damage FROM skill_record WHERE player = ?")?;
+    let records = stmt.query_map([player], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    records.collect()
+}
+
+// keeps the higher of the stored and the given max hits
+pub fn update(
+    tx: &Transaction,
+    player: &str,
+    encounter_id: i64,
+    max_hits: &[(u32, i64)],
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO skill_record (player, skill_id, max_damage, encounter_id)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(player, skill_id) DO UPDATE SET
+            max_damage = excluded.max_damage,
+            encounter_id = excluded.encounter_id
+        WHERE excluded.max_damage > skill_record.max_damage",
+    )?;
+    for (skill_id, max_damage) in max_hits {
+        stmt.execute(params![player, skill_id, max_damage, encounter_id])?;
+    }
+    Ok(())
+}
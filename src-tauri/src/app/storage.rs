@@ -0,0 +1,454 @@
+use crate::parser::models::{StorageCategory, StorageFallback, StorageFreed};
+use crate::parser::packet_dump;
+use log::{error, info, warn};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tauri::AppHandle;
+
+const DB_FILE: &str = "encounters.db";
+// uncheckpointed pages, only meaningful next to the database they belong to
+const DB_WAL_FILE: &str = "encounters.db-wal";
+const DB_SHM_FILE: &str = "encounters.db-shm";
+const SETTINGS_FILE: &str = "settings.json";
+// state files older versions wrote next to the exe, moved along with the db
+const STATE_FILES: [&str; 4] = [
+    "local_players.json",
+    "current_region",
+    "window-geometry.json",
+    "clickthrough",
+];
+const WRITE_PROBE_FILE: &str = ".write-probe";
+// written to the fallback dir once it is used, holds the preferred dir
+const FALLBACK_MARKER_FILE: &str = "storage-fallback";
+const EXPORT_DIR: &str = "exports";
+// left behind by interrupted backups and migrations, or kept by hand
+const BACKUP_SUFFIXES: [&str; 4] = [".snapshot", ".migrating", ".bak", ".zip"];
+// a restore staged for the next start, deleting it would cancel the restore
+const STAGED_RESTORE_FILE: &str = "encounters.db.restore";
+// basename of the rotated log files flexi_logger writes next to the exe
+const LOG_BASENAME: &str = "loa_logs";
+
+pub const STORAGE_CATEGORIES: [&str; 6] = ["db", "wal", "backups", "logs", "dumps", "exports"];
+// what the scheduled cleanup goes through, exports are only deleted by hand
+pub const AUTO_CLEANUP_CATEGORIES: [&str; 3] = ["backups", "logs", "dumps"];
+
+struct DataDir {
+    path: PathBuf,
+    fallback: Option<StorageFallback>,
+}
+
+fn get_data_dir(app: &AppHandle) -> &'static DataDir {
+    static DATA_DIR: OnceLock<DataDir> = OnceLock::new();
+    DATA_DIR.get_or_init(|| resolve_writable(app))
+}
+
+// directory holding encounters.db, settings.json and every other file the app
+// writes. older versions kept them in the resource dir, which is read-only
+// under program files and wiped on reinstall, so they are copied to the app
+// data dir on first start.
+// the old files are left in place, if the copy fails the resource dir is used
+// for this run so nothing is lost
+pub fn data_dir(app: &AppHandle) -> PathBuf {
+    get_data_dir(app).path.clone()
+}
+
+// set when the usual data dir wasn't writable and the local app data dir is
+// used instead
+pub fn fallback(app: &AppHandle) -> Option<StorageFallback> {
+    get_data_dir(app).fallback.clone()
+}
+
+// rotated logs go next to the exe unless that is read-only
+// tauri.conf.json identifier, the logger starts before there is an app handle
+// to resolve the local data dir with
+fn app_identifier() -> &'static str {
+    static IDENTIFIER: OnceLock<String> = OnceLock::new();
+    IDENTIFIER.get_or_init(|| {
+        let config: serde_json::Value = serde_json::from_str(include_str!("../../tauri.conf.json"))
+            .expect("tauri.conf.json is valid json");
+        config["tauri"]["bundle"]["identifier"]
+            .as_str()
+            .expect("tauri.conf.json has a bundle identifier")
+            .to_string()
+    })
+}
+
+pub fn log_dir() -> PathBuf {
+    static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+    LOG_DIR
+        .get_or_init(|| {
+            let mut exe_dir = std::env::current_exe().expect("Can't find path to executable");
+            exe_dir.pop();
+            if is_writable(&exe_dir) {
+                return exe_dir;
+            }
+            tauri::api::path::local_data_dir()
+                .map(|dir| dir.join(app_identifier()))
+                .filter(|dir| is_writable(dir))
+                .unwrap_or_else(std::env::temp_dir)
+        })
+        .clone()
+}
+
+// locked down machines can deny writes to both the resource and the app data
+// dir, the local app data dir is the last place tried before giving up.
+// once used it stays in use, going back to the preferred dir after it became
+// writable again would leave everything saved since behind
+fn resolve_writable(app: &AppHandle) -> DataDir {
+    let preferred = resolve(app);
+    let local = app
+        .path_resolver()
+        .app_local_data_dir()
+        .filter(|local| *local != preferred);
+    if let Some(local) = local
+        .as_ref()
+        .filter(|local| local.join(FALLBACK_MARKER_FILE).exists() && is_writable(local))
+    {
+        info!(
+            "using fallback data dir {} from an earlier run",
+            local.display()
+        );
+        return fallback_dir(&preferred, local.clone());
+    }
+    if is_writable(&preferred) {
+        return DataDir {
+            path: preferred,
+            fallback: None,
+        };
+    }
+    let Some(local) = local.filter(|local| is_writable(local)) else {
+        error!(
+            "{} is not writable and there is no fallback, saving will fail",
+            preferred.display()
+        );
+        return DataDir {
+            path: preferred,
+            fallback: None,
+        };
+    };
+
+    warn!(
+        "{} is not writable, using {}",
+        preferred.display(),
+        local.display()
+    );
+    if let Err(e) = migrate(&preferred, &local) {
+        error!("failed to copy data to {}: {}", local.display(), e);
+    }
+    let marker = preferred.to_string_lossy();
+    if let Err(e) = fs::write(local.join(FALLBACK_MARKER_FILE), marker.as_bytes()) {
+        warn!("failed to remember the fallback data dir: {}", e);
+    }
+    fallback_dir(&preferred, local)
+}
+
+fn fallback_dir(preferred: &Path, local: PathBuf) -> DataDir {
+    DataDir {
+        fallback: Some(StorageFallback {
+            preferred: preferred.to_string_lossy().to_string(),
+            path: local.to_string_lossy().to_string(),
+        }),
+        path: local,
+    }
+}
+
+// creates the directory and writes a file to it, permissions alone don't
+// show folder redirection or controlled folder access denying writes
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(WRITE_PROBE_FILE);
+    let writable = fs::create_dir_all(dir).is_ok() && fs::write(&probe, b"").is_ok();
+    fs::remove_file(&probe).ok();
+    writable
+}
+
+fn resolve(app: &AppHandle) -> PathBuf {
+    let resource_dir = app
+        .path_resolver()
+        .resource_dir()
+        .expect("could not get resource dir");
+    let Some(app_data_dir) = app.path_resolver().app_data_dir() else {
+        warn!(
+            "could not get app data dir, using {}",
+            resource_dir.display()
+        );
+        return resource_dir;
+    };
+    if app_data_dir == resource_dir {
+        return resource_dir;
+    }
+
+    match migrate(&resource_dir, &app_data_dir) {
+        Ok(()) => {
+            info!("using data dir {}", app_data_dir.display());
+            app_data_dir
+        }
+        Err(e) => {
+            error!(
+                "failed to migrate data to {}, falling back to {}: {}",
+                app_data_dir.display(),
+                resource_dir.display(),
+                e
+            );
+            resource_dir
+        }
+    }
+}
+
+fn migrate(old_dir: &Path, new_dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(new_dir)?;
+
+    let old_db = old_dir.join(DB_FILE);
+    let new_db = new_dir.join(DB_FILE);
+    if old_db.exists() && !new_db.exists() {
+        info!(
+            "migrating encounters database from {} to {}",
+            old_db.display(),
+            new_db.display()
+        );
+        // the wal goes first so the database never appears without it
+        let old_wal = old_dir.join(DB_WAL_FILE);
+        if old_wal.exists() {
+            copy_verified(&old_wal, &new_dir.join(DB_WAL_FILE))?;
+        }
+        copy_verified(&old_db, &new_db)?;
+        info!(
+            "encounters database migrated, old copy kept at {}",
+            old_db.display()
+        );
+    }
+
+    let old_settings = old_dir.join(SETTINGS_FILE);
+    let new_settings = new_dir.join(SETTINGS_FILE);
+    if old_settings.exists() && !new_settings.exists() {
+        copy_verified(&old_settings, &new_settings)?;
+        info!(
+            "settings migrated from {} to {}",
+            old_settings.display(),
+            new_settings.display()
+        );
+    }
+
+    for file in STATE_FILES {
+        let old_file = old_dir.join(file);
+        let new_file = new_dir.join(file);
+        if old_file.exists() && !new_file.exists() {
+            copy_verified(&old_file, &new_file)?;
+        }
+    }
+
+    Ok(())
+}
+
+// copies through a temporary file and only renames it once the size matches,
+// so an interrupted copy is retried on the next start
+fn copy_verified(from: &Path, to: &Path) -> io::Result<()> {
+    let mut tmp = to.as_os_str().to_owned();
+    tmp.push(".migrating");
+    let tmp = PathBuf::from(tmp);
+
+    let copied = fs::copy(from, &tmp)?;
+    let expected = fs::metadata(from)?.len();
+    if copied != expected || fs::metadata(&tmp)?.len() != expected {
+        fs::remove_file(&tmp).ok();
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "copy of {} is incomplete ({} of {} bytes)",
+                from.display(),
+                copied,
+                expected
+            ),
+        ));
+    }
+
+    fs::rename(&tmp, to)
+}
+
+// the directories managed files are looked for in
+pub struct StorageDirs {
+    pub data: PathBuf,
+    pub resource: PathBuf,
+    pub logs: PathBuf,
+}
+
+impl StorageDirs {
+    pub fn new(app: &AppHandle) -> Result<Self, String> {
+        let resource = app
+            .path_resolver()
+            .resource_dir()
+            .ok_or("could not get resource dir")?;
+        Ok(Self {
+            data: data_dir(app),
+            resource,
+            logs: log_dir(),
+        })
+    }
+
+    // the database, its wal and the settings in use, never deleted whatever
+    // category they turn up in
+    fn is_live(&self, path: &Path) -> bool {
+        [DB_FILE, DB_WAL_FILE, DB_SHM_FILE, SETTINGS_FILE]
+            .iter()
+            .any(|file| path == self.data.join(file))
+    }
+}
+
+struct ManagedFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+    protected: bool,
+}
+
+pub fn storage_breakdown(dirs: &StorageDirs) -> Vec<StorageCategory> {
+    STORAGE_CATEGORIES
+        .iter()
+        .map(|&category| {
+            let files = managed_files(dirs, category);
+            StorageCategory {
+                category: category.to_string(),
+                files: files.len(),
+                bytes: files.iter().map(|file| file.size).sum(),
+                deletable_bytes: files
+                    .iter()
+                    .filter(|file| !file.protected)
+                    .map(|file| file.size)
+                    .sum(),
+            }
+        })
+        .collect()
+}
+
+// deletes the files of the categories last modified at least older_than_days
+// ago, 0 deletes all of them. files that can't be removed, e.g. because
+// another program has them open, are skipped
+pub fn cleanup_storage(
+    dirs: &StorageDirs,
+    categories: &[String],
+    older_than_days: u32,
+) -> Result<Vec<StorageFreed>, String> {
+    if let Some(unknown) = categories
+        .iter()
+        .find(|category| !STORAGE_CATEGORIES.contains(&category.as_str()))
+    {
+        return Err(format!("unknown storage category {}", unknown));
+    }
+    let cutoff = SystemTime::now()
+        .checked_sub(Duration::from_secs(older_than_days as u64 * 24 * 60 * 60))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let mut freed = Vec::new();
+    for category in categories {
+        let mut result = StorageFreed {
+            category: category.clone(),
+            ..Default::default()
+        };
+        for file in managed_files(dirs, category) {
+            if file.protected || dirs.is_live(&file.path) || file.modified > cutoff {
+                continue;
+            }
+            match fs::remove_file(&file.path) {
+                Ok(()) => {
+                    result.files += 1;
+                    result.bytes += file.size;
+                }
+                Err(e) => warn!("failed to delete {}: {}", file.path.display(), e),
+            }
+        }
+        if result.files > 0 {
+            info!(
+                "deleted {} {} files, {} bytes",
+                result.files, category, result.bytes
+            );
+        }
+        freed.push(result);
+    }
+    Ok(freed)
+}
+
+fn managed_files(dirs: &StorageDirs, category: &str) -> Vec<ManagedFile> {
+    match category {
+        "db" => [dirs.data.join(DB_FILE)]
+            .into_iter()
+            .filter_map(|path| managed_file(path, true))
+            .collect(),
+        "wal" => [dirs.data.join(DB_WAL_FILE), dirs.data.join(DB_SHM_FILE)]
+            .into_iter()
+            .filter_map(|path| managed_file(path, true))
+            .collect(),
+        "backups" => {
+            let mut files = files_in(&dirs.data, |name| {
+                name == STAGED_RESTORE_FILE
+                    || BACKUP_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+            });
+            for file in files.iter_mut() {
+                file.protected = file.path.ends_with(STAGED_RESTORE_FILE);
+            }
+            // the copies migrate kept in the resource dir, once the data dir
+            // has its own database
+            if dirs.resource != dirs.data && dirs.data.join(DB_FILE).exists() {
+                files.extend(
+                    [DB_FILE, DB_WAL_FILE, SETTINGS_FILE]
+                        .iter()
+                        .filter_map(|file| managed_file(dirs.resource.join(file), false)),
+                );
+            }
+            files
+        }
+        "logs" => {
+            let mut files = files_in(&dirs.logs, |name| {
+                name.starts_with(LOG_BASENAME) && name.ends_with(".log")
+            });
+            // the file being written to, the rotated ones carry a timestamp
+            for file in files.iter_mut() {
+                file.protected = file
+                    .path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().contains("CURRENT"));
+            }
+            files
+        }
+        "dumps" => {
+            let mut files = files_in(&dirs.data.join(packet_dump::DUMP_DIR), |_| true);
+            // dumps recorded before they moved to the data dir
+            if dirs.resource != dirs.data {
+                files.extend(files_in(&dirs.resource.join(packet_dump::DUMP_DIR), |_| {
+                    true
+                }));
+            }
+            for file in files.iter_mut() {
+                file.protected = packet_dump::in_use(&file.path);
+            }
+            files
+        }
+        "exports" => files_in(&dirs.data.join(EXPORT_DIR), |_| true),
+        _ => Vec::new(),
+    }
+}
+
+fn files_in(dir: &Path, matches: impl Fn(&str) -> bool) -> Vec<ManagedFile> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| matches(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| managed_file(entry.path(), false))
+        .collect()
+}
+
+fn managed_file(path: PathBuf, protected: bool) -> Option<ManagedFile> {
+    let metadata = fs::metadata(&path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    Some(ManagedFile {
+        size: metadata.len(),
+        modified: metadata.modified().unwrap_or_else(|_| SystemTime::now()),
+        path,
+        protected,
+    })
+}
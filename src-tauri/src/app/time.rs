@@ -0,0 +1,184 @@
+use chrono::{Datelike, Duration, Local, LocalResult, NaiveDate, TimeZone};
+
+// timestamps below this are seconds, no encounter is older than 1973 in ms
+// and no encounter is newer than the year 5138 in seconds
+pub const SECONDS_CUTOFF: i64 = 100_000_000_000;
+
+// some old rows were written in seconds instead of milliseconds
+pub fn normalize_epoch_ms(timestamp: i64) -> i64 {
+    if timestamp > 0 && timestamp < SECONDS_CUTOFF {
+        timestamp * 1000
+    } else {
+        timestamp
+    }
+}
+
+// local calendar day an encounter happened on. an instant always maps to a
+// single local time, so this is only ambiguous for out of range values
+pub fn local_date(epoch_ms: i64) -> NaiveDate {
+    date_in(&Local, epoch_ms)
+}
+
+fn date_in<Tz: TimeZone>(tz: &Tz, epoch_ms: i64) -> NaiveDate {
+    match tz.timestamp_millis_opt(normalize_epoch_ms(epoch_ms)) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.date_naive(),
+        LocalResult::None => NaiveDate::default(),
+    }
+}
+
+// first instant of a local day. midnight can be skipped by a dst change in
+// some zones, then the day starts at the first hour that exists
+pub fn local_day_start_ms(date: NaiveDate) -> i64 {
+    day_start_in(&Local, date)
+}
+
+fn day_start_in<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> i64 {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    for hour in 0..24 {
+        match tz.from_local_datetime(&(midnight + Duration::hours(hour))) {
+            LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => {
+                return time.timestamp_millis()
+            }
+            LocalResult::None => continue,
+        }
+    }
+    midnight.and_utc().timestamp_millis()
+}
+
+// [start, end) of a local day in epoch ms, 23 or 25 hours long around dst changes
+pub fn local_day_bounds(date: NaiveDate) -> (i64, i64) {
+    day_bounds_in(&Local, date)
+}
+
+fn day_bounds_in<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> (i64, i64) {
+    let next = date.succ_opt().unwrap_or(date);
+    (day_start_in(tz, date), day_start_in(tz, next))
+}
+
+// monday of the week containing date
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDateTime};
+
+    const HOUR_MS: i64 = 3600 * 1000;
+
+    // a zone that moves from one offset to another at a single utc instant,
+    // enough to stand in for a dst change without depending on the os zone
+    #[derive(Clone)]
+    struct Shift {
+        at: NaiveDateTime,
+        before: FixedOffset,
+        after: FixedOffset,
+    }
+
+    impl Shift {
+        // moves from utc+before to utc+after when the local clock reads `local`
+        fn new(local: NaiveDateTime, before: i32, after: i32) -> Shift {
+            Shift {
+                at: local - Duration::hours(before as i64),
+                before: FixedOffset::east_opt(before * 3600).unwrap(),
+                after: FixedOffset::east_opt(after * 3600).unwrap(),
+            }
+        }
+    }
+
+    impl TimeZone for Shift {
+        type Offset = FixedOffset;
+
+        fn from_offset(offset: &FixedOffset) -> Shift {
+            Shift {
+                at: NaiveDateTime::MIN,
+                before: *offset,
+                after: *offset,
+            }
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let offsets: Vec<FixedOffset> = [self.before, self.after]
+                .into_iter()
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect();
+            match offsets[..] {
+                [offset] => LocalResult::Single(offset),
+                [first, second] if first != second => LocalResult::Ambiguous(first, second),
+                [offset, _] => LocalResult::Single(offset),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            if *utc < self.at {
+                self.before
+            } else {
+                self.after
+            }
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    #[test]
+    fn skipped_midnight_starts_the_day_an_hour_later() {
+        let tz = Shift::new(date(31).and_hms_opt(0, 0, 0).unwrap(), 1, 2);
+        let start = day_start_in(&tz, date(31));
+        // 00:00 doesn't exist, 01:00 in utc+2 is 23:00 utc the day before
+        let expected = date(30).and_hms_opt(23, 0, 0).unwrap().and_utc();
+        assert_eq!(start, expected.timestamp_millis());
+
+        let (start, end) = day_bounds_in(&tz, date(31));
+        assert_eq!(end - start, 23 * HOUR_MS);
+        let (start, end) = day_bounds_in(&tz, date(30));
+        assert_eq!(end - start, 24 * HOUR_MS);
+    }
+
+    #[test]
+    fn repeated_midnight_starts_the_day_at_the_first_one() {
+        // 01:00 goes back to 00:00
+        let tz = Shift::new(date(31).and_hms_opt(1, 0, 0).unwrap(), 1, 0);
+        let start = day_start_in(&tz, date(31));
+        // the first 00:00 is still in utc+1
+        let expected = date(30).and_hms_opt(23, 0, 0).unwrap().and_utc();
+        assert_eq!(start, expected.timestamp_millis());
+
+        let (start, end) = day_bounds_in(&tz, date(30));
+        assert_eq!(end - start, 24 * HOUR_MS);
+        let (start, end) = day_bounds_in(&tz, date(31));
+        assert_eq!(end - start, 25 * HOUR_MS);
+    }
+
+    #[test]
+    fn instants_around_a_change_keep_their_local_day() {
+        let tz = Shift::new(date(31).and_hms_opt(0, 0, 0).unwrap(), 1, 2);
+        let (start, end) = day_bounds_in(&tz, date(31));
+        assert_eq!(date_in(&tz, start - 1), date(30));
+        assert_eq!(date_in(&tz, start), date(31));
+        assert_eq!(date_in(&tz, end - 1), date(31));
+        assert_eq!(
+            date_in(&tz, end),
+            NaiveDate::from_ymd_opt(2024, 4, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn seconds_are_read_as_ms() {
+        let tz = Shift::new(date(31).and_hms_opt(0, 0, 0).unwrap(), 1, 2);
+        let (start, _) = day_bounds_in(&tz, date(30));
+        assert_eq!(date_in(&tz, start / 1000 + 60), date(30));
+        assert_eq!(normalize_epoch_ms(start), start);
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::Utc;
+use log::{info, warn};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Icon, Window};
+
+use crate::app;
+use crate::parser::capture_status::get_capture_status;
+
+// no packets for this long usually means the wrong adapter or a dead capture
+const PACKET_WATCHDOG_TIMEOUT_MS: i64 = 60_000;
+// status is checked at this rate, which also caps how often the icon is swapped
+const STATUS_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrayStatus {
+    Capturing,
+    Paused,
+    NoPackets,
+    Stopped,
+}
+
+impl TrayStatus {
+    fn icon(&self) -> Icon {
+        let bytes: &[u8] = match self {
+            TrayStatus::Capturing => include_bytes!("../../icons/icon.png"),
+            TrayStatus::Paused => include_bytes!("../../icons/icon-paused.png"),
+            TrayStatus::NoPackets | TrayStatus::Stopped => {
+                include_bytes!("../../icons/icon-error.png")
+            }
+        };
+        Icon::Raw(bytes.to_vec())
+    }
+
+    fn tooltip(&self) -> &'static str {
+        match self {
+            TrayStatus::Capturing => "LOA Logs - capturing",
+            TrayStatus::Paused => "LOA Logs - paused",
+            TrayStatus::NoPackets => {
+                "LOA Logs - no packets received, check game and network adapter"
+            }
+            TrayStatus::Stopped => "LOA Logs - capture stopped, check the logs",
+        }
+    }
+}
+
+fn current_status() -> TrayStatus {
+    let status = get_capture_status();
+    if !status.capturing {
+        TrayStatus::Stopped
+    } else if status.paused {
+        TrayStatus::Paused
+    } else if Utc::now().timestamp_millis() - status.last_packet > PACKET_WATCHDOG_TIMEOUT_MS {
+        TrayStatus::NoPackets
+    } else {
+        TrayStatus::Capturing
+    }
+}
+
+// keeps the tray icon and tooltip in sync with the parser, only touching
+// the tray when the status actually changes
+pub fn start_status_watcher(app: AppHandle) {
+    thread::spawn(move || {
+        // give the capture a moment to start before reporting it as stopped
+        thread::sleep(STATUS_CHECK_INTERVAL);
+        let mut shown = TrayStatus::Capturing;
+        loop {
+            let status = current_status();
+            if status != shown {
+                info!("tray status changed: {:?}", status);
+                let tray = app.tray_handle();
+                if let Err(e) = tray.set_icon(status.icon()) {
+                    warn!("failed to set tray icon: {}", e);
+                }
+                tray.set_tooltip(status.tooltip()).ok();
+                shown = status;
+            }
+            thread::sleep(STATUS_CHECK_INTERVAL);
+        }
+    });
+}
+
+// clickthrough is reapplied since a hidden window can lose it
+pub fn show_meter(meter: &Window) {
+    meter.show().ok();
+    meter.unminimize().ok();
+    meter.set_ignore_cursor_events(app::is_clickthrough()).ok();
+}
+
+pub fn hide_meter(meter: &Window) {
+    meter.hide().ok();
+}
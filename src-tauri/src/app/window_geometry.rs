@@ -0,0 +1,124 @@
+use crate::app::storage;
+use hashbrown::HashMap;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager, PhysicalSize, Size, Window};
+
+const GEOMETRY_FILE: &str = "window-geometry.json";
+
+// monitor and scale factor a window was on when its state was saved
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowGeometry {
+    monitor: Option<String>,
+    scale_factor: f64,
+}
+
+// last known scale factor per window label
+fn get_scale_factors() -> &'static Mutex<HashMap<String, f64>> {
+    static SCALE_FACTORS: OnceLock<Mutex<HashMap<String, f64>>> = OnceLock::new();
+    SCALE_FACTORS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn geometry_path(app: &AppHandle) -> PathBuf {
+    storage::data_dir(app).join(GEOMETRY_FILE)
+}
+
+// called whenever the window-state plugin's file is saved
+pub fn save(app: &AppHandle) {
+    let geometry: HashMap<String, WindowGeometry> = app
+        .windows()
+        .into_iter()
+        .filter_map(|(label, window)| {
+            let monitor = window.current_monitor().ok().flatten()?;
+            Some((
+                label,
+                WindowGeometry {
+                    monitor: monitor.name().cloned(),
+                    scale_factor: monitor.scale_factor(),
+                },
+            ))
+        })
+        .collect();
+
+    match serde_json::to_string(&geometry) {
+        Ok(json) => {
+            if let Err(e) = fs::write(geometry_path(app), json) {
+                warn!("failed to save window geometry: {}", e);
+            }
+        }
+        Err(e) => warn!("failed to serialize window geometry: {}", e),
+    }
+}
+
+// the plugin restores the size saved on the old monitor, which comes out tiny or
+// huge when the window reappears on a monitor with a different scale factor.
+// rescales it so the window keeps the physical size it was saved with
+pub fn correct_restored(window: &Window) {
+    let Ok(scale_factor) = window.scale_factor() else {
+        return;
+    };
+    get_scale_factors()
+        .lock()
+        .unwrap()
+        .insert(window.label().to_string(), scale_factor);
+
+    let Some(saved) = fs::read_to_string(geometry_path(&window.app_handle()))
+        .ok()
+        .and_then(|json| serde_json::from_str::<HashMap<String, WindowGeometry>>(&json).ok())
+        .and_then(|mut geometry| geometry.remove(window.label()))
+    else {
+        return;
+    };
+    if saved.scale_factor <= 0.0 || (saved.scale_factor - scale_factor).abs() < 0.01 {
+        return;
+    }
+
+    let Ok(size) = window.inner_size() else {
+        return;
+    };
+    let ratio = saved.scale_factor / scale_factor;
+    info!(
+        "{} window moved from {:?} ({}x) to a {}x monitor, rescaling",
+        window.label(),
+        saved.monitor,
+        saved.scale_factor,
+        scale_factor
+    );
+    resize(window, size, ratio);
+}
+
+// the os grows the window to keep its logical size when the dpi changes,
+// undo that so the window keeps its physical footprint
+pub fn on_scale_factor_changed(
+    window: &Window,
+    scale_factor: f64,
+    new_inner_size: PhysicalSize<u32>,
+) {
+    let previous = get_scale_factors()
+        .lock()
+        .unwrap()
+        .insert(window.label().to_string(), scale_factor);
+    let Some(previous) = previous else {
+        return;
+    };
+    if (previous - scale_factor).abs() < 0.01 {
+        return;
+    }
+
+    resize(window, new_inner_size, previous / scale_factor);
+    save(&window.app_handle());
+}
+
+fn resize(window: &Window, size: PhysicalSize<u32>, ratio: f64) {
+    let size = PhysicalSize {
+        width: (size.width as f64 * ratio).round().max(1.0) as u32,
+        height: (size.height as f64 * ratio).round().max(1.0) as u32,
+    };
+    if let Err(e) = window.set_size(Size::Physical(size)) {
+        warn!("failed to resize {} window: {}", window.label(), e);
+    }
+}
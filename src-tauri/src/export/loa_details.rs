@@ -0,0 +1,591 @@
+use crate::app::time::normalize_epoch_ms;
+use crate::parser::models::{
+    DamageStats, Encounter, EncounterDamageStats, EncounterEntity, EntityType, Skill, SkillStats,
+    StatusEffect, StatusEffectTarget, SKILL_DATA,
+};
+use crate::parser::utils::get_class_from_id;
+use hashbrown::HashMap;
+use serde_json::{json, Map, Value};
+
+pub const FORMAT: &str = "loa-details";
+
+// conversions that can't be represented in the loa-details session format,
+// written into the meta block of every export
+const LOSSY_CONVERSIONS: &[&str] = &[
+    "healing is not tracked, healingDone is always 0",
+    "per-hit and per-cast logs, tripods, gems and ark passive data are dropped",
+    "hitsBuffedBy/hitsDebuffedBy are not tracked, only damage dealt under buffs is exported",
+    "projectile and summon entities are exported as UNKNOWN",
+    "lastUpdate of every entity is the encounter's last combat packet",
+    "startedOn is the fight start, time before the first hit is not recorded",
+];
+
+// maps a stored encounter onto a loa-details session, timestamps are already ms
+pub fn to_session(encounter: &Encounter, meter_version: &str) -> Value {
+    let entities: Map<String, Value> = encounter
+        .entities
+        .values()
+        .map(|entity| {
+            let session = entity_to_session(entity, encounter.last_combat_packet);
+            (entity.id.to_string(), session)
+        })
+        .collect();
+
+    let stats = &encounter.encounter_damage_stats;
+    json!({
+        "startedOn": encounter.fight_start,
+        "lastCombatPacket": encounter.last_combat_packet,
+        "fightStartedOn": encounter.fight_start,
+        "localPlayer": encounter.local_player,
+        "currentBoss": encounter
+            .entities
+            .get(&encounter.current_boss_name)
+            .map(|boss| entity_to_session(boss, encounter.last_combat_packet)),
+        "entities": entities,
+        "damageStatistics": {
+            "totalDamageDealt": stats.total_damage_dealt,
+            "topDamageDealt": stats.top_damage_dealt,
+            "totalDamageTaken": stats.total_damage_taken,
+            "topDamageTaken": stats.top_damage_taken,
+            "totalHealingDone": 0,
+            "topHealingDone": 0,
+            "totalShieldDone": stats.total_shielding,
+            "topShieldDone": encounter
+                .entities
+                .values()
+                .map(|e| e.damage_stats.shields_given)
+                .max()
+                .unwrap_or_default(),
+            "totalEffectiveShieldingDone": stats.total_effective_shielding,
+            "buffs": status_effects_to_session(&stats.buffs),
+            "debuffs": status_effects_to_session(&stats.debuffs),
+            "appliedShieldingBuffs": status_effects_to_session(&stats.applied_shield_buffs),
+        },
+        "meta": {
+            "exportedBy": format!("LOA Logs v{}", meter_version),
+            "format": FORMAT,
+            "boss": encounter.current_boss_name,
+            "difficulty": encounter.difficulty,
+            "cleared": encounter.cleared,
+            "lossy": LOSSY_CONVERSIONS,
+        },
+    })
+}
+
+fn entity_to_session(entity: &EncounterEntity, last_update: i64) -> Value {
+    let stats = &entity.damage_stats;
+    let skills: Map<String, Value> = entity
+        .skills
+        .values()
+        .map(|skill| (skill.id.to_string(), skill_to_session(skill)))
+        .collect();
+
+    json!({
+        "lastUpdate": last_update,
+        "id": entity.id.to_string(),
+        "npcId": entity.npc_id,
+        "name": entity.name,
+        "entityType": entity_type_to_session(&entity.entity_type),
+        "isPlayer": entity.entity_type == EntityType::PLAYER,
+        "classId": entity.class_id,
+        "class": entity.class,
+        "gearScore": entity.gear_score,
+        "isDead": entity.is_dead,
+        "deathTime": stats.death_time,
+        "deaths": stats.deaths,
+        "currentHp": entity.current_hp,
+        "maxHp": entity.max_hp,
+        "skills": skills,
+        "damageInfo": {
+            "damageDealt": stats.damage_dealt,
+            "rdpsDamageReceived": entity.skills.values().map(|s| s.rdps_damage_received).sum::<i64>(),
+            "rdpsDamageReceivedSupp": entity.skills.values().map(|s| s.rdps_damage_received_support).sum::<i64>(),
+            "rdpsDamageGiven": entity.skills.values().map(|s| s.rdps_damage_given).sum::<i64>(),
+            "damageDealtBuffedBySupport": stats.buffed_by_support,
+            "damageDealtDebuffedBySupport": stats.debuffed_by_support,
+        },
+        "damageTaken": stats.damage_taken,
+        "hits": {
+            "casts": entity.skill_stats.casts,
+            "total": entity.skill_stats.hits,
+            "crit": entity.skill_stats.crits,
+            "backAttack": entity.skill_stats.back_attacks,
+            "frontAttack": entity.skill_stats.front_attacks,
+            "counter": entity.skill_stats.counters,
+        },
+        "damageDealtBuffedBy": stats.buffed_by,
+        "damageDealtDebuffedBy": stats.debuffed_by,
+        "healingDone": 0,
+        "shieldDone": stats.shields_given,
+        "shieldReceived": stats.shields_received,
+        "damagePreventedByShield": stats.damage_absorbed,
+        "damagePreventedWithShieldOnOthers": stats.damage_absorbed_on_others,
+        "shieldDoneBy": stats.shields_given_by,
+        "shieldReceivedBy": stats.shields_received_by,
+        "damagePreventedByShieldBy": stats.damage_absorbed_by,
+        "damagePreventedWithShieldOnOthersBy": stats.damage_absorbed_on_others_by,
+    })
+}
+
+fn skill_to_session(skill: &Skill) -> Value {
+    json!({
+        "id": skill.id,
+        "name": skill.name,
+        "icon": skill.icon,
+        "maxDamage": skill.max_damage,
+        "damageInfo": {
+            "damageDealt": skill.total_damage,
+            "rdpsDamageReceived": skill.rdps_damage_received,
+            "rdpsDamageReceivedSupp": skill.rdps_damage_received_support,
+            "rdpsDamageGiven": skill.rdps_damage_given,
+            "damageDealtBuffedBySupport": skill.buffed_by_support,
+            "damageDealtDebuffedBySupport": skill.debuffed_by_support,
+        },
+        "hits": {
+            "casts": skill.casts,
+            "total": skill.hits,
+            "crit": skill.crits,
+            "backAttack": skill.back_attacks,
+            "frontAttack": skill.front_attacks,
+            "counter": 0,
+        },
+        "damageDealtBuffedBy": skill.buffed_by,
+        "damageDealtDebuffedBy": skill.debuffed_by,
+    })
+}
+
+fn status_effects_to_session(status_effects: &HashMap<u32, StatusEffect>) -> Value {
+    let status_effects: Map<String, Value> = status_effects
+        .iter()
+        .map(|(id, effect)| {
+            (
+                id.to_string(),
+                json!({
+                    "target": format!("{:?}", effect.target),
+                    "category": effect.category,
+                    "buffcategory": effect.buff_category,
+                    "bufftype": effect.buff_type,
+                    "uniquegroup": effect.unique_group,
+                    "source": effect.source,
+                }),
+            )
+        })
+        .collect();
+    Value::Object(status_effects)
+}
+
+// loa-details numbers its entity types without projectiles or summons
+fn entity_type_to_session(entity_type: &EntityType) -> i32 {
+    match entity_type {
+        EntityType::MONSTER => 1,
+        EntityType::BOSS => 2,
+        EntityType::GUARDIAN => 3,
+        EntityType::PLAYER => 4,
+        EntityType::NPC => 5,
+        EntityType::ESTHER => 6,
+        _ => 0,
+    }
+}
+
+// maps a loa-details session back onto an encounter, the inverse of to_session.
+// returns None when the session has no fight or no entities worth keeping
+pub fn from_session(session: &Value) -> Option<Encounter> {
+    // older sessions can have their timestamps in seconds
+    let fight_start = normalize_epoch_ms(int(session, "fightStartedOn"));
+    let last_combat_packet = normalize_epoch_ms(int(session, "lastCombatPacket"));
+    if fight_start <= 0 || last_combat_packet <= fight_start {
+        return None;
+    }
+
+    let entities: HashMap<String, EncounterEntity> = session
+        .get("entities")?
+        .as_object()?
+        .values()
+        .map(entity_from_session)
+        .map(|entity| (entity.name.clone(), entity))
+        .collect();
+    if entities.is_empty() {
+        return None;
+    }
+
+    let current_boss_name = session
+        .get("currentBoss")
+        .map(|boss| string(boss, "name"))
+        .unwrap_or_default();
+    let meta = session.get("meta");
+    let stats = session.get("damageStatistics").unwrap_or(&Value::Null);
+
+    Some(Encounter {
+        last_combat_packet,
+        fight_start,
+        local_player: string(session, "localPlayer"),
+        current_boss: entities.get(&current_boss_name).cloned(),
+        current_boss_name,
+        entities,
+        encounter_damage_stats: EncounterDamageStats {
+            total_damage_dealt: int(stats, "totalDamageDealt"),
+            top_damage_dealt: int(stats, "topDamageDealt"),
+            total_damage_taken: int(stats, "totalDamageTaken"),
+            top_damage_taken: int(stats, "topDamageTaken"),
+            total_shielding: int(stats, "totalShieldDone") as u64,
+            total_effective_shielding: int(stats, "totalEffectiveShieldingDone") as u64,
+            buffs: status_effects_from_session(stats.get("buffs")),
+            debuffs: status_effects_from_session(stats.get("debuffs")),
+            applied_shield_buffs: status_effects_from_session(stats.get("appliedShieldingBuffs")),
+            ..Default::default()
+        },
+        duration: last_combat_packet - fight_start,
+        difficulty: meta
+            .and_then(|meta| meta.get("difficulty"))
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        cleared: meta
+            .and_then(|meta| meta.get("cleared"))
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+// unknown class and skill ids keep their id with a placeholder name
+// so the damage still shows up instead of being dropped
+fn entity_from_session(entity: &Value) -> EncounterEntity {
+    let class_id = int(entity, "classId") as u32;
+    let mut class = get_class_from_id(&class_id);
+    if class == "Unknown" {
+        class = string(entity, "class");
+        if class.is_empty() {
+            class = format!("Unknown Class {}", class_id);
+        }
+    }
+
+    let skills: HashMap<u32, Skill> = entity
+        .get("skills")
+        .and_then(Value::as_object)
+        .map(|skills| {
+            skills
+                .values()
+                .map(skill_from_session)
+                .map(|skill| (skill.id, skill))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let damage_info = entity.get("damageInfo").unwrap_or(&Value::Null);
+    let hits = entity.get("hits").unwrap_or(&Value::Null);
+
+    EncounterEntity {
+        id: string(entity, "id").parse().unwrap_or_default(),
+        npc_id: int(entity, "npcId") as u32,
+        name: string(entity, "name"),
+        entity_type: entity_type_from_session(int(entity, "entityType")),
+        class_id,
+        class,
+        gear_score: entity
+            .get("gearScore")
+            .and_then(Value::as_f64)
+            .unwrap_or_default() as f32,
+        current_hp: int(entity, "currentHp"),
+        max_hp: int(entity, "maxHp"),
+        is_dead: entity
+            .get("isDead")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        skills,
+        damage_stats: DamageStats {
+            damage_dealt: int(damage_info, "damageDealt"),
+            damage_taken: int(entity, "damageTaken"),
+            buffed_by: id_map(entity.get("damageDealtBuffedBy")),
+            debuffed_by: id_map(entity.get("damageDealtDebuffedBy")),
+            buffed_by_support: int(damage_info, "damageDealtBuffedBySupport"),
+            debuffed_by_support: int(damage_info, "damageDealtDebuffedBySupport"),
+            shields_given: int(entity, "shieldDone") as u64,
+            shields_received: int(entity, "shieldReceived") as u64,
+            damage_absorbed: int(entity, "damagePreventedByShield") as u64,
+            damage_absorbed_on_others: int(entity, "damagePreventedWithShieldOnOthers") as u64,
+            shields_given_by: id_map(entity.get("shieldDoneBy")),
+            shields_received_by: id_map(entity.get("shieldReceivedBy")),
+            damage_absorbed_by: id_map(entity.get("damagePreventedByShieldBy")),
+            damage_absorbed_on_others_by: id_map(entity.get("damagePreventedWithShieldOnOthersBy")),
+            deaths: int(entity, "deaths"),
+            death_time: int(entity, "deathTime"),
+            rdps_damage_received: int(damage_info, "rdpsDamageReceived"),
+            rdps_damage_received_support: int(damage_info, "rdpsDamageReceivedSupp"),
+            rdps_damage_given: int(damage_info, "rdpsDamageGiven"),
+            ..Default::default()
+        },
+        skill_stats: SkillStats {
+            casts: int(hits, "casts"),
+            hits: int(hits, "total"),
+            crits: int(hits, "crit"),
+            back_attacks: int(hits, "backAttack"),
+            front_attacks: int(hits, "frontAttack"),
+            counters: int(hits, "counter"),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn skill_from_session(skill: &Value) -> Skill {
+    let id = int(skill, "id") as u32;
+    let mut name = string(skill, "name");
+    let mut icon = string(skill, "icon");
+    if let Some(data) = SKILL_DATA.get(&id) {
+        if name.is_empty() {
+            name = data.name.clone().unwrap_or_default();
+        }
+        if icon.is_empty() {
+            icon = data.icon.clone().unwrap_or_default();
+        }
+    }
+    if name.is_empty() {
+        name = format!("Unknown Skill {}", id);
+    }
+
+    let damage_info = skill.get("damageInfo").unwrap_or(&Value::Null);
+    let hits = skill.get("hits").unwrap_or(&Value::Null);
+
+    Skill {
+        id,
+        name,
+        icon,
+        total_damage: int(damage_info, "damageDealt"),
+        max_damage: int(skill, "maxDamage"),
+        buffed_by: id_map(skill.get("damageDealtBuffedBy")),
+        debuffed_by: id_map(skill.get("damageDealtDebuffedBy")),
+        buffed_by_support: int(damage_info, "damageDealtBuffedBySupport"),
+        debuffed_by_support: int(damage_info, "damageDealtDebuffedBySupport"),
+        casts: int(hits, "casts"),
+        hits: int(hits, "total"),
+        crits: int(hits, "crit"),
+        back_attacks: int(hits, "backAttack"),
+        front_attacks: int(hits, "frontAttack"),
+        rdps_damage_received: int(damage_info, "rdpsDamageReceived"),
+        rdps_damage_received_support: int(damage_info, "rdpsDamageReceivedSupp"),
+        rdps_damage_given: int(damage_info, "rdpsDamageGiven"),
+        ..Default::default()
+    }
+}
+
+fn status_effects_from_session(status_effects: Option<&Value>) -> HashMap<u32, StatusEffect> {
+    let Some(status_effects) = status_effects.and_then(Value::as_object) else {
+        return HashMap::new();
+    };
+
+    status_effects
+        .iter()
+        .filter_map(|(id, effect)| {
+            let id = id.parse().ok()?;
+            let target = match effect.get("target").and_then(Value::as_str) {
+                Some("PARTY") => StatusEffectTarget::PARTY,
+                Some("SELF") => StatusEffectTarget::SELF,
+                _ => StatusEffectTarget::OTHER,
+            };
+            let effect = StatusEffect {
+                target,
+                category: string(effect, "category"),
+                buff_category: string(effect, "buffcategory"),
+                buff_type: int(effect, "bufftype") as u32,
+                unique_group: int(effect, "uniquegroup") as u32,
+                source: effect
+                    .get("source")
+                    .and_then(|source| serde_json::from_value(source.clone()).ok())
+                    .unwrap_or_default(),
+            };
+            Some((id, effect))
+        })
+        .collect()
+}
+
+fn entity_type_from_session(entity_type: i64) -> EntityType {
+    match entity_type {
+        1 => EntityType::MONSTER,
+        2 => EntityType::BOSS,
+        3 => EntityType::GUARDIAN,
+        4 => EntityType::PLAYER,
+        5 => EntityType::NPC,
+        6 => EntityType::ESTHER,
+        _ => EntityType::UNKNOWN,
+    }
+}
+
+fn id_map<T: TryFrom<i64> + Default>(map: Option<&Value>) -> HashMap<u32, T> {
+    map.and_then(Value::as_object)
+        .map(|map| {
+            map.iter()
+                .filter_map(|(id, value)| {
+                    let value = T::try_from(value.as_i64()?).unwrap_or_default();
+                    Some((id.parse().ok()?, value))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn int(value: &Value, key: &str) -> i64 {
+    value
+        .get(key)
+        .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)))
+        .unwrap_or_default()
+}
+
+fn string(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encounter() -> Encounter {
+        let player_skill = Skill {
+            id: 16140,
+            name: "Earthquake Chain".to_string(),
+            icon: "bk_skill_01_1.png".to_string(),
+            total_damage: 1_200_000,
+            max_damage: 300_000,
+            casts: 4,
+            hits: 8,
+            crits: 5,
+            back_attacks: 3,
+            front_attacks: 1,
+            ..Default::default()
+        };
+        let player = EncounterEntity {
+            id: 1001,
+            name: "Player".to_string(),
+            entity_type: EntityType::PLAYER,
+            class_id: 102,
+            class: get_class_from_id(&102),
+            gear_score: 1660.5,
+            current_hp: 200_000,
+            max_hp: 250_000,
+            skills: HashMap::from([(player_skill.id, player_skill)]),
+            damage_stats: DamageStats {
+                damage_dealt: 1_200_000,
+                damage_taken: 50_000,
+                deaths: 1,
+                death_time: 1_700_000_030_000,
+                buffed_by: HashMap::from([(211_606, 400_000)]),
+                shields_received: 20_000,
+                ..Default::default()
+            },
+            skill_stats: SkillStats {
+                casts: 4,
+                hits: 8,
+                crits: 5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let boss = EncounterEntity {
+            id: 2002,
+            npc_id: 480_010,
+            name: "Boss".to_string(),
+            entity_type: EntityType::BOSS,
+            current_hp: 0,
+            max_hp: 10_000_000,
+            is_dead: true,
+            damage_stats: DamageStats {
+                damage_taken: 1_200_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        Encounter {
+            fight_start: 1_700_000_000_000,
+            last_combat_packet: 1_700_000_060_000,
+            duration: 60_000,
+            local_player: "Player".to_string(),
+            current_boss_name: "Boss".to_string(),
+            entities: HashMap::from([(player.name.clone(), player), (boss.name.clone(), boss)]),
+            encounter_damage_stats: EncounterDamageStats {
+                total_damage_dealt: 1_200_000,
+                top_damage_dealt: 1_200_000,
+                total_damage_taken: 50_000,
+                top_damage_taken: 50_000,
+                ..Default::default()
+            },
+            difficulty: Some("Hard".to_string()),
+            cleared: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn session_round_trip_keeps_the_encounter() {
+        let original = encounter();
+        let session = to_session(&original, "1.0.0");
+        let restored = from_session(&session).expect("session should convert back");
+
+        assert_eq!(restored.fight_start, original.fight_start);
+        assert_eq!(restored.last_combat_packet, original.last_combat_packet);
+        assert_eq!(restored.duration, original.duration);
+        assert_eq!(restored.local_player, original.local_player);
+        assert_eq!(restored.current_boss_name, original.current_boss_name);
+        assert_eq!(restored.difficulty, original.difficulty);
+        assert_eq!(restored.cleared, original.cleared);
+        assert_eq!(
+            restored.encounter_damage_stats.total_damage_dealt,
+            original.encounter_damage_stats.total_damage_dealt
+        );
+        assert_eq!(restored.entities.len(), original.entities.len());
+        assert_eq!(
+            restored.current_boss.as_ref().map(|boss| boss.npc_id),
+            Some(480_010)
+        );
+
+        let player = &restored.entities["Player"];
+        let expected = &original.entities["Player"];
+        assert_eq!(player.id, expected.id);
+        assert_eq!(player.entity_type, EntityType::PLAYER);
+        assert_eq!(player.class, expected.class);
+        assert_eq!(player.gear_score, expected.gear_score);
+        assert_eq!(player.max_hp, expected.max_hp);
+        assert_eq!(player.damage_stats.damage_dealt, 1_200_000);
+        assert_eq!(player.damage_stats.deaths, 1);
+        assert_eq!(player.damage_stats.death_time, 1_700_000_030_000);
+        assert_eq!(player.damage_stats.buffed_by.get(&211_606), Some(&400_000));
+        assert_eq!(player.damage_stats.shields_received, 20_000);
+        assert_eq!(player.skill_stats.crits, 5);
+
+        let skill = &player.skills[&16140];
+        assert_eq!(skill.name, "Earthquake Chain");
+        assert_eq!(skill.icon, "bk_skill_01_1.png");
+        assert_eq!(skill.total_damage, 1_200_000);
+        assert_eq!(skill.max_damage, 300_000);
+        assert_eq!(skill.back_attacks, 3);
+
+        let boss = &restored.entities["Boss"];
+        assert_eq!(boss.entity_type, EntityType::BOSS);
+        assert!(boss.is_dead);
+    }
+
+    #[test]
+    fn session_in_seconds_is_read_as_ms() {
+        let mut session = to_session(&encounter(), "1.0.0");
+        session["fightStartedOn"] = json!(1_700_000_000);
+        session["lastCombatPacket"] = json!(1_700_000_060);
+
+        let restored = from_session(&session).unwrap();
+        assert_eq!(restored.fight_start, 1_700_000_000_000);
+        assert_eq!(restored.duration, 60_000);
+    }
+
+    #[test]
+    fn session_without_a_fight_is_skipped() {
+        let mut session = to_session(&encounter(), "1.0.0");
+        session["lastCombatPacket"] = session["fightStartedOn"].clone();
+        assert!(from_session(&session).is_none());
+
+        let mut session = to_session(&encounter(), "1.0.0");
+        session["entities"] = json!({});
+        assert!(from_session(&session).is_none());
+    }
+}
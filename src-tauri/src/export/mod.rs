@@ -0,0 +1,3 @@
+pub mod loa_details;
+pub mod native;
+pub mod share;
@@ -0,0 +1,146 @@
+use crate::parser::models::{Encounter, ExportedEncounter, ParserMetrics, EXPORT_SCHEMA_VERSION};
+use serde_json::{json, Value};
+
+// link opens the encounter on the pc it was exported from
+pub fn to_json(
+    encounter: Encounter,
+    meter_version: &str,
+    link: Option<String>,
+) -> Result<String, String> {
+    let export = ExportedEncounter {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        meter_version: meter_version.to_string(),
+        link,
+        encounter,
+        parser_metrics: None,
+    };
+    serde_json::to_string(&export).map_err(|e| e.to_string())
+}
+
+// an unfinished fight for a bug report, importable like any other export
+pub fn to_live_dump(
+    mut encounter: Encounter,
+    meter_version: &str,
+    metrics: ParserMetrics,
+) -> Result<String, String> {
+    encounter
+        .encounter_damage_stats
+        .misc
+        .get_or_insert_with(Default::default)
+        .live_dump = Some(true);
+    let export = ExportedEncounter {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        meter_version: meter_version.to_string(),
+        link: None,
+        encounter,
+        parser_metrics: Some(metrics),
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+}
+
+// upgrades older exports one version at a time, exports from a newer
+// meter are refused since fields they rely on would be silently dropped
+pub fn from_json(json: &str) -> Result<ExportedEncounter, String> {
+    let mut value: Value =
+        serde_json::from_str(json).map_err(|e| format!("invalid export file: {}", e))?;
+
+    let mut version = match value.get("schemaVersion") {
+        Some(version) => version
+            .as_u64()
+            .ok_or("invalid export file: schemaVersion is not a number")?
+            as u32,
+        None if value.get("fightStart").is_some() && value.get("entities").is_some() => 0,
+        None => return Err("not an encounter export".to_string()),
+    };
+
+    if version > EXPORT_SCHEMA_VERSION {
+        return Err(format!(
+            "this encounter was exported by a newer version of LOA Logs (schema v{}, supported up to v{}), update to import it",
+            version, EXPORT_SCHEMA_VERSION
+        ));
+    }
+
+    while version < EXPORT_SCHEMA_VERSION {
+        value = match version {
+            0 => upgrade_v0(value),
+            _ => return Err(format!("no converter for export schema v{}", version)),
+        };
+        version += 1;
+    }
+
+    serde_json::from_value(value).map_err(|e| format!("invalid export file: {}", e))
+}
+
+// v0 is a bare serialized encounter, from before exports had an envelope
+fn upgrade_v0(encounter: Value) -> Value {
+    json!({
+        "schemaVersion": 1,
+        "meterVersion": "unknown",
+        "encounter": encounter,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT_V0: &str = include_str!("fixtures/export_v0.json");
+    const EXPORT_V1: &str = include_str!("fixtures/export_v1.json");
+
+    fn assert_fixture_encounter(encounter: &Encounter) {
+        assert_eq!(encounter.fight_start, 1_700_000_000_000);
+        assert_eq!(encounter.duration, 60_000);
+        assert_eq!(encounter.local_player, "Player");
+        assert_eq!(encounter.difficulty.as_deref(), Some("Hard"));
+        assert!(encounter.cleared);
+        let player = &encounter.entities["Player"];
+        assert_eq!(player.class_id, 102);
+        assert_eq!(player.damage_stats.damage_dealt, 1_200_000);
+        assert_eq!(player.skill_stats.back_attacks, 3);
+    }
+
+    #[test]
+    fn bare_encounter_is_upgraded_from_v0() {
+        let export = from_json(EXPORT_V0).unwrap();
+        assert_eq!(export.schema_version, EXPORT_SCHEMA_VERSION);
+        assert_eq!(export.meter_version, "unknown");
+        assert_eq!(export.link, None);
+        assert_fixture_encounter(&export.encounter);
+    }
+
+    #[test]
+    fn current_export_is_read_as_is() {
+        let export = from_json(EXPORT_V1).unwrap();
+        assert_eq!(export.schema_version, 1);
+        assert_eq!(export.meter_version, "1.10.0");
+        assert_eq!(export.link.as_deref(), Some("loalogs://encounter/42"));
+        assert_fixture_encounter(&export.encounter);
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let encounter = from_json(EXPORT_V1).unwrap().encounter;
+        let json = to_json(encounter, "2.0.0", None).unwrap();
+        let export = from_json(&json).unwrap();
+        assert_eq!(export.meter_version, "2.0.0");
+        assert_fixture_encounter(&export.encounter);
+    }
+
+    #[test]
+    fn newer_export_is_refused() {
+        let mut value: Value = serde_json::from_str(EXPORT_V1).unwrap();
+        value["schemaVersion"] = json!(EXPORT_SCHEMA_VERSION + 1);
+        let error = from_json(&value.to_string()).unwrap_err();
+        assert!(error.contains("newer version"), "{}", error);
+    }
+
+    #[test]
+    fn other_json_is_not_an_export() {
+        assert_eq!(
+            from_json(r#"{"players": []}"#).unwrap_err(),
+            "not an encounter export"
+        );
+        assert!(from_json("not json").is_err());
+        assert!(from_json(r#"{"schemaVersion": "1"}"#).is_err());
+    }
+}
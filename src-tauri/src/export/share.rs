@@ -0,0 +1,183 @@
+use crate::parser::models::{
+    DamageStats, Encounter, EncounterDamageStats, EncounterEntity, EntityType, SharedEncounter,
+    SharedPlayer, SharedSkill, Skill, SkillStats,
+};
+use crate::parser::utils::get_class_from_id;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hashbrown::HashMap;
+use std::io::Read;
+
+pub const PREFIX: &str = "LOAM1:";
+// discord cuts messages long before this, it's meant for pastebins and dms
+pub const MAX_ENCODED_BYTES: usize = 100 * 1024;
+// skills kept per player, fewer are tried when the string gets too long
+const TOP_SKILLS: [usize; 3] = [8, 3, 0];
+// highest regular zstd level, the strings are small so speed doesn't matter
+const ZSTD_LEVEL: i32 = 19;
+
+// summary, per player totals and top skills as a LOAM1: string. cast logs,
+// buffs and timelines are left out
+pub fn encode(encounter: &Encounter, meter_version: &str) -> Result<String, String> {
+    let mut size = 0;
+    for top_skills in TOP_SKILLS {
+        let shared = to_shared(encounter, meter_version, top_skills);
+        let json = serde_json::to_vec(&shared).map_err(|e| e.to_string())?;
+        let compressed =
+            zstd::encode_all(json.as_slice(), ZSTD_LEVEL).map_err(|e| e.to_string())?;
+
+        let encoded = format!("{}{}", PREFIX, STANDARD.encode(compressed));
+        if encoded.len() <= MAX_ENCODED_BYTES {
+            return Ok(encoded);
+        }
+        size = encoded.len();
+    }
+    Err(format!(
+        "encounter is too large to share, {} KB without any skills (max {} KB)",
+        size / 1024,
+        MAX_ENCODED_BYTES / 1024
+    ))
+}
+
+pub fn decode(text: &str) -> Result<SharedEncounter, String> {
+    let text = text.trim();
+    let data = text
+        .strip_prefix(PREFIX)
+        .ok_or("clipboard doesn't hold a shared encounter")?;
+    if text.len() > MAX_ENCODED_BYTES {
+        return Err("shared encounter is too large".to_string());
+    }
+    let compressed = STANDARD
+        .decode(data)
+        .map_err(|e| format!("invalid shared encounter: {}", e))?;
+    let decoder = zstd::Decoder::new(compressed.as_slice())
+        .map_err(|e| format!("invalid shared encounter: {}", e))?;
+    let mut json = Vec::new();
+    decoder
+        .take(MAX_ENCODED_BYTES as u64 * 100)
+        .read_to_end(&mut json)
+        .map_err(|e| format!("invalid shared encounter: {}", e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("invalid shared encounter: {}", e))
+}
+
+fn to_shared(encounter: &Encounter, meter_version: &str, top_skills: usize) -> SharedEncounter {
+    let mut players: Vec<SharedPlayer> = encounter
+        .entities
+        .values()
+        .filter(|e| {
+            e.entity_type == EntityType::PLAYER
+                && (e.class_id != 0 || e.name == encounter.local_player)
+                && e.damage_stats.damage_dealt > 0
+        })
+        .map(|entity| {
+            let mut skills: Vec<&Skill> = entity.skills.values().collect();
+            skills.sort_by(|a, b| b.total_damage.cmp(&a.total_damage));
+            SharedPlayer {
+                name: entity.name.clone(),
+                class_id: entity.class_id,
+                gear_score: entity.gear_score,
+                max_hp: entity.max_hp,
+                damage: entity.damage_stats.damage_dealt,
+                dps: entity.damage_stats.dps,
+                damage_taken: entity.damage_stats.damage_taken,
+                deaths: entity.damage_stats.deaths,
+                skills: skills
+                    .into_iter()
+                    .take(top_skills)
+                    .map(|skill| SharedSkill {
+                        id: skill.id,
+                        name: skill.name.clone(),
+                        damage: skill.total_damage,
+                        max_damage: skill.max_damage,
+                        casts: skill.casts,
+                        hits: skill.hits,
+                        crits: skill.crits,
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    players.sort_by(|a, b| b.damage.cmp(&a.damage));
+
+    SharedEncounter {
+        meter_version: meter_version.to_string(),
+        boss: encounter.current_boss_name.clone(),
+        difficulty: encounter.difficulty.clone(),
+        cleared: encounter.cleared,
+        fight_start: encounter.fight_start,
+        duration: encounter.duration,
+        local_player: encounter.local_player.clone(),
+        total_damage: encounter.encounter_damage_stats.total_damage_dealt,
+        players,
+    }
+}
+
+// rebuilds an encounter good enough for the logs list and damage tab, the
+// skills that didn't make the cut are missing from the breakdown
+pub fn to_encounter(shared: SharedEncounter) -> Encounter {
+    let entities: HashMap<String, EncounterEntity> = shared
+        .players
+        .into_iter()
+        .map(|player| {
+            let skills = player
+                .skills
+                .into_iter()
+                .map(|skill| {
+                    let skill = Skill {
+                        id: skill.id,
+                        name: skill.name,
+                        total_damage: skill.damage,
+                        max_damage: skill.max_damage,
+                        casts: skill.casts,
+                        hits: skill.hits,
+                        crits: skill.crits,
+                        ..Default::default()
+                    };
+                    (skill.id, skill)
+                })
+                .collect();
+            let entity = EncounterEntity {
+                name: player.name,
+                entity_type: EntityType::PLAYER,
+                class_id: player.class_id,
+                class: get_class_from_id(&player.class_id),
+                gear_score: player.gear_score,
+                current_hp: player.max_hp,
+                max_hp: player.max_hp,
+                skills,
+                damage_stats: DamageStats {
+                    damage_dealt: player.damage,
+                    dps: player.dps,
+                    damage_taken: player.damage_taken,
+                    deaths: player.deaths,
+                    ..Default::default()
+                },
+                skill_stats: SkillStats::default(),
+                ..Default::default()
+            };
+            (entity.name.clone(), entity)
+        })
+        .collect();
+
+    let top_damage_dealt = entities
+        .values()
+        .map(|e| e.damage_stats.damage_dealt)
+        .max()
+        .unwrap_or_default();
+    Encounter {
+        last_combat_packet: shared.fight_start + shared.duration,
+        fight_start: shared.fight_start,
+        local_player: shared.local_player,
+        current_boss_name: shared.boss,
+        entities,
+        encounter_damage_stats: EncounterDamageStats {
+            total_damage_dealt: shared.total_damage,
+            top_damage_dealt,
+            ..Default::default()
+        },
+        duration: shared.duration,
+        difficulty: shared.difficulty,
+        cleared: shared.cleared,
+        ..Default::default()
+    }
+}
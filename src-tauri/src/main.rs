@@ -4,32 +4,80 @@
 )]
 
 mod app;
+mod export;
 mod parser;
 
 use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
     fs::{self, File},
     io::{Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Mutex,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
+use app::db_pool::DbPool;
+use app::deep_link::DeepLink;
+use app::destructive::DestructiveTokens;
+use app::search_suggestions::SuggestionCache;
 use flate2::read::GzDecoder;
 use hashbrown::HashMap;
 use log::{error, info, warn};
 use parser::models::*;
-
-use rusqlite::{params, params_from_iter, Connection, Transaction};
+use parser::name_mask;
+use parser::packet_source::PacketSource;
+use parser::utils::EncounterSave;
+use parser::BOSS_HP_WINDOW_LABEL;
+
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
 use sysinfo::System;
 use tauri::{
-    api::process::Command, CustomMenuItem, LogicalPosition, LogicalSize, Manager, Position, Size,
-    SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
+    api::notification::Notification, api::process::Command, AppHandle, ClipboardManager,
+    CustomMenuItem, GlobalShortcutManager, LogicalPosition, LogicalSize, Manager, Position, Size,
+    State, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem, WindowBuilder,
+    WindowUrl,
 };
 use tauri_plugin_window_state::{AppHandleExt, StateFlags, WindowExt};
 use tokio::task;
-use window_vibrancy::{apply_blur, clear_blur};
+use window_vibrancy::{
+    apply_acrylic, apply_blur, apply_mica, clear_acrylic, clear_blur, clear_mica,
+};
 
 const METER_WINDOW_LABEL: &str = "main";
+const CLICKTHROUGH_STATE_FILE: &str = "clickthrough";
+
+static CLICKTHROUGH_SHORTCUT: Mutex<Option<String>> = Mutex::new(None);
+// link the app was started with, opened once the logs window is listening
+static PENDING_DEEP_LINK: Mutex<Option<String>> = Mutex::new(None);
+// why settings.json was replaced with defaults at startup
+static SETTINGS_LOAD_ERROR: Mutex<Option<String>> = Mutex::new(None);
+static SETTINGS_LOAD_ERROR_EMITTED: AtomicBool = AtomicBool::new(false);
+static DB_VERSION_TOO_NEW_EMITTED: AtomicBool = AtomicBool::new(false);
+
+// stored in PRAGMA user_version, bump when adding a migration
+const DB_SCHEMA_VERSION: i32 = 8;
+// set while a live capture parser is running or waiting for the network
+static LIVE_PARSER_RUNNING: AtomicBool = AtomicBool::new(false);
+// no more update checks once an update is waiting for a restart
+static UPDATE_INSTALLED: AtomicBool = AtomicBool::new(false);
+const UPDATE_CHECK_ATTEMPTS: u32 = 3;
+const UPDATE_RETRY_DELAY: Duration = Duration::from_secs(200);
+const UPDATE_RECHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+// how often a pending install checks whether the fight is over
+#[cfg(not(debug_assertions))]
+const UPDATE_IDLE_POLL_INTERVAL: Duration = Duration::from_secs(30);
+// the first automatic storage cleanup waits until startup is done
+const STORAGE_CLEANUP_DELAY: Duration = Duration::from_secs(10 * 60);
+const STORAGE_CLEANUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+#[cfg(not(debug_assertions))]
+const MANUAL_DOWNLOAD_URL: &str = "https://github.com/snoww/loa-logs/releases/latest";
 const LOGS_WINDOW_LABEL: &str = "logs";
 const WINDOW_STATE_FLAGS: StateFlags = StateFlags::from_bits_truncate(
     StateFlags::FULLSCREEN.bits()
@@ -38,10 +86,29 @@ const WINDOW_STATE_FLAGS: StateFlags = StateFlags::from_bits_truncate(
         | StateFlags::SIZE.bits()
         | StateFlags::VISIBLE.bits(),
 );
+// focus changes can come in bursts, the state is saved at most this often
+const FOCUS_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+static LAST_FOCUS_SAVE: Mutex<Option<Instant>> = Mutex::new(None);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    app::init();
+    let args: Vec<String> = std::env::args().collect();
+    let replay = args
+        .iter()
+        .position(|arg| arg == "--replay")
+        .and_then(|i| args.get(i + 1))
+        .map(|path| {
+            (
+                PathBuf::from(path),
+                args.iter().any(|arg| arg == "--realtime"),
+            )
+        });
+    let headless = replay.is_some() || args.iter().any(|arg| arg == "--headless");
+    *PENDING_DEEP_LINK.lock().unwrap() = args
+        .iter()
+        .find(|arg| app::deep_link::is_deep_link(arg))
+        .cloned();
+    app::init(headless);
 
     std::panic::set_hook(Box::new(|info| {
         error!("Panicked: {:?}", info);
@@ -49,10 +116,15 @@ async fn main() -> Result<()> {
         app::get_logger().unwrap().flush();
     }));
 
+    if headless {
+        return run_headless(replay);
+    }
+
     let quit = CustomMenuItem::new("quit".to_string(), "Quit");
     let show_logs = CustomMenuItem::new("show-logs".to_string(), "Show Logs");
     let show_meter = CustomMenuItem::new("show-meter".to_string(), "Show Meter");
     let hide_meter = CustomMenuItem::new("hide".to_string(), "Hide Meter");
+    let toggle_boss_hp = CustomMenuItem::new("toggle-boss-hp".to_string(), "Toggle Boss HP");
     let load_saved_pos = CustomMenuItem::new("load".to_string(), "Load Saved");
     let save_current_pos = CustomMenuItem::new("save".to_string(), "Save Position");
     let reset = CustomMenuItem::new("reset".to_string(), "Reset Window");
@@ -61,6 +133,7 @@ async fn main() -> Result<()> {
         .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(show_meter)
         .add_item(hide_meter)
+        .add_item(toggle_boss_hp)
         .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(save_current_pos)
         .add_item(load_saved_pos)
@@ -68,63 +141,45 @@ async fn main() -> Result<()> {
         .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(quit);
 
-    let system_tray = SystemTray::new().with_menu(tray_menu);
+    let system_tray = SystemTray::new()
+        .with_menu(tray_menu)
+        .with_tooltip("LOA Logs");
     
     tauri::Builder::default()
         .setup(|app| {
             info!("starting app v{}", app.package_info().version.to_string());
 
-            let resource_path = app
-                .path_resolver()
-                .resource_dir()
-                .expect("could not get resource dir");
+            let data_path = app::storage::data_dir(&app.handle());
+            app::backup::apply_staged_restore(&data_path);
 
-            match setup_db(&resource_path) {
-                Ok(_) => (),
+            let pool = DbPool::new(data_path.join("encounters.db"));
+            match setup_db(&data_path) {
+                Ok(Some(newer_version)) => pool.set_newer_version(newer_version),
+                Ok(None) => (),
                 Err(e) => {
                     warn!("error setting up database: {}", e);
                 }
             }
-
-            let handle = app.handle();
-            tauri::async_runtime::spawn(async move {
-                match tauri::updater::builder(handle).check().await {
-                    Ok(update) => {
-                        if update.is_update_available() {
-                            #[cfg(not(debug_assertions))]
-                            {
-                                info!(
-                                    "update available, downloading update: v{}",
-                                    update.latest_version()
-                                );
-
-                                unload_driver();
-                                remove_driver();
-
-                                update
-                                    .download_and_install()
-                                    .await
-                                    .map_err(|e| {
-                                        error!("failed to download update: {}", e);
-                                    })
-                                    .ok();
-                            }
-                        } else {
-                            info!("no update available");
-                        }
-                    }
-                    Err(e) => {
-                        warn!("failed to get update: {}", e);
-                    }
-                }
-            });
-
-            let settings = read_settings(&resource_path).ok();
+            app.manage(pool);
+            app.manage(SuggestionCache::default());
+            app.manage(DestructiveTokens::default());
+
+            start_update_checker(app.handle());
+            start_storage_maintenance(app.handle());
+            std::thread::spawn(repair_start_on_boot);
+            std::thread::spawn(app::deep_link::register);
+
+            let mut settings = load_settings(&data_path);
+            if let Some(settings) = settings.as_mut() {
+                migrate_access_token(&data_path, settings);
+            }
+            start_session(&app.handle(), settings.as_ref());
 
             let meter_window = app.get_window(METER_WINDOW_LABEL).unwrap();
             meter_window
                 .restore_state(WINDOW_STATE_FLAGS)
                 .expect("failed to restore window state");
+            app::window_geometry::correct_restored(&meter_window);
             // #[cfg(debug_assertions)]
             // {
             //     meter_window.open_devtools();
@@ -134,11 +189,47 @@ async fn main() -> Result<()> {
             logs_window
                 .restore_state(WINDOW_STATE_FLAGS)
                 .expect("failed to restore window state");
+            app::window_geometry::correct_restored(&logs_window);
+
+            let boss_hp_window = app.get_window(BOSS_HP_WINDOW_LABEL).unwrap();
+            boss_hp_window
+                .restore_state(WINDOW_STATE_FLAGS)
+                .expect("failed to restore window state");
+            app::window_geometry::correct_restored(&boss_hp_window);
+            let boss_hp_clickthrough = settings
+                .as_ref()
+                .map_or(true, |settings| settings.general.boss_hp_clickthrough);
+            boss_hp_window
+                .set_ignore_cursor_events(boss_hp_clickthrough)
+                .unwrap();
+
+            let clickthrough_path = data_path.join(CLICKTHROUGH_STATE_FILE);
+            if fs::read_to_string(clickthrough_path).is_ok_and(|state| state.trim() == "true") {
+                apply_clickthrough(&app.handle(), true);
+            }
+            let clickthrough_shortcut = settings
+                .as_ref()
+                .map(|settings| settings.shortcuts.toggle_clickthrough.clone())
+                .unwrap_or_else(default_toggle_clickthrough);
+            register_clickthrough_shortcut(&app.handle(), &clickthrough_shortcut);
+            if let Some(settings) = settings.as_ref() {
+                app::peek::set_shortcut(&settings.shortcuts.peek);
+            }
+            if let Some(fallback) = app::storage::fallback(&app.handle()) {
+                app.emit_all("storage-fallback", fallback).ok();
+            }
+            app::peek::start(meter_window.clone());
 
-            let mut port = 6040;
+            let port = live_capture_port(settings.as_ref());
 
             if let Some(settings) = settings.clone() {
                 info!("settings loaded");
+                apply_settings(&settings);
+                // applied before showing the window so the backdrop doesn't flash in
+                if !settings.general.backdrop.is_empty() {
+                    let applied = apply_backdrop(&meter_window, &settings.general.backdrop);
+                    info!("applied window backdrop: {}", applied);
+                }
                 if !settings.general.hide_meter_on_start {
                     meter_window.show().unwrap();
                 }
@@ -149,10 +240,6 @@ async fn main() -> Result<()> {
                     meter_window.set_always_on_top(false).unwrap();
                 }
 
-                if settings.general.auto_iface && settings.general.port > 0 {
-                    port = settings.general.port;
-                }
-
                 if settings.general.start_loa_on_start {
                     info!("auto launch game enabled");
                     start_loa_process();
@@ -162,13 +249,8 @@ async fn main() -> Result<()> {
                 logs_window.show().unwrap();
             }
 
-            info!("listening on port: {}", port);
-            remove_driver();
-            task::spawn_blocking(move || {
-                parser::start(meter_window, port, settings).map_err(|e| {
-                    error!("unexpected error occurred in parser: {}", e);
-                })
-            });
+            spawn_parser(meter_window, PacketSource::Live { port }, settings);
+            app::tray::start_status_watcher(app.handle());
 
             // #[cfg(debug_assertions)]
             // {
@@ -181,9 +263,14 @@ async fn main() -> Result<()> {
             tauri_plugin_window_state::Builder::new()
                 .skip_initial_state(METER_WINDOW_LABEL)
                 .skip_initial_state(LOGS_WINDOW_LABEL)
+                .skip_initial_state(BOSS_HP_WINDOW_LABEL)
                 .build(),
         )
-        .plugin(tauri_plugin_single_instance::init(|_app, _argv, _cwd| {}))
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            if let Some(url) = argv.iter().find(|arg| app::deep_link::is_deep_link(arg)) {
+                handle_deep_link(app, url);
+            }
+        }))
         .on_window_event(|event| match event.event() {
             tauri::WindowEvent::CloseRequested { api, .. } => {
                 api.prevent_close();
@@ -193,6 +280,15 @@ async fn main() -> Result<()> {
                     let meter_window = app_handle.get_window(METER_WINDOW_LABEL).unwrap();
                     let logs_window = app_handle.get_window(LOGS_WINDOW_LABEL).unwrap();
 
+                    let close_to_tray = read_settings(&app::storage::data_dir(&app_handle))
+                        .is_ok_and(|settings| settings.general.close_to_tray);
+                    if close_to_tray {
+                        // keep the parser and tray running, quit from the tray exits
+                        save_window_state(&app_handle);
+                        meter_window.hide().unwrap();
+                        return;
+                    }
+
                     if logs_window.is_minimized().unwrap() {
                         logs_window.unminimize().unwrap();
                     }
@@ -201,24 +297,33 @@ async fn main() -> Result<()> {
                         meter_window.unminimize().unwrap();
                     }
 
-                    app_handle
-                        .save_window_state(WINDOW_STATE_FLAGS)
-                        .expect("failed to save window state");
-                    unload_driver();
-                    app_handle.exit(0);
-                } else if event.window().label() == LOGS_WINDOW_LABEL {
+                    shutdown(&app_handle);
+                } else if event.window().label() == LOGS_WINDOW_LABEL
+                    || event.window().label() == BOSS_HP_WINDOW_LABEL
+                {
                     event.window().hide().unwrap();
                 }
             }
             tauri::WindowEvent::Focused(focused) => {
                 if !focused {
-                    event
-                        .window()
-                        .app_handle()
-                        .save_window_state(WINDOW_STATE_FLAGS)
-                        .expect("failed to save window state");
+                    let mut last_save = LAST_FOCUS_SAVE.lock().unwrap();
+                    if last_save.map_or(true, |last| last.elapsed() >= FOCUS_SAVE_INTERVAL) {
+                        *last_save = Some(Instant::now());
+                        save_window_state(&event.window().app_handle());
+                    }
                 }
             }
+            tauri::WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+                ..
+            } => {
+                app::window_geometry::on_scale_factor_changed(
+                    event.window(),
+                    *scale_factor,
+                    *new_inner_size,
+                );
+            }
             _ => {}
         })
         .system_tray(system_tray)
@@ -231,39 +336,37 @@ async fn main() -> Result<()> {
                 if let Some(meter) = app.get_window(METER_WINDOW_LABEL) {
                     meter.show().unwrap();
                     meter.unminimize().unwrap();
-                    meter.set_ignore_cursor_events(false).unwrap()
                 }
+                // an escape hatch for a meter that can't be clicked, the saved
+                // state is kept for the next startup
+                set_session_clickthrough(app, false);
             }
             SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
                 "quit" => {
-                    app.save_window_state(WINDOW_STATE_FLAGS)
-                        .expect("failed to save window state");
-                    unload_driver();
-                    app.exit(0);
+                    shutdown(app);
                 }
                 "hide" => {
                     if let Some(meter) = app.get_window(METER_WINDOW_LABEL) {
-                        meter.hide().unwrap();
+                        app::tray::hide_meter(&meter);
                     }
                 }
+                "toggle-boss-hp" => {
+                    toggle_boss_hp(app);
+                }
                 "show-meter" => {
                     if let Some(meter) = app.get_window(METER_WINDOW_LABEL) {
-                        meter.show().unwrap();
-                        meter.unminimize().unwrap();
-                        meter.set_ignore_cursor_events(false).unwrap()
+                        app::tray::show_meter(&meter);
                     }
                 }
                 "load" => {
                     if let Some(meter) = app.get_window(METER_WINDOW_LABEL) {
                         meter.restore_state(WINDOW_STATE_FLAGS).unwrap();
+                        app::window_geometry::correct_restored(&meter);
                     }
                 }
                 "save" => {
-                    if let Some(meter) = app.get_window(METER_WINDOW_LABEL) {
-                        meter
-                            .app_handle()
-                            .save_window_state(WINDOW_STATE_FLAGS)
-                            .unwrap();
+                    if app.get_window(METER_WINDOW_LABEL).is_some() {
+                        save_window_state(app);
                     }
                 }
                 "reset" => {
@@ -280,8 +383,8 @@ async fn main() -> Result<()> {
                         meter.show().unwrap();
                         meter.unminimize().unwrap();
                         meter.set_focus().unwrap();
-                        meter.set_ignore_cursor_events(false).unwrap();
                     }
+                    set_session_clickthrough(app, false);
                 }
                 "show-logs" => {
                     if let Some(logs) = app.get_window(LOGS_WINDOW_LABEL) {
@@ -296,29 +399,63 @@ async fn main() -> Result<()> {
         .invoke_handler(tauri::generate_handler![
             load_encounters_preview,
             load_encounter,
+            load_encounter_for_sync,
+            load_entity_details,
+            get_hp_segment_breakdown,
             get_encounter_count,
             open_most_recent_encounter,
+            get_recent_encounters,
+            get_search_suggestions,
+            get_best_splits,
+            get_character_activity,
+            get_sessions,
+            get_boss_note,
+            list_boss_notes,
+            set_boss_note,
+            normalize_boss_names,
+            rebuild_previews,
+            get_class_metadata,
+            open_encounter,
+            open_pending_deep_link,
             delete_encounter,
             delete_encounters,
             toggle_meter_window,
             toggle_logs_window,
+            toggle_boss_hp_window,
             open_url,
             save_settings,
             get_settings,
+            dismiss_settings_load_error,
             open_folder,
             open_db_path,
             delete_encounters_below_min_duration,
+            request_destructive_token,
             get_db_info,
+            estimate_cleanup,
+            get_storage_breakdown,
+            get_storage_fallback,
+            cleanup_storage,
+            get_death_causes,
             disable_blur,
             enable_blur,
+            set_window_backdrop,
+            set_opacity,
             write_log,
+            app::frontend_panic,
+            get_network_interfaces,
+            restart_parser,
             toggle_encounter_favorite,
+            toggle_encounters_favorite,
             delete_all_encounters,
             delete_all_uncleared_encounters,
             enable_aot,
             disable_aot,
             set_clickthrough,
+            restore_clickthrough_shortcut,
             optimize_database,
+            backup_database,
+            restore_database,
+            restore_config,
             check_start_on_boot,
             set_start_on_boot,
             check_loa_running,
@@ -327,6 +464,28 @@ async fn main() -> Result<()> {
             sync,
             remove_driver,
             unload_driver,
+            get_live_encounter,
+            get_query_stats,
+            load_boss_casts,
+            get_capture_status,
+            set_tracked_connection,
+            get_parser_metrics,
+            get_session_stats,
+            reset_session_stats,
+            get_number_format_preview,
+            restart_app,
+            replay_capture,
+            stop_and_collect_dump,
+            dump_live_encounter,
+            get_ignored_entities,
+            add_ignored_entity,
+            remove_ignored_entity,
+            export_encounter_compat,
+            import_foreign_db,
+            export_encounter,
+            copy_encounter_to_clipboard,
+            paste_encounter_from_clipboard,
+            import_encounter,
         ])
         .run(tauri::generate_context!())
         .expect("error while running application");
@@ -334,17 +493,207 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn get_db_connection(resource_path: &Path) -> Result<Connection, rusqlite::Error> {
-    let path = resource_path.join("encounters.db");
-    if !path.exists() {
-        setup_db(resource_path)?;
+// records encounters without any windows, tray or updater, e.g. on a second pc.
+// with a replay the packets come from a capture file and the app exits once it's done
+fn run_headless(replay: Option<(PathBuf, bool)>) -> Result<()> {
+    let mut context = tauri::generate_context!();
+    context.config_mut().tauri.windows.clear();
+
+    tauri::Builder::default()
+        .setup(move |app| {
+            info!(
+                "starting app v{} in headless mode",
+                app.package_info().version.to_string()
+            );
+
+            let data_path = app::storage::data_dir(&app.handle());
+            app::backup::apply_staged_restore(&data_path);
+
+            let pool = DbPool::new(data_path.join("encounters.db"));
+            match setup_db(&data_path) {
+                Ok(Some(newer_version)) => pool.set_newer_version(newer_version),
+                Ok(None) => (),
+                Err(e) => warn!("error setting up database: {}", e),
+            }
+            app.manage(pool);
+            app.manage(SuggestionCache::default());
+            app.manage(DestructiveTokens::default());
+
+            let settings = load_settings(&data_path);
+            let port = live_capture_port(settings.as_ref());
+            if let Some(settings) = settings.as_ref() {
+                info!("settings loaded");
+                apply_settings(settings);
+            }
+
+            // the parser emits through a window, give it a hidden one
+            // that doesn't load the frontend
+            let meter_window = WindowBuilder::new(
+                app,
+                METER_WINDOW_LABEL,
+                WindowUrl::External("about:blank".parse().unwrap()),
+            )
+            .visible(false)
+            .skip_taskbar(true)
+            .build()?;
+
+            let app_handle = app.handle();
+            match replay {
+                Some((path, realtime)) => {
+                    let parser = spawn_parser(
+                        meter_window,
+                        PacketSource::Replay { path, realtime },
+                        settings,
+                    );
+                    let app_handle = app_handle.clone();
+                    task::spawn(async move {
+                        parser.await.ok();
+                        end_session(&app_handle);
+                        app_handle.exit(0);
+                    });
+                }
+                None => {
+                    spawn_parser(meter_window, PacketSource::Live { port }, settings);
+                }
+            }
+
+            ctrlc::set_handler(move || {
+                info!("received ctrl+c, saving current encounter");
+                app_handle.trigger_global("flush-request", None);
+                if !parser::wait_for_flush(Duration::from_secs(10)) {
+                    warn!("timed out saving current encounter");
+                }
+                end_session(&app_handle);
+                if let Ok(logger) = app::get_logger() {
+                    logger.flush();
+                }
+                app_handle.exit(0);
+            })?;
+
+            Ok(())
+        })
+        .run(context)
+        .expect("error while running application");
+
+    Ok(())
+}
+
+fn spawn_parser(
+    meter_window: tauri::Window,
+    source: PacketSource,
+    settings: Option<Settings>,
+) -> task::JoinHandle<()> {
+    let live = !source.is_replay();
+    if let PacketSource::Live { port } = source {
+        info!("listening on port: {}", port);
+        LIVE_PARSER_RUNNING.store(true, Ordering::Relaxed);
+        remove_driver();
+    }
+    task::spawn_blocking(move || {
+        if live {
+            let Some(interfaces) = app::network::wait_for_network() else {
+                // started again through restart_parser once the network is back
+                warn!("no network adapter found, live capture not started");
+                LIVE_PARSER_RUNNING.store(false, Ordering::Relaxed);
+                meter_window.emit("no-network-interface", ()).ok();
+                return;
+            };
+            let interface = settings
+                .as_ref()
+                .map(|settings| &settings.general)
+                .filter(|general| !general.auto_iface)
+                .and_then(|general| app::network::resolve_interface(&interfaces, general))
+                .map(|interface| interface.name.clone());
+            parser::capture_status::set_interface(interface);
+        }
+        let error_window = meter_window.clone();
+        let result = parser::start(meter_window, source, settings);
+        if live {
+            parser::capture_status::set_capturing(false);
+            LIVE_PARSER_RUNNING.store(false, Ordering::Relaxed);
+        }
+        if let Err(e) = result {
+            error!("unexpected error occurred in parser: {:#}", e);
+            if live {
+                let message = format!("{:#}", e);
+                let error = ParserError {
+                    category: parser_error_category(&message).to_string(),
+                    message,
+                };
+                parser::capture_status::set_error(error.clone());
+                error_window.emit("parser-error", error).ok();
+            }
+        }
+    })
+}
+
+fn live_capture_port(settings: Option<&Settings>) -> u16 {
+    match settings {
+        Some(settings) if settings.general.auto_iface && settings.general.port > 0 => {
+            settings.general.port
+        }
+        _ => 6040,
+    }
+}
+
+// coarse guess at the cause so the frontend can suggest a fix
+fn parser_error_category(message: &str) -> &'static str {
+    let message = message.to_lowercase();
+    if ["access is denied", "permission", "administrator", "elevat"]
+        .iter()
+        .any(|hint| message.contains(hint))
+    {
+        "permission"
+    } else if ["driver", "windivert", "npcap", "pcap"]
+        .iter()
+        .any(|hint| message.contains(hint))
+    {
+        "driver"
+    } else if ["address", "port", "interface", "adapter", "network"]
+        .iter()
+        .any(|hint| message.contains(hint))
+    {
+        "interface"
+    } else {
+        "unknown"
+    }
+}
+
+// starts live capture again after it failed or gave up waiting for the network
+#[tauri::command]
+fn restart_parser(window: tauri::Window) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let meter_window = app_handle
+        .get_window(METER_WINDOW_LABEL)
+        .ok_or("meter window not found")?;
+    if LIVE_PARSER_RUNNING.swap(true, Ordering::Relaxed) {
+        return Err("capture is already running".to_string());
     }
-    Connection::open(path)
+    let settings = read_settings(&app::storage::data_dir(&app_handle)).ok();
+    let port = live_capture_port(settings.as_ref());
+    spawn_parser(meter_window, PacketSource::Live { port }, settings);
+    Ok(())
 }
 
-fn setup_db(resource_path: &Path) -> Result<(), rusqlite::Error> {
+// returns the schema version if the database was created by a newer app,
+// in which case nothing is migrated and the database is left untouched
+fn setup_db(data_path: &Path) -> Result<Option<i32>, rusqlite::Error> {
     info!("setting up database");
-    let mut conn = Connection::open(resource_path.join("encounters.db"))?;
+    let mut conn = Connection::open(data_path.join("encounters.db"))?;
+    migrate_db(&mut conn)
+}
+
+// returns the schema version when the database is too new to migrate
+fn migrate_db(conn: &mut Connection) -> Result<Option<i32>, rusqlite::Error> {
+    let user_version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if user_version > DB_SCHEMA_VERSION {
+        warn!(
+            "database schema version {} is newer than {}, opening read only",
+            user_version, DB_SCHEMA_VERSION
+        );
+        return Ok(Some(user_version));
+    }
+
     let tx = conn.transaction()?;
 
     // FIXME: replace me with idempotent migrations
@@ -372,10 +721,30 @@ fn setup_db(resource_path: &Path) -> Result<(), rusqlite::Error> {
     }
 
     migration_specs(&tx)?;
+    migration_preview_players_dps(&tx)?;
+    migration_dps_alive(&tx)?;
+    migration_damage_mitigation(&tx)?;
+    migration_preview_region(&tx)?;
+    migration_preview_local_rank(&tx)?;
+    migration_preview_filter_indexes(&tx)?;
+    migration_entity_index(&tx)?;
+    migration_sessions(&tx)?;
+    migration_boss_notes(&tx)?;
+    migration_preview_dungeon(&tx)?;
+    migration_skill_records(&tx)?;
+    // scans whole tables, done once
+    if user_version < 3 {
+        migration_fight_start_ms(&tx)?;
+    }
+    if user_version < 5 {
+        migration_negative_durations(&tx)?;
+    }
 
     stmt.finalize()?;
+    tx.pragma_update(None, "user_version", DB_SCHEMA_VERSION)?;
     info!("finished setting up database");
-    tx.commit()
+    tx.commit()?;
+    Ok(None)
 }
 
 fn migration_legacy_encounter(tx: &Transaction) -> Result<(), rusqlite::Error> {
@@ -592,6 +961,64 @@ fn migration_sync(tx: &Transaction) -> Result<(), rusqlite::Error> {
     )
 }
 
+fn migration_sessions(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS session (
+            id INTEGER PRIMARY KEY,
+            start_time INTEGER NOT NULL,
+            end_time INTEGER,
+            version TEXT,
+            capture_mode TEXT,
+            interface_ip TEXT,
+            encounters_saved INTEGER NOT NULL DEFAULT 0
+        );",
+    )
+}
+
+// dungeon runs keep their own type in the log list, apart from boss fights
+fn migration_preview_dungeon(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["encounter_preview", "dungeon"])? {
+        tx.execute(
+            "ALTER TABLE encounter_preview ADD COLUMN dungeon BOOLEAN NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    stmt.finalize()
+}
+
+fn migration_boss_notes(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS boss_note (
+            boss TEXT PRIMARY KEY,
+            note TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );",
+    )
+}
+
+// a record goes with the encounter it was set in, every delete path removes
+// rows from encounter so a trigger catches all of them
+fn migration_skill_records(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS skill_record (
+            player TEXT NOT NULL,
+            skill_id INTEGER NOT NULL,
+            max_damage INTEGER NOT NULL,
+            encounter_id INTEGER NOT NULL,
+            PRIMARY KEY (player, skill_id)
+        );
+        CREATE INDEX IF NOT EXISTS skill_record_encounter_index
+        ON skill_record (encounter_id);
+        CREATE TRIGGER IF NOT EXISTS encounter_skill_record_ad AFTER DELETE ON encounter BEGIN
+            DELETE FROM skill_record WHERE encounter_id = old.id;
+        END;",
+    )
+}
+
 fn migration_specs(tx: &Transaction) -> Result<(), rusqlite::Error> {
     let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
     if !stmt.exists(["entity", "spec"])? {
@@ -608,176 +1035,363 @@ fn migration_specs(tx: &Transaction) -> Result<(), rusqlite::Error> {
     stmt.finalize()
 }
 
-#[tauri::command]
-fn load_encounters_preview(
-    window: tauri::Window,
-    page: i32,
-    page_size: i32,
-    search: String,
-    filter: SearchFilter,
-) -> EncountersOverview {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    let mut params = vec![];
-
-    let join_clause = if search.len() > 2 {
-        let escaped_search = search
-            .split_whitespace()
-            .map(|word| format!("\"{}\"", word.replace("\"", "")))
-            .collect::<Vec<_>>()
-            .join(" ");
-        params.push(escaped_search);
-        "JOIN encounter_search(?) ON encounter_search.rowid = e.id"
-    } else {
-        ""
-    };
+fn migration_preview_local_rank(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["encounter_preview", "local_rank"])? {
+        // older encounters have no recorded rank and stay null
+        tx.execute_batch(
+            "
+            ALTER TABLE encounter_preview ADD COLUMN local_rank INTEGER;
+            ALTER TABLE encounter_preview ADD COLUMN party_size INTEGER;
+            ",
+        )?;
+    }
+    stmt.finalize()
+}
 
-    params.push((filter.min_duration * 1000).to_string());
+fn migration_preview_region(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["encounter_preview", "region"])? {
+        info!("adding region to encounter previews");
+        tx.execute_batch(
+            "
+            ALTER TABLE encounter_preview ADD COLUMN region TEXT;
+            UPDATE encounter_preview SET region = (
+                SELECT json_extract(misc, '$.region')
+                FROM encounter
+                WHERE encounter.id = encounter_preview.id
+            );
+            CREATE INDEX IF NOT EXISTS encounter_preview_region_index
+            ON encounter_preview(region);
+            ",
+        )?;
+    }
+    stmt.finalize()
+}
 
-    let boss_filter = if !filter.bosses.is_empty() {
-        let mut placeholders = "?,".repeat(filter.bosses.len());
-        placeholders.pop(); // remove trailing comma
-        params.extend(filter.bosses);
-        format!("AND e.current_boss IN ({})", placeholders)
-    } else {
-        "".to_string()
-    };
+// the cleared-only and difficulty filters are sorted by fight start,
+// without these they scan the whole preview table
+fn migration_preview_filter_indexes(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = ?")?;
+    if !stmt.exists(["encounter_preview_difficulty_fight_start_index"])? {
+        info!("adding filter indexes to encounter previews");
+        tx.execute_batch(
+            "
+            CREATE INDEX IF NOT EXISTS encounter_preview_cleared_fight_start_index
+            ON encounter_preview(cleared, fight_start);
+            CREATE INDEX IF NOT EXISTS encounter_preview_difficulty_fight_start_index
+            ON encounter_preview(difficulty, fight_start);
+            ANALYZE encounter_preview;
+            ",
+        )?;
+    }
+    stmt.finalize()
+}
 
-    let raid_clear_filter = if filter.cleared {
-        "AND cleared = 1"
-    } else {
-        ""
-    };
+// entities used to be keyed by (name, encounter_id), so two entities sharing a
+// name in one encounter overwrote each other. the table is rebuilt with a
+// per-encounter index for entities that share a name
+fn migration_entity_index(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["entity", "entity_index"])? {
+        info!("rebuilding entity table with entity index");
+        tx.execute_batch(
+            "
+            CREATE TABLE entity_new (
+                name TEXT,
+                character_id INTEGER,
+                encounter_id INTEGER NOT NULL,
+                npc_id INTEGER,
+                entity_type TEXT,
+                class_id INTEGER,
+                class TEXT,
+                gear_score REAL,
+                current_hp INTEGER,
+                max_hp INTEGER,
+                is_dead INTEGER,
+                skills TEXT,
+                damage_stats TEXT,
+                dps INTEGER,
+                skill_stats TEXT,
+                last_update INTEGER,
+                engravings TEXT,
+                gear_hash TEXT,
+                spec TEXT,
+                ark_passive_active BOOLEAN,
+                ark_passive_data TEXT,
+                entity_index INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (name, encounter_id, entity_index),
+                FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
+            );
+            INSERT INTO entity_new (
+            name, character_id, encounter_id, npc_id, entity_type, class_id, class,
+            gear_score, current_hp, max_hp, is_dead, skills, damage_stats, dps,
+            skill_stats, last_update, engravings, gear_hash, spec,
+            ark_passive_active, ark_passive_data
+            )
+            SELECT
+            name, character_id, encounter_id, npc_id, entity_type, class_id, class,
+            gear_score, current_hp, max_hp, is_dead, skills, damage_stats, dps,
+            skill_stats, last_update, engravings, gear_hash, spec,
+            ark_passive_active, ark_passive_data
+            FROM entity;
+            DROP TABLE entity;
+            ALTER TABLE entity_new RENAME TO entity;
+            CREATE INDEX IF NOT EXISTS entity_encounter_id_index
+            ON entity (encounter_id desc);
+            CREATE INDEX IF NOT EXISTS entity_name_index
+            ON entity (name);
+            CREATE INDEX IF NOT EXISTS entity_class_index
+            ON entity (class);
+            ",
+        )?;
+    }
+    stmt.finalize()
+}
 
-    let favorite_filter = if filter.favorite {
-        "AND favorite = 1"
-    } else {
-        ""
-    };
+// a few old rows have their timestamps in seconds and sort to 1970
+fn migration_fight_start_ms(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let fixed = tx.execute(
+        "UPDATE encounter_preview SET fight_start = fight_start * 1000
+        WHERE fight_start > 0 AND fight_start < ?",
+        [app::time::SECONDS_CUTOFF],
+    )?;
+    tx.execute(
+        "UPDATE encounter SET last_combat_packet = last_combat_packet * 1000
+        WHERE last_combat_packet > 0 AND last_combat_packet < ?",
+        [app::time::SECONDS_CUTOFF],
+    )?;
+    if fixed > 0 {
+        info!("fixed {} encounters with timestamps in seconds", fixed);
+    }
+    Ok(())
+}
 
-    let boss_only_damage_filter = if filter.boss_only_damage {
-        "AND boss_only_damage = 1"
-    } else {
-        ""
-    };
+// rows saved across a clock jump or an i32 overflow have negative durations
+// and dps. durations are recomputed from the encounter, dps from the total
+// damage where the duration is usable and cleared otherwise
+fn migration_negative_durations(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let fixed = tx.execute(
+        "UPDATE encounter_preview SET duration = coalesce((
+            SELECT e.last_combat_packet - encounter_preview.fight_start
+            FROM encounter e
+            WHERE e.id = encounter_preview.id
+                AND e.last_combat_packet >= encounter_preview.fight_start
+        ), 0)
+        WHERE duration < 0",
+        [],
+    )?;
+    tx.execute_batch(
+        "
+        UPDATE encounter SET dps = (
+            SELECT CASE WHEN ep.duration > 0 AND encounter.total_damage_dealt >= 0
+                THEN encounter.total_damage_dealt / max(ep.duration / 1000, 1)
+            END
+            FROM encounter_preview ep
+            WHERE ep.id = encounter.id
+        )
+        WHERE dps < 0;
+        UPDATE entity SET dps = NULL WHERE dps < 0;
+        UPDATE encounter_preview SET my_dps = (
+            SELECT max(en.dps) FROM entity en
+            WHERE en.encounter_id = encounter_preview.id AND en.name = encounter_preview.local_player
+        )
+        WHERE my_dps < 0;
+        UPDATE encounter_preview SET my_dps_alive = NULL WHERE my_dps_alive < 0;
+        ",
+    )?;
+    if fixed > 0 {
+        info!("fixed {} encounters with negative durations", fixed);
+    }
+    Ok(())
+}
 
-    let difficulty_filter = if !filter.difficulty.is_empty() {
-        params.push(filter.difficulty);
-        "AND difficulty = ?"
-    } else {
-        ""
-    };
+fn migration_damage_mitigation(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["encounter", "total_damage_absorbed"])? {
+        tx.execute_batch(
+            "
+            ALTER TABLE encounter ADD COLUMN total_damage_absorbed INTEGER DEFAULT 0;
+            ALTER TABLE encounter ADD COLUMN total_damage_taken_unmitigated INTEGER DEFAULT 0;
+            ",
+        )?;
+    }
+    stmt.finalize()
+}
 
-    let order = if filter.order == 1 { "ASC" } else { "DESC" };
-    let sort = format!("e.{}", filter.sort);
-
-    let count_params = params.clone();
-
-    let query = format!(
-        "SELECT
-    e.id,
-    e.fight_start,
-    e.current_boss,
-    e.duration,
-    e.difficulty,
-    e.favorite,
-    e.cleared,
-    e.local_player,
-    e.my_dps,
-    e.players
-    FROM encounter_preview e {}
-    WHERE e.duration > ? {}
-    {} {} {} {}
-    ORDER BY {} {}
-    LIMIT ?
-    OFFSET ?",
-        join_clause,
-        boss_filter,
-        raid_clear_filter,
-        favorite_filter,
-        difficulty_filter,
-        boss_only_damage_filter,
-        sort,
-        order
-    );
+fn migration_dps_alive(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["encounter_preview", "my_dps_alive"])? {
+        info!("adding alive dps to encounter previews");
+        tx.execute_batch(
+            "
+            ALTER TABLE encounter_preview ADD COLUMN my_dps_alive INTEGER;
+            CREATE INDEX IF NOT EXISTS encounter_preview_my_dps_alive_index
+            ON encounter_preview(my_dps_alive);
+            ",
+        )?;
+    }
+    // older encounters have no dead time recorded, so alive dps is just dps
+    tx.execute(
+        "UPDATE encounter_preview SET my_dps_alive = coalesce(my_dps, 0) WHERE my_dps_alive IS NULL",
+        [],
+    )?;
+    stmt.finalize()
+}
 
-    let mut stmt = conn.prepare_cached(&query).unwrap();
+fn migration_preview_players_dps(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["encounter_preview", "players_version"])? {
+        info!("adding player dps to encounter previews");
+        tx.execute(
+            "ALTER TABLE encounter_preview ADD COLUMN players_version INTEGER NOT NULL DEFAULT 1",
+            [],
+        )?;
+    }
+    stmt.finalize()?;
 
-    let offset = (page - 1) * page_size;
+    let mut select_stmt =
+        tx.prepare("SELECT id, players FROM encounter_preview WHERE players_version < ?")?;
+    let rows = select_stmt
+        .query_map([PREVIEW_PLAYERS_VERSION], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1).unwrap_or_default(),
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    select_stmt.finalize()?;
+
+    if rows.is_empty() {
+        return Ok(());
+    }
 
-    params.push(page_size.to_string());
-    params.push(offset.to_string());
+    let mut dps_stmt =
+        tx.prepare("SELECT coalesce(dps, 0) FROM entity WHERE encounter_id = ? AND name = ?")?;
+    let mut update_stmt =
+        tx.prepare("UPDATE encounter_preview SET players = ?, players_version = ? WHERE id = ?")?;
+    for (id, players) in rows {
+        let players = app::db::parse_preview_players(&players)
+            .into_iter()
+            .map(|mut player| {
+                if player.dps == 0 {
+                    player.dps = dps_stmt
+                        .query_row(params![id, player.name], |row| row.get(0))
+                        .unwrap_or_default();
+                }
+                format!("{}:{}:{}", player.class_id, player.name, player.dps)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        update_stmt.execute(params![players, PREVIEW_PLAYERS_VERSION, id])?;
+    }
+    dps_stmt.finalize()?;
+    update_stmt.finalize()
+}
 
-    let encounter_iter = stmt
-        .query_map(params_from_iter(params), |row| {
-            let classes: String = row.get(9).unwrap_or_default();
+#[tauri::command]
+fn load_encounters_preview(
+    pool: State<'_, DbPool>,
+    page: i32,
+    page_size: i32,
+    search: String,
+    filter: SearchFilter,
+) -> EncountersOverview {
+    let conn = pool
+        .read("load_encounters_preview")
+        .expect("could not get db connection");
+    let query = app::db::PreviewQuery {
+        page,
+        page_size,
+        search,
+        filter,
+    };
+    let mut overview = app::db::load_previews(&conn, query).expect("could not query encounters");
+    name_mask::mask_previews(&mut overview.encounters);
+    overview
+}
 
-            let (classes, names) = classes
-                .split(',')
-                .map(|s| {
-                    let info: Vec<&str> = s.split(':').collect();
-                    if info.len() != 2 {
-                        return (101, "Unknown".to_string());
-                    }
-                    (info[0].parse::<i32>().unwrap_or(101), info[1].to_string())
-                })
-                .unzip();
+// only for the log view. names are masked and oversized skill breakdowns left
+// out here and not in read_encounter, so exports and uploads get all of it
+#[tauri::command(async)]
+fn load_encounter(pool: State<'_, DbPool>, id: i64) -> Result<Encounter, EncounterLoadError> {
+    let mut encounter = read_encounter(pool, id)?;
+    name_mask::mask_encounter(&mut encounter, None);
+    app::payload_guard::guard_encounter(&mut encounter, id);
+    Ok(encounter)
+}
 
-            Ok(EncounterPreview {
-                id: row.get(0)?,
-                fight_start: row.get(1)?,
-                boss_name: row.get(2)?,
-                duration: row.get(3)?,
-                classes,
-                names,
-                difficulty: row.get(4)?,
-                favorite: row.get(5)?,
-                cleared: row.get(6)?,
-                local_player: row.get(7)?,
-                my_dps: row.get(8).unwrap_or(0),
+// the encounter as saved, unmasked and with every skill, for uploading. refused
+// while names are masked so the real names never reach the webview
+#[tauri::command(async)]
+fn load_encounter_for_sync(
+    pool: State<'_, DbPool>,
+    id: i64,
+) -> Result<Encounter, EncounterLoadError> {
+    if name_mask::is_enabled() {
+        return Err(encounter_load_error(
+            "masked",
+            "names are masked, uploading is disabled".to_string(),
+        ));
+    }
+    read_encounter(pool, id)
+}
+
+// damage of every player by boss hp decile, empty when the fight had no hp
+// timeline or was saved before segments were recorded
+#[tauri::command(async)]
+fn get_hp_segment_breakdown(
+    pool: State<'_, DbPool>,
+    encounter_id: i64,
+) -> Result<Vec<HpSegmentRow>, EncounterLoadError> {
+    let mut encounter = read_encounter(pool, encounter_id)?;
+    name_mask::mask_encounter(&mut encounter, None);
+    let mut rows: Vec<HpSegmentRow> = encounter
+        .entities
+        .into_values()
+        .filter(|entity| entity.entity_type == EntityType::PLAYER)
+        .filter_map(|entity| {
+            Some(HpSegmentRow {
+                damage: entity.damage_stats.damage_by_hp_segment?,
+                name: entity.name,
+                class_id: entity.class_id,
             })
         })
-        .expect("could not query encounters");
-
-    let encounters: Vec<EncounterPreview> = encounter_iter.collect::<Result<_, _>>().unwrap();
-
-    let query = format!(
-        "
-        SELECT COUNT(*)
-        FROM encounter_preview e {}
-        WHERE duration > ? {}
-        {} {} {} {}
-        ",
-        join_clause,
-        boss_filter,
-        raid_clear_filter,
-        favorite_filter,
-        difficulty_filter,
-        boss_only_damage_filter
-    );
+        .collect();
+    rows.sort_by(|a, b| {
+        b.damage
+            .iter()
+            .sum::<i64>()
+            .cmp(&a.damage.iter().sum::<i64>())
+    });
+    Ok(rows)
+}
 
-    let count: i32 = conn
-        .query_row_and_then(&query, params_from_iter(count_params), |row| row.get(0))
-        .expect("could not get encounter count");
+// full entity for one that load_encounter left out, by its (masked) entity key
+#[tauri::command(async)]
+fn load_entity_details(
+    pool: State<'_, DbPool>,
+    id: i64,
+    name: String,
+) -> Result<EncounterEntity, EncounterLoadError> {
+    let mut encounter = read_encounter(pool, id)?;
+    name_mask::mask_encounter(&mut encounter, None);
+    encounter.entities.remove(&name).ok_or_else(|| {
+        encounter_load_error(
+            "not_found",
+            format!("entity {} not found in encounter {}", name, id),
+        )
+    })
+}
 
-    EncountersOverview {
-        encounters,
-        total_encounters: count,
-    }
+fn read_encounter(pool: State<'_, DbPool>, id: i64) -> Result<Encounter, EncounterLoadError> {
+    let conn = pool
+        .read("load_encounter")
+        .map_err(|e| encounter_load_error("database", e.to_string()))?;
+    read_encounter_with(&conn, id)
 }
 
-#[tauri::command(async)]
-fn load_encounter(window: tauri::Window, id: String) -> Encounter {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn read_encounter_with(conn: &Connection, id: i64) -> Result<Encounter, EncounterLoadError> {
     let mut encounter_stmt = conn
         .prepare_cached(
             "
@@ -802,7 +1416,9 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
        total_effective_shielding,
        applied_shield_buffs,
        boss_hp_log,
-       stagger_log
+       stagger_log,
+       total_damage_absorbed,
+       total_damage_taken_unmitigated
     FROM encounter JOIN encounter_preview USING (id)
     WHERE id = ?
     ",
@@ -810,12 +1426,14 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
         .unwrap();
 
     let mut compressed = false;
-    let mut encounter = encounter_stmt
+    let encounter = encounter_stmt
         .query_row(params![id], |row| {
-            let misc_str: String = row.get(12).unwrap_or_default();
-            let misc = serde_json::from_str::<EncounterMisc>(misc_str.as_str())
-                .map(Some)
-                .unwrap_or_default();
+            let misc: Option<EncounterMisc> = read_json_column(row, 12, false)?;
+            let misc = misc.map(|mut misc| {
+                // boss casts are loaded separately through load_boss_casts
+                misc.boss_casts = None;
+                misc
+            });
 
             let mut boss_hp_log: HashMap<String, Vec<BossHpLog>> = HashMap::new();
             let mut stagger_stats: Option<StaggerStats> = None;
@@ -842,69 +1460,19 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
                 }
             }
 
-            let buffs: HashMap<u32, StatusEffect>;
-            let debuffs: HashMap<u32, StatusEffect>;
-            let applied_shield_buffs: HashMap<u32, StatusEffect>;
+            let buffs: HashMap<u32, StatusEffect> = read_json_column(row, 10, compressed)?;
+            let debuffs: HashMap<u32, StatusEffect> = read_json_column(row, 11, compressed)?;
+            let applied_shield_buffs: HashMap<u32, StatusEffect> =
+                read_json_column(row, 19, compressed)?;
             if compressed {
-                let raw_bytes: Vec<u8> = row.get(10).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut buff_string = String::new();
-                decompress
-                    .read_to_string(&mut buff_string)
-                    .expect("could not decompress buffs");
-                buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(buff_string.as_str())
-                    .unwrap_or_default();
-
-                let raw_bytes: Vec<u8> = row.get(11).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut debuff_string = String::new();
-                decompress
-                    .read_to_string(&mut debuff_string)
-                    .expect("could not decompress debuffs");
-                debuffs =
-                    serde_json::from_str::<HashMap<u32, StatusEffect>>(debuff_string.as_str())
-                        .unwrap_or_default();
-
-                let raw_bytes: Vec<u8> = row.get(19).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut applied_shield_buff_string = String::new();
-                decompress
-                    .read_to_string(&mut applied_shield_buff_string)
-                    .expect("could not decompress applied_shield_buffs");
-                applied_shield_buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(
-                    applied_shield_buff_string.as_str(),
-                )
-                .unwrap_or_default();
-
-                let raw_bytes: Vec<u8> = row.get(20).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut boss_string = String::new();
-                decompress
-                    .read_to_string(&mut boss_string)
-                    .expect("could not decompress boss_hp_log");
-                boss_hp_log =
-                    serde_json::from_str::<HashMap<String, Vec<BossHpLog>>>(boss_string.as_str())
-                        .unwrap_or_default();
-
-                let stagger_str: String = row.get(21).unwrap_or_default();
-                stagger_stats = serde_json::from_str::<Option<StaggerStats>>(stagger_str.as_str())
-                    .unwrap_or_default();
-            } else {
-                let buff_str: String = row.get(10).unwrap_or_default();
-                buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(buff_str.as_str())
-                    .unwrap_or_default();
-                let debuff_str: String = row.get(11).unwrap_or_default();
-                debuffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(debuff_str.as_str())
-                    .unwrap_or_default();
-                let applied_shield_buff_str: String = row.get(19).unwrap_or_default();
-                applied_shield_buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(
-                    applied_shield_buff_str.as_str(),
-                )
-                .unwrap_or_default();
+                boss_hp_log = read_json_column(row, 20, true)?;
+                stagger_stats = read_json_column(row, 21, false)?;
             }
 
             let total_shielding = row.get(17).unwrap_or_default();
             let total_effective_shielding = row.get(18).unwrap_or_default();
+            let total_damage_absorbed = row.get(22).unwrap_or_default();
+            let total_damage_taken_unmitigated = row.get(23).unwrap_or_default();
 
             Ok(Encounter {
                 last_combat_packet: row.get(0)?,
@@ -917,13 +1485,16 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
                     top_damage_dealt: row.get(6)?,
                     total_damage_taken: row.get(7)?,
                     top_damage_taken: row.get(8)?,
-                    dps: row.get(9)?,
+                    // cleared for rows that had a negative dps
+                    dps: row.get(9).unwrap_or_default(),
                     buffs,
                     debuffs,
                     misc,
                     total_shielding,
                     total_effective_shielding,
                     applied_shield_buffs,
+                    total_damage_absorbed,
+                    total_damage_taken_unmitigated,
                     boss_hp_log,
                     stagger_stats,
                     ..Default::default()
@@ -935,7 +1506,14 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
                 ..Default::default()
             })
         })
-        .unwrap_or_else(|_| Encounter::default());
+        .optional()
+        .map_err(load_error_from_sql)?;
+    let Some(mut encounter) = encounter else {
+        return Err(encounter_load_error(
+            "not_found",
+            format!("encounter {} no longer exists", id),
+        ));
+    };
 
     let mut entity_stmt = conn
         .prepare_cached(
@@ -957,7 +1535,8 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
         engravings,
         spec,
         ark_passive_active,
-        ark_passive_data
+        ark_passive_data,
+        entity_index
     FROM entity
     WHERE encounter_id = ?;
     ",
@@ -966,56 +1545,22 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
 
     let entity_iter = entity_stmt
         .query_map(params![id], |row| {
-            let skills: HashMap<u32, Skill>;
-            let damage_stats: DamageStats;
-
-            if compressed {
-                let raw_bytes: Vec<u8> = row.get(7).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut skill_string = String::new();
-                decompress
-                    .read_to_string(&mut skill_string)
-                    .expect("could not decompress skills");
-                skills = serde_json::from_str::<HashMap<u32, Skill>>(skill_string.as_str())
-                    .unwrap_or_default();
-
-                let raw_bytes: Vec<u8> = row.get(8).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut damage_stats_string = String::new();
-                decompress
-                    .read_to_string(&mut damage_stats_string)
-                    .expect("could not decompress damage stats");
-                damage_stats = serde_json::from_str::<DamageStats>(damage_stats_string.as_str())
-                    .unwrap_or_default();
-            } else {
-                let skill_str: String = row.get(7).unwrap_or_default();
-                skills = serde_json::from_str::<HashMap<u32, Skill>>(skill_str.as_str())
-                    .unwrap_or_default();
-
-                let damage_stats_str: String = row.get(8).unwrap_or_default();
-                damage_stats = serde_json::from_str::<DamageStats>(damage_stats_str.as_str())
-                    .unwrap_or_default();
-            }
-
-            let skill_stats_str: String = row.get(9).unwrap_or_default();
-            let skill_stats =
-                serde_json::from_str::<SkillStats>(skill_stats_str.as_str()).unwrap_or_default();
+            let skills: HashMap<u32, Skill> = read_json_column(row, 7, compressed)?;
+            let damage_stats: DamageStats = read_json_column(row, 8, compressed)?;
+            let skill_stats: SkillStats = read_json_column(row, 9, false)?;
 
             let entity_type: String = row.get(11).unwrap_or_default();
 
-            let engravings_str: String = row.get(14).unwrap_or_default();
-            let engravings = serde_json::from_str::<Option<Vec<String>>>(engravings_str.as_str())
-                .unwrap_or_default();
+            let engravings: Option<Vec<String>> = read_json_column(row, 14, false)?;
 
             let spec: Option<String> = row.get(15).unwrap_or_default();
             let ark_passive_active: Option<bool> = row.get(16).unwrap_or_default();
 
-            let ark_passive_data_str: String = row.get(17).unwrap_or_default();
-            let ark_passive_data =
-                serde_json::from_str::<Option<ArkPassiveData>>(ark_passive_data_str.as_str())
-                    .unwrap_or_default();
+            let ark_passive_data: Option<ArkPassiveData> = read_json_column(row, 17, false)?;
+
+            let entity_index: i32 = row.get(18).unwrap_or_default();
 
-            Ok(EncounterEntity {
+            let entity = EncounterEntity {
                 name: row.get(0)?,
                 class_id: row.get(1)?,
                 class: row.get(2)?,
@@ -1035,13 +1580,28 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
                 ark_passive_active,
                 ark_passive_data,
                 ..Default::default()
-            })
+            };
+            Ok((entity_index, entity))
         })
         .unwrap();
 
+    // entities sharing a name get a unique key, the name stays as is
     let mut entities: HashMap<String, EncounterEntity> = HashMap::new();
-    for entity in entity_iter.flatten() {
-        entities.insert(entity.name.to_string(), entity);
+    for row in entity_iter {
+        // a corrupt entity fails the encounter, unreadable rows are skipped
+        let (entity_index, entity) = match row {
+            Ok(row) => row,
+            Err(e @ rusqlite::Error::FromSqlConversionFailure(..)) => {
+                return Err(load_error_from_sql(e))
+            }
+            Err(_) => continue,
+        };
+        let key = if entity_index == 0 {
+            entity.name.clone()
+        } else {
+            format!("{} ({})", entity.name, entity_index + 1)
+        };
+        entities.insert(key, entity);
     }
 
     let mut sync_stmt = conn
@@ -1059,17 +1619,83 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
 
     encounter.entities = entities;
 
-    encounter
+    Ok(encounter)
+}
+
+// reads a json column of a saved encounter, gzip compressed in newer versions.
+// empty columns from older versions read as the default value
+fn read_json_column<T: DeserializeOwned + Default>(
+    row: &rusqlite::Row,
+    idx: usize,
+    compressed: bool,
+) -> Result<T, rusqlite::Error> {
+    let corrupt = |e: String| {
+        let column = row.as_ref().column_name(idx).unwrap_or("unknown column");
+        rusqlite::Error::FromSqlConversionFailure(
+            idx,
+            rusqlite::types::Type::Text,
+            format!("{} is corrupt: {}", column, e).into(),
+        )
+    };
+
+    let json = if compressed {
+        let raw_bytes: Vec<u8> = row.get(idx).unwrap_or_default();
+        let mut json = String::new();
+        if !raw_bytes.is_empty() {
+            GzDecoder::new(raw_bytes.as_slice())
+                .read_to_string(&mut json)
+                .map_err(|e| corrupt(e.to_string()))?;
+        }
+        json
+    } else {
+        row.get::<_, String>(idx).unwrap_or_default()
+    };
+
+    if json.is_empty() || json == "null" {
+        return Ok(T::default());
+    }
+    serde_json::from_str(&json).map_err(|e| corrupt(e.to_string()))
+}
+
+fn encounter_load_error(category: &str, message: String) -> EncounterLoadError {
+    EncounterLoadError {
+        message,
+        category: category.to_string(),
+    }
+}
+
+// corrupt json columns carry the column name, anything else is a database error
+fn load_error_from_sql(e: rusqlite::Error) -> EncounterLoadError {
+    match e {
+        rusqlite::Error::FromSqlConversionFailure(_, _, e) => {
+            encounter_load_error("corrupt", e.to_string())
+        }
+        e => encounter_load_error("database", e.to_string()),
+    }
+}
+
+#[tauri::command(async)]
+fn load_boss_casts(pool: State<'_, DbPool>, encounter_id: i64) -> Result<Vec<BossCast>, String> {
+    let conn = pool.read("load_boss_casts").map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare_cached("SELECT misc FROM encounter WHERE id = ?")
+        .map_err(|e| e.to_string())?;
+
+    let misc_str: Option<String> = stmt
+        .query_row(params![encounter_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(misc_str
+        .and_then(|misc| serde_json::from_str::<EncounterMisc>(&misc).ok())
+        .and_then(|misc| misc.boss_casts)
+        .unwrap_or_default())
 }
 
 #[tauri::command]
-fn get_sync_candidates(window: tauri::Window, force_resync: bool) -> Vec<i32> {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn get_sync_candidates(pool: State<'_, DbPool>, force_resync: bool) -> Vec<i64> {
+    let conn = pool
+        .read("get_sync_candidates")
+        .expect("could not get db connection");
     let query = if force_resync { "= '0'" } else { "IS NULL" };
     let mut stmt = conn
         .prepare_cached(&format!(
@@ -1093,13 +1719,10 @@ fn get_sync_candidates(window: tauri::Window, force_resync: bool) -> Vec<i32> {
 }
 
 #[tauri::command]
-fn get_encounter_count(window: tauri::Window) -> i32 {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn get_encounter_count(pool: State<'_, DbPool>) -> i32 {
+    let conn = pool
+        .read("get_encounter_count")
+        .expect("could not get db connection");
     let mut stmt = conn
         .prepare_cached("SELECT COUNT(*) FROM encounter_preview")
         .unwrap();
@@ -1110,13 +1733,10 @@ fn get_encounter_count(window: tauri::Window) -> i32 {
 }
 
 #[tauri::command]
-fn open_most_recent_encounter(window: tauri::Window) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn open_most_recent_encounter(window: tauri::Window, pool: State<'_, DbPool>) {
+    let conn = pool
+        .read("open_most_recent_encounter")
+        .expect("could not get db connection");
     let mut stmt = conn
         .prepare_cached(
             "
@@ -1128,7 +1748,7 @@ fn open_most_recent_encounter(window: tauri::Window) {
         )
         .unwrap();
 
-    let id_result: Result<i32, rusqlite::Error> = stmt.query_row(params![], |row| row.get(0));
+    let id_result: Result<i64, rusqlite::Error> = stmt.query_row(params![], |row| row.get(0));
 
     if let Some(logs) = window.app_handle().get_window(LOGS_WINDOW_LABEL) {
         match id_result {
@@ -1143,37 +1763,257 @@ fn open_most_recent_encounter(window: tauri::Window) {
 }
 
 #[tauri::command]
-fn toggle_encounter_favorite(window: tauri::Window, id: i32) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn get_recent_encounters(pool: State<'_, DbPool>, limit: i32) -> Vec<RecentEncounter> {
+    let conn = match pool.read("get_recent_encounters") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return Vec::new();
+        }
+    };
+    // ordered by fight_start alone so sqlite walks encounter_preview_fight_start_index
     let mut stmt = conn
         .prepare_cached(
             "
-    UPDATE encounter_preview
-    SET favorite = NOT favorite
-    WHERE id = ?;
+    SELECT id, current_boss, duration, cleared, my_dps, fight_start
+    FROM encounter_preview
+    ORDER BY fight_start DESC
+    LIMIT ?;
     ",
         )
         .unwrap();
 
-    stmt.execute(params![id]).unwrap();
+    let limit = limit.clamp(1, MAX_RECENT_ENCOUNTERS);
+    stmt.query_map(params![limit], |row| {
+        Ok(RecentEncounter {
+            id: row.get(0)?,
+            boss_name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            duration: row.get(2)?,
+            cleared: row.get(3)?,
+            my_dps: row.get::<_, Option<i64>>(4)?.unwrap_or_default(),
+            fight_start: row.get(5)?,
+        })
+    })
+    .and_then(|rows| rows.collect())
+    .unwrap_or_else(|e| {
+        warn!("could not load recent encounters: {}", e);
+        Vec::new()
+    })
 }
 
+// there are no character aliases yet, so this matches the exact name
 #[tauri::command]
-fn delete_encounter(window: tauri::Window, id: String) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    conn.execute("PRAGMA foreign_keys = ON;", params![])
+fn get_character_activity(
+    pool: State<'_, DbPool>,
+    alias_or_name: String,
+    weeks: i32,
+) -> Vec<ActivityWeek> {
+    let conn = match pool.read("get_character_activity") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return Vec::new();
+        }
+    };
+    app::activity::character_activity(&conn, alias_or_name.trim(), weeks).unwrap_or_else(|e| {
+        warn!("could not load character activity: {}", e);
+        Vec::new()
+    })
+}
+
+// runs saved before splits were recorded, or without a boss hp log, have no
+// hpSplits and are left out instead of counting as zeroes
+#[tauri::command]
+fn get_best_splits(pool: State<'_, DbPool>, boss: String, difficulty: String) -> Vec<BestSplit> {
+    let conn = match pool.read("get_best_splits") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut stmt = conn
+        .prepare_cached(
+            "
+    SELECT e.id, json_extract(e.misc, '$.hpSplits')
+    FROM encounter_preview ep
+    JOIN encounter e ON e.id = ep.id
+    WHERE ep.current_boss = ? AND ep.difficulty = ? AND ep.cleared = 1
+        AND json_extract(e.misc, '$.hpSplits') IS NOT NULL;
+    ",
+        )
         .unwrap();
+
+    let runs = stmt
+        .query_map(params![boss, difficulty], |row| {
+            let splits: String = row.get(1)?;
+            Ok((row.get::<_, i64>(0)?, splits))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .unwrap_or_else(|e| {
+            warn!("could not load hp splits: {}", e);
+            Vec::new()
+        });
+
+    let mut best: BTreeMap<Reverse<i32>, BestSplit> = BTreeMap::new();
+    for (id, splits) in runs {
+        let Ok(splits) = serde_json::from_str::<Vec<HpSplit>>(&splits) else {
+            continue;
+        };
+        let mut previous = 0;
+        for split in splits {
+            let segment = split.time - previous;
+            previous = split.time;
+            let entry = best.entry(Reverse(split.percent)).or_insert(BestSplit {
+                percent: split.percent,
+                time: split.time,
+                encounter_id: id,
+                best_segment: segment,
+            });
+            if split.time < entry.time {
+                entry.time = split.time;
+                entry.encounter_id = id;
+            }
+            entry.best_segment = entry.best_segment.min(segment);
+        }
+    }
+    best.into_values().collect()
+}
+
+#[tauri::command]
+fn get_search_suggestions(
+    pool: State<'_, DbPool>,
+    suggestions: State<'_, SuggestionCache>,
+    prefix: String,
+    limit: i32,
+) -> Result<Vec<SearchSuggestion>, String> {
+    let conn = pool
+        .read("get_search_suggestions")
+        .map_err(|e| e.to_string())?;
+    suggestions
+        .suggest(&conn, &prefix, limit)
+        .map_err(|e| e.to_string())
+}
+
+// opens a loameter:// link in the logs window, a link that can't be opened
+// shows a toast instead of being dropped
+fn handle_deep_link(app: &AppHandle, url: &str) {
+    let Some(logs) = app.get_window(LOGS_WINDOW_LABEL) else {
+        return;
+    };
+    info!("opening link {}", url);
+    let result = app::deep_link::parse(url).and_then(|link| match link {
+        DeepLink::Encounter(id) => {
+            let pool = app.state::<DbPool>();
+            let exists = pool
+                .read("handle_deep_link")
+                .and_then(|conn| {
+                    conn.query_row(
+                        "SELECT EXISTS(SELECT 1 FROM encounter_preview WHERE id = ?)",
+                        [id],
+                        |row| row.get::<_, bool>(0),
+                    )
+                })
+                .map_err(|e| e.to_string())?;
+            if !exists {
+                return Err(format!("encounter {} was not found", id));
+            }
+            logs.emit("show-latest-encounter", id.to_string())
+                .map_err(|e| e.to_string())
+        }
+        DeepLink::Search(boss) => logs
+            .emit("deep-link-search", boss)
+            .map_err(|e| e.to_string()),
+    });
+    if let Err(e) = result {
+        warn!("could not open link {}: {}", url, e);
+        logs.emit("deep-link-error", e).ok();
+    }
+}
+
+// called by the logs window once it listens for deep link events
+#[tauri::command]
+fn open_pending_deep_link(window: tauri::Window) {
+    if let Some(url) = PENDING_DEEP_LINK.lock().unwrap().take() {
+        handle_deep_link(&window.app_handle(), &url);
+    }
+}
+
+#[tauri::command]
+fn open_encounter(window: tauri::Window, id: i64) {
+    if let Some(logs) = window.app_handle().get_window(LOGS_WINDOW_LABEL) {
+        logs.emit("show-latest-encounter", id.to_string()).unwrap();
+    }
+}
+
+#[tauri::command]
+fn toggle_encounter_favorite(
+    window: tauri::Window,
+    pool: State<'_, DbPool>,
+    id: i64,
+) -> Result<bool, String> {
+    let mut conn = pool
+        .write("toggle_encounter_favorite")
+        .map_err(|e| e.to_string())?;
+    let changed = toggle_favorites(&mut conn, &[id]).map_err(|e| e.to_string())?;
+    let favorite = changed
+        .first()
+        .map(|change| change.favorite)
+        .ok_or_else(|| format!("encounter {} not found", id))?;
+    window.emit_all("encounter-favorite-changed", &changed).ok();
+    Ok(favorite)
+}
+
+// ids that no longer exist are left out of the result
+#[tauri::command]
+fn toggle_encounters_favorite(
+    window: tauri::Window,
+    pool: State<'_, DbPool>,
+    ids: Vec<i64>,
+) -> Result<Vec<FavoriteChanged>, String> {
+    let mut conn = pool
+        .write("toggle_encounters_favorite")
+        .map_err(|e| e.to_string())?;
+    let changed = toggle_favorites(&mut conn, &ids).map_err(|e| e.to_string())?;
+    window.emit_all("encounter-favorite-changed", &changed).ok();
+    Ok(changed)
+}
+
+fn toggle_favorites(
+    conn: &mut Connection,
+    ids: &[i64],
+) -> Result<Vec<FavoriteChanged>, rusqlite::Error> {
+    let tx = conn.transaction()?;
+    let mut changed = Vec::with_capacity(ids.len());
+    {
+        let mut stmt = tx.prepare_cached(
+            "
+    UPDATE encounter_preview
+    SET favorite = NOT favorite
+    WHERE id = ?
+    RETURNING favorite;
+    ",
+        )?;
+        for &id in ids {
+            let favorite = stmt.query_row(params![id], |row| row.get(0)).optional()?;
+            if let Some(favorite) = favorite {
+                changed.push(FavoriteChanged { id, favorite });
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(changed)
+}
+
+#[tauri::command]
+fn delete_encounter(pool: State<'_, DbPool>, suggestions: State<'_, SuggestionCache>, id: i64) {
+    let conn = match pool.write("delete_encounter") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return;
+        }
+    };
     let mut stmt = conn
         .prepare_cached(
             "
@@ -1186,28 +2026,41 @@ fn delete_encounter(window: tauri::Window, id: String) {
     info!("deleting encounter: {}", id);
 
     stmt.execute(params![id]).unwrap();
+    suggestions.invalidate();
 }
 
-#[tauri::command]
-fn delete_encounters(window: tauri::Window, ids: Vec<i32>) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    conn.execute("PRAGMA foreign_keys = ON;", params![])
-        .unwrap();
-
-    let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
-    let placeholders_str = placeholders.join(",");
-
-    let sql = format!("DELETE FROM encounter WHERE id IN ({})", placeholders_str);
-    let mut stmt = conn.prepare_cached(&sql).unwrap();
+// a full encounter is read per preview, so batches stay small
+const REBUILD_BATCH_SIZE: usize = 100;
 
-    info!("deleting encounters: {:?}", ids);
+// returns how many encounters were deleted
+#[tauri::command]
+fn delete_encounters(
+    window: tauri::Window,
+    pool: State<'_, DbPool>,
+    ids: Vec<i64>,
+) -> Result<usize, String> {
+    let mut conn = pool.write("delete_encounters").map_err(|e| e.to_string())?;
+
+    info!("deleting {} encounters", ids.len());
+    let deleted = app::db::delete_encounters(&mut conn, &ids).map_err(|e| e.to_string())?;
+    drop(conn);
+    window.state::<SuggestionCache>().invalidate();
+
+    schedule_vacuum(window.app_handle());
+    Ok(deleted)
+}
 
-    stmt.execute(params_from_iter(ids)).unwrap();
+// reclaims space after a bulk delete without making the command wait for it
+fn schedule_vacuum(app: AppHandle) {
+    std::thread::spawn(move || {
+        let pool = app.state::<DbPool>();
+        let result = pool
+            .write("vacuum")
+            .and_then(|conn| conn.execute("VACUUM", []));
+        if let Err(e) = result {
+            warn!("failed to vacuum database: {}", e);
+        }
+    });
 }
 
 #[tauri::command]
@@ -1237,6 +2090,21 @@ fn toggle_logs_window(window: tauri::Window) {
     }
 }
 
+#[tauri::command]
+fn toggle_boss_hp_window(window: tauri::Window) {
+    toggle_boss_hp(&window.app_handle());
+}
+
+fn toggle_boss_hp(app: &AppHandle) {
+    if let Some(boss_hp) = app.get_window(BOSS_HP_WINDOW_LABEL) {
+        if boss_hp.is_visible().unwrap() {
+            boss_hp.hide().unwrap();
+        } else {
+            boss_hp.show().unwrap();
+        }
+    }
+}
+
 #[tauri::command]
 fn open_url(window: tauri::Window, url: String) {
     if let Some(logs) = window.app_handle().get_window(LOGS_WINDOW_LABEL) {
@@ -1244,242 +2112,1202 @@ fn open_url(window: tauri::Window, url: String) {
     }
 }
 
-#[tauri::command]
-fn save_settings(window: tauri::Window, settings: Settings) {
-    let mut path: PathBuf = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    path.push("settings.json");
-    let mut file = File::create(path).expect("could not create settings file");
-    file.write_all(serde_json::to_string_pretty(&settings).unwrap().as_bytes())
-        .expect("could not write to settings file");
+// moves a plaintext sync token out of settings.json into the credential store
+fn migrate_access_token(data_path: &Path, settings: &mut Settings) {
+    if store_access_token(settings) {
+        if let Err(e) = write_settings(data_path, settings) {
+            warn!("failed to rewrite settings: {}", e);
+        }
+    }
 }
 
-fn read_settings(resource_path: &Path) -> Result<Settings, Box<dyn std::error::Error>> {
-    let mut path = resource_path.to_path_buf();
-    path.push("settings.json");
-    let mut file = File::open(path)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    let settings = serde_json::from_str(&contents)?;
-    Ok(settings)
+// returns true when the token was moved and the settings need to be rewritten
+fn store_access_token(settings: &mut Settings) -> bool {
+    if settings.sync.access_token.is_empty() {
+        return false;
+    }
+    match app::secrets::set_secret(app::secrets::SYNC_ACCESS_TOKEN, &settings.sync.access_token) {
+        Ok(_) => {
+            info!("moved sync access token to the credential store");
+            settings.sync.access_token.clear();
+            true
+        }
+        Err(e) => {
+            warn!("{}", e);
+            false
+        }
+    }
+}
+
+fn write_settings(data_path: &Path, settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::create(data_path.join("settings.json"))?;
+    file.write_all(serde_json::to_string_pretty(settings)?.as_bytes())?;
+    Ok(())
 }
 
 #[tauri::command]
-fn get_settings(window: tauri::Window) -> Option<Settings> {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    read_settings(&path).ok()
+fn set_secret(name: String, value: String) -> Result<(), String> {
+    app::secrets::set_secret(&name, &value)
 }
 
 #[tauri::command]
-fn open_folder(path: String) {
-    let mut path = path;
-    if path.contains("USERPROFILE") {
-        if let Ok(user_dir) = std::env::var("USERPROFILE") {
-            path = path.replace("USERPROFILE", user_dir.as_str());
-        }
-    }
-    info!("open_folder: {}", path);
-    Command::new("explorer").args([path.as_str()]).spawn().ok();
+fn get_secret(name: String) -> Result<Option<String>, String> {
+    app::secrets::get_secret(&name)
 }
 
 #[tauri::command]
-fn open_db_path(window: tauri::Window) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    info!("open_db_path: {}", path.display());
-    Command::new("explorer")
-        .args([path.to_str().unwrap()])
-        .spawn()
-        .ok();
+fn delete_secret(name: String) -> Result<(), String> {
+    app::secrets::delete_secret(&name)
 }
 
 #[tauri::command]
-fn delete_encounters_below_min_duration(
+fn get_ignored_entities() -> IgnoredEntities {
+    parser::ignore_list::get()
+}
+
+#[tauri::command]
+fn add_ignored_entity(
     window: tauri::Window,
-    min_duration: i64,
-    keep_favorites: bool,
-) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    if keep_favorites {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE duration < ? AND favorite = 0
-            )",
-            params![min_duration * 1000],
-        )
-        .unwrap();
-    } else {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE duration < ?
-            )",
-            params![min_duration * 1000],
-        )
-        .unwrap();
+    name: Option<String>,
+    npc_id: Option<u32>,
+) -> Result<IgnoredEntities, String> {
+    update_ignored_entities(&window, |general| {
+        add_ignored(general, name.as_deref(), npc_id)
+    })
+}
+
+fn add_ignored(general: &mut GeneralSettings, name: Option<&str>, npc_id: Option<u32>) {
+    if let Some(name) = name.map(|name| name.trim()) {
+        if !name.is_empty() && !general.ignored_names.iter().any(|n| n == name) {
+            general.ignored_names.push(name.to_string());
+        }
+    }
+    if let Some(npc_id) = npc_id {
+        if !general.ignored_npc_ids.contains(&npc_id) {
+            general.ignored_npc_ids.push(npc_id);
+        }
     }
-    conn.execute("VACUUM", params![]).unwrap();
 }
 
 #[tauri::command]
-fn sync(window: tauri::Window, encounter: i32, upstream: String, failed: bool) {
-    let path = window
+fn remove_ignored_entity(
+    window: tauri::Window,
+    name: Option<String>,
+    npc_id: Option<u32>,
+) -> Result<IgnoredEntities, String> {
+    update_ignored_entities(&window, |general| {
+        if let Some(name) = name.as_ref() {
+            general.ignored_names.retain(|n| n != name.trim());
+        }
+        if let Some(npc_id) = npc_id {
+            general.ignored_npc_ids.retain(|&id| id != npc_id);
+        }
+    })
+}
+
+// applied to the parser right away, the windows are told so their copy of the
+// settings doesn't put the old list back on their next save
+fn update_ignored_entities(
+    window: &tauri::Window,
+    update: impl FnOnce(&mut GeneralSettings),
+) -> Result<IgnoredEntities, String> {
+    if app::backup::config_restored() {
+        return Err("settings were restored from a backup, restart first".to_string());
+    }
+    let data_path = app::storage::data_dir(&window.app_handle());
+    let settings = update_settings_file(&data_path, update)?;
+    parser::ignore_list::set(
+        &settings.general.ignored_names,
+        &settings.general.ignored_npc_ids,
+    );
+    let ignored = parser::ignore_list::get();
+    window
         .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+        .emit_all("ignored-entities-changed", &ignored)
+        .ok();
+    Ok(ignored)
+}
 
-    conn.execute(
-        "
-        INSERT OR REPLACE INTO sync_logs (encounter_id, upstream_id, failed)
-        VALUES(?, ?, ?);
-        ",
-        params![encounter, upstream, failed],
-    )
-    .unwrap();
+// without a settings.json the defaults are taken from an empty one, the derived
+// Default skips the field defaults serde fills in
+fn update_settings_file(
+    data_path: &Path,
+    update: impl FnOnce(&mut GeneralSettings),
+) -> Result<Settings, String> {
+    let mut settings = if data_path.join("settings.json").exists() {
+        read_settings(data_path).map_err(|e| e.to_string())?
+    } else {
+        serde_json::from_str::<Settings>("{}").map_err(|e| e.to_string())?
+    };
+    update(&mut settings.general);
+    write_settings(data_path, &settings).map_err(|e| e.to_string())?;
+    Ok(settings)
 }
 
 #[tauri::command]
-fn delete_all_uncleared_encounters(window: tauri::Window, keep_favorites: bool) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    if keep_favorites {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE cleared = 0 AND favorite = 0
-            )",
-            [],
-        )
-        .unwrap();
-    } else {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE cleared = 0
-            )",
-            [],
-        )
-        .unwrap();
+fn save_settings(window: tauri::Window, mut settings: Settings) -> Result<(), String> {
+    if app::backup::config_restored() {
+        warn!("settings were restored from a backup, not saving until restart");
+        return Ok(());
     }
-    conn.execute("VACUUM", params![]).unwrap();
+    store_access_token(&mut settings);
+    let mut path = app::storage::data_dir(&window.app_handle());
+    path.push("settings.json");
+    // a read-only data dir without fallback, the settings still apply for this run
+    let written = File::create(&path).and_then(|mut file| {
+        file.write_all(serde_json::to_string_pretty(&settings).unwrap().as_bytes())
+    });
+    if let Err(e) = &written {
+        error!("failed to write {}: {}", path.display(), e);
+    }
+    register_clickthrough_shortcut(
+        &window.app_handle(),
+        &settings.shortcuts.toggle_clickthrough,
+    );
+    app::peek::set_shortcut(&settings.shortcuts.peek);
+    apply_settings(&settings);
+    window.app_handle().trigger_global(
+        "auto-favorite-request",
+        Some(
+            json!({
+                "enabled": settings.general.auto_favorite_pbs,
+                "minDurationS": settings.logs.min_encounter_duration,
+            })
+            .to_string(),
+        ),
+    );
+    window.app_handle().trigger_global(
+        "emit-interval-request",
+        Some(settings.general.emit_interval_ms.to_string()),
+    );
+    window.app_handle().trigger_global(
+        "fade-settings-request",
+        Some(
+            json!({
+                "enabled": settings.general.fade_out_of_combat,
+                "delayS": settings.general.fade_delay_s,
+                "opacity": settings.general.fade_opacity,
+            })
+            .to_string(),
+        ),
+    );
+    written.map_err(|e| format!("could not save settings: {}", e))
 }
 
+// class names, colors and icons with the user's color overrides applied
 #[tauri::command]
-fn delete_all_encounters(window: tauri::Window, keep_favorites: bool) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-
-    if keep_favorites {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE favorite = 0
-            )",
-            [],
-        )
-        .unwrap();
-    } else {
-        conn.execute("DELETE FROM encounter", []).unwrap();
+fn get_class_metadata(window: tauri::Window) -> HashMap<u32, ClassMetadata> {
+    let data_path = app::storage::data_dir(&window.app_handle());
+    let overrides = read_settings(&data_path)
+        .map(|settings| settings.ui.class_colors)
+        .unwrap_or_default();
+    let mut classes = CLASS_METADATA.clone();
+    for (class_id, color) in overrides {
+        classes
+            .entry(class_id)
+            .or_insert_with(|| parser::utils::get_class_metadata(class_id))
+            .color = color;
     }
-    conn.execute("VACUUM", []).unwrap();
+    classes
 }
 
-#[tauri::command]
-fn get_db_info(window: tauri::Window, min_duration: i64) -> EncounterDbInfo {
-    let mut path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    let encounter_count = conn
-        .query_row("SELECT COUNT(*) FROM encounter_preview", [], |row| {
-            row.get(0)
-        })
-        .unwrap();
-    let encounter_filtered_count = conn
-        .query_row(
-            "SELECT COUNT(*) FROM encounter_preview WHERE duration >= ?",
-            params![min_duration * 1000],
-            |row| row.get(0),
-        )
-        .unwrap();
+/// Pushes the settings that live in global parser and app state, shared by
+/// both startup paths and `save_settings`.
+fn apply_settings(settings: &Settings) {
+    app::pipe_server::set_enabled(settings.general.pipe_server);
+    name_mask::set_enabled(settings.general.mask_names);
+    app::payload_guard::set_limit_mb(settings.general.ipc_payload_limit_mb);
+    parser::dungeon::set_mode(&settings.general.dungeon_mode);
+    parser::clock::set_gap_mode(&settings.general.clock_gap_mode);
+    parser::clients::set_main_character(&settings.general.main_character);
+    app::number_format::set_format(&settings.ui.number_format);
+    parser::prepull::set(
+        settings.general.trim_prepull,
+        settings.general.trim_prepull_max_percent,
+    );
+    parser::ignore_list::set(
+        &settings.general.ignored_names,
+        &settings.general.ignored_npc_ids,
+    );
+}
 
-    path.push("encounters.db");
-    let metadata = fs::metadata(path).expect("could not get db metadata");
+fn read_settings(data_path: &Path) -> Result<Settings, Box<dyn std::error::Error>> {
+    let mut path = data_path.to_path_buf();
+    path.push("settings.json");
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let value: serde_json::Value = serde_json::from_str(&contents)?;
+    let has_toggle_clickthrough = value.pointer("/shortcuts/toggleClickthrough").is_some();
+    let mut settings: Settings = serde_json::from_value(value)?;
+    // the disable clickthrough shortcut became the clickthrough toggle
+    let shortcuts = &mut settings.shortcuts;
+    if !has_toggle_clickthrough && !shortcuts.disable_clickthrough.key.is_empty() {
+        shortcuts.toggle_clickthrough = shortcuts.disable_clickthrough.clone();
+    }
+    Ok(settings)
+}
+
+// used at startup, a settings.json that can't be read is moved aside so it
+// isn't overwritten by the defaults and the error is kept for the frontend
+fn load_settings(data_path: &Path) -> Option<Settings> {
+    let path = data_path.join("settings.json");
+    if !path.exists() {
+        return None;
+    }
+
+    match read_settings(data_path) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            error!("failed to read settings, using defaults: {}", e);
+            let broken_path = data_path.join(format!(
+                "settings.json.broken-{}",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ));
+            match fs::rename(&path, &broken_path) {
+                Ok(_) => info!("moved broken settings to {}", broken_path.display()),
+                Err(e) => warn!("failed to move broken settings: {}", e),
+            }
+            *SETTINGS_LOAD_ERROR.lock().unwrap() = Some(e.to_string());
+            None
+        }
+    }
+}
+
+// the settings are saved right after startup, so the error is only cleared
+// once the user dismissed it
+#[tauri::command]
+fn dismiss_settings_load_error() {
+    SETTINGS_LOAD_ERROR.lock().unwrap().take();
+}
+
+#[tauri::command]
+fn get_settings(window: tauri::Window) -> SettingsState {
+    let load_error = SETTINGS_LOAD_ERROR.lock().unwrap().clone();
+    // the meter asks for its settings once its listeners are set up
+    if let Some(load_error) = load_error.as_ref() {
+        if window.label() == METER_WINDOW_LABEL
+            && !SETTINGS_LOAD_ERROR_EMITTED.swap(true, Ordering::Relaxed)
+        {
+            window.emit("settings-load-error", load_error).ok();
+        }
+    }
+    if let Some(db_version) = window.state::<DbPool>().newer_version() {
+        if window.label() == METER_WINDOW_LABEL
+            && !DB_VERSION_TOO_NEW_EMITTED.swap(true, Ordering::Relaxed)
+        {
+            let payload = DbVersionTooNew {
+                db_version,
+                app_version: DB_SCHEMA_VERSION,
+            };
+            window.emit("db-version-too-new", payload).ok();
+        }
+    }
+
+    SettingsState {
+        settings: read_settings(&app::storage::data_dir(&window.app_handle())).ok(),
+        load_error,
+    }
+}
+
+#[tauri::command]
+fn stop_and_collect_dump() -> Result<String, String> {
+    parser::packet_dump::stop_and_collect().map(|path| path.to_string_lossy().to_string())
+}
+
+// writes the fight in progress to a file for a bug report without ending it,
+// next to the packet dumps unless a path is given. returns the written path
+#[tauri::command]
+async fn dump_live_encounter(
+    window: tauri::Window,
+    path: Option<String>,
+) -> Result<String, String> {
+    let encounter = task::spawn_blocking(parser::live_delta::request_dump)
+        .await
+        .map_err(|e| e.to_string())??;
+    let meter_version = window.app_handle().package_info().version.to_string();
+    let json =
+        export::native::to_live_dump(encounter, &meter_version, parser::metrics::get_metrics())?;
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir =
+                app::storage::data_dir(&window.app_handle()).join(parser::packet_dump::DUMP_DIR);
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            dir.join(format!(
+                "live-{}.json",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ))
+        }
+    };
+    fs::write(&path, json).map_err(|e| format!("failed to write {:?}: {}", path, e))?;
+    info!("dumped live encounter to {:?}", path);
+    Ok(path.to_string_lossy().to_string())
+}
+
+// dev tool, feeds a recorded capture through a second parser next to the live one
+#[tauri::command]
+fn replay_capture(
+    window: tauri::Window,
+    path: String,
+    realtime: Option<bool>,
+) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        return Err(format!("capture {:?} not found", path));
+    }
+    let app_handle = window.app_handle();
+    let meter_window = app_handle
+        .get_window(METER_WINDOW_LABEL)
+        .ok_or("meter window not found")?;
+    let settings = read_settings(&app::storage::data_dir(&app_handle)).ok();
+    spawn_parser(
+        meter_window,
+        PacketSource::Replay {
+            path,
+            realtime: realtime.unwrap_or(false),
+        },
+        settings,
+    );
+    Ok(())
+}
+
+#[tauri::command]
+fn export_encounter_compat(window: tauri::Window, id: i64, path: String) -> Result<(), String> {
+    let meter_version = window.app_handle().package_info().version.to_string();
+    let encounter = read_encounter(window.state(), id).map_err(|e| e.message)?;
+    let session = export::loa_details::to_session(&encounter, &meter_version);
+    let json = serde_json::to_string(&session).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("failed to write {}: {}", path, e))
+}
+
+const IMPORT_BATCH_SIZE: usize = 50;
+// fights on the same boss starting this close together are the same encounter
+const DUPLICATE_FIGHT_START_MS: i64 = 5000;
+
+// imports encounters from another meter's history, a folder of session files
+// or a single file holding one session or an array of them
+#[tauri::command]
+async fn import_foreign_db(
+    window: tauri::Window,
+    pool: State<'_, DbPool>,
+    path: String,
+    format: String,
+) -> Result<ImportProgress, String> {
+    if format != export::loa_details::FORMAT {
+        return Err(format!("unsupported import format: {}", format));
+    }
+
+    let path = PathBuf::from(path);
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(&path)
+            .map_err(|e| format!("failed to read {:?}: {}", path, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        files.sort();
+        files
+    } else if path.exists() {
+        vec![path]
+    } else {
+        return Err(format!("{:?} not found", path));
+    };
+
+    let mut sessions: Vec<Option<Value>> = Vec::new();
+    for file in files {
+        match fs::read_to_string(&file)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Value>(&json).ok())
+        {
+            Some(Value::Array(array)) => sessions.extend(array.into_iter().map(Some)),
+            Some(session) => sessions.push(Some(session)),
+            None => {
+                warn!("could not read session file {:?}", file);
+                sessions.push(None);
+            }
+        }
+    }
+
+    let mut conn = pool.write("import_foreign_db").map_err(|e| e.to_string())?;
+    let meter_version = format!("{} import", export::loa_details::FORMAT);
+
+    let mut progress = ImportProgress {
+        total: sessions.len(),
+        ..Default::default()
+    };
+    info!("importing {} {} sessions", progress.total, format);
+
+    for batch in sessions.chunks(IMPORT_BATCH_SIZE) {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for session in batch {
+            let Some(encounter) = session.as_ref().and_then(export::loa_details::from_session)
+            else {
+                progress.failed += 1;
+                continue;
+            };
+
+            if find_duplicate_encounter(&tx, encounter.fight_start, &encounter.current_boss_name)
+                .map_err(|e| e.to_string())?
+                .is_some()
+            {
+                progress.skipped += 1;
+                continue;
+            }
+
+            insert_imported_encounter(&tx, encounter, meter_version.clone());
+            progress.imported += 1;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        window
+            .emit("import-progress", progress.clone())
+            .map_err(|e| e.to_string())?;
+    }
+
+    info!(
+        "import finished: {} imported, {} skipped, {} failed",
+        progress.imported, progress.skipped, progress.failed
+    );
+    window.state::<SuggestionCache>().invalidate();
+    Ok(progress)
+}
+
+#[tauri::command]
+fn export_encounter(window: tauri::Window, id: i64, path: String) -> Result<(), String> {
+    let meter_version = window.app_handle().package_info().version.to_string();
+    let boss_casts = load_boss_casts(window.state(), id)?;
+    let mut encounter = read_encounter(window.state(), id).map_err(|e| e.message)?;
+    if let Some(misc) = encounter.encounter_damage_stats.misc.as_mut() {
+        if !boss_casts.is_empty() {
+            misc.boss_casts = Some(boss_casts);
+        }
+    }
+    let link = app::deep_link::encounter_url(id);
+    let json = export::native::to_json(encounter, &meter_version, Some(link))?;
+    fs::write(&path, json).map_err(|e| format!("failed to write {}: {}", path, e))
+}
+
+// puts a compact LOAM1: string of the encounter on the clipboard for pasting
+// in chat, returns its length
+#[tauri::command]
+fn copy_encounter_to_clipboard(window: tauri::Window, id: i64) -> Result<usize, String> {
+    let meter_version = window.app_handle().package_info().version.to_string();
+    let mut encounter = read_encounter(window.state(), id).map_err(|e| e.message)?;
+    name_mask::mask_encounter(&mut encounter, None);
+    let text = export::share::encode(&encounter, &meter_version)?;
+    window
+        .app_handle()
+        .clipboard_manager()
+        .write_text(text.clone())
+        .map_err(|e| e.to_string())?;
+    Ok(text.len())
+}
+
+// decodes a LOAM1: string from the clipboard. with import it is also saved,
+// with only the players and skills the string carried
+#[tauri::command]
+fn paste_encounter_from_clipboard(
+    window: tauri::Window,
+    import: Option<bool>,
+) -> Result<PastedEncounter, String> {
+    let text = window
+        .app_handle()
+        .clipboard_manager()
+        .read_text()
+        .map_err(|e| e.to_string())?
+        .ok_or("clipboard is empty")?;
+    let shared = export::share::decode(&text)?;
+    if !import.unwrap_or(false) {
+        return Ok(PastedEncounter {
+            encounter: shared,
+            id: None,
+        });
+    }
+
+    let pool = window.state::<DbPool>();
+    let mut conn = pool.write("paste_encounter").map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if let Some(id) = find_duplicate_encounter(&tx, shared.fight_start, &shared.boss)
+        .map_err(|e| e.to_string())?
+    {
+        info!("pasted encounter is already saved as {}", id);
+        return Ok(PastedEncounter {
+            encounter: shared,
+            id: Some(id),
+        });
+    }
+    let meter_version = shared.meter_version.clone();
+    let id = insert_imported_encounter(
+        &tx,
+        export::share::to_encounter(shared.clone()),
+        meter_version,
+    );
+    tx.commit().map_err(|e| e.to_string())?;
+    window.state::<SuggestionCache>().invalidate();
+    Ok(PastedEncounter {
+        encounter: shared,
+        id: Some(id),
+    })
+}
+
+// returns the id of the imported encounter, or of the one it duplicates
+#[tauri::command]
+fn import_encounter(
+    pool: State<'_, DbPool>,
+    suggestions: State<'_, SuggestionCache>,
+    path: String,
+) -> Result<i64, String> {
+    let json = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let export = export::native::from_json(&json)?;
+    let encounter = export.encounter;
+
+    let mut conn = pool.write("import_encounter").map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    if let Some(id) =
+        find_duplicate_encounter(&tx, encounter.fight_start, &encounter.current_boss_name)
+            .map_err(|e| e.to_string())?
+    {
+        info!("encounter from {} is already saved as {}", path, id);
+        return Ok(id);
+    }
+
+    info!(
+        "importing encounter exported by v{} (schema v{})",
+        export.meter_version, export.schema_version
+    );
+    let id = insert_imported_encounter(&tx, encounter, export.meter_version);
+    tx.commit().map_err(|e| e.to_string())?;
+    suggestions.invalidate();
+    Ok(id)
+}
+
+// saves an encounter that didn't come from the live parser, so there are no
+// per-hit logs; whatever the source kept in misc is carried over
+fn insert_imported_encounter(tx: &Transaction, encounter: Encounter, meter_version: String) -> i64 {
+    let misc = encounter
+        .encounter_damage_stats
+        .misc
+        .clone()
+        .unwrap_or_default();
+    let raid_clear = misc.raid_clear.unwrap_or(encounter.cleared);
+    let raid_difficulty = encounter.difficulty.clone().unwrap_or_default();
+    let boss_hp_log = encounter.encounter_damage_stats.boss_hp_log.clone();
+    let mut party_info: Vec<(i32, Vec<String>)> =
+        misc.party_info.unwrap_or_default().into_iter().collect();
+    party_info.sort_by_key(|(index, _)| *index);
+
+    parser::utils::insert_data(
+        tx,
+        EncounterSave {
+            encounter,
+            boss_hp_log,
+            raid_clear,
+            party_info: party_info.into_iter().map(|(_, party)| party).collect(),
+            raid_difficulty,
+            region: misc.region,
+            meter_version,
+            ntp_fight_start: misc.ntp_fight_start.unwrap_or_default(),
+            rdps_valid: misc.rdps_valid.unwrap_or_default(),
+            manual: true,
+            boss_casts: misc.boss_casts.unwrap_or_default(),
+            stagger_checks: misc.stagger_checks.unwrap_or_default(),
+            session_id: misc.session_id,
+            dungeon: misc.dungeon,
+            clock_gaps: misc.clock_gaps.unwrap_or_default(),
+            death_causes: misc
+                .top_killer
+                .map(|top_killer| top_killer.causes)
+                .unwrap_or_default(),
+            client_index: misc.client_index,
+            prepull_damage: misc.prepull_damage,
+            ..Default::default()
+        },
+    )
+}
+
+// an encounter on the same boss that started within DUPLICATE_FIGHT_START_MS
+fn find_duplicate_encounter(
+    tx: &Transaction,
+    fight_start: i64,
+    boss: &str,
+) -> Result<Option<i64>, rusqlite::Error> {
+    let mut stmt = tx.prepare_cached(
+        "
+    SELECT id
+    FROM encounter_preview
+    WHERE current_boss = ? AND fight_start BETWEEN ? AND ?
+    LIMIT 1
+    ",
+    )?;
+    stmt.query_row(
+        params![
+            boss,
+            fight_start - DUPLICATE_FIGHT_START_MS,
+            fight_start + DUPLICATE_FIGHT_START_MS
+        ],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+// slowest recent db commands and queries, for support
+#[tauri::command]
+fn get_query_stats() -> Vec<app::query_stats::QueryStat> {
+    app::query_stats::get_query_stats()
+}
+
+#[tauri::command]
+fn get_live_encounter() -> Option<Encounter> {
+    parser::live_delta::get_live_encounter()
+}
+
+#[tauri::command]
+fn get_capture_status() -> CaptureStatus {
+    parser::capture_status::get_capture_status()
+}
+
+// follows the given game client while several run at once, none goes back to
+// the main character setting
+#[tauri::command]
+fn set_tracked_connection(index: Option<usize>) -> Result<(), String> {
+    parser::clients::set_tracked(index)
+}
+
+// examples for the settings page, format is the unsaved one being edited
+#[tauri::command]
+fn get_number_format_preview(format: NumberFormat) -> Vec<NumberFormatExample> {
+    app::number_format::preview(&format)
+}
+
+#[tauri::command]
+fn get_parser_metrics() -> ParserMetrics {
+    parser::metrics::get_metrics()
+}
+
+#[tauri::command]
+fn get_session_stats() -> SessionStats {
+    parser::session_stats::get_stats()
+}
+
+#[tauri::command]
+fn reset_session_stats() {
+    parser::session_stats::reset();
+    info!("session stats reset");
+}
+
+#[tauri::command]
+fn open_folder(path: String) {
+    let mut path = path;
+    if path.contains("USERPROFILE") {
+        if let Ok(user_dir) = std::env::var("USERPROFILE") {
+            path = path.replace("USERPROFILE", user_dir.as_str());
+        }
+    }
+    info!("open_folder: {}", path);
+    Command::new("explorer").args([path.as_str()]).spawn().ok();
+}
+
+#[tauri::command]
+fn open_db_path(window: tauri::Window) {
+    let path = app::storage::data_dir(&window.app_handle());
+    info!("open_db_path: {}", path.display());
+    Command::new("explorer")
+        .args([path.to_str().unwrap()])
+        .spawn()
+        .ok();
+}
+
+#[tauri::command]
+fn request_destructive_token(tokens: State<'_, DestructiveTokens>, action: String) -> String {
+    tokens.issue(&action)
+}
+
+// mass deletes need a token from request_destructive_token, a dry run only
+// returns how many encounters would be deleted
+#[tauri::command]
+fn delete_encounters_below_min_duration(
+    pool: State<'_, DbPool>,
+    suggestions: State<'_, SuggestionCache>,
+    tokens: State<'_, DestructiveTokens>,
+    min_duration: i64,
+    keep_favorites: bool,
+    confirm_token: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<usize, String> {
+    let dry_run = dry_run.unwrap_or_default();
+    if !dry_run {
+        tokens.consume(
+            "delete_encounters_below_min_duration",
+            confirm_token.as_deref(),
+        )?;
+    }
+    let conn = pool
+        .write("delete_encounters_below_min_duration")
+        .map_err(|e| e.to_string())?;
+    let filter = if keep_favorites {
+        "duration < ? AND favorite = 0"
+    } else {
+        "duration < ?"
+    };
+    let deleted = app::db::delete_previews_where(&conn, filter, &[&(min_duration * 1000)], dry_run)
+        .map_err(|e| e.to_string())?;
+    if !dry_run {
+        suggestions.invalidate();
+    }
+    Ok(deleted)
+}
+
+#[tauri::command]
+fn sync(pool: State<'_, DbPool>, encounter: i64, upstream: String, failed: bool) {
+    let conn = match pool.write("sync") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return;
+        }
+    };
+
+    conn.execute(
+        "
+        INSERT OR REPLACE INTO sync_logs (encounter_id, upstream_id, failed)
+        VALUES(?, ?, ?);
+        ",
+        params![encounter, upstream, failed],
+    )
+    .unwrap();
+}
+
+#[tauri::command]
+fn delete_all_uncleared_encounters(
+    pool: State<'_, DbPool>,
+    suggestions: State<'_, SuggestionCache>,
+    tokens: State<'_, DestructiveTokens>,
+    keep_favorites: bool,
+    confirm_token: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<usize, String> {
+    let dry_run = dry_run.unwrap_or_default();
+    if !dry_run {
+        tokens.consume("delete_all_uncleared_encounters", confirm_token.as_deref())?;
+    }
+    let conn = pool
+        .write("delete_all_uncleared_encounters")
+        .map_err(|e| e.to_string())?;
+    let filter = if keep_favorites {
+        "cleared = 0 AND favorite = 0"
+    } else {
+        "cleared = 0"
+    };
+    let deleted =
+        app::db::delete_previews_where(&conn, filter, &[], dry_run).map_err(|e| e.to_string())?;
+    if !dry_run {
+        suggestions.invalidate();
+    }
+    Ok(deleted)
+}
+
+#[tauri::command]
+fn delete_all_encounters(
+    pool: State<'_, DbPool>,
+    suggestions: State<'_, SuggestionCache>,
+    tokens: State<'_, DestructiveTokens>,
+    keep_favorites: bool,
+    confirm_token: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<usize, String> {
+    let dry_run = dry_run.unwrap_or_default();
+    if !dry_run {
+        tokens.consume("delete_all_encounters", confirm_token.as_deref())?;
+    }
+    let conn = pool
+        .write("delete_all_encounters")
+        .map_err(|e| e.to_string())?;
+    let filter = if keep_favorites { "favorite = 0" } else { "1" };
+    let deleted =
+        app::db::delete_previews_where(&conn, filter, &[], dry_run).map_err(|e| e.to_string())?;
+    if !dry_run {
+        suggestions.invalidate();
+    }
+    Ok(deleted)
+}
+
+// how much deleting a cleanup selection would free, without deleting
+// anything. the length scan reads every affected row, which takes a while on
+// big databases, so it runs on a blocking thread and reports progress
+#[tauri::command]
+async fn estimate_cleanup(
+    window: tauri::Window,
+    min_duration: i64,
+    older_than: Option<i64>,
+    uncleared_only: bool,
+    keep_favorites: bool,
+) -> Result<CleanupEstimate, String> {
+    task::spawn_blocking(move || {
+        let pool = window.state::<DbPool>();
+        let conn = pool.read("estimate_cleanup").map_err(|e| e.to_string())?;
+        let (filter, params) =
+            app::db::cleanup_filter(min_duration, older_than, uncleared_only, keep_favorites);
+        app::db::estimate_cleanup(&conn, &filter, &params, |progress| {
+            window.emit("cleanup-estimate-progress", progress).ok();
+        })
+        .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// sizes of the files the app keeps besides encounters, by category
+#[tauri::command(async)]
+fn get_storage_breakdown(window: tauri::Window) -> Result<Vec<StorageCategory>, String> {
+    let dirs = app::storage::StorageDirs::new(&window.app_handle())?;
+    Ok(app::storage::storage_breakdown(&dirs))
+}
+
+// where the data lives when the usual data dir was read-only, the
+// storage-fallback event is emitted before the windows can listen
+#[tauri::command]
+fn get_storage_fallback(window: tauri::Window) -> Option<StorageFallback> {
+    app::storage::fallback(&window.app_handle())
+}
+
+// deletes the files of the categories older than older_than_days, the live
+// database, wal and settings are always kept
+#[tauri::command(async)]
+fn cleanup_storage(
+    window: tauri::Window,
+    categories: Vec<String>,
+    older_than_days: u32,
+) -> Result<Vec<StorageFreed>, String> {
+    let dirs = app::storage::StorageDirs::new(&window.app_handle())?;
+    app::storage::cleanup_storage(&dirs, &categories, older_than_days)
+}
+
+// what killed players on recent wipes of a boss, since is a unix ms timestamp
+#[tauri::command]
+fn get_death_causes(
+    pool: State<'_, DbPool>,
+    boss: String,
+    difficulty: Option<String>,
+    since: Option<i64>,
+) -> Result<Vec<DeathCause>, String> {
+    let conn = pool.read("get_death_causes").map_err(|e| e.to_string())?;
+    app::db::death_causes(&conn, &boss, difficulty.as_deref(), since.unwrap_or(0))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_db_info(pool: State<'_, DbPool>, min_duration: i64) -> EncounterDbInfo {
+    let conn = pool
+        .read("get_db_info")
+        .expect("could not get db connection");
+    let encounter_count = conn
+        .query_row("SELECT COUNT(*) FROM encounter_preview", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    let encounter_filtered_count = conn
+        .query_row(
+            "SELECT COUNT(*) FROM encounter_preview WHERE duration >= ?",
+            params![min_duration * 1000],
+            |row| row.get(0),
+        )
+        .unwrap();
+
+    let metadata = fs::metadata(pool.path()).expect("could not get db metadata");
 
     let size_in_bytes = metadata.len();
     let size_in_kb = size_in_bytes as f64 / 1024.0;
     let size_in_mb = size_in_kb / 1024.0;
     let size_in_gb = size_in_mb / 1024.0;
 
-    let size_str = if size_in_gb >= 1.0 {
-        format!("{:.2} GB", size_in_gb)
-    } else if size_in_mb >= 1.0 {
-        format!("{:.2} MB", size_in_mb)
+    let size_str = if size_in_gb >= 1.0 {
+        format!("{:.2} GB", size_in_gb)
+    } else if size_in_mb >= 1.0 {
+        format!("{:.2} MB", size_in_mb)
+    } else {
+        format!("{:.2} KB", size_in_kb)
+    };
+
+    EncounterDbInfo {
+        size: size_str,
+        total_encounters: encounter_count,
+        total_encounters_filtered: encounter_filtered_count,
+    }
+}
+
+#[tauri::command]
+fn optimize_database(pool: State<'_, DbPool>) {
+    let conn = match pool.write("optimize_database") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return;
+        }
+    };
+    conn.execute_batch(
+        "
+        INSERT INTO encounter_search(encounter_search) VALUES('optimize');
+        VACUUM;
+        ",
+    )
+    .unwrap();
+    info!("optimized database");
+}
+
+// renames bosses saved under a localized name, e.g. imported from a client
+// in another language, so they group with the english ones in search and
+// best splits. the original name is kept in misc
+#[tauri::command]
+fn normalize_boss_names(window: tauri::Window, pool: State<'_, DbPool>) -> Result<usize, String> {
+    let mut conn = pool
+        .write("normalize_boss_names")
+        .map_err(|e| e.to_string())?;
+    let renamed = normalize_boss_names_tx(&mut conn).map_err(|e| e.to_string())?;
+    drop(conn);
+    if renamed > 0 {
+        window.state::<SuggestionCache>().invalidate();
+    }
+    info!("normalized {} boss names", renamed);
+    Ok(renamed)
+}
+
+fn normalize_boss_names_tx(conn: &mut Connection) -> Result<usize, rusqlite::Error> {
+    let tx = conn.transaction()?;
+    let bosses: Vec<(i64, String, u32)> = tx
+        .prepare(
+            "SELECT ep.id, ep.current_boss, en.npc_id
+            FROM encounter_preview ep
+            JOIN entity en ON en.encounter_id = ep.id AND en.name = ep.current_boss
+            WHERE en.npc_id > 0",
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let mut renamed = 0;
+    for (id, localized, npc_id) in bosses {
+        let Some(canonical) = parser::utils::canonical_boss_name(npc_id) else {
+            continue;
+        };
+        if canonical == localized {
+            continue;
+        }
+        let taken: bool = tx
+            .prepare_cached(
+                "SELECT EXISTS(SELECT 1 FROM entity WHERE encounter_id = ? AND name = ?)",
+            )?
+            .query_row(params![id, canonical], |row| row.get(0))?;
+        if taken {
+            continue;
+        }
+
+        tx.prepare_cached("UPDATE entity SET name = ?1 WHERE encounter_id = ?2 AND name = ?3")?
+            .execute(params![canonical, id, localized])?;
+        tx.prepare_cached("UPDATE encounter_preview SET current_boss = ? WHERE id = ?")?
+            .execute(params![canonical, id])?;
+        tx.prepare_cached(
+            "UPDATE encounter SET misc = json_set(coalesce(misc, '{}'), '$.localizedBossName', ?)
+            WHERE id = ?",
+        )?
+        .execute(params![localized, id])?;
+        rename_boss_hp_log(&tx, id, &localized, canonical)?;
+        renamed += 1;
+    }
+    tx.commit()?;
+    Ok(renamed)
+}
+
+// the hp log is keyed by boss name, gzipped since 1.13.5 and plain json before
+fn rename_boss_hp_log(
+    conn: &Connection,
+    id: i64,
+    localized: &str,
+    canonical: &str,
+) -> Result<(), rusqlite::Error> {
+    let raw: Option<(bool, Vec<u8>)> = conn
+        .prepare_cached("SELECT boss_hp_log FROM encounter WHERE id = ?")?
+        .query_row([id], |row| {
+            Ok(match row.get_ref(0)? {
+                ValueRef::Blob(bytes) => Some((true, bytes.to_vec())),
+                ValueRef::Text(text) => Some((false, text.to_vec())),
+                _ => None,
+            })
+        })?;
+    let Some((compressed, bytes)) = raw else {
+        return Ok(());
+    };
+
+    let json = if compressed {
+        let mut json = String::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut json)
+            .ok()
+            .map(|_| json)
+    } else {
+        String::from_utf8(bytes).ok()
+    };
+    let parsed: Option<HashMap<String, Vec<BossHpLog>>> =
+        json.and_then(|json| serde_json::from_str(&json).ok());
+    let mut log = match parsed {
+        Some(log) => log,
+        None => {
+            warn!("boss hp log of encounter {} is unreadable, left as is", id);
+            return Ok(());
+        }
+    };
+    let Some(entries) = log.remove(localized) else {
+        return Ok(());
+    };
+    log.insert(canonical.to_string(), entries);
+
+    let mut stmt = conn.prepare_cached("UPDATE encounter SET boss_hp_log = ? WHERE id = ?")?;
+    if compressed {
+        stmt.execute(params![parser::utils::compress_json(&log), id])?;
+    } else {
+        stmt.execute(params![serde_json::to_string(&log).unwrap_or_default(), id])?;
+    }
+    Ok(())
+}
+
+// re-derives the denormalized preview columns from the saved encounter and
+// its entities, for rows saved before a column existed or that got out of
+// sync with their encounter. every encounter when ids is None
+#[tauri::command(async)]
+fn rebuild_previews(
+    window: tauri::Window,
+    pool: State<'_, DbPool>,
+    ids: Option<Vec<i64>>,
+) -> Result<RebuildProgress, String> {
+    let ids = match ids {
+        Some(ids) => ids,
+        None => pool
+            .read("rebuild_previews")
+            .map_err(|e| e.to_string())?
+            .prepare("SELECT id FROM encounter_preview ORDER BY id")
+            .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+            .map_err(|e| e.to_string())?,
+    };
+
+    let mut progress = RebuildProgress {
+        total: ids.len(),
+        ..Default::default()
+    };
+    info!("rebuilding {} encounter previews", progress.total);
+
+    // the writer is taken per batch, so saving a fight doesn't wait for the
+    // whole rebuild
+    for batch in ids.chunks(REBUILD_BATCH_SIZE) {
+        let mut conn = pool.write("rebuild_previews").map_err(|e| e.to_string())?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for &id in batch {
+            match rebuild_preview(&tx, id) {
+                Ok(()) => progress.rebuilt += 1,
+                Err(e) => {
+                    warn!("could not rebuild preview of encounter {}: {}", id, e);
+                    progress.failed += 1;
+                }
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        drop(conn);
+        window
+            .emit("rebuild-progress", progress.clone())
+            .map_err(|e| e.to_string())?;
+    }
+
+    info!(
+        "rebuild finished: {} rebuilt, {} failed",
+        progress.rebuilt, progress.failed
+    );
+    window.state::<SuggestionCache>().invalidate();
+    Ok(progress)
+}
+
+// same derivation insert_data uses when saving
+fn rebuild_preview(tx: &Transaction, id: i64) -> Result<(), String> {
+    let encounter = read_encounter_with(tx, id).map_err(|e| e.message)?;
+    let (players, my_dps, my_dps_alive) = parser::utils::preview_players(&encounter);
+    let local_rank = parser::utils::compute_local_rank(&encounter);
+    let region = encounter
+        .encounter_damage_stats
+        .misc
+        .as_ref()
+        .and_then(|misc| misc.region.clone());
+
+    tx.prepare_cached(
+        "UPDATE encounter_preview
+        SET players = ?1,
+            players_version = ?2,
+            my_dps = ?3,
+            my_dps_alive = ?4,
+            local_rank = ?5,
+            party_size = ?6,
+            region = ?7
+        WHERE id = ?8",
+    )
+    .and_then(|mut stmt| {
+        stmt.execute(params![
+            players,
+            PREVIEW_PLAYERS_VERSION,
+            my_dps,
+            my_dps_alive,
+            local_rank.as_ref().map(|r| r.rank),
+            local_rank.as_ref().map(|r| r.party_size),
+            region,
+            id
+        ])
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn backup_config_paths(app: &AppHandle) -> Result<app::backup::ConfigPaths, String> {
+    let config_dir = app
+        .path_resolver()
+        .app_config_dir()
+        .ok_or("could not get app config dir")?;
+    Ok(app::backup::ConfigPaths {
+        settings: app::storage::data_dir(app).join("settings.json"),
+        window_state: config_dir.join(app::backup::WINDOW_STATE_FILE),
+    })
+}
+
+// include_config writes a zip with settings and window state next to the database
+#[tauri::command(async)]
+fn backup_database(
+    window: tauri::Window,
+    pool: State<'_, DbPool>,
+    path: String,
+    include_config: bool,
+) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let config = if include_config {
+        save_window_state(&app_handle);
+        Some(backup_config_paths(&app_handle)?)
     } else {
-        format!("{:.2} KB", size_in_kb)
+        None
     };
+    let conn = pool.read("backup_database").map_err(|e| e.to_string())?;
+    app::backup::backup(
+        &conn,
+        Path::new(&path),
+        config.as_ref(),
+        &app_handle.package_info().version.to_string(),
+    )
+}
 
-    EncounterDbInfo {
-        size: size_str,
-        total_encounters: encounter_count,
-        total_encounters_filtered: encounter_filtered_count,
-    }
+// the database is swapped in on the next start, config_available tells the
+// frontend to ask before calling restore_config
+#[tauri::command(async)]
+fn restore_database(window: tauri::Window, path: String) -> Result<RestoreResult, String> {
+    let data_path = app::storage::data_dir(&window.app_handle());
+    app::backup::stage_restore(Path::new(&path), &data_path)
 }
 
 #[tauri::command]
-fn optimize_database(window: tauri::Window) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    conn.execute_batch(
-        "
-        INSERT INTO encounter_search(encounter_search) VALUES('optimize');
-        VACUUM;
-        ",
-    )
-    .unwrap();
-    info!("optimized database");
+fn restore_config(window: tauri::Window, path: String) -> Result<(), String> {
+    let config = backup_config_paths(&window.app_handle())?;
+    app::backup::restore_config(Path::new(&path), &config)
 }
 
 #[tauri::command]
@@ -1496,6 +3324,57 @@ fn enable_blur(window: tauri::Window) {
     }
 }
 
+#[tauri::command]
+fn set_opacity(window: tauri::Window, opacity: f64) {
+    if let Some(meter_window) = window.app_handle().get_window(METER_WINDOW_LABEL) {
+        app::opacity::set_manual(true);
+        app::opacity::set_window_opacity(&meter_window, opacity);
+    }
+}
+
+#[tauri::command]
+fn set_window_backdrop(window: tauri::Window, kind: String) -> Result<String, String> {
+    let meter_window = window
+        .app_handle()
+        .get_window(METER_WINDOW_LABEL)
+        .ok_or("meter window not found")?;
+    if !matches!(kind.as_str(), "blur" | "acrylic" | "mica" | "none") {
+        return Err(format!("unknown backdrop: {}", kind));
+    }
+    Ok(apply_backdrop(&meter_window, &kind))
+}
+
+// applies the requested backdrop and returns the one actually used.
+// acrylic and mica need windows 11 (build 22000+), older versions get blur
+fn apply_backdrop(meter_window: &tauri::Window, kind: &str) -> String {
+    let is_win11 = System::kernel_version()
+        .and_then(|build| build.trim().parse::<u32>().ok())
+        .is_some_and(|build| build >= 22000);
+    let kind = match kind {
+        "acrylic" | "mica" if !is_win11 => {
+            info!("{} backdrop is not supported, falling back to blur", kind);
+            "blur"
+        }
+        "blur" | "acrylic" | "mica" => kind,
+        _ => "none",
+    };
+
+    clear_blur(meter_window).ok();
+    clear_acrylic(meter_window).ok();
+    clear_mica(meter_window).ok();
+    let result = match kind {
+        "blur" => apply_blur(meter_window, Some((10, 10, 10, 50))),
+        "acrylic" => apply_acrylic(meter_window, Some((10, 10, 10, 50))),
+        "mica" => apply_mica(meter_window, Some(true)),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        warn!("failed to apply {} backdrop: {}", kind, e);
+        return "none".to_string();
+    }
+    kind.to_string()
+}
+
 #[tauri::command]
 fn enable_aot(window: tauri::Window) {
     if let Some(meter_window) = window.app_handle().get_window(METER_WINDOW_LABEL) {
@@ -1512,8 +3391,66 @@ fn disable_aot(window: tauri::Window) {
 
 #[tauri::command]
 fn set_clickthrough(window: tauri::Window, set: bool) {
-    if let Some(meter_window) = window.app_handle().get_window(METER_WINDOW_LABEL) {
-        meter_window.set_ignore_cursor_events(set).unwrap();
+    apply_clickthrough(&window.app_handle(), set);
+}
+
+// sets clickthrough on the meter window and remembers it for the next startup
+fn apply_clickthrough(app: &AppHandle, set: bool) {
+    if !set_session_clickthrough(app, set) {
+        return;
+    }
+
+    let path = app::storage::data_dir(app).join(CLICKTHROUGH_STATE_FILE);
+    if let Err(e) = fs::write(path, set.to_string()) {
+        warn!("failed to save clickthrough state: {}", e);
+    }
+}
+
+// sets clickthrough for this run only, false when the window refused it and
+// nothing changed
+fn set_session_clickthrough(app: &AppHandle, set: bool) -> bool {
+    let Some(meter_window) = app.get_window(METER_WINDOW_LABEL) else {
+        return true;
+    };
+    if let Err(e) = meter_window.set_ignore_cursor_events(set) {
+        warn!("failed to set clickthrough: {}", e);
+        return false;
+    }
+    app::set_clickthrough(set);
+    meter_window.emit("clickthrough-changed", set).ok();
+    true
+}
+
+// the frontend unregisters every global shortcut before registering its own,
+// which drops the clickthrough toggle too
+#[tauri::command]
+fn restore_clickthrough_shortcut(window: tauri::Window, shortcut: Shortcut) {
+    register_clickthrough_shortcut(&window.app_handle(), &shortcut);
+}
+
+// replaces the previously registered clickthrough toggle shortcut
+fn register_clickthrough_shortcut(app: &AppHandle, shortcut: &Shortcut) {
+    let mut current = CLICKTHROUGH_SHORTCUT.lock().unwrap();
+    let mut manager = app.global_shortcut_manager();
+    if let Some(accelerator) = current.take() {
+        manager.unregister(&accelerator).ok();
+    }
+    if shortcut.modifier.is_empty() || shortcut.key.is_empty() {
+        return;
+    }
+
+    let accelerator = format!("{}+{}", shortcut.modifier, shortcut.key);
+    let handle = app.clone();
+    match manager.register(&accelerator, move || {
+        let set = !app::is_clickthrough();
+        info!("toggling clickthrough: {}", set);
+        apply_clickthrough(&handle, set);
+    }) {
+        Ok(_) => *current = Some(accelerator),
+        Err(e) => warn!(
+            "failed to register clickthrough shortcut {}: {}",
+            accelerator, e
+        ),
     }
 }
 
@@ -1522,6 +3459,313 @@ fn remove_driver() {
     Command::new("sc").args(["delete", "windivert"]).output().expect("unable to delete driver");
 }
 
+// runs the automatic storage cleanup once a day when auto_cleanup_storage is
+// on, settings are read on every run like the update checker does
+fn start_storage_maintenance(app: AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(STORAGE_CLEANUP_DELAY);
+        loop {
+            let general = read_settings(&app::storage::data_dir(&app))
+                .map(|settings| settings.general)
+                .unwrap_or_default();
+            if general.auto_cleanup_storage {
+                let categories = app::storage::AUTO_CLEANUP_CATEGORIES.map(String::from);
+                let result = app::storage::StorageDirs::new(&app).and_then(|dirs| {
+                    // at least a day so a backup made just now survives
+                    let days = general.auto_cleanup_days.max(1);
+                    app::storage::cleanup_storage(&dirs, &categories, days)
+                });
+                if let Err(e) = result {
+                    warn!("automatic storage cleanup failed: {}", e);
+                }
+            }
+            std::thread::sleep(STORAGE_CLEANUP_INTERVAL);
+        }
+    });
+}
+
+// the first check is retried with backoff (0, 200 and 600 seconds in),
+// after that it rechecks every UPDATE_RECHECK_INTERVAL while the app runs
+fn start_update_checker(app: AppHandle) {
+    std::thread::spawn(move || {
+        while !UPDATE_INSTALLED.load(Ordering::Relaxed) {
+            for attempt in 1..=UPDATE_CHECK_ATTEMPTS {
+                match tauri::async_runtime::block_on(check_for_update(&app)) {
+                    Ok(_) => break,
+                    Err(e) => {
+                        warn!(
+                            "failed to get update (attempt {}/{}): {}",
+                            attempt, UPDATE_CHECK_ATTEMPTS, e
+                        );
+                        if attempt < UPDATE_CHECK_ATTEMPTS {
+                            std::thread::sleep(UPDATE_RETRY_DELAY * 2u32.pow(attempt - 1));
+                        }
+                    }
+                }
+            }
+            std::thread::sleep(UPDATE_RECHECK_INTERVAL);
+        }
+    });
+}
+
+async fn check_for_update(app: &AppHandle) -> Result<(), tauri::updater::Error> {
+    // read on every check so a changed setting applies without a restart
+    let update_mode = read_settings(&app::storage::data_dir(app))
+        .map(|settings| settings.general.update_mode)
+        .unwrap_or_default();
+    if update_mode == "off" {
+        info!("update checks are disabled");
+        return Ok(());
+    }
+
+    let update = tauri::updater::builder(app.clone()).check().await?;
+    if !update.is_update_available() {
+        info!("no update available");
+        return Ok(());
+    }
+    if update_mode == "notify" {
+        info!(
+            "update available: v{}, not downloading",
+            update.latest_version()
+        );
+        return Ok(());
+    }
+
+    #[cfg(not(debug_assertions))]
+    {
+        info!(
+            "update available, downloading update: v{}",
+            update.latest_version()
+        );
+
+        // installing unloads the capture driver, so it waits for the fight to end
+        if parser::capture_status::is_in_combat() {
+            info!("in combat, installing the update once the fight is over");
+            while parser::capture_status::is_in_combat() {
+                std::thread::sleep(UPDATE_IDLE_POLL_INTERVAL);
+            }
+        }
+
+        unload_driver();
+        remove_driver();
+
+        let old_version = update.current_version().to_string();
+        let new_version = update.latest_version().to_string();
+        match update.download_and_install().await {
+            Ok(_) => {
+                UPDATE_INSTALLED.store(true, Ordering::Relaxed);
+                notify_update_installed(app, old_version, new_version);
+            }
+            Err(e) => {
+                error!("failed to download update: {}", e);
+                app.emit_all(
+                    "update-download-failed",
+                    UpdateDownloadFailed {
+                        version: new_version,
+                        download_url: manual_download_url(app).await,
+                        error: e.to_string(),
+                    },
+                )
+                .ok();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// installer url from the update manifest, for downloading it by hand
+#[cfg(not(debug_assertions))]
+async fn manual_download_url(app: &AppHandle) -> String {
+    let endpoint = app
+        .config()
+        .tauri
+        .updater
+        .endpoints
+        .as_ref()
+        .and_then(|endpoints| endpoints.first())
+        .map(|endpoint| endpoint.to_string());
+    if let Some(endpoint) = endpoint {
+        if let Ok(response) = reqwest::get(endpoint).await {
+            if let Ok(manifest) = response.json::<Value>().await {
+                if let Some(url) = manifest
+                    .pointer("/platforms/windows-x86_64/url")
+                    .and_then(Value::as_str)
+                {
+                    return url.to_string();
+                }
+            }
+        }
+    }
+    MANUAL_DOWNLOAD_URL.to_string()
+}
+
+// the running session keeps the old version until restarted
+fn notify_update_installed(app: &AppHandle, old_version: String, new_version: String) {
+    info!("update v{} installed, restart pending", new_version);
+    Notification::new(&app.config().tauri.bundle.identifier)
+        .title("LOA Logs")
+        .body(format!(
+            "LOA Logs v{} installed - restart to apply",
+            new_version
+        ))
+        .show()
+        .map_err(|e| warn!("failed to show update notification: {}", e))
+        .ok();
+    app.emit_all(
+        "update-installed",
+        UpdateInstalled {
+            old_version,
+            new_version,
+        },
+    )
+    .ok();
+}
+
+#[tauri::command]
+fn restart_app(window: tauri::Window) -> Result<(), String> {
+    if parser::capture_status::is_in_combat() {
+        return Err("can't restart during an encounter".to_string());
+    }
+    let app_handle = window.app_handle();
+    if !app::backup::config_restored() {
+        app_handle
+            .save_window_state(WINDOW_STATE_FLAGS)
+            .map_err(|e| e.to_string())?;
+        app::window_geometry::save(&app_handle);
+    }
+    end_session(&app_handle);
+    unload_driver();
+    app_handle.restart();
+    Ok(())
+}
+
+// failing to save the window state only loses the last position,
+// it must not stop the app from quitting
+fn save_window_state(app: &AppHandle) {
+    // a restored window state is applied on the next start
+    if app::backup::config_restored() {
+        return;
+    }
+    if let Err(e) = app.save_window_state(WINDOW_STATE_FLAGS) {
+        warn!("failed to save window state: {}", e);
+    }
+    app::window_geometry::save(app);
+}
+
+fn start_session(app: &AppHandle, settings: Option<&Settings>) {
+    let pool = app.state::<DbPool>();
+    if pool.is_read_only() {
+        return;
+    }
+    let (capture_mode, interface_ip) = match settings {
+        Some(settings) if settings.general.raw_socket => {
+            ("raw socket", settings.general.ip.as_str())
+        }
+        Some(settings) if !settings.general.auto_iface => {
+            ("windivert", settings.general.ip.as_str())
+        }
+        _ => ("windivert", "auto"),
+    };
+    let version = app.package_info().version.to_string();
+    let result = pool
+        .write("start_session")
+        .and_then(|conn| app::session::start(&conn, &version, capture_mode, interface_ip));
+    match result {
+        Ok(id) => info!("started session {}", id),
+        Err(e) => warn!("failed to record session: {}", e),
+    }
+}
+
+#[tauri::command]
+fn get_boss_note(pool: State<'_, DbPool>, boss: String) -> Option<BossNote> {
+    let conn = match pool.read("get_boss_note") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return None;
+        }
+    };
+    app::boss_notes::get(&conn, &boss).unwrap_or_else(|e| {
+        warn!("could not load boss note: {}", e);
+        None
+    })
+}
+
+#[tauri::command]
+fn list_boss_notes(pool: State<'_, DbPool>) -> Vec<BossNote> {
+    let conn = match pool.read("list_boss_notes") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return Vec::new();
+        }
+    };
+    app::boss_notes::list(&conn).unwrap_or_else(|e| {
+        warn!("could not load boss notes: {}", e);
+        Vec::new()
+    })
+}
+
+// an empty note deletes it
+#[tauri::command]
+fn set_boss_note(pool: State<'_, DbPool>, boss: String, note: String) -> Result<(), String> {
+    let conn = pool.write("set_boss_note").map_err(|e| e.to_string())?;
+    app::boss_notes::set(&conn, &boss, &note)
+}
+
+#[tauri::command]
+fn get_sessions(pool: State<'_, DbPool>, limit: i32) -> Vec<AppSession> {
+    let conn = match pool.read("get_sessions") {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("could not get db connection: {}", e);
+            return Vec::new();
+        }
+    };
+    app::session::list(&conn, limit.clamp(1, app::db::MAX_PAGE_SIZE)).unwrap_or_else(|e| {
+        warn!("could not load sessions: {}", e);
+        Vec::new()
+    })
+}
+
+// written on every way out of the app, a restart starts a new session
+fn end_session(app: &AppHandle) {
+    let pool = app.state::<DbPool>();
+    if pool.is_read_only() {
+        return;
+    }
+    let result = pool
+        .write("end_session")
+        .and_then(|conn| app::session::end(&conn));
+    if let Err(e) = result {
+        warn!("failed to end session: {}", e);
+    }
+}
+
+// every step runs even when an earlier one failed
+fn shutdown(app: &AppHandle) {
+    info!("shutting down");
+    save_window_state(app);
+    end_session(app);
+
+    let pool = app.state::<DbPool>();
+    if !pool.is_read_only() {
+        let result = pool
+            .write("shutdown")
+            .and_then(|conn| conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);"));
+        if let Err(e) = result {
+            warn!("failed to checkpoint database: {}", e);
+        }
+    }
+
+    unload_driver();
+    if let Ok(logger) = app::get_logger() {
+        logger.flush();
+    }
+    app.exit(0);
+}
+
 #[tauri::command]
 fn unload_driver() {
     let output = Command::new("sc").args(["stop", "windivert"]).output();
@@ -1538,63 +3782,102 @@ fn unload_driver() {
     }
 }
 
+const START_ON_BOOT_TASK: &str = "LOA_Logs_Auto_Start";
+
 #[tauri::command]
-fn check_start_on_boot() -> bool {
-    // Run the `schtasks` command to query the task
-    let output = Command::new("schtasks")
-        .args(["/query", "/tn", "LOA_Logs_Auto_Start"])
-        .output();
+fn check_start_on_boot() -> Result<bool, String> {
+    Ok(start_on_boot_path()?.is_some())
+}
 
-    match output {
-        Ok(output) => output.status.success(),
-        Err(_) => false,
+// returns whether start on boot is enabled afterwards
+#[tauri::command]
+fn set_start_on_boot(set: bool) -> Result<bool, String> {
+    if set {
+        let app_path = std::env::current_exe()
+            .map_err(|e| format!("could not get current exe path: {}", e))?;
+        register_start_on_boot(&app_path.to_string_lossy())?;
+        info!("enabled start on boot");
+    } else {
+        run_schtasks(&["/delete", "/tn", START_ON_BOOT_TASK, "/f"])
+            .map_err(|e| format!("error disabling start on boot: {}", e))?;
+        info!("disabled start on boot");
     }
+    check_start_on_boot()
 }
 
-#[tauri::command]
-fn set_start_on_boot(set: bool) {
-    let app_path = match std::env::current_exe() {
-        Ok(path) => path.to_string_lossy().to_string(),
+// the task keeps pointing at the old exe after an update moved the install
+// or the folder was moved by hand, so it's registered again for this one
+fn repair_start_on_boot() {
+    let registered = match start_on_boot_path() {
+        Ok(Some(path)) => path,
+        Ok(None) => return,
         Err(e) => {
-            warn!("could not get current exe path: {}", e);
+            warn!("could not check start on boot: {}", e);
             return;
         }
     };
+    let Ok(app_path) = std::env::current_exe() else {
+        return;
+    };
+    let app_path = app_path.to_string_lossy().to_string();
+    if registered.eq_ignore_ascii_case(&app_path) {
+        return;
+    }
 
-    let task_name = "LOA_Logs_Auto_Start";
+    info!(
+        "start on boot points to {}, registering {} instead",
+        registered, app_path
+    );
+    if let Err(e) = register_start_on_boot(&app_path) {
+        warn!("{}", e);
+    }
+}
 
-    if set {
-        Command::new("schtasks")
-            .args(["/delete", "/tn", task_name, "/f"])
-            .output().ok();
-        
-        let output = Command::new("schtasks")
-            .args([
-                "/create", "/tn", task_name, "/tr", &format!("\"{}\"", &app_path), "/sc", "onlogon", "/rl", "highest",
-            ])
-            .output();
-
-        match output {
-            Ok(_) => {
-                info!("enabled start on boot");
-            }
-            Err(e) => {
-                warn!("error enabling start on boot: {}", e);
-            }
-        }
-    } else {
-        let output = Command::new("schtasks")
-            .args(["/delete", "/tn", task_name, "/f"])
-            .output();
+fn register_start_on_boot(app_path: &str) -> Result<(), String> {
+    let command = format!("\"{}\"", app_path);
+    run_schtasks(&[
+        "/create",
+        "/tn",
+        START_ON_BOOT_TASK,
+        "/tr",
+        &command,
+        "/sc",
+        "onlogon",
+        "/rl",
+        "highest",
+        "/f",
+    ])
+    .map_err(|e| format!("error enabling start on boot: {}", e))
+}
 
-        match output {
-            Ok(_) => {
-                info!("disabled start on boot");
-            }
-            Err(e) => {
-                warn!("error disabling start on boot: {}", e);
-            }
-        }
+// exe the boot task launches, None when there is no task
+fn start_on_boot_path() -> Result<Option<String>, String> {
+    let output = Command::new("schtasks")
+        .args(["/query", "/tn", START_ON_BOOT_TASK, "/xml"])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let xml = output.stdout.replace('\0', "");
+    let path = xml
+        .split_once("<Command>")
+        .and_then(|(_, rest)| rest.split_once("</Command>"))
+        .map(|(command, _)| command.trim().trim_matches('"').replace("&amp;", "&"))
+        .unwrap_or_default();
+    Ok(Some(path))
+}
+
+fn run_schtasks(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("schtasks")
+        .args(args)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(output.stderr.trim().to_string())
     }
 }
 
@@ -1627,6 +3910,311 @@ fn start_loa_process() {
 }
 
 #[tauri::command]
-fn write_log(message: String) {
-    info!("{}", message);
+async fn get_network_interfaces() -> Vec<app::network::NetworkInterface> {
+    task::spawn_blocking(app::network::list_interfaces)
+        .await
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+fn write_log(window: tauri::Window, level: String, message: String, context: Option<String>) {
+    app::log_frontend(
+        window.label(),
+        app::parse_log_level(&level),
+        &message,
+        context.as_deref(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fully migrated database, the same schema setup_db leaves on disk,
+    // with foreign keys on like every pooled connection
+    pub fn test_db() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate_db(&mut conn).unwrap(), None);
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        conn
+    }
+
+    fn query_plan(conn: &Connection, query: &str) -> String {
+        let mut stmt = conn
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", query))
+            .unwrap();
+        let details = stmt
+            .query_map([], |row| row.get::<_, String>(3))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        details.join("\n")
+    }
+
+    #[test]
+    fn ignoring_without_settings_keeps_the_serde_defaults() {
+        let dir = std::env::temp_dir().join(format!("loa-logs-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::remove_file(dir.join("settings.json")).ok();
+
+        update_settings_file(&dir, |general| add_ignored(general, Some(" Bob "), None)).unwrap();
+        let settings = read_settings(&dir).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        let mut expected: Settings = serde_json::from_str("{}").unwrap();
+        expected.general.ignored_names = vec!["Bob".to_string()];
+        assert_eq!(settings, expected);
+        assert!(settings.general.show_names);
+        assert!(settings.general.auto_iface);
+        assert_eq!(
+            settings.general.fade_opacity,
+            parser::idle_fade::DEFAULT_FADE_OPACITY
+        );
+        assert_eq!(
+            settings.general.trim_prepull_max_percent,
+            parser::prepull::DEFAULT_MAX_TRIM_PERCENT
+        );
+        assert_eq!(settings.general.auto_cleanup_days, 30);
+        assert_eq!(
+            settings.shortcuts.toggle_clickthrough,
+            parser::models::default_toggle_clickthrough()
+        );
+        assert!(settings.logs.split_party_damage);
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        let mut conn = test_db();
+        assert_eq!(migrate_db(&mut conn).unwrap(), None);
+        let user_version: i32 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(user_version, DB_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn deleting_encounters_removes_their_skill_records() {
+        let mut conn = test_db();
+        for id in 1..=3 {
+            conn.execute("INSERT INTO encounter (id) VALUES (?)", [id])
+                .unwrap();
+            conn.execute(
+                "INSERT INTO encounter_preview (id, fight_start, current_boss, duration)
+                VALUES (?, ?, 'Boss', ?)",
+                [id, id * 1000, id * 10_000],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO skill_record (player, skill_id, max_damage, encounter_id)
+                VALUES ('Player', ?, 100, ?)",
+                [id, id],
+            )
+            .unwrap();
+        }
+
+        app::db::delete_encounters(&mut conn, &[1]).unwrap();
+        app::db::delete_previews_where(&conn, "duration < ?", &[&25_000], false).unwrap();
+        let records: Vec<i64> = conn
+            .prepare("SELECT encounter_id FROM skill_record")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records, vec![3]);
+    }
+
+    #[test]
+    fn cleared_filter_uses_its_index() {
+        let conn = test_db();
+        let plan = query_plan(
+            &conn,
+            "SELECT id FROM encounter_preview WHERE cleared = 1 ORDER BY fight_start DESC LIMIT 10",
+        );
+        assert!(
+            plan.contains("encounter_preview_cleared_fight_start_index"),
+            "{}",
+            plan
+        );
+        assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+    }
+
+    #[test]
+    fn difficulty_filter_uses_its_index() {
+        let conn = test_db();
+        let plan = query_plan(
+            &conn,
+            "SELECT id FROM encounter_preview WHERE difficulty = 'Hard' ORDER BY fight_start DESC LIMIT 10",
+        );
+        assert!(
+            plan.contains("encounter_preview_difficulty_fight_start_index"),
+            "{}",
+            plan
+        );
+        assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+    }
+
+    fn saved_fight(fight_start: i64) -> Encounter {
+        let player = EncounterEntity {
+            name: "Player".to_string(),
+            entity_type: EntityType::PLAYER,
+            class_id: 102,
+            class: "Berserker".to_string(),
+            max_hp: 100_000,
+            damage_stats: DamageStats {
+                damage_dealt: 1_000_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Encounter {
+            fight_start,
+            last_combat_packet: fight_start + 60_000,
+            local_player: player.name.clone(),
+            current_boss_name: "Boss".to_string(),
+            entities: HashMap::from([(player.name.clone(), player)]),
+            encounter_damage_stats: EncounterDamageStats {
+                total_damage_dealt: 1_000_000,
+                top_damage_dealt: 1_000_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT count(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn resave_overwrites_the_first_save() {
+        let mut conn = test_db();
+        let tx = conn.transaction().unwrap();
+        let first = insert_imported_encounter(&tx, saved_fight(1_700_000_000_000), "1".into());
+        let second = insert_imported_encounter(&tx, saved_fight(1_700_000_000_000), "1".into());
+        tx.commit().unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(count(&conn, "encounter"), 1);
+        assert_eq!(count(&conn, "encounter_preview"), 1);
+        assert_eq!(count(&conn, "entity"), 1);
+        let plan = query_plan(
+            &conn,
+            "SELECT id FROM encounter_preview WHERE fight_start = 1 AND local_player = 'a'",
+        );
+        assert!(!plan.contains("SCAN"), "{}", plan);
+    }
+
+    // re-saving looks the fight up by index, so it costs about as much as the
+    // first save however many fights are stored.
+    // cargo test upsert_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn upsert_benchmark() {
+        const FIGHTS: u32 = 2000;
+        let mut conn = test_db();
+        let tx = conn.transaction().unwrap();
+        let save_all = |tx: &Transaction| {
+            let started = Instant::now();
+            for i in 0..FIGHTS {
+                let fight = saved_fight(1_700_000_000_000 + i as i64 * 60_000);
+                insert_imported_encounter(tx, fight, "1".into());
+            }
+            started.elapsed() / FIGHTS
+        };
+        let insert = save_all(&tx);
+        let upsert = save_all(&tx);
+        tx.commit().unwrap();
+
+        println!(
+            "{} fights, insert {:?}, re-save {:?}",
+            FIGHTS, insert, upsert
+        );
+        assert_eq!(count(&conn, "encounter_preview"), FIGHTS as i64);
+        assert!(
+            upsert < insert * 3,
+            "insert {:?}, re-save {:?}",
+            insert,
+            upsert
+        );
+    }
+
+    // (duration, my_dps, encounter dps) of a saved fight
+    fn saved_dps(conn: &Connection, id: i64) -> (i64, Option<i64>, Option<i64>) {
+        conn.query_row(
+            "SELECT ep.duration, ep.my_dps, e.dps
+            FROM encounter_preview ep JOIN encounter e ON e.id = ep.id
+            WHERE ep.id = ?",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn zero_and_day_long_fights_save_usable_dps() {
+        let mut conn = test_db();
+        let tx = conn.transaction().unwrap();
+        let mut instant = saved_fight(1_700_000_000_000);
+        instant.last_combat_packet = instant.fight_start;
+        let instant = insert_imported_encounter(&tx, instant, "1".into());
+        let mut day = saved_fight(1_700_100_000_000);
+        day.last_combat_packet = day.fight_start + 24 * 3600 * 1000;
+        let day = insert_imported_encounter(&tx, day, "1".into());
+        // the clock went back mid fight
+        let mut backwards = saved_fight(1_700_200_000_000);
+        backwards.last_combat_packet = backwards.fight_start - 60_000;
+        let backwards = insert_imported_encounter(&tx, backwards, "1".into());
+        tx.commit().unwrap();
+
+        assert_eq!(
+            saved_dps(&conn, instant),
+            (0, Some(1_000_000), Some(1_000_000))
+        );
+        assert_eq!(saved_dps(&conn, day), (86_400_000, Some(11), Some(11)));
+        assert_eq!(
+            saved_dps(&conn, backwards),
+            (0, Some(1_000_000), Some(1_000_000))
+        );
+    }
+
+    #[test]
+    fn negative_durations_are_recomputed() {
+        let mut conn = test_db();
+        conn.execute_batch(
+            "
+            INSERT INTO encounter (id, last_combat_packet, total_damage_dealt, dps)
+            VALUES (1, 1700000060000, 6000000, -5), (2, 1699999000000, 100, -5);
+            INSERT INTO encounter_preview (id, fight_start, local_player, duration, my_dps)
+            VALUES (1, 1700000000000, 'Player', -60000, -1), (2, 1700000000000, 'Player', -5, -1);
+            INSERT INTO entity (name, encounter_id, dps) VALUES ('Player', 1, -7);
+            ",
+        )
+        .unwrap();
+        let tx = conn.transaction().unwrap();
+        migration_negative_durations(&tx).unwrap();
+        tx.commit().unwrap();
+
+        // recomputed from the last packet
+        assert_eq!(saved_dps(&conn, 1), (60_000, None, Some(100_000)));
+        // the last packet is before the start too, nothing to recompute from
+        assert_eq!(saved_dps(&conn, 2), (0, None, None));
+        let entity_dps: Option<i64> = conn
+            .query_row("SELECT dps FROM entity", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(entity_dps, None);
+    }
+
+    #[test]
+    fn entity_rebuild_keeps_its_indexes() {
+        let conn = test_db();
+        let mut stmt = conn
+            .prepare("SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = ?")
+            .unwrap();
+        assert!(stmt.exists(["entity_name_index"]).unwrap());
+        assert!(stmt.exists(["entity_class_index"]).unwrap());
+    }
 }
@@ -0,0 +1,113 @@
+use chrono::Utc;
+use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{Window, Wry};
+
+use crate::app;
+
+pub const DEFAULT_RELEASE_DELAY_MS: u64 = 3000;
+// a fight without combat packets for this long counts as over
+const COMBAT_IDLE_TIMEOUT_MS: i64 = 10_000;
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+// makes the meter clickthrough while in combat and restores the user's own
+// clickthrough state once the fight has been over for the release delay.
+// checks run on a timer so the window is released even when packets stop
+#[derive(Clone)]
+pub struct AutoClickthrough {
+    state: Arc<Mutex<AutoClickthroughState>>,
+    stopped: Arc<AtomicBool>,
+}
+
+struct AutoClickthroughState {
+    enabled: bool,
+    release_delay: Duration,
+    fight_start: i64,
+    last_combat_packet: i64,
+    active: bool,
+    out_of_combat_since: Option<Instant>,
+}
+
+impl AutoClickthrough {
+    pub fn start(window: Window<Wry>, enabled: bool, release_delay_ms: u64) -> Self {
+        let auto_clickthrough = Self {
+            state: Arc::new(Mutex::new(AutoClickthroughState {
+                enabled,
+                release_delay: Duration::from_millis(release_delay_ms),
+                fight_start: 0,
+                last_combat_packet: 0,
+                active: false,
+                out_of_combat_since: None,
+            })),
+            stopped: Arc::new(AtomicBool::new(false)),
+        };
+
+        let state = auto_clickthrough.state.clone();
+        let stopped = auto_clickthrough.stopped.clone();
+        thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                thread::sleep(TICK_INTERVAL);
+                state.lock().unwrap().tick(&window);
+            }
+        });
+
+        auto_clickthrough
+    }
+
+    // ends the timer thread, used when the parser stops
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.state.lock().unwrap().enabled = enabled;
+    }
+
+    pub fn set_release_delay(&self, release_delay_ms: u64) {
+        self.state.lock().unwrap().release_delay = Duration::from_millis(release_delay_ms);
+    }
+
+    pub fn on_combat(&self, fight_start: i64, last_combat_packet: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.fight_start = fight_start;
+        state.last_combat_packet = last_combat_packet;
+    }
+}
+
+impl AutoClickthroughState {
+    fn tick(&mut self, window: &Window<Wry>) {
+        let in_combat = self.enabled
+            && self.fight_start > 0
+            && Utc::now().timestamp_millis() - self.last_combat_packet < COMBAT_IDLE_TIMEOUT_MS;
+
+        if in_combat {
+            // a fight that restarts during the release delay keeps clickthrough on
+            self.out_of_combat_since = None;
+            if !self.active {
+                self.active = true;
+                info!("enabling clickthrough for combat");
+                apply(window, true);
+            }
+            return;
+        }
+
+        if !self.active {
+            return;
+        }
+        let since = *self.out_of_combat_since.get_or_insert_with(Instant::now);
+        if !self.enabled || since.elapsed() >= self.release_delay {
+            self.active = false;
+            self.out_of_combat_since = None;
+            info!("restoring clickthrough after combat");
+            apply(window, app::is_clickthrough());
+        }
+    }
+}
+
+fn apply(window: &Window<Wry>, set: bool) {
+    window.set_ignore_cursor_events(set).ok();
+    window.emit("auto-clickthrough", set).ok();
+}
@@ -0,0 +1,68 @@
+use crate::parser::clients;
+use crate::parser::models::{CaptureStatus, ParserError};
+use chrono::Utc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// kept outside the mutex since they are updated for every packet
+static LAST_PACKET: AtomicI64 = AtomicI64::new(0);
+static LAST_COMBAT_PACKET: AtomicI64 = AtomicI64::new(0);
+
+// a fight without combat packets for this long is not considered ongoing
+const IN_COMBAT_TIMEOUT_MS: i64 = 10_000;
+
+fn get_status() -> &'static Mutex<CaptureStatus> {
+    static CAPTURE_STATUS: OnceLock<Mutex<CaptureStatus>> = OnceLock::new();
+    CAPTURE_STATUS.get_or_init(|| Mutex::new(CaptureStatus::default()))
+}
+
+pub fn get_capture_status() -> CaptureStatus {
+    let mut status = get_status().lock().unwrap().clone();
+    status.last_packet = LAST_PACKET.load(Ordering::Relaxed);
+    status.clients = clients::get_clients();
+    status
+}
+
+pub fn set_capturing(capturing: bool) {
+    let mut status = get_status().lock().unwrap();
+    status.capturing = capturing;
+    if capturing {
+        status.error = None;
+    }
+}
+
+// kept until the next successful start so a window that wasn't listening
+// when the parser failed can still show it
+pub fn set_error(error: ParserError) {
+    get_status().lock().unwrap().error = Some(error);
+}
+
+pub fn set_paused(paused: bool) {
+    get_status().lock().unwrap().paused = paused;
+}
+
+pub fn set_region(region: Option<String>) {
+    get_status().lock().unwrap().region = region;
+}
+
+pub fn set_interface(interface: Option<String>) {
+    get_status().lock().unwrap().interface = interface;
+}
+
+pub fn on_packet() {
+    LAST_PACKET.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+}
+
+pub fn on_combat(fight_start: i64, last_combat_packet: i64) {
+    let last = if fight_start > 0 {
+        last_combat_packet
+    } else {
+        0
+    };
+    LAST_COMBAT_PACKET.store(last, Ordering::Relaxed);
+}
+
+pub fn is_in_combat() -> bool {
+    let last = LAST_COMBAT_PACKET.load(Ordering::Relaxed);
+    last > 0 && Utc::now().timestamp_millis() - last < IN_COMBAT_TIMEOUT_MS
+}
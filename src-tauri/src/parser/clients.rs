@@ -0,0 +1,163 @@
+use crate::parser::clock;
+use crate::parser::models::CaptureClient;
+use log::{info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// a client whose local player cast nothing for this long is treated as closed
+// or as an earlier character on the same client
+const CLIENT_TIMEOUT_MS: i64 = 5 * 60_000;
+
+// meter-core hands over the packets of every game connection as one stream,
+// so clients are told apart by the local player each InitPC introduces.
+// index is the order the clients showed up in since the parser started
+struct Client {
+    name: String,
+    character_id: u64,
+    entity_id: u64,
+    last_seen: i64,
+}
+
+struct Clients {
+    clients: Vec<Client>,
+    // set_tracked_connection override, wins over the main character
+    manual: Option<usize>,
+    main_character: String,
+    // the client the encounter state follows
+    current: Option<usize>,
+}
+
+static CLIENTS: Mutex<Clients> = Mutex::new(Clients {
+    clients: Vec::new(),
+    manual: None,
+    main_character: String::new(),
+    current: None,
+});
+static MULTIPLE_REPORTED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_main_character(name: &str) {
+    CLIENTS.lock().unwrap().main_character = name.trim().to_string();
+}
+
+pub fn set_tracked(index: Option<usize>) -> Result<(), String> {
+    let mut clients = CLIENTS.lock().unwrap();
+    if let Some(index) = index {
+        if index >= clients.clients.len() {
+            return Err(format!("no client with index {}", index));
+        }
+        info!(
+            "tracking client {} ({})",
+            index, clients.clients[index].name
+        );
+        clients.current = Some(index);
+    }
+    clients.manual = index;
+    Ok(())
+}
+
+// called for every InitPC, returns whether the parser should switch to this
+// local player. with a single client that's always the case, with several
+// running only the tracked one's InitPC is applied since it resets entities
+pub fn on_init_pc(name: &str, character_id: u64, entity_id: u64) -> bool {
+    let now = clock::now().timestamp_millis();
+    let mut clients = CLIENTS.lock().unwrap();
+    let index = match clients
+        .clients
+        .iter()
+        .position(|client| client.character_id == character_id)
+    {
+        Some(index) => index,
+        None => {
+            clients.clients.push(Client {
+                name: name.to_string(),
+                character_id,
+                entity_id,
+                last_seen: now,
+            });
+            clients.clients.len() - 1
+        }
+    };
+    let client = &mut clients.clients[index];
+    client.entity_id = entity_id;
+    client.last_seen = now;
+
+    if active_count(&clients.clients, now) < 2 {
+        clients.current = Some(index);
+        return true;
+    }
+    if !MULTIPLE_REPORTED.swap(true, Ordering::Relaxed) {
+        warn!("packets from more than one game client, following one of them");
+    }
+    let tracked = clients
+        .manual
+        .or_else(|| main_character_index(&clients))
+        .unwrap_or(index);
+    clients.current = Some(tracked);
+    tracked == index
+}
+
+// skill casts keep a client alive, a character that logged out stops casting
+pub fn on_skill_start(entity_id: u64, character_id: u64) {
+    if entity_id == 0 {
+        return;
+    }
+    let mut clients = CLIENTS.lock().unwrap();
+    let now = clock::now().timestamp_millis();
+    for client in clients.clients.iter_mut() {
+        if client.entity_id == entity_id
+            || (character_id != 0 && client.character_id == character_id)
+        {
+            client.last_seen = now;
+        }
+    }
+}
+
+// index of the followed client while several are running, encounters saved
+// then are tagged with it
+pub fn tracked_index() -> Option<usize> {
+    let clients = CLIENTS.lock().unwrap();
+    if active_count(&clients.clients, clock::now().timestamp_millis()) < 2 {
+        return None;
+    }
+    clients.current
+}
+
+pub fn get_clients() -> Vec<CaptureClient> {
+    let clients = CLIENTS.lock().unwrap();
+    let now = clock::now().timestamp_millis();
+    clients
+        .clients
+        .iter()
+        .enumerate()
+        .map(|(index, client)| CaptureClient {
+            index,
+            name: client.name.clone(),
+            character_id: client.character_id,
+            last_seen: client.last_seen,
+            active: is_active(client, now),
+            tracked: clients.current == Some(index),
+            manual: clients.manual == Some(index),
+        })
+        .collect()
+}
+
+fn main_character_index(clients: &Clients) -> Option<usize> {
+    if clients.main_character.is_empty() {
+        return None;
+    }
+    clients
+        .clients
+        .iter()
+        .position(|client| client.name.eq_ignore_ascii_case(&clients.main_character))
+}
+
+fn is_active(client: &Client, now: i64) -> bool {
+    now - client.last_seen < CLIENT_TIMEOUT_MS
+}
+
+fn active_count(clients: &[Client], now: i64) -> usize {
+    clients
+        .iter()
+        .filter(|client| is_active(client, now))
+        .count()
+}
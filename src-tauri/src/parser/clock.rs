@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+// no packets at all for this long means the pc slept or the clock jumped,
+// the game server keeps sending something every few seconds
+pub const MAX_PACKET_GAP_MS: i64 = 60_000;
+
+static SPLIT_ON_GAP: AtomicBool = AtomicBool::new(false);
+
+// kept per thread so a replay running next to the live parser has its own
+// clock, each parser runs on its own thread
+thread_local! {
+    // timestamp of the packet being replayed, 0 while capturing live
+    static REPLAY_TIME: Cell<i64> = const { Cell::new(0) };
+    // wall clock time and the instant it was read at, live timestamps count on
+    // from there so changing the system time mid-fight doesn't skew durations
+    static ANCHOR: Cell<Option<(DateTime<Utc>, Instant)>> = const { Cell::new(None) };
+    // clock gaps cut out of the current fight, parser time runs this far
+    // behind the wall clock until the next resync
+    static SKIPPED: Cell<i64> = const { Cell::new(0) };
+}
+
+// current time as seen by the parser, replays use the recorded packet times
+// so durations come out the same as they did live
+pub fn now() -> DateTime<Utc> {
+    let now = match REPLAY_TIME.get() {
+        0 => monotonic_now(),
+        millis => DateTime::from_timestamp_millis(millis).unwrap_or_else(Utc::now),
+    };
+    now - chrono::Duration::milliseconds(SKIPPED.get())
+}
+
+fn monotonic_now() -> DateTime<Utc> {
+    let (wall, instant) = ANCHOR.get().unwrap_or_else(|| {
+        let anchor = (Utc::now(), Instant::now());
+        ANCHOR.set(Some(anchor));
+        anchor
+    });
+    chrono::Duration::from_std(instant.elapsed())
+        .map(|elapsed| wall + elapsed)
+        .unwrap_or_else(|_| Utc::now())
+}
+
+// catches up with the wall clock, only done between fights
+pub fn resync() {
+    ANCHOR.set(None);
+    SKIPPED.set(0);
+}
+
+// leaves a gap out of parser time, so the fight continues where it was
+// before the gap without moving its start
+pub fn skip(ms: i64) {
+    SKIPPED.set(SKIPPED.get() + ms);
+}
+
+// how far parser time is behind the wall clock
+pub fn skipped() -> i64 {
+    SKIPPED.get()
+}
+
+pub fn set_replay_time(millis: i64) {
+    REPLAY_TIME.set(millis);
+}
+
+// parsers run on pooled threads, clears whatever an earlier run on this
+// thread left behind
+pub fn reset() {
+    REPLAY_TIME.set(0);
+    ANCHOR.set(None);
+    SKIPPED.set(0);
+}
+
+// "split" saves the fight at a gap and starts a new one, anything else cuts
+// the gap out of the fight's duration
+pub fn set_gap_mode(mode: &str) {
+    SPLIT_ON_GAP.store(mode == "split", Ordering::Relaxed);
+}
+
+pub fn split_on_gap() -> bool {
+    SPLIT_ON_GAP.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis() -> i64 {
+        now().timestamp_millis()
+    }
+
+    #[test]
+    fn skipped_gaps_are_left_out_of_parser_time() {
+        reset();
+        set_replay_time(100_000);
+        skip(40_000);
+        assert_eq!(millis(), 60_000);
+        assert_eq!(skipped(), 40_000);
+
+        // later packets continue from before the gap
+        set_replay_time(205_000);
+        assert_eq!(millis(), 165_000);
+        skip(100_000);
+        assert_eq!(millis(), 65_000);
+        assert_eq!(skipped(), 140_000);
+    }
+
+    #[test]
+    fn resync_catches_up_with_skipped_gaps() {
+        reset();
+        set_replay_time(100_000);
+        skip(40_000);
+        resync();
+        assert_eq!(millis(), 100_000);
+        assert_eq!(skipped(), 0);
+
+        skip(5_000);
+        reset();
+        assert_eq!(skipped(), 0);
+    }
+
+    #[test]
+    fn skipped_gaps_apply_to_live_time() {
+        reset();
+        let before = millis();
+        skip(3_600_000);
+        let after = millis();
+        // an hour behind, give or take the time between the two reads
+        assert!(before - after >= 3_599_000 && before - after <= 3_600_000);
+        reset();
+    }
+}
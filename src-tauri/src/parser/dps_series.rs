@@ -0,0 +1,75 @@
+use crate::parser::models::DpsSample;
+use std::collections::VecDeque;
+
+pub const DPS_SERIES_SIZE: usize = 120;
+
+// rolling per-second damage buffer for the live dps graph
+#[derive(Debug)]
+pub struct DpsSeries {
+    fight_start: i64,
+    samples: VecDeque<DpsSample>,
+}
+
+impl DpsSeries {
+    pub fn new() -> Self {
+        Self {
+            fight_start: 0,
+            samples: VecDeque::with_capacity(DPS_SERIES_SIZE),
+        }
+    }
+
+    pub fn reset(&mut self, fight_start: i64) {
+        self.fight_start = fight_start;
+        self.samples.clear();
+    }
+
+    pub fn on_damage(&mut self, timestamp: i64, damage: i64, is_local: bool) {
+        let sample = self.sample_at(timestamp);
+        sample.party_damage += damage;
+        if is_local {
+            sample.local_damage += damage;
+        }
+    }
+
+    pub fn on_boss_hp(&mut self, timestamp: i64, hp_percent: f32) {
+        self.sample_at(timestamp).boss_hp_percent = hp_percent;
+    }
+
+    pub fn samples(&self) -> Vec<DpsSample> {
+        self.samples.iter().cloned().collect()
+    }
+
+    // returns the sample for the second containing timestamp,
+    // filling any skipped seconds so the graph has no holes
+    fn sample_at(&mut self, timestamp: i64) -> &mut DpsSample {
+        let time = ((timestamp - self.fight_start) / 1000).max(0);
+        let last = self.samples.back().map(|s| (s.time, s.boss_hp_percent));
+        match last {
+            Some((last_time, _)) if last_time >= time => {}
+            Some((last_time, boss_hp_percent)) => {
+                let start = last_time.max(time - DPS_SERIES_SIZE as i64) + 1;
+                for t in start..=time {
+                    self.push(DpsSample {
+                        time: t,
+                        boss_hp_percent,
+                        ..Default::default()
+                    });
+                }
+            }
+            None => self.push(DpsSample {
+                time,
+                boss_hp_percent: 1.0,
+                ..Default::default()
+            }),
+        }
+
+        self.samples.back_mut().unwrap()
+    }
+
+    fn push(&mut self, sample: DpsSample) {
+        if self.samples.len() == DPS_SERIES_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+}
@@ -0,0 +1,33 @@
+use crate::parser::models::DUNGEON_ZONES;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DungeonMode {
+    Normal,
+    Aggregate,
+    Ignore,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+// from GeneralSettings::dungeon_mode, anything unknown records normally
+pub fn set_mode(mode: &str) {
+    let mode = match mode {
+        "aggregate" => DungeonMode::Aggregate,
+        "ignore" => DungeonMode::Ignore,
+        _ => DungeonMode::Normal,
+    };
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn mode() -> DungeonMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => DungeonMode::Aggregate,
+        2 => DungeonMode::Ignore,
+        _ => DungeonMode::Normal,
+    }
+}
+
+pub fn dungeon_name(zone_id: u32) -> Option<&'static str> {
+    DUNGEON_ZONES.get(&zone_id).copied()
+}
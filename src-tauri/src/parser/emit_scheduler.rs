@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_EMIT_INTERVAL_MS: u64 = 200;
+const LOW_PERFORMANCE_EMIT_INTERVAL_MS: u64 = 1500;
+
+// decides when the coalesced encounter snapshot is flushed to the frontend.
+// periodic updates wait for the interval, discrete events (fight start/end,
+// deaths, phase changes) request an immediate flush.
+pub struct EmitScheduler {
+    interval_ms: Arc<AtomicU64>,
+    low_performance_mode: bool,
+    last_flush: Instant,
+    immediate: bool,
+    seq: Arc<AtomicU64>,
+}
+
+impl EmitScheduler {
+    pub fn new(interval_ms: u64, low_performance_mode: bool) -> Self {
+        Self {
+            interval_ms: Arc::new(AtomicU64::new(interval_ms)),
+            low_performance_mode,
+            last_flush: Instant::now(),
+            immediate: false,
+            seq: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn set_low_performance_mode(&mut self, low_performance_mode: bool) {
+        self.low_performance_mode = low_performance_mode;
+    }
+
+    // shared handle so the interval can be changed from an event listener
+    pub fn interval_handle(&self) -> Arc<AtomicU64> {
+        self.interval_ms.clone()
+    }
+
+    pub fn interval(&self) -> Duration {
+        let mut interval_ms = self.interval_ms.load(Ordering::Relaxed);
+        if interval_ms == 0 {
+            interval_ms = DEFAULT_EMIT_INTERVAL_MS;
+        }
+        if self.low_performance_mode {
+            interval_ms = interval_ms.max(LOW_PERFORMANCE_EMIT_INTERVAL_MS);
+        }
+        Duration::from_millis(interval_ms)
+    }
+
+    pub fn request_immediate(&mut self) {
+        self.immediate = true;
+    }
+
+    pub fn should_flush(&self) -> bool {
+        self.immediate || self.last_flush.elapsed() >= self.interval()
+    }
+
+    // restarts the interval, the sequence number is only taken once a batch
+    // is actually sent
+    pub fn flush(&mut self) {
+        self.immediate = false;
+        self.last_flush = Instant::now();
+    }
+
+    // shared with the emit task so empty batches don't burn a sequence number
+    pub fn seq_handle(&self) -> Arc<AtomicU64> {
+        self.seq.clone()
+    }
+}
@@ -1,26 +1,59 @@
-use chrono::Utc;
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use log::{info, warn};
 use meter_core::packets::definitions::PKTIdentityGaugeChangeNotify;
 use moka::sync::Cache;
 use rsntp::SntpClient;
-use rusqlite::Connection;
-use std::cmp::{max, Ordering};
+use std::cmp::{max, min, Ordering};
 use std::default::Default;
+use std::sync::atomic::{self, AtomicUsize};
 
 use crate::parser::debug_print;
 use tauri::{Manager, Window, Wry};
 use tokio::task;
 
-use crate::parser::entity_tracker::{Entity, EntityTracker};
+use crate::app::boss_notes;
+use crate::app::db_pool::DbPool;
+use crate::app::search_suggestions::SuggestionCache;
+use crate::app::session;
+use crate::app::skill_records;
+use crate::parser::clients;
+use crate::parser::clock;
+use crate::parser::dps_series::DpsSeries;
+use crate::parser::dungeon::{self, DungeonMode};
+use crate::parser::entity_tracker::{get_skill_class_id, Entity, EntityTracker};
+use crate::parser::ignore_list;
+use crate::parser::interner::{intern_with, Name, NameKind};
+use crate::parser::metrics;
 use crate::parser::models::*;
+use crate::parser::name_mask;
+use crate::parser::prepull;
 use crate::parser::rdps::*;
+use crate::parser::session_stats;
 use crate::parser::skill_tracker::SkillTracker;
 use crate::parser::stats_api::{PlayerStats, StatsApi};
 use crate::parser::status_tracker::StatusEffectDetails;
 use crate::parser::utils::*;
 
 const RDPS_VALID_LIMIT: i64 = 25_000;
+// how long after a skill cast a debuff removal is still credited to it
+const CLEANSE_WINDOW_MS: i64 = 1000;
+const BOSS_CAST_LIMIT: usize = 5000;
+// a boss pattern is closed by the next boss cast or after this long
+const BOSS_PATTERN_TIMEOUT_MS: i64 = 20_000;
+// activity closer together than this is merged into one active interval
+const UPTIME_MERGE_GAP_MS: i64 = 2000;
+// a hit this long before a player died counts as a cause of the death
+const DEATH_CAUSE_WINDOW_MS: i64 = 3000;
+// one battle item can spawn several projectiles or traps, spawns of the same
+// item this close together are one use
+const ITEM_USE_WINDOW_MS: i64 = 1000;
+
+// encounters currently being written to the db
+static SAVES_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+pub fn saves_in_flight() -> usize {
+    SAVES_IN_FLIGHT.load(atomic::Ordering::Relaxed)
+}
 
 #[derive(Debug)]
 pub struct EncounterState {
@@ -37,17 +70,63 @@ pub struct EncounterState {
     damage_log: HashMap<String, Vec<(i64, i64)>>,
     identity_log: HashMap<String, IdentityLog>,
     cast_log: HashMap<String, HashMap<u32, Vec<i32>>>,
+    // entity id -> (skill id, timestamp) of the latest cast
+    last_skill_cast: HashMap<u64, (u32, i64)>,
+    // entity name -> currently open (start, end) active interval
+    active_intervals: HashMap<String, (i64, i64)>,
 
     boss_hp_log: HashMap<String, Vec<BossHpLog>>,
+    boss_casts: Vec<BossCast>,
+    boss_pattern: Option<StaggerCheck>,
+    stagger_checks: Vec<StaggerCheck>,
+    // boss skill ids that ended in a stagger, kept across pulls so later casts
+    // are tracked as checks from the start
+    stagger_check_skills: HashSet<u32>,
+    // npcs that sit under their own "Name (2)" key, see npc_key
+    entity_keys: HashMap<u64, String>,
+    // npcs that spawned and haven't been removed yet
+    spawned_npcs: HashSet<u64>,
 
     stagger_log: Vec<(i32, f32)>,
     stagger_intervals: Vec<(i32, i32)>,
 
+    // chaos dungeon or cube run recorded as one encounter, see on_zone_load
+    dungeon_run: Option<DungeonRun>,
+    // player damage when the current stage opened
+    dungeon_stage_damage: i64,
+    // the last floor was left and no zone of the run has loaded since
+    dungeon_awaiting_zone: bool,
+    // in a dungeon zone that isn't recorded
+    ignore_zone: bool,
+    // stretches without packets, see on_clock_gap
+    clock_gaps: Vec<ClockGap>,
+    // player -> (timestamp, "source: skill") of npc hits in the last
+    // DEATH_CAUSE_WINDOW_MS
+    recent_hits_taken: HashMap<String, Vec<(i64, String)>>,
+    // "source: skill" -> players that died shortly after being hit by it
+    death_causes: HashMap<String, i64>,
+    // boss name -> timestamp it was first hit, where prepull trims to
+    boss_first_hit: HashMap<String, i64>,
+    // whether the first save of the fight trimmed the prepull, see
+    // prepull::should_trim
+    prepull_trim: Option<bool>,
+    // skill id -> the local player's best hit in earlier fights, loaded at
+    // fight start
+    skill_records: HashMap<u32, i64>,
+
     pub party_info: Vec<Vec<String>>,
     pub raid_difficulty: String,
     pub raid_difficulty_id: u32,
     pub boss_only_damage: bool,
+    pub auto_favorite_pbs: bool,
+    pub min_duration_ms: i64,
+    // flags for the current encounter, next_performance takes over on reset
+    // so a fight isn't half recorded with each setting
+    pub performance: PerformanceFlags,
+    pub next_performance: PerformanceFlags,
     pub region: Option<String>,
+    // region when the fight started, unknown if it could not be detected
+    fight_region: String,
 
     sntp_client: SntpClient,
     ntp_fight_start: i64,
@@ -55,6 +134,7 @@ pub struct EncounterState {
     pub rdps_valid: bool,
 
     pub skill_tracker: SkillTracker,
+    pub dps_series: DpsSeries,
 
     custom_id_map: HashMap<u32, u32>,
 
@@ -75,15 +155,39 @@ impl EncounterState {
             damage_log: HashMap::new(),
             identity_log: HashMap::new(),
             boss_hp_log: HashMap::new(),
+            boss_casts: Vec::new(),
+            boss_pattern: None,
+            stagger_checks: Vec::new(),
+            stagger_check_skills: HashSet::new(),
+            entity_keys: HashMap::new(),
+            spawned_npcs: HashSet::new(),
             cast_log: HashMap::new(),
+            last_skill_cast: HashMap::new(),
+            active_intervals: HashMap::new(),
             stagger_log: Vec::new(),
             stagger_intervals: Vec::new(),
 
+            dungeon_run: None,
+            dungeon_stage_damage: 0,
+            dungeon_awaiting_zone: false,
+            ignore_zone: false,
+            clock_gaps: Vec::new(),
+            recent_hits_taken: HashMap::new(),
+            death_causes: HashMap::new(),
+            boss_first_hit: HashMap::new(),
+            prepull_trim: None,
+            skill_records: HashMap::new(),
+
             party_info: Vec::new(),
             raid_difficulty: "".to_string(),
             raid_difficulty_id: 0,
             boss_only_damage: false,
+            auto_favorite_pbs: false,
+            min_duration_ms: 0,
+            performance: PerformanceFlags::default(),
+            next_performance: PerformanceFlags::default(),
             region: None,
+            fight_region: String::new(),
 
             sntp_client: SntpClient::new(),
             ntp_fight_start: 0,
@@ -92,6 +196,7 @@ impl EncounterState {
             rdps_valid: false,
 
             skill_tracker: SkillTracker::new(),
+            dps_series: DpsSeries::new(),
 
             custom_id_map: HashMap::new(),
 
@@ -102,28 +207,46 @@ impl EncounterState {
     // keep all player entities, reset all stats
     pub fn soft_reset(&mut self, keep_bosses: bool) {
         let clone = self.encounter.clone();
+        metrics::reset();
 
         self.encounter.fight_start = 0;
         self.encounter.boss_only_damage = self.boss_only_damage;
+        self.dungeon_stage_damage = 0;
+        self.clock_gaps = Vec::new();
+        self.recent_hits_taken = HashMap::new();
+        self.death_causes = HashMap::new();
+        self.boss_first_hit = HashMap::new();
+        self.prepull_trim = None;
+        clock::resync();
         self.encounter.entities = HashMap::new();
+        // only players and bosses are kept, neither gets a key of its own
+        self.entity_keys = HashMap::new();
         self.encounter.current_boss_name = "".to_string();
         self.encounter.encounter_damage_stats = Default::default();
         self.prev_stagger = 0;
         self.raid_clear = false;
+        self.performance = self.next_performance;
 
         self.damage_log = HashMap::new();
         self.identity_log = HashMap::new();
         self.cast_log = HashMap::new();
+        self.last_skill_cast = HashMap::new();
+        self.active_intervals = HashMap::new();
         self.boss_hp_log = HashMap::new();
+        self.boss_casts = Vec::new();
+        self.boss_pattern = None;
+        self.stagger_checks = Vec::new();
         self.stagger_log = Vec::new();
         self.stagger_intervals = Vec::new();
         self.party_info = Vec::new();
 
         self.ntp_fight_start = 0;
+        self.fight_region = String::new();
 
         self.rdps_valid = false;
 
         self.skill_tracker = SkillTracker::new();
+        self.dps_series.reset(0);
 
         self.custom_id_map = HashMap::new();
         
@@ -185,8 +308,20 @@ impl EncounterState {
     }
 
     pub fn on_init_env(&mut self, entity: Entity, stats_api: &StatsApi) {
+        self.spawned_npcs = HashSet::new();
+        self.ignore_zone = false;
+        // a dungeon run goes on across floors until a zone outside it loads,
+        // or a second zone change comes without any of its floors loading
+        let in_dungeon_run = self.dungeon_run.is_some() && !self.dungeon_awaiting_zone;
+        if in_dungeon_run {
+            self.close_dungeon_stage();
+            self.dungeon_awaiting_zone = true;
+        } else {
+            self.finish_dungeon_run(stats_api);
+        }
+
         // if not already saved to db, we save again
-        if !self.saved && !self.encounter.current_boss_name.is_empty() {
+        if !in_dungeon_run && !self.saved && !self.encounter.current_boss_name.is_empty() {
             self.save_to_db(stats_api, false);
         }
 
@@ -202,6 +337,9 @@ impl EncounterState {
             self.encounter.entities.insert(entity.name.clone(), entity);
         }
         self.encounter.local_player = entity.name;
+        if in_dungeon_run {
+            return;
+        }
 
         // remove unrelated entities
         self.encounter.entities.retain(|_, e| {
@@ -215,7 +353,121 @@ impl EncounterState {
         self.soft_reset(false);
     }
 
+    // starts or continues a dungeon run when the zone belongs to one, and
+    // saves a finished run once a zone outside it loads
+    pub fn on_zone_load(&mut self, zone_id: u32, stats_api: &StatsApi) {
+        let dungeon = dungeon::dungeon_name(zone_id);
+        let mode = dungeon::mode();
+        self.ignore_zone = dungeon.is_some() && mode == DungeonMode::Ignore;
+        let Some(name) = dungeon.filter(|_| mode == DungeonMode::Aggregate) else {
+            self.finish_dungeon_run(stats_api);
+            return;
+        };
+
+        if self
+            .dungeon_run
+            .as_ref()
+            .is_some_and(|run| run.name != name)
+        {
+            self.finish_dungeon_run(stats_api);
+        }
+        if self.dungeon_run.is_none() {
+            info!("starting {} run", name);
+            self.dungeon_run = Some(DungeonRun {
+                name: name.to_string(),
+                stages: Vec::new(),
+            });
+        }
+        self.dungeon_awaiting_zone = false;
+
+        let player_damage = self.player_damage();
+        let Some(run) = self.dungeon_run.as_mut() else {
+            return;
+        };
+        // the zone can be announced more than once
+        if run
+            .stages
+            .last()
+            .is_some_and(|stage| stage.zone_id == zone_id && stage.end == 0)
+        {
+            return;
+        }
+        run.stages.push(DungeonStage {
+            zone_id,
+            ..Default::default()
+        });
+        self.dungeon_stage_damage = player_damage;
+    }
+
+    fn close_dungeon_stage(&mut self) {
+        let player_damage = self.player_damage();
+        let last_combat_packet = self.encounter.last_combat_packet;
+        let Some(stage) = self
+            .dungeon_run
+            .as_mut()
+            .and_then(|run| run.stages.last_mut())
+            .filter(|stage| stage.end == 0)
+        else {
+            return;
+        };
+        // a stage without any combat keeps zero timestamps
+        if stage.start > 0 {
+            stage.end = last_combat_packet;
+        }
+        stage.damage = player_damage - self.dungeon_stage_damage;
+    }
+
+    fn finish_dungeon_run(&mut self, stats_api: &StatsApi) {
+        let Some(run) = self.dungeon_run.as_ref() else {
+            return;
+        };
+        info!("finished {} run", run.name);
+        self.encounter.current_boss_name = run.name.clone();
+        self.close_dungeon_stage();
+        self.save_to_db(stats_api, false);
+        self.dungeon_run = None;
+        self.dungeon_awaiting_zone = false;
+        self.soft_reset(false);
+        self.saved = false;
+    }
+
+    pub fn local_cooldown_efficiency(&self) -> HashMap<u32, Option<f64>> {
+        let duration = self.encounter.last_combat_packet - self.encounter.fight_start;
+        self.cast_log
+            .get(&self.encounter.local_player)
+            .map(|log| cooldown_efficiency(log, duration))
+            .unwrap_or_default()
+    }
+
+    fn player_damage(&self) -> i64 {
+        self.encounter
+            .entities
+            .values()
+            .filter(|e| e.entity_type == EntityType::PLAYER)
+            .map(|e| e.damage_stats.damage_dealt)
+            .sum()
+    }
+
+    // nothing was received from start to end, most likely the pc slept. the
+    // gap is cut out of parser time, timestamps after it continue where the
+    // fight left off. the fight start stays put, it identifies the fight
+    pub fn on_clock_gap(&mut self, start: i64, end: i64) {
+        self.clock_gaps.push(wall_clock_gap(start, end, false));
+        clock::skip(end - start);
+    }
+
+    // saves the fight as it was before the gap, the caller resets afterwards
+    pub fn split_at_clock_gap(&mut self, start: i64, end: i64, stats_api: &StatsApi) {
+        self.clock_gaps.push(wall_clock_gap(start, end, true));
+        self.save_to_db(stats_api, false);
+        self.saved = true;
+    }
+
     pub fn on_phase_transition(&mut self, phase_code: i32, stats_api: &mut StatsApi) {
+        // pulls inside a dungeon run are not split into encounters
+        if self.dungeon_run.is_some() {
+            return;
+        }
         self.window
             .emit("phase-transition", phase_code)
             .expect("failed to emit phase-transition");
@@ -252,6 +504,7 @@ impl EncounterState {
             .entities
             .entry(entity.name.clone())
             .and_modify(|player| {
+                metrics::on_entity_merged();
                 player.id = entity.id;
                 player.gear_score = entity.gear_level;
                 player.current_hp = hp;
@@ -271,11 +524,13 @@ impl EncounterState {
     // add or update npc to encounter
     // we set current boss if npc matches criteria
     pub fn on_new_npc(&mut self, entity: Entity, hp: i64, max_hp: i64) {
-        let entity_name = entity.name.clone();
+        let entity_name = self.npc_key(&entity);
+        self.spawned_npcs.insert(entity.id);
         self.encounter
             .entities
             .entry(entity_name.clone())
             .and_modify(|e| {
+                metrics::on_entity_merged();
                 if entity.entity_type != EntityType::BOSS && e.entity_type != EntityType::BOSS {
                     e.npc_id = entity.npc_id;
                     e.id = entity.id;
@@ -315,11 +570,83 @@ impl EncounterState {
         }
     }
 
+    fn boss_note(&self) -> Option<String> {
+        if self.encounter.current_boss_name.is_empty() {
+            return None;
+        }
+        let pool = self.window.state::<DbPool>();
+        let conn = pool.read("boss_note").ok()?;
+        match boss_notes::get(&conn, &self.encounter.current_boss_name) {
+            Ok(note) => note.map(|note| note.note),
+            Err(e) => {
+                warn!("failed to load boss note: {}", e);
+                None
+            }
+        }
+    }
+
+    fn load_skill_records(&self) -> HashMap<u32, i64> {
+        if self.encounter.local_player.is_empty() {
+            return HashMap::new();
+        }
+        let pool = self.window.state::<DbPool>();
+        let Ok(conn) = pool.read("skill_records") else {
+            return HashMap::new();
+        };
+        skill_records::get(&conn, &self.encounter.local_player).unwrap_or_else(|e| {
+            warn!("failed to load skill records: {}", e);
+            HashMap::new()
+        })
+    }
+
+    // npcs are keyed by name, so a respawn is merged into the earlier entry.
+    // a non-boss npc spawning while another one with its name is still around,
+    // like the esthers of two players, gets its own "Name (2)" entry instead.
+    // bosses are always merged, they come back with a new id between phases
+    fn npc_key(&mut self, entity: &Entity) -> String {
+        if let Some(key) = self.entity_keys.get(&entity.id) {
+            return key.clone();
+        }
+        let mut key = entity.name.clone();
+        if entity.entity_type == EntityType::BOSS {
+            return key;
+        }
+        let mut index = 1;
+        while let Some(existing) = self.encounter.entities.get(&key) {
+            if existing.id == entity.id
+                || existing.entity_type == EntityType::BOSS
+                || existing.entity_type == EntityType::PLAYER
+                || !self.spawned_npcs.contains(&existing.id)
+            {
+                break;
+            }
+            index += 1;
+            key = format!("{} ({})", entity.name, index);
+        }
+        if index > 1 {
+            self.entity_keys.insert(entity.id, key.clone());
+        }
+        key
+    }
+
+    // key of an entity in encounter.entities
+    fn entity_key(&self, entity: &Entity) -> String {
+        match self.entity_keys.get(&entity.id) {
+            Some(key) => key.clone(),
+            None => entity.name.clone(),
+        }
+    }
+
+    pub fn on_object_removed(&mut self, id: u64) {
+        self.spawned_npcs.remove(&id);
+    }
+
     pub fn on_death(&mut self, dead_entity: &Entity) {
+        let key = self.entity_key(dead_entity);
         let entity = self
             .encounter
             .entities
-            .entry(dead_entity.name.clone())
+            .entry(key)
             .or_insert_with(|| encounter_entity_from_entity(dead_entity));
 
         if (dead_entity.entity_type != EntityType::PLAYER
@@ -341,7 +668,57 @@ impl EncounterState {
         entity.current_hp = 0;
         entity.is_dead = true;
         entity.damage_stats.deaths += 1;
-        entity.damage_stats.death_time = Utc::now().timestamp_millis();
+        let timestamp = clock::now().timestamp_millis();
+        entity.damage_stats.death_time = timestamp;
+
+        if let Some(hits) = self.recent_hits_taken.remove(&dead_entity.name) {
+            let mut causes: Vec<String> = hits
+                .into_iter()
+                .filter(|(hit_at, _)| timestamp - hit_at <= DEATH_CAUSE_WINDOW_MS)
+                .map(|(_, cause)| cause)
+                .collect();
+            causes.sort();
+            causes.dedup();
+            for cause in causes {
+                *self.death_causes.entry(cause).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // battle items and sidereals, only counted for players already in the encounter
+    pub fn on_item_used(&mut self, source_entity: &Entity, item: &str, kind: &str, timestamp: i64) {
+        if self.encounter.fight_start == 0 {
+            return;
+        }
+        let Some(entity) = self.encounter.entities.get_mut(&source_entity.name) else {
+            return;
+        };
+        let uses = entity
+            .skill_stats
+            .item_uses
+            .entry(item.to_string())
+            .or_default();
+        let offset = timestamp - self.encounter.fight_start;
+        let repeated = uses
+            .last()
+            .is_some_and(|last| offset - last < ITEM_USE_WINDOW_MS);
+        if kind == "item" && repeated {
+            return;
+        }
+        uses.push(offset);
+
+        let used = ItemUsed {
+            player: name_mask::mask_player(
+                &entity.name,
+                &entity.class,
+                &self.encounter.local_player,
+            ),
+            item: item.to_string(),
+            kind: kind.to_string(),
+            count: uses.len(),
+            timestamp,
+        };
+        self.window.emit("item-used", used).ok();
     }
 
     pub fn on_skill_start(
@@ -358,45 +735,42 @@ impl EncounterState {
         }
         let skill_name = get_skill_name(&skill_id);
         let mut tripod_change = false;
-        let entity = self
-            .encounter
-            .entities
-            .entry(source_entity.name.clone())
-            .or_insert_with(|| {
-                let (skill_name, skill_icon, summons) = get_skill_name_and_icon(
-                    &skill_id,
-                    &0,
-                    skill_name.clone(),
-                    &self.skill_tracker,
-                    source_entity.id,
-                );
-                let mut entity = encounter_entity_from_entity(source_entity);
-                entity.skill_stats = SkillStats {
+        let key = self.entity_key(source_entity);
+        let entity = self.encounter.entities.entry(key).or_insert_with(|| {
+            let (skill_name, skill_icon, summons) = get_skill_name_and_icon(
+                &skill_id,
+                &0,
+                skill_name.clone(),
+                &self.skill_tracker,
+                source_entity.id,
+            );
+            let mut entity = encounter_entity_from_entity(source_entity);
+            entity.skill_stats = SkillStats {
+                casts: 0,
+                ..Default::default()
+            };
+            entity.skills = HashMap::from([(
+                skill_id,
+                Skill {
+                    id: skill_id,
+                    name: {
+                        if skill_name.is_empty() {
+                            skill_id.to_string()
+                        } else {
+                            skill_name
+                        }
+                    },
+                    icon: skill_icon,
+                    tripod_index,
+                    tripod_level,
+                    summon_sources: summons,
                     casts: 0,
                     ..Default::default()
-                };
-                entity.skills = HashMap::from([(
-                    skill_id,
-                    Skill {
-                        id: skill_id,
-                        name: {
-                            if skill_name.is_empty() {
-                                skill_id.to_string()
-                            } else {
-                                skill_name
-                            }
-                        },
-                        icon: skill_icon,
-                        tripod_index,
-                        tripod_level,
-                        summon_sources: summons,
-                        casts: 0,
-                        ..Default::default()
-                    },
-                )]);
-                tripod_change = true;
-                entity
-            });
+                },
+            )]);
+            tripod_change = true;
+            entity
+        });
 
         if entity.class_id == 0
             && source_entity.entity_type == EntityType::PLAYER
@@ -406,15 +780,40 @@ impl EncounterState {
             entity.class = get_class_from_id(&source_entity.class_id);
         }
 
+        if entity.is_dead && entity.damage_stats.death_time > 0 {
+            entity.damage_stats.dead_for += max(timestamp - entity.damage_stats.death_time, 0);
+        }
         entity.is_dead = false;
         entity.skill_stats.casts += 1;
+        if entity.entity_type == EntityType::PLAYER {
+            track_activity(
+                &mut self.active_intervals,
+                &entity.name,
+                &mut entity.damage_stats,
+                timestamp,
+            );
+        }
 
         let relative_timestamp = if self.encounter.fight_start == 0 {
             0
         } else {
-            (timestamp - self.encounter.fight_start) as i32
+            saturating_i32(timestamp - self.encounter.fight_start)
         };
 
+        if is_awakening_skill(skill_id) {
+            entity
+                .damage_stats
+                .awakening
+                .awakening_casts
+                .push(relative_timestamp);
+        } else if is_hyper_awakening_skill(skill_id) {
+            entity
+                .damage_stats
+                .awakening
+                .hyper_awakening_casts
+                .push(relative_timestamp);
+        }
+
         // if skills have different ids but the same name, we group them together
         // dunno if this is right approach xd
         let mut skill_id = skill_id;
@@ -504,12 +903,17 @@ impl EncounterState {
             //     });
             // }
         }
-        self.cast_log
+        let casts = self
+            .cast_log
             .entry(entity.name.clone())
             .or_default()
             .entry(skill_id)
-            .or_default()
-            .push(relative_timestamp);
+            .or_default();
+        if self.performance.cast_log_limit == 0 || casts.len() < self.performance.cast_log_limit {
+            casts.push(relative_timestamp);
+        }
+        self.last_skill_cast
+            .insert(source_entity.id, (skill_id, timestamp));
 
         (skill_id, skill_summon_sources)
     }
@@ -568,6 +972,13 @@ impl EncounterState {
         {
             return;
         }
+        // summons deal damage as their owner, so the projectile is checked too
+        if ignore_list::is_ignored(dmg_src_entity)
+            || ignore_list::is_ignored(proj_entity)
+            || ignore_list::is_ignored(dmg_target_entity)
+        {
+            return;
+        }
 
         let mut skill_effect_id = damage_data.skill_effect_id;
         if proj_entity.entity_type == EntityType::PROJECTILE
@@ -576,17 +987,19 @@ impl EncounterState {
             skill_effect_id = proj_entity.skill_effect_id;
         }
 
+        let source_key = self.entity_key(dmg_src_entity);
+        let target_key = self.entity_key(dmg_target_entity);
         let mut source_entity = self
             .encounter
             .entities
-            .entry(dmg_src_entity.name.clone())
+            .entry(source_key.clone())
             .or_insert_with(|| encounter_entity_from_entity(dmg_src_entity))
             .to_owned();
 
         let mut target_entity = self
             .encounter
             .entities
-            .entry(dmg_target_entity.name.clone())
+            .entry(target_key.clone())
             .or_insert_with(|| {
                 let mut target_entity = encounter_entity_from_entity(dmg_target_entity);
                 target_entity.current_hp = damage_data.target_current_hp;
@@ -609,7 +1022,12 @@ impl EncounterState {
 
         if self.encounter.fight_start == 0 {
             self.encounter.fight_start = timestamp;
+            self.fight_region = self
+                .region
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string());
             self.skill_tracker.fight_start = timestamp;
+            self.dps_series.reset(timestamp);
             if source_entity.entity_type == EntityType::PLAYER && damage_data.skill_id > 0 {
                 self.skill_tracker.new_cast(
                     source_entity.id,
@@ -626,12 +1044,30 @@ impl EncounterState {
             };
 
             self.encounter.boss_only_damage = self.boss_only_damage;
+            let boss_note = self.boss_note();
+            self.skill_records = self.load_skill_records();
             self.window
-                .emit("raid-start", timestamp)
+                .emit(
+                    "raid-start",
+                    RaidStart {
+                        timestamp,
+                        boss_name: self.encounter.current_boss_name.clone(),
+                        boss_note,
+                    },
+                )
                 .expect("failed to emit raid-start");
         }
 
         self.encounter.last_combat_packet = timestamp;
+        if let Some(stage) = self
+            .dungeon_run
+            .as_mut()
+            .and_then(|run| run.stages.last_mut())
+        {
+            if stage.start == 0 && stage.end == 0 {
+                stage.start = timestamp;
+            }
+        }
 
         source_entity.id = dmg_src_entity.id;
 
@@ -650,25 +1086,47 @@ impl EncounterState {
         } else {
             skill_effect_id
         };
+        // damage from battle items is grouped into one pseudo skill
+        if damage_data.skill_id == 0 && BATTLE_ITEMS.contains_key(&skill_effect_id) {
+            skill_id = BATTLE_ITEM_SKILL_ID;
+            source_entity
+                .skills
+                .entry(skill_id)
+                .or_insert_with(|| Skill {
+                    id: skill_id,
+                    name: BATTLE_ITEM_SKILL_NAME.to_string(),
+                    ..Default::default()
+                });
+        }
 
-        let skill_data = get_skill(&skill_id);
-        let mut skill_name = "".to_string();
+        // borrow the static skill data instead of cloning it on every hit
+        let skill_data = SKILL_DATA.get(&skill_id);
+        let mut skill_name = if skill_id == BATTLE_ITEM_SKILL_ID {
+            Name::from(BATTLE_ITEM_SKILL_NAME)
+        } else {
+            intern_with(NameKind::Skill, skill_id, || {
+                skill_data
+                    .and_then(|skill_data| skill_data.name.clone())
+                    .unwrap_or_default()
+            })
+        };
         let mut skill_summon_sources: Option<Vec<u32>> = None;
-        if let Some(skill_data) = skill_data.as_ref() {
-            skill_name = skill_data.name.clone().unwrap_or_default();
+        if let Some(skill_data) = skill_data {
             skill_summon_sources.clone_from(&skill_data.summon_source_skills);
         }
 
         if skill_name.is_empty() {
-            (skill_name, _, skill_summon_sources) = get_skill_name_and_icon(
+            let (name, _, summon_sources) = get_skill_name_and_icon(
                 &skill_id,
                 &skill_effect_id,
                 skill_id.to_string(),
                 &self.skill_tracker,
                 source_entity.id,
             );
+            skill_name = Name::from(name);
+            skill_summon_sources = summon_sources;
         }
-        let relative_timestamp = (timestamp - self.encounter.fight_start) as i32;
+        let relative_timestamp = saturating_i32(timestamp - self.encounter.fight_start);
 
         if !source_entity.skills.contains_key(&skill_id) {
             if let Some(skill) = source_entity
@@ -681,7 +1139,7 @@ impl EncounterState {
                 let (skill_name, skill_icon, _) = get_skill_name_and_icon(
                     &skill_id,
                     &skill_effect_id,
-                    skill_name.clone(),
+                    skill_name.to_string(),
                     &self.skill_tracker,
                     source_entity.id,
                 );
@@ -714,19 +1172,50 @@ impl EncounterState {
         };
 
         skill.total_damage += damage;
-        if damage > skill.max_damage {
+        let new_max_hit = damage > skill.max_damage;
+        if new_max_hit {
             skill.max_damage = damage;
         }
         skill.last_timestamp = timestamp;
 
         source_entity.damage_stats.damage_dealt += damage;
+        // live estimate, redone from the encounter's boss npc ids when saving
+        if target_entity.entity_type == EntityType::BOSS {
+            source_entity.damage_stats.damage_to_boss += damage;
+        } else if target_entity.entity_type != EntityType::PLAYER {
+            source_entity.damage_stats.damage_to_adds += damage;
+        }
+        if target_entity.entity_type != EntityType::PLAYER {
+            *source_entity
+                .damage_by_npc
+                .entry(target_entity.npc_id)
+                .or_default() += damage;
+        }
 
         let is_hyper_awakening = is_hyper_awakening_skill(skill.id);
         if is_hyper_awakening {
             source_entity.damage_stats.hyper_awakening_damage += damage;
+            source_entity.damage_stats.awakening.hyper_awakening_damage += damage;
+        } else if is_awakening_skill(skill.id) {
+            source_entity.damage_stats.awakening.awakening_damage += damage;
         }
 
         target_entity.damage_stats.damage_taken += damage;
+        target_entity.damage_stats.damage_taken_unmitigated += damage;
+
+        if source_entity.entity_type == EntityType::PLAYER
+            && target_entity.entity_type == EntityType::BOSS
+        {
+            let stagger = SKILL_EFFECT_DATA
+                .get(&skill_effect_id)
+                .map_or(0, |effect| effect.stagger);
+            if stagger > 0 {
+                let name = source_entity.name.clone();
+                if let Some(pattern) = self.current_boss_pattern(timestamp) {
+                    *pattern.stagger.entry(name).or_default() += stagger as i64;
+                }
+            }
+        }
 
         source_entity.skill_stats.hits += 1;
         skill.hits += 1;
@@ -752,6 +1241,13 @@ impl EncounterState {
             skill.front_attack_damage += damage;
             skill_hit.front_attack = true;
         }
+        let non_crits = skill.hits - skill.crits;
+        if non_crits > 0 {
+            skill.avg_hit = (skill.total_damage - skill.crit_damage) / non_crits;
+        }
+        if skill.crits > 0 {
+            skill.avg_crit_hit = skill.crit_damage / skill.crits;
+        }
 
         if source_entity.entity_type == EntityType::PLAYER {
             self.encounter.encounter_damage_stats.total_damage_dealt += damage;
@@ -760,10 +1256,25 @@ impl EncounterState {
                 source_entity.damage_stats.damage_dealt,
             );
 
-            self.damage_log
-                .entry(source_entity.name.clone())
-                .or_default()
-                .push((timestamp, damage));
+            if self.performance.rolling_dps {
+                self.damage_log
+                    .entry(source_entity.name.clone())
+                    .or_default()
+                    .push((timestamp, damage));
+            }
+            track_activity(
+                &mut self.active_intervals,
+                &source_entity.name,
+                &mut source_entity.damage_stats,
+                timestamp,
+            );
+            if self.performance.rolling_dps {
+                self.dps_series.on_damage(
+                    timestamp,
+                    damage,
+                    source_entity.name == self.encounter.local_player,
+                );
+            }
 
             let mut is_buffed_by_support = false;
             let mut is_buffed_by_identity = false;
@@ -918,11 +1429,22 @@ impl EncounterState {
 
                 filtered_se_on_source_ids.push(*buff_id);
 
-                skill
-                    .buffed_by
-                    .entry(*buff_id)
-                    .and_modify(|e| *e += damage)
-                    .or_insert(damage);
+                if self.performance.skill_buff_attribution {
+                    skill
+                        .buffed_by
+                        .entry(*buff_id)
+                        .and_modify(|e| *e += damage)
+                        .or_insert(damage);
+                    if self
+                        .encounter
+                        .encounter_damage_stats
+                        .buffs
+                        .get(buff_id)
+                        .is_some_and(is_support_synergy)
+                    {
+                        *skill.synergy_buffed_by.entry(*buff_id).or_default() += damage;
+                    }
+                }
                 source_entity
                     .damage_stats
                     .buffed_by
@@ -935,11 +1457,22 @@ impl EncounterState {
                     break;
                 }
 
-                skill
-                    .debuffed_by
-                    .entry(*debuff_id)
-                    .and_modify(|e| *e += damage)
-                    .or_insert(damage);
+                if self.performance.skill_buff_attribution {
+                    skill
+                        .debuffed_by
+                        .entry(*debuff_id)
+                        .and_modify(|e| *e += damage)
+                        .or_insert(damage);
+                    if self
+                        .encounter
+                        .encounter_damage_stats
+                        .debuffs
+                        .get(debuff_id)
+                        .is_some_and(is_support_synergy)
+                    {
+                        *skill.synergy_buffed_by.entry(*debuff_id).or_default() += damage;
+                    }
+                }
                 source_entity
                     .damage_stats
                     .debuffed_by
@@ -948,6 +1481,34 @@ impl EncounterState {
                     .or_insert(damage);
             }
 
+            if new_max_hit {
+                let stats = &self.encounter.encounter_damage_stats;
+                let mut synergies: Vec<u32> = filtered_se_on_source_ids
+                    .iter()
+                    .filter(|id| stats.buffs.get(*id).is_some_and(is_support_synergy))
+                    .copied()
+                    .collect();
+                if !is_hyper_awakening {
+                    synergies.extend(
+                        se_on_target_ids
+                            .iter()
+                            .filter(|id| stats.debuffs.get(*id).is_some_and(is_support_synergy)),
+                    );
+                }
+                let personal_record = source_entity.name == self.encounter.local_player
+                    && self
+                        .skill_records
+                        .get(&skill.id)
+                        .is_some_and(|&record| damage > record);
+                skill.max_hit = Some(MaxHit {
+                    damage,
+                    timestamp: relative_timestamp as i64,
+                    crit: skill_hit.crit,
+                    synergies,
+                    personal_record,
+                });
+            }
+
             skill_hit.buffed_by = filtered_se_on_source_ids;
             if !is_hyper_awakening {
                 skill_hit.debuffed_by = se_on_target_ids;
@@ -1537,7 +2098,31 @@ impl EncounterState {
         }
 
         if target_entity.entity_type == EntityType::PLAYER {
+            target_entity.damage_stats.hits_taken += 1;
+            let skill = source_entity
+                .skills
+                .get(&skill_id)
+                .map_or("", |skill| skill.name.as_str());
+            let source = target_entity
+                .damage_stats
+                .damage_taken_by
+                .entry(format!("{}: {}", source_entity.name, skill))
+                .or_default();
+            source.damage += damage;
+            source.hits += 1;
+            if source_entity.entity_type != EntityType::PLAYER {
+                let hits = self
+                    .recent_hits_taken
+                    .entry(target_entity.name.clone())
+                    .or_default();
+                hits.retain(|(hit_at, _)| timestamp - hit_at <= DEATH_CAUSE_WINDOW_MS);
+                hits.push((timestamp, format!("{}: {}", source_entity.name, skill)));
+            }
+
             self.encounter.encounter_damage_stats.total_damage_taken += damage;
+            self.encounter
+                .encounter_damage_stats
+                .total_damage_taken_unmitigated += damage;
             self.encounter.encounter_damage_stats.top_damage_taken = max(
                 self.encounter.encounter_damage_stats.top_damage_taken,
                 target_entity.damage_stats.damage_taken,
@@ -1548,14 +2133,12 @@ impl EncounterState {
             self.encounter
                 .current_boss_name
                 .clone_from(&target_entity.name);
+            self.boss_first_hit
+                .entry(target_entity.name.clone())
+                .or_insert(timestamp);
             target_entity.id = dmg_target_entity.id;
             target_entity.npc_id = dmg_target_entity.npc_id;
 
-            let log = self
-                .boss_hp_log
-                .entry(target_entity.name.clone())
-                .or_default();
-
             let current_hp = if target_entity.current_hp >= 0 {
                 target_entity.current_hp + target_entity.current_shield as i64
             } else {
@@ -1567,14 +2150,24 @@ impl EncounterState {
                 0.0
             };
 
-            let relative_timestamp_s = relative_timestamp / 1000;
+            if self.performance.boss_hp_log {
+                let log = self
+                    .boss_hp_log
+                    .entry(target_entity.name.clone())
+                    .or_default();
+                let relative_timestamp_s = relative_timestamp / 1000;
 
-            if log.is_empty() || log.last().unwrap().time != relative_timestamp_s {
-                log.push(BossHpLog::new(relative_timestamp_s, current_hp, hp_percent));
-            } else {
-                let last = log.last_mut().unwrap();
-                last.hp = current_hp;
-                last.p = hp_percent;
+                if log.is_empty() || log.last().unwrap().time != relative_timestamp_s {
+                    log.push(BossHpLog::new(relative_timestamp_s, current_hp, hp_percent));
+                } else {
+                    let last = log.last_mut().unwrap();
+                    last.hp = current_hp;
+                    last.p = hp_percent;
+                }
+            }
+
+            if self.performance.rolling_dps {
+                self.dps_series.on_boss_hp(timestamp, hp_percent);
             }
         }
 
@@ -1588,15 +2181,114 @@ impl EncounterState {
             );
         }
 
-        self.encounter
-            .entities
-            .insert(source_entity.name.clone(), source_entity);
-        self.encounter
-            .entities
-            .insert(target_entity.name.clone(), target_entity);
+        self.encounter.entities.insert(source_key, source_entity);
+        self.encounter.entities.insert(target_key, target_entity);
+    }
+
+    pub fn on_boss_cast(&mut self, skill_id: u32, timestamp: i64) {
+        if self.encounter.fight_start == 0 {
+            return;
+        }
+
+        // get_skill_name falls back to the numeric id for unknown skills
+        let skill_name = get_skill_name(&skill_id);
+        let boss_cast = BossCast {
+            offset_ms: timestamp - self.encounter.fight_start,
+            skill_id,
+            skill_name,
+        };
+
+        self.finish_boss_pattern(timestamp);
+        let pattern = StaggerCheck {
+            start: boss_cast.offset_ms,
+            end: boss_cast.offset_ms,
+            skill_id,
+            skill_name: boss_cast.skill_name.clone(),
+            stagger_check: self.stagger_check_skills.contains(&skill_id),
+            ..Default::default()
+        };
+        if pattern.stagger_check {
+            if let Err(e) = self.window.emit("stagger-check-started", pattern.clone()) {
+                warn!("failed to emit stagger-check-started: {}", e);
+            }
+        }
+        self.boss_pattern = Some(pattern);
+
+        if self.boss_casts.len() >= BOSS_CAST_LIMIT {
+            return;
+        }
+        if boss_cast.skill_name != skill_id.to_string() {
+            if let Err(e) = self.window.emit("boss-cast", boss_cast.clone()) {
+                warn!("failed to emit boss-cast: {}", e);
+            }
+        }
+        self.boss_casts.push(boss_cast);
+    }
+
+    // returns the boss pattern that is still running at timestamp
+    fn current_boss_pattern(&mut self, timestamp: i64) -> Option<&mut StaggerCheck> {
+        let relative_timestamp = timestamp - self.encounter.fight_start;
+        if self
+            .boss_pattern
+            .as_ref()
+            .is_some_and(|p| relative_timestamp - p.start > BOSS_PATTERN_TIMEOUT_MS)
+        {
+            self.finish_boss_pattern(timestamp);
+        }
+        self.boss_pattern.as_mut().map(|pattern| {
+            pattern.end = max(pattern.end, relative_timestamp);
+            pattern
+        })
+    }
+
+    // keeps the pattern as a marker if it was a stagger check or got countered
+    fn finish_boss_pattern(&mut self, timestamp: i64) {
+        let Some(mut pattern) = self.boss_pattern.take() else {
+            return;
+        };
+        pattern.end = min(
+            timestamp - self.encounter.fight_start,
+            pattern.start + BOSS_PATTERN_TIMEOUT_MS,
+        )
+        .max(pattern.end);
+        if !pattern.stagger_check && pattern.counters.is_empty() {
+            return;
+        }
+        if pattern.stagger_check {
+            let mut result = pattern.clone();
+            name_mask::mask_stagger_check(&mut result, &self.encounter);
+            if let Err(e) = self.window.emit("stagger-check-result", result) {
+                warn!("failed to emit stagger-check-result: {}", e);
+            }
+        }
+        if self.stagger_checks.len() < BOSS_CAST_LIMIT {
+            self.stagger_checks.push(pattern);
+        }
+    }
+
+    // a paralyzed boss was staggered out of the pattern it was casting, which
+    // makes that pattern a stagger check
+    pub fn on_boss_paralyzed(&mut self, target_entity: &Entity, timestamp: i64) {
+        if self.encounter.fight_start == 0 || target_entity.entity_type != EntityType::BOSS {
+            return;
+        }
+        let Some(pattern) = self.current_boss_pattern(timestamp) else {
+            return;
+        };
+        pattern.stagger_check = true;
+        let skill_id = pattern.skill_id;
+        self.stagger_check_skills.insert(skill_id);
+        self.finish_boss_pattern(timestamp);
     }
 
     pub fn on_counterattack(&mut self, source_entity: &Entity) {
+        if self.encounter.fight_start > 0 {
+            let name = source_entity.name.clone();
+            if let Some(pattern) = self.current_boss_pattern(clock::now().timestamp_millis()) {
+                *pattern.counters.entry(name).or_default() += 1;
+            }
+        }
+
         let entity = self
             .encounter
             .entities
@@ -1635,11 +2327,12 @@ impl EncounterState {
             .entities
             .get_mut(&self.encounter.local_player)
         {
+            let timestamp = clock::now().timestamp_millis();
             self.identity_log
                 .entry(entity.name.clone())
                 .or_default()
                 .push((
-                    Utc::now().timestamp_millis(),
+                    timestamp,
                     (
                         pkt.identity_gauge1,
                         pkt.identity_gauge2,
@@ -1695,6 +2388,67 @@ impl EncounterState {
     //     }
     // }
 
+    // debuffs on a player that were cancelled before expiring are credited to the
+    // most recent cleanse skill cast within CLEANSE_WINDOW_MS. battle items (potions)
+    // used by the target itself count as self cleanses so supports are compared fairly
+    pub fn on_debuffs_cleansed(
+        &mut self,
+        target_entity: &Entity,
+        debuffs: Vec<StatusEffectDetails>,
+        timestamp: i64,
+    ) {
+        if self.encounter.fight_start == 0 || target_entity.entity_type != EntityType::PLAYER {
+            return;
+        }
+        if self
+            .encounter
+            .entities
+            .get(&target_entity.name)
+            .map_or(true, |e| e.is_dead)
+        {
+            return;
+        }
+
+        let caster = self
+            .last_skill_cast
+            .iter()
+            .filter(|(entity_id, (skill_id, cast_time))| {
+                timestamp - cast_time <= CLEANSE_WINDOW_MS
+                    && (CLEANSE_SKILLS.contains(skill_id)
+                        || (**entity_id == target_entity.id && get_skill_class_id(skill_id) == 0))
+            })
+            .max_by_key(|(_, (_, cast_time))| *cast_time)
+            .map(|(entity_id, (skill_id, _))| (*entity_id, *skill_id));
+        let (caster_id, skill_id) = match caster {
+            Some(caster) => caster,
+            None => return,
+        };
+
+        let is_self_cleanse = !CLEANSE_SKILLS.contains(&skill_id);
+        let caster = match self
+            .encounter
+            .entities
+            .values_mut()
+            .find(|e| e.id == caster_id && e.entity_type == EntityType::PLAYER)
+        {
+            Some(caster) => caster,
+            None => return,
+        };
+
+        for debuff in debuffs {
+            if is_self_cleanse {
+                caster.skill_stats.self_cleanses += 1;
+            } else {
+                caster.skill_stats.cleanses += 1;
+            }
+            *caster
+                .damage_stats
+                .cleansed_debuffs
+                .entry(debuff.status_effect_id)
+                .or_default() += 1;
+        }
+    }
+
     pub fn on_boss_shield(&mut self, target_entity: &Entity, shield: u64) {
         if target_entity.entity_type == EntityType::BOSS
             && target_entity.name == self.encounter.current_boss_name
@@ -1825,6 +2579,7 @@ impl EncounterState {
 
             if source_entity.id == target_entity.id {
                 source_entity_state.damage_stats.damage_absorbed += shield_removed;
+                source_entity_state.damage_stats.damage_taken_unmitigated += shield_removed as i64;
                 source_entity_state.damage_stats.damage_absorbed_on_others += shield_removed;
                 source_entity_state
                     .damage_stats
@@ -1844,6 +2599,7 @@ impl EncounterState {
                     .insert(source_entity_state.name.clone(), source_entity_state);
             } else {
                 target_entity_state.damage_stats.damage_absorbed += shield_removed;
+                target_entity_state.damage_stats.damage_taken_unmitigated += shield_removed as i64;
                 source_entity_state.damage_stats.damage_absorbed_on_others += shield_removed;
                 target_entity_state
                     .damage_stats
@@ -1869,61 +2625,117 @@ impl EncounterState {
             self.encounter
                 .encounter_damage_stats
                 .total_effective_shielding += shield_removed;
+            self.encounter.encounter_damage_stats.total_damage_absorbed += shield_removed;
+            self.encounter
+                .encounter_damage_stats
+                .total_damage_taken_unmitigated += shield_removed as i64;
+        }
+    }
+
+    // refreshes uptime_percent for every player, including the interval
+    // that is still open, so idle players drop while the fight goes on
+    pub fn update_uptime(&mut self) {
+        let duration = self.encounter.last_combat_packet - self.encounter.fight_start;
+        if self.encounter.fight_start == 0 || duration <= 0 {
+            return;
+        }
+        for entity in self.encounter.entities.values_mut() {
+            if entity.entity_type != EntityType::PLAYER {
+                continue;
+            }
+            let open = self
+                .active_intervals
+                .get(&entity.name)
+                .map_or(0, |(start, end)| end - start);
+            let active_time = entity.damage_stats.active_time + open;
+            entity.damage_stats.uptime_percent =
+                (active_time as f64 / duration as f64 * 100.0).min(100.0);
         }
     }
 
     pub fn save_to_db(&mut self, stats_api: &StatsApi, manual: bool) {
         if !manual {
-            if self.encounter.fight_start == 0
-                || self.encounter.current_boss_name.is_empty()
-                || !self
-                    .encounter
-                    .entities
-                    .contains_key(&self.encounter.current_boss_name)
-                || !self
-                    .encounter
-                    .entities
-                    .values()
-                    .any(|e| e.entity_type == EntityType::PLAYER && e.damage_stats.damage_dealt > 0)
-            {
+            if self.ignore_zone {
+                info!("not saving encounter in an ignored dungeon zone");
                 return;
             }
-
-            if let Some(current_boss) = self
-                .encounter
-                .entities
-                .get(&self.encounter.current_boss_name)
-            {
+            if self.encounter.fight_start == 0 || self.player_damage() == 0 {
+                return;
+            }
+            // a dungeon run is named after the dungeon and has no boss entity
+            if self.dungeon_run.is_none() {
+                let Some(current_boss) = self
+                    .encounter
+                    .entities
+                    .get(&self.encounter.current_boss_name)
+                else {
+                    return;
+                };
                 if current_boss.current_hp == current_boss.max_hp {
                     return;
                 }
             }
         }
 
+        session_stats::on_encounter(&self.encounter);
+
+        if self.window.state::<DbPool>().is_read_only() {
+            warn!("database was created by a newer version, not saving to db");
+            return;
+        }
+
         if !self.damage_is_valid {
             warn!("damage decryption is invalid, not saving to db");
         }
 
+        self.update_uptime();
         let mut encounter = self.encounter.clone();
-        let mut path = self
-            .window
-            .app_handle()
-            .path_resolver()
-            .resource_dir()
-            .expect("could not get resource dir");
-        path.push("encounters.db");
         let prev_stagger = self.prev_stagger;
 
-        let damage_log = self.damage_log.clone();
+        let mut damage_log = self.damage_log.clone();
         let identity_log = self.identity_log.clone();
-        let cast_log = self.cast_log.clone();
-        let boss_hp_log = self.boss_hp_log.clone();
-        let stagger_log = self.stagger_log.clone();
+        let mut cast_log = self.cast_log.clone();
+        let mut boss_hp_log = self.boss_hp_log.clone();
+        let mut boss_casts = self.boss_casts.clone();
+        self.finish_boss_pattern(self.encounter.last_combat_packet);
+        let stagger_checks = self.stagger_checks.clone();
+        let mut stagger_log = self.stagger_log.clone();
         let stagger_intervals = self.stagger_intervals.clone();
         let raid_clear = self.raid_clear;
+        let dungeon = self.dungeon_run.clone();
+        let clock_gaps = self.clock_gaps.clone();
+        let death_causes = self.death_causes.clone();
+        let client_index = clients::tracked_index();
+        let first_boss_hit = self
+            .boss_first_hit
+            .get(&self.encounter.current_boss_name)
+            .copied()
+            .filter(|_| self.dungeon_run.is_none());
+        // a resave has to keep the fight start it was first saved with
+        let trim_prepull = *self.prepull_trim.get_or_insert_with(|| {
+            first_boss_hit.is_some_and(|first_hit| prepull::should_trim(&encounter, first_hit))
+        });
+        let prepull_damage = first_boss_hit
+            .filter(|_| trim_prepull)
+            .and_then(|first_hit| {
+                prepull::trim(
+                    &mut encounter,
+                    first_hit,
+                    &mut damage_log,
+                    &mut boss_hp_log,
+                    &mut cast_log,
+                    &mut boss_casts,
+                    &mut stagger_log,
+                )
+            });
         let party_info = self.party_info.clone();
         let raid_difficulty = self.raid_difficulty.clone();
         let region = self.region.clone();
+        let fight_region = if self.fight_region.is_empty() {
+            "unknown".to_string()
+        } else {
+            self.fight_region.clone()
+        };
         let meter_version = self.window.app_handle().package_info().version.to_string();
 
         let ntp_fight_start = self.ntp_fight_start;
@@ -1944,7 +2756,25 @@ impl EncounterState {
 
         encounter.current_boss_name = update_current_boss_name(&encounter.current_boss_name);
 
+        let auto_favorite_pbs = self.auto_favorite_pbs;
+        let min_duration_ms = self.min_duration_ms;
+
+        let local_player = encounter.local_player.clone();
+        let local_max_hits: Vec<(u32, i64)> = encounter
+            .entities
+            .get(&local_player)
+            .map(|entity| {
+                entity
+                    .skills
+                    .values()
+                    .filter(|skill| skill.max_damage > 0)
+                    .map(|skill| (skill.id, skill.max_damage))
+                    .collect()
+            })
+            .unwrap_or_default();
+
         let window = self.window.clone();
+        SAVES_IN_FLIGHT.fetch_add(1, atomic::Ordering::Relaxed);
         task::spawn(async move {
             let player_infos = if !raid_difficulty.is_empty()
                 && !encounter.current_boss_name.is_empty()
@@ -1966,33 +2796,87 @@ impl EncounterState {
                 None
             };
 
-            let mut conn = Connection::open(path).expect("failed to open database");
+            let pool = window.state::<DbPool>();
+            let mut conn = pool
+                .write("save_encounter")
+                .expect("failed to open database");
             let tx = conn.transaction().expect("failed to create transaction");
 
             let encounter_id = insert_data(
                 &tx,
-                encounter,
-                prev_stagger,
-                damage_log,
-                identity_log,
-                cast_log,
-                boss_hp_log,
-                stagger_log,
-                stagger_intervals,
-                raid_clear,
-                party_info,
-                raid_difficulty,
-                region,
-                player_infos,
-                meter_version,
-                ntp_fight_start,
-                rdps_valid,
-                manual,
-                skill_cast_log,
+                EncounterSave {
+                    encounter,
+                    prev_stagger,
+                    damage_log,
+                    identity_log,
+                    cast_log,
+                    boss_hp_log,
+                    stagger_log,
+                    stagger_intervals,
+                    raid_clear,
+                    party_info,
+                    raid_difficulty,
+                    region: Some(fight_region),
+                    player_info: player_infos,
+                    meter_version,
+                    ntp_fight_start,
+                    rdps_valid,
+                    manual,
+                    skill_cast_log,
+                    boss_casts,
+                    stagger_checks,
+                    session_id: session::current_id(),
+                    dungeon,
+                    clock_gaps,
+                    death_causes,
+                    client_index,
+                    prepull_damage,
+                },
             );
+            if let Err(e) = session::record_encounter(&tx) {
+                warn!("failed to count encounter for session: {}", e);
+            }
+            let records = skill_records::update(&tx, &local_player, encounter_id, &local_max_hits);
+            if let Err(e) = records {
+                warn!("failed to update skill records: {}", e);
+            }
+            let personal_best = if auto_favorite_pbs && raid_clear {
+                favorite_if_personal_best(&tx, encounter_id, min_duration_ms)
+            } else {
+                None
+            };
 
             tx.commit().expect("failed to commit transaction");
             info!("saved to db");
+            window.state::<SuggestionCache>().invalidate();
+            SAVES_IN_FLIGHT.fetch_sub(1, atomic::Ordering::Relaxed);
+
+            // read back after the commit so the event matches what the logs show
+            let saved = conn.query_row(
+                "SELECT current_boss, cleared, duration, my_dps FROM encounter_preview WHERE id = ?",
+                [encounter_id],
+                |row| {
+                    Ok(EncounterSaved {
+                        id: encounter_id,
+                        boss_name: row.get(0)?,
+                        cleared: row.get::<_, Option<bool>>(1)?.unwrap_or_default(),
+                        duration: row.get(2)?,
+                        my_dps: row.get::<_, Option<i64>>(3)?.unwrap_or_default(),
+                        manual,
+                    })
+                },
+            );
+            drop(conn);
+            match saved {
+                Ok(saved) => {
+                    window.emit_all("encounter-saved", saved).ok();
+                }
+                Err(e) => warn!("failed to read saved encounter {}: {}", encounter_id, e),
+            }
+
+            if let Some(personal_best) = personal_best {
+                window.emit_all("encounter-pb", personal_best).ok();
+            }
 
             if raid_clear {
                 window
@@ -2002,3 +2886,35 @@ impl EncounterState {
         });
     }
 }
+
+// extends the entity's open active interval, or closes it into active_time
+// and starts a new one when the gap is too large
+fn track_activity(
+    active_intervals: &mut HashMap<String, (i64, i64)>,
+    name: &str,
+    damage_stats: &mut DamageStats,
+    timestamp: i64,
+) {
+    match active_intervals.get_mut(name) {
+        Some((_, end)) if timestamp - *end <= UPTIME_MERGE_GAP_MS => {
+            *end = max(*end, timestamp);
+        }
+        Some(interval) => {
+            damage_stats.active_time += interval.1 - interval.0;
+            *interval = (timestamp, timestamp);
+        }
+        None => {
+            active_intervals.insert(name.to_string(), (timestamp, timestamp));
+        }
+    }
+}
+
+// start and end are parser times, earlier gaps of the fight are added back
+fn wall_clock_gap(start: i64, end: i64, split: bool) -> ClockGap {
+    let skipped = clock::skipped();
+    ClockGap {
+        start: start + skipped,
+        end: end + skipped,
+        split,
+    }
+}
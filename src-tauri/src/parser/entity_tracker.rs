@@ -1,3 +1,4 @@
+use crate::parser::clock;
 use crate::parser::id_tracker::IdTracker;
 use crate::parser::models::EntityType::*;
 use crate::parser::models::{EncounterEntity, EntityType, Esther, LocalInfo, LocalPlayer, PassiveOption, ESTHER_DATA, NPC_DATA, SKILL_DATA};
@@ -279,7 +280,7 @@ impl EntityTracker {
         pkt: PKTPartyStatusEffectAddNotify,
         entities: &HashMap<String, EncounterEntity>,
     ) -> Vec<StatusEffectDetails> {
-        let timestamp = Utc::now();
+        let timestamp = clock::now();
         let mut shields: Vec<StatusEffectDetails> = Vec::new();
         for sed in pkt.status_effect_datas {
             let entity = self.get_source_entity(sed.source_id);
@@ -306,7 +307,7 @@ impl EntityTracker {
     pub fn party_status_effect_remove(
         &mut self,
         pkt: PKTPartyStatusEffectRemoveNotify,
-    ) -> (bool, Vec<StatusEffectDetails>, bool) {
+    ) -> (bool, Vec<StatusEffectDetails>, bool, Vec<StatusEffectDetails>) {
         self.status_tracker.borrow_mut().remove_status_effects(
             pkt.character_id,
             pkt.status_effect_instance_ids,
@@ -511,7 +512,7 @@ impl EntityTracker {
     }
 
     fn build_and_register_status_effects(&mut self, seds: Vec<StatusEffectData>, target_id: u64) {
-        let timestamp = Utc::now();
+        let timestamp = clock::now();
         for sed in seds.into_iter() {
             self.build_and_register_status_effect(&sed, target_id, timestamp, None);
         }
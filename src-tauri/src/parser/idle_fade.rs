@@ -0,0 +1,111 @@
+use chrono::Utc;
+use log::info;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{Window, Wry};
+
+use crate::app::opacity;
+
+pub const DEFAULT_FADE_DELAY_S: u64 = 30;
+pub const DEFAULT_FADE_OPACITY: f64 = 0.3;
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FadeSettings {
+    pub enabled: bool,
+    pub delay_s: u64,
+    pub opacity: f64,
+}
+
+// dims the meter window once no combat happened for the fade delay and
+// restores full opacity on the first combat packet afterwards
+#[derive(Clone)]
+pub struct IdleFade {
+    state: Arc<Mutex<IdleFadeState>>,
+    window: Window<Wry>,
+    stopped: Arc<AtomicBool>,
+}
+
+struct IdleFadeState {
+    settings: FadeSettings,
+    last_activity: i64,
+    faded: bool,
+}
+
+impl IdleFade {
+    pub fn start(window: Window<Wry>, settings: FadeSettings) -> Self {
+        let idle_fade = Self {
+            state: Arc::new(Mutex::new(IdleFadeState {
+                settings,
+                last_activity: Utc::now().timestamp_millis(),
+                faded: false,
+            })),
+            window,
+            stopped: Arc::new(AtomicBool::new(false)),
+        };
+
+        let clone = idle_fade.clone();
+        thread::spawn(move || {
+            while !clone.stopped.load(Ordering::Relaxed) {
+                thread::sleep(TICK_INTERVAL);
+                clone.tick();
+            }
+        });
+
+        idle_fade
+    }
+
+    // ends the fade thread, used when the parser stops
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn update_settings(&self, settings: FadeSettings) {
+        let mut state = self.state.lock().unwrap();
+        state.settings = settings;
+        if !state.faded || opacity::is_manual() {
+            return;
+        }
+        if settings.enabled {
+            opacity::set_window_opacity(&self.window, settings.opacity);
+        } else {
+            state.faded = false;
+            opacity::set_window_opacity(&self.window, 1.0);
+        }
+    }
+
+    pub fn on_combat(&self, fight_start: i64, last_combat_packet: i64) {
+        if fight_start <= 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if last_combat_packet <= state.last_activity {
+            return;
+        }
+        state.last_activity = last_combat_packet;
+        if state.faded {
+            state.faded = false;
+            opacity::set_manual(false);
+            info!("restoring meter opacity");
+            opacity::set_window_opacity(&self.window, 1.0);
+        }
+    }
+
+    fn tick(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.faded || !state.settings.enabled {
+            return;
+        }
+        let idle_ms = Utc::now().timestamp_millis() - state.last_activity;
+        if idle_ms >= state.settings.delay_s as i64 * 1000 {
+            state.faded = true;
+            opacity::set_manual(false);
+            info!("fading meter after {}s out of combat", idle_ms / 1000);
+            opacity::set_window_opacity(&self.window, state.settings.opacity);
+        }
+    }
+}
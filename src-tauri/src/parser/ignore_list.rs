@@ -0,0 +1,49 @@
+use crate::parser::entity_tracker::Entity;
+use crate::parser::models::IgnoredEntities;
+use hashbrown::HashSet;
+use std::sync::Mutex;
+
+// entities left out of live and saved encounters, from GeneralSettings
+// ignored_names and ignored_npc_ids. saved encounters keep what they had
+struct IgnoreList {
+    names: HashSet<String>,
+    npc_ids: HashSet<u32>,
+}
+
+static IGNORE_LIST: Mutex<Option<IgnoreList>> = Mutex::new(None);
+
+pub fn set(names: &[String], npc_ids: &[u32]) {
+    let names: HashSet<String> = names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    let npc_ids: HashSet<u32> = npc_ids.iter().copied().filter(|&id| id > 0).collect();
+    *IGNORE_LIST.lock().unwrap() = if names.is_empty() && npc_ids.is_empty() {
+        None
+    } else {
+        Some(IgnoreList { names, npc_ids })
+    };
+}
+
+pub fn get() -> IgnoredEntities {
+    let list = IGNORE_LIST.lock().unwrap();
+    let mut ignored = IgnoredEntities::default();
+    if let Some(list) = list.as_ref() {
+        ignored.names = list.names.iter().cloned().collect();
+        ignored.names.sort();
+        ignored.npc_ids = list.npc_ids.iter().copied().collect();
+        ignored.npc_ids.sort();
+    }
+    ignored
+}
+
+// matches by name, or by npc id so every copy of a summon or pet is caught
+pub fn is_ignored(entity: &Entity) -> bool {
+    let list = IGNORE_LIST.lock().unwrap();
+    let Some(list) = list.as_ref() else {
+        return false;
+    };
+    list.names.contains(&entity.name)
+        || (entity.npc_id > 0 && list.npc_ids.contains(&entity.npc_id))
+}
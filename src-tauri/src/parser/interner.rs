@@ -0,0 +1,86 @@
+use hashbrown::HashMap;
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    Skill,
+    Buff,
+    BuffTarget,
+}
+
+// shared name for status effects, which are rebuilt on every buff packet.
+// clones only bump a refcount, so each buff name is stored once. skill hits
+// only use it to skip a lookup allocation, the skill and entity names kept
+// in the encounter are still plain strings
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Name(Arc<str>);
+
+impl Name {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for Name {
+    fn default() -> Self {
+        Name(Arc::from(""))
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name(Arc::from(name))
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Name(Arc::from(name))
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// the parser runs on a single thread, so the cache needs no locking.
+// names come from static game data and are never evicted
+thread_local! {
+    static NAMES: RefCell<HashMap<(NameKind, u32), Name>> = RefCell::new(HashMap::new());
+}
+
+// returns the cached name for id, resolving it on first use
+pub fn intern_with(kind: NameKind, id: u32, resolve: impl FnOnce() -> String) -> Name {
+    NAMES.with(|names| {
+        names
+            .borrow_mut()
+            .entry((kind, id))
+            .or_insert_with(|| Name::from(resolve()))
+            .clone()
+    })
+}
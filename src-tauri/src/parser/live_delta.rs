@@ -0,0 +1,248 @@
+use crate::app::pipe_server;
+use crate::parser::models::{Encounter, LiveDelta, SessionStats};
+use hashbrown::HashMap;
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{Window, Wry};
+
+const FULL_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+// packets keep coming during a fight, no answer in this long means there's none
+const DUMP_TIMEOUT: Duration = Duration::from_secs(3);
+
+// checked for every packet, so the lock is only taken once a dump was requested
+static DUMP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn get_live_state() -> &'static Mutex<LiveDeltaTracker> {
+    static LIVE_STATE: OnceLock<Mutex<LiveDeltaTracker>> = OnceLock::new();
+    LIVE_STATE.get_or_init(|| Mutex::new(LiveDeltaTracker::new()))
+}
+
+// last snapshot that was sent to the frontend, used to resync after a gap
+pub fn get_live_encounter() -> Option<Encounter> {
+    get_live_state().lock().unwrap().last_encounter.clone()
+}
+
+pub fn request_full_snapshot() {
+    get_live_state().lock().unwrap().force_full = true;
+}
+
+// asks the live parser for a copy of the encounter it's working on. the parser
+// answers between two packets, so the copy is never half way through one
+pub fn request_dump() -> Result<Encounter, String> {
+    let (tx, rx) = sync_channel(1);
+    get_live_state().lock().unwrap().dump_reply = Some(tx);
+    DUMP_REQUESTED.store(true, Ordering::Relaxed);
+
+    let encounter = rx.recv_timeout(DUMP_TIMEOUT);
+    DUMP_REQUESTED.store(false, Ordering::Relaxed);
+    get_live_state().lock().unwrap().dump_reply = None;
+    match encounter {
+        Ok(encounter) if encounter.fight_start > 0 => Ok(encounter),
+        _ => Err("no fight in progress".to_string()),
+    }
+}
+
+pub fn dump_requested() -> bool {
+    DUMP_REQUESTED.load(Ordering::Relaxed)
+}
+
+// cloned under the live state lock, after which the request is cleared
+pub fn answer_dump(encounter: &Encounter) {
+    let mut live_state = get_live_state().lock().unwrap();
+    if let Some(tx) = live_state.dump_reply.take() {
+        DUMP_REQUESTED.store(false, Ordering::Relaxed);
+        tx.try_send(encounter.clone()).ok();
+    }
+}
+
+// emits the next delta update, or a full snapshot on the first update,
+// every FULL_SNAPSHOT_INTERVAL and whenever a resync was requested.
+// the lock is held while emitting so sequence numbers arrive in order
+pub fn emit_live_delta(
+    window: &Window<Wry>,
+    encounter: Encounter,
+    party: Option<HashMap<i32, Vec<String>>>,
+    invalid_damage: bool,
+    session: Option<SessionStats>,
+) {
+    let mut tracker = get_live_state().lock().unwrap();
+    let mut delta = tracker.next(encounter, party, invalid_damage);
+    delta.session = session;
+    pipe_server::publish("live-delta", &delta);
+    window
+        .emit("live-delta", delta)
+        .expect("failed to emit live-delta");
+}
+
+struct LiveDeltaTracker {
+    seq: u64,
+    last_full: Option<Instant>,
+    force_full: bool,
+    last_fields: Map<String, Value>,
+    last_entities: HashMap<String, Map<String, Value>>,
+    last_encounter: Option<Encounter>,
+    dump_reply: Option<SyncSender<Encounter>>,
+}
+
+impl LiveDeltaTracker {
+    fn new() -> Self {
+        Self {
+            seq: 0,
+            last_full: None,
+            force_full: false,
+            last_fields: Map::new(),
+            last_entities: HashMap::new(),
+            last_encounter: None,
+            dump_reply: None,
+        }
+    }
+
+    fn next(
+        &mut self,
+        encounter: Encounter,
+        party: Option<HashMap<i32, Vec<String>>>,
+        invalid_damage: bool,
+    ) -> LiveDelta {
+        self.seq += 1;
+
+        let mut fields = match serde_json::to_value(&encounter) {
+            Ok(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+        let entities: HashMap<String, Map<String, Value>> = match fields.remove("entities") {
+            Some(Value::Object(entities)) => entities
+                .into_iter()
+                .filter_map(|(name, entity)| match entity {
+                    Value::Object(entity) => Some((name, entity)),
+                    _ => None,
+                })
+                .collect(),
+            _ => HashMap::new(),
+        };
+
+        let full = self.force_full
+            || self.last_encounter.is_none()
+            || self.last_full.map_or(true, |last_full| {
+                last_full.elapsed() >= FULL_SNAPSHOT_INTERVAL
+            })
+            || self.last_fields.get("fightStart") != fields.get("fightStart");
+
+        let mut delta = LiveDelta {
+            seq: self.seq,
+            full,
+            party,
+            invalid_damage,
+            ..Default::default()
+        };
+
+        if full {
+            self.force_full = false;
+            self.last_full = Some(Instant::now());
+            delta.encounter = Some(encounter.clone());
+        } else {
+            delta.fields = diff_fields(&self.last_fields, &fields);
+            for (name, entity) in entities.iter() {
+                let changed = match self.last_entities.get(name) {
+                    Some(last) => diff_entity(last, entity),
+                    None => entity.clone(),
+                };
+                if !changed.is_empty() {
+                    delta.entities.insert(name.clone(), changed);
+                }
+            }
+            delta.removed = self
+                .last_entities
+                .keys()
+                .filter(|name| !entities.contains_key(*name))
+                .cloned()
+                .collect();
+        }
+
+        self.last_fields = fields;
+        self.last_entities = entities;
+        self.last_encounter = Some(encounter);
+
+        delta
+    }
+}
+
+fn diff_fields(old: &Map<String, Value>, new: &Map<String, Value>) -> Map<String, Value> {
+    new.iter()
+        .filter(|(key, value)| old.get(*key) != Some(*value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+// like diff_fields, but skills only carry the skill entries that changed. an
+// entity's skills never go away, so there's nothing to remove
+fn diff_entity(old: &Map<String, Value>, new: &Map<String, Value>) -> Map<String, Value> {
+    let mut changed = diff_fields(old, new);
+    if let (Some(Value::Object(old_skills)), Some(Value::Object(new_skills))) =
+        (old.get("skills"), new.get("skills"))
+    {
+        if changed.contains_key("skills") {
+            let skills = diff_fields(old_skills, new_skills);
+            changed.insert("skills".to_string(), Value::Object(skills));
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn only_changed_skills_are_sent() {
+        let old = object(json!({
+            "name": "Bob",
+            "skills": {
+                "1": { "totalDamage": 100 },
+                "2": { "totalDamage": 50 },
+            },
+        }));
+        let new = object(json!({
+            "name": "Bob",
+            "skills": {
+                "1": { "totalDamage": 100 },
+                "2": { "totalDamage": 80 },
+                "3": { "totalDamage": 10 },
+            },
+        }));
+
+        let changed = diff_entity(&old, &new);
+        assert_eq!(
+            Value::Object(changed),
+            json!({
+                "skills": {
+                    "2": { "totalDamage": 80 },
+                    "3": { "totalDamage": 10 },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn unchanged_skills_are_left_out() {
+        let entity = object(json!({
+            "currentHp": 10,
+            "skills": { "1": { "totalDamage": 100 } },
+        }));
+        let mut new = entity.clone();
+        new.insert("currentHp".to_string(), json!(5));
+
+        let changed = diff_entity(&entity, &new);
+        assert_eq!(Value::Object(changed), json!({ "currentHp": 5 }));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::parser::models::{ParserMetrics, UnknownOpcode};
+use hashbrown::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// distinct unknown opcodes kept per encounter, later ones are only counted
+const MAX_UNKNOWN_OPCODES: usize = 64;
+// a game patch usually breaks most packets at once, a few bad ones are noise
+const OUTDATED_MIN_PACKETS: u64 = 1000;
+const OUTDATED_FAILURE_RATIO: f64 = 0.05;
+
+// updated for every packet, so kept outside the mutex
+static PACKETS_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static DECODE_FAILURES: AtomicU64 = AtomicU64::new(0);
+static UNKNOWN_OPCODES_TOTAL: AtomicU64 = AtomicU64::new(0);
+static ENTITIES_CREATED: AtomicU64 = AtomicU64::new(0);
+static ENTITIES_MERGED: AtomicU64 = AtomicU64::new(0);
+static OUTDATED_REPORTED: AtomicBool = AtomicBool::new(false);
+
+fn get_unknown_opcodes() -> &'static Mutex<HashMap<u16, u64>> {
+    static UNKNOWN_OPCODES: OnceLock<Mutex<HashMap<u16, u64>>> = OnceLock::new();
+    UNKNOWN_OPCODES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn on_packet() {
+    PACKETS_RECEIVED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn on_decode_failure() {
+    DECODE_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+// the live capture drops opcodes it doesn't know, only replays of captures
+// from another client version report them
+pub fn on_unknown_opcode(opcode: u16) {
+    UNKNOWN_OPCODES_TOTAL.fetch_add(1, Ordering::Relaxed);
+    let mut opcodes = get_unknown_opcodes().lock().unwrap();
+    if let Some(count) = opcodes.get_mut(&opcode) {
+        *count += 1;
+    } else if opcodes.len() < MAX_UNKNOWN_OPCODES {
+        opcodes.insert(opcode, 1);
+    }
+}
+
+pub fn on_entity_created() {
+    ENTITIES_CREATED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn on_entity_merged() {
+    ENTITIES_MERGED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn reset() {
+    PACKETS_RECEIVED.store(0, Ordering::Relaxed);
+    DECODE_FAILURES.store(0, Ordering::Relaxed);
+    UNKNOWN_OPCODES_TOTAL.store(0, Ordering::Relaxed);
+    ENTITIES_CREATED.store(0, Ordering::Relaxed);
+    ENTITIES_MERGED.store(0, Ordering::Relaxed);
+    OUTDATED_REPORTED.store(false, Ordering::Relaxed);
+    get_unknown_opcodes().lock().unwrap().clear();
+}
+
+pub fn get_metrics() -> ParserMetrics {
+    let mut unknown_opcodes: Vec<UnknownOpcode> = get_unknown_opcodes()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(&opcode, &count)| UnknownOpcode { opcode, count })
+        .collect();
+    unknown_opcodes.sort_by(|a, b| b.count.cmp(&a.count));
+
+    ParserMetrics {
+        packets_received: PACKETS_RECEIVED.load(Ordering::Relaxed),
+        decode_failures: DECODE_FAILURES.load(Ordering::Relaxed),
+        unknown_opcodes_total: UNKNOWN_OPCODES_TOTAL.load(Ordering::Relaxed),
+        unknown_opcodes,
+        entities_created: ENTITIES_CREATED.load(Ordering::Relaxed),
+        entities_merged: ENTITIES_MERGED.load(Ordering::Relaxed),
+    }
+}
+
+// returns the metrics once per encounter when so many packets fail to decode
+// that the protocol most likely changed
+pub fn check_outdated() -> Option<ParserMetrics> {
+    let packets = PACKETS_RECEIVED.load(Ordering::Relaxed);
+    if packets < OUTDATED_MIN_PACKETS || OUTDATED_REPORTED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let failures = DECODE_FAILURES.load(Ordering::Relaxed);
+    if (failures as f64) / (packets as f64) < OUTDATED_FAILURE_RATIO {
+        return None;
+    }
+    OUTDATED_REPORTED.store(true, Ordering::Relaxed);
+    Some(get_metrics())
+}
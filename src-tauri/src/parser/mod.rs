@@ -1,47 +1,122 @@
+mod auto_clickthrough;
+pub mod capture_status;
+pub mod clients;
+pub mod clock;
+mod dps_series;
+pub mod dungeon;
+mod emit_scheduler;
 pub mod encounter_state;
 mod entity_tracker;
 mod id_tracker;
+pub mod idle_fade;
+pub mod ignore_list;
+mod interner;
+pub mod live_delta;
+pub mod metrics;
 pub mod models;
+pub mod name_mask;
+pub mod packet_dump;
+pub mod packet_source;
 mod party_tracker;
+pub mod prepull;
 mod rdps;
+pub mod session_stats;
 mod skill_tracker;
 mod stats_api;
 mod status_tracker;
-mod utils;
+pub mod utils;
 
 use self::models::{Settings, TripodIndex, TripodLevel};
+use crate::app::pipe_server;
+use crate::app::storage;
+use crate::parser::auto_clickthrough::{AutoClickthrough, DEFAULT_RELEASE_DELAY_MS};
+use crate::parser::emit_scheduler::{EmitScheduler, DEFAULT_EMIT_INTERVAL_MS};
 use crate::parser::encounter_state::EncounterState;
 use crate::parser::entity_tracker::{get_current_and_max_hp, EntityTracker};
 use crate::parser::id_tracker::IdTracker;
+use crate::parser::idle_fade::{
+    FadeSettings, IdleFade, DEFAULT_FADE_DELAY_S, DEFAULT_FADE_OPACITY,
+};
+use crate::parser::live_delta::{emit_live_delta, request_full_snapshot};
 use crate::parser::models::{
-    DamageData, EntityType, Identity, LocalInfo, LocalPlayer, Stagger, VALID_ZONES,
+    AutoFavoriteSettings, BossHpUpdate, DamageData, DpsSeriesUpdate, EntityType, IdentityUpdate,
+    LiveUpdate, LocalInfo, LocalPlayer, PerformanceFlags, Stagger, BATTLE_ITEMS, SIDEREAL_SKILLS,
+    VALID_ZONES,
 };
+use crate::parser::packet_source::PacketSource;
 use crate::parser::party_tracker::PartyTracker;
 use crate::parser::stats_api::{StatsApi, API_URL};
 use crate::parser::status_tracker::{
     get_status_effect_value, StatusEffectDetails, StatusEffectTargetType, StatusEffectType,
     StatusTracker,
 };
-use crate::parser::utils::get_class_from_id;
+use crate::parser::utils::{
+    calculate_dps_alive, compute_local_rank, get_class_from_id, is_paralyzation_effect,
+};
 use anyhow::Result;
-use chrono::Utc;
 use hashbrown::HashMap;
 use log::{info, warn};
 use meter_core::packets::definitions::*;
 use meter_core::packets::opcodes::Pkt;
-use meter_core::start_capture;
 use reqwest::Client;
 use serde_json::json;
 use std::cell::RefCell;
 use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tauri::{Manager, Window, Wry};
+use tauri::{EventHandler, Manager, Window, Wry};
 use uuid::Uuid;
 
-pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Result<()> {
+pub const BOSS_HP_WINDOW_LABEL: &str = "boss-hp";
+
+// set once a flush request was handled by the parser loop
+static FLUSHED: AtomicBool = AtomicBool::new(false);
+
+// waits until a flush request was handled and its save has been written
+pub fn wait_for_flush(timeout: Duration) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if FLUSHED.load(Ordering::Relaxed) && encounter_state::saves_in_flight() == 0 {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+// undoes what a parser run set up when it returns, so a restarted parser
+// doesn't leave the old listeners and timer threads running
+struct RunCleanup {
+    window: Window<Wry>,
+    listeners: Vec<EventHandler>,
+    auto_clickthrough: AutoClickthrough,
+    idle_fade: IdleFade,
+}
+
+impl RunCleanup {
+    fn listen<F>(&mut self, event: &str, handler: F)
+    where
+        F: Fn(tauri::Event) + Send + 'static,
+    {
+        let listener = self.window.listen_global(event, handler);
+        self.listeners.push(listener);
+    }
+}
+
+impl Drop for RunCleanup {
+    fn drop(&mut self) {
+        for listener in self.listeners.drain(..) {
+            self.window.unlisten(listener);
+        }
+        self.auto_clickthrough.stop();
+        self.idle_fade.stop();
+    }
+}
+
+pub fn start(window: Window<Wry>, source: PacketSource, settings: Option<Settings>) -> Result<()> {
+    clock::reset();
     let id_tracker = Rc::new(RefCell::new(IdTracker::new()));
     let party_tracker = Rc::new(RefCell::new(PartyTracker::new(id_tracker.clone())));
     let status_tracker = Rc::new(RefCell::new(StatusTracker::new(party_tracker.clone())));
@@ -51,25 +126,35 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
         party_tracker.clone(),
     );
     let mut state = EncounterState::new(window.clone());
-    let mut resource_path = window.app_handle().path_resolver().resource_dir().unwrap();
-    resource_path.push("current_region");
-    let region_file_path = resource_path.to_string_lossy();
+    let data_path = storage::data_dir(&window.app_handle());
+    let region_path = data_path.join("current_region");
+    let region_file_path = region_path.to_string_lossy();
     let mut stats_api = StatsApi::new(window.clone(), region_file_path.to_string());
-    let rx = match start_capture(port, region_file_path.to_string()) {
-        Ok(rx) => rx,
-        Err(e) => {
-            warn!("Error starting capture: {}", e);
-            return Ok(());
-        }
+    let capture_dump = settings.as_ref().is_some_and(|s| s.general.capture_dump);
+    let dump_dir = data_path.join(packet_dump::DUMP_DIR);
+    // replays are already dumps
+    packet_dump::init(dump_dir, capture_dump && !source.is_replay());
+
+    let packets = match source.open(region_file_path.to_string()) {
+        Ok(packets) => packets,
+        Err(e) => return Err(e.context("error starting capture")),
     };
+    if !source.is_replay() {
+        capture_status::set_capturing(true);
+    }
 
     let damage_handler = meter_core::decryption::DamageEncryptionHandler::new();
     let damage_handler = damage_handler.start()?;
 
-    let mut last_update = Instant::now();
-    let mut duration = Duration::from_millis(500);
+    let mut emit_interval_ms = DEFAULT_EMIT_INTERVAL_MS;
+    let low_performance_mode = Arc::new(AtomicBool::new(false));
     let mut last_party_update = Instant::now();
     let party_duration = Duration::from_millis(2000);
+    let mut last_series_update = Instant::now();
+    let series_duration = Duration::from_secs(1);
+    let mut last_boss_hp_update = Instant::now();
+    let boss_hp_duration = Duration::from_millis(500);
+    let mut last_boss_hp: Option<BossHpUpdate> = None;
     let mut raid_end_cd = Instant::now();
 
     let client = Client::new();
@@ -79,24 +164,89 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
     let reset = Arc::new(AtomicBool::new(false));
     let pause = Arc::new(AtomicBool::new(false));
     let save = Arc::new(AtomicBool::new(false));
+    let flush = Arc::new(AtomicBool::new(false));
     let boss_only_damage = Arc::new(AtomicBool::new(false));
+    let live_delta_updates = Arc::new(AtomicBool::new(false));
+    let live_damage_taken = Arc::new(AtomicBool::new(false));
+    let live_session_stats = Arc::new(AtomicBool::new(false));
+    let auto_favorite_pbs = Arc::new(AtomicBool::new(false));
+    let min_duration_ms = Arc::new(AtomicI64::new(0));
+    let mut auto_clickthrough_in_combat = false;
+    let mut auto_clickthrough_delay_ms = DEFAULT_RELEASE_DELAY_MS;
+    let mut fade_settings = FadeSettings {
+        enabled: false,
+        delay_s: DEFAULT_FADE_DELAY_S,
+        opacity: DEFAULT_FADE_OPACITY,
+    };
     if let Some(settings) = settings {
         if settings.general.boss_only_damage {
             boss_only_damage.store(true, Ordering::Relaxed);
             info!("boss only damage enabled")
         }
         if settings.general.low_performance_mode {
-            duration = Duration::from_millis(1500);
+            low_performance_mode.store(true, Ordering::Relaxed);
             info!("low performance mode enabled")
         }
+        emit_interval_ms = settings.general.emit_interval_ms;
+        if settings.general.live_delta_updates {
+            live_delta_updates.store(true, Ordering::Relaxed);
+            info!("live delta updates enabled")
+        }
+        if settings.general.live_damage_taken {
+            live_damage_taken.store(true, Ordering::Relaxed);
+            info!("live damage taken breakdown enabled")
+        }
+        if settings.general.live_session_stats {
+            live_session_stats.store(true, Ordering::Relaxed);
+            info!("live session stats enabled")
+        }
+        if settings.general.auto_favorite_pbs {
+            auto_favorite_pbs.store(true, Ordering::Relaxed);
+            info!("auto favorite personal bests enabled")
+        }
+        min_duration_ms.store(
+            settings.logs.min_encounter_duration as i64 * 1000,
+            Ordering::Relaxed,
+        );
+        if settings.general.auto_clickthrough_in_combat {
+            auto_clickthrough_in_combat = true;
+            info!("auto clickthrough in combat enabled")
+        }
+        auto_clickthrough_delay_ms = settings.general.auto_clickthrough_delay_ms;
+        fade_settings = FadeSettings {
+            enabled: settings.general.fade_out_of_combat,
+            delay_s: settings.general.fade_delay_s,
+            opacity: settings.general.fade_opacity,
+        };
+    }
+    // a replay shouldn't change how the window behaves
+    if source.is_replay() {
+        auto_clickthrough_in_combat = false;
+        fade_settings.enabled = false;
     }
+    let auto_clickthrough = AutoClickthrough::start(
+        window.clone(),
+        auto_clickthrough_in_combat,
+        auto_clickthrough_delay_ms,
+    );
+    let idle_fade = IdleFade::start(window.clone(), fade_settings);
+    let mut run = RunCleanup {
+        window: window.clone(),
+        listeners: Vec::new(),
+        auto_clickthrough: auto_clickthrough.clone(),
+        idle_fade: idle_fade.clone(),
+    };
+    let performance = PerformanceFlags::new(low_performance_mode.load(Ordering::Relaxed));
+    state.performance = performance;
+    state.next_performance = performance;
+    let mut emit_scheduler = EmitScheduler::new(emit_interval_ms, performance.low_performance_mode);
+    let mut last_fight_start = 0;
 
     // read saved local players
     // this info is used in case meter was opened late
     let mut local_info: LocalInfo = LocalInfo::default();
-    let mut local_player_path = window.app_handle().path_resolver().resource_dir().unwrap();
+    let local_player_path = data_path.join("local_players.json");
     let mut client_id = "".to_string();
-    local_player_path.push("local_players.json");
 
     if local_player_path.exists() {
         let local_players_file = std::fs::read_to_string(local_player_path.clone())?;
@@ -117,7 +267,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
     let emit_details = Arc::new(AtomicBool::new(false));
 
     let meter_window_clone = window.clone();
-    window.listen_global("reset-request", {
+    run.listen("reset-request", {
         let reset_clone = reset.clone();
         let meter_window_clone = meter_window_clone.clone();
         move |_event| {
@@ -127,7 +277,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
         }
     });
 
-    window.listen_global("save-request", {
+    run.listen("save-request", {
         let save_clone = save.clone();
         let meter_window_clone = meter_window_clone.clone();
         move |_event| {
@@ -137,11 +287,20 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
         }
     });
 
-    window.listen_global("pause-request", {
+    run.listen("flush-request", {
+        let flush_clone = flush.clone();
+        move |_event| {
+            flush_clone.store(true, Ordering::Relaxed);
+            info!("flushing current encounter");
+        }
+    });
+
+    run.listen("pause-request", {
         let pause_clone = pause.clone();
         let meter_window_clone = meter_window_clone.clone();
         move |_event| {
             let prev = pause_clone.fetch_xor(true, Ordering::Relaxed);
+            capture_status::set_paused(!prev);
             if prev {
                 info!("unpausing meter");
             } else {
@@ -151,7 +310,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
         }
     });
 
-    window.listen_global("boss-only-damage-request", {
+    run.listen("boss-only-damage-request", {
         let boss_only_damage = boss_only_damage.clone();
         move |event| {
             if let Some(bod) = event.payload() {
@@ -166,7 +325,141 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
         }
     });
 
-    window.listen_global("emit-details-request", {
+    run.listen("emit-interval-request", {
+        let interval = emit_scheduler.interval_handle();
+        move |event| {
+            if let Some(interval_ms) = event.payload().and_then(|p| p.parse::<u64>().ok()) {
+                interval.store(interval_ms, Ordering::Relaxed);
+                info!("emit interval set to {}ms", interval_ms);
+            }
+        }
+    });
+
+    run.listen("live-delta-request", {
+        let live_delta_updates = live_delta_updates.clone();
+        move |event| {
+            if let Some(enabled) = event.payload() {
+                if enabled == "true" {
+                    live_delta_updates.store(true, Ordering::Relaxed);
+                    request_full_snapshot();
+                    info!("live delta updates enabled")
+                } else {
+                    live_delta_updates.store(false, Ordering::Relaxed);
+                    info!("live delta updates disabled")
+                }
+            }
+        }
+    });
+
+    // takes effect from the next encounter on
+    run.listen("low-performance-request", {
+        let low_performance_mode = low_performance_mode.clone();
+        move |event| {
+            if let Some(enabled) = event.payload() {
+                if enabled == "true" {
+                    low_performance_mode.store(true, Ordering::Relaxed);
+                    info!("low performance mode enabled")
+                } else {
+                    low_performance_mode.store(false, Ordering::Relaxed);
+                    info!("low performance mode disabled")
+                }
+            }
+        }
+    });
+
+    run.listen("live-damage-taken-request", {
+        let live_damage_taken = live_damage_taken.clone();
+        move |event| {
+            if let Some(enabled) = event.payload() {
+                if enabled == "true" {
+                    live_damage_taken.store(true, Ordering::Relaxed);
+                    info!("live damage taken breakdown enabled")
+                } else {
+                    live_damage_taken.store(false, Ordering::Relaxed);
+                    info!("live damage taken breakdown disabled")
+                }
+            }
+        }
+    });
+
+    run.listen("live-session-stats-request", {
+        let live_session_stats = live_session_stats.clone();
+        move |event| {
+            if let Some(enabled) = event.payload() {
+                if enabled == "true" {
+                    live_session_stats.store(true, Ordering::Relaxed);
+                    info!("live session stats enabled")
+                } else {
+                    live_session_stats.store(false, Ordering::Relaxed);
+                    info!("live session stats disabled")
+                }
+            }
+        }
+    });
+
+    run.listen("auto-favorite-request", {
+        let auto_favorite_pbs = auto_favorite_pbs.clone();
+        let min_duration_ms = min_duration_ms.clone();
+        move |event| {
+            if let Some(settings) = event
+                .payload()
+                .and_then(|p| serde_json::from_str::<AutoFavoriteSettings>(p).ok())
+            {
+                auto_favorite_pbs.store(settings.enabled, Ordering::Relaxed);
+                min_duration_ms.store(settings.min_duration_s as i64 * 1000, Ordering::Relaxed);
+                info!("auto favorite settings updated: {:?}", settings);
+            }
+        }
+    });
+
+    run.listen("auto-clickthrough-request", {
+        let auto_clickthrough = auto_clickthrough.clone();
+        move |event| {
+            if let Some(enabled) = event.payload() {
+                if enabled == "true" {
+                    auto_clickthrough.set_enabled(true);
+                    info!("auto clickthrough in combat enabled")
+                } else {
+                    auto_clickthrough.set_enabled(false);
+                    info!("auto clickthrough in combat disabled")
+                }
+            }
+        }
+    });
+
+    run.listen("auto-clickthrough-delay-request", {
+        let auto_clickthrough = auto_clickthrough.clone();
+        move |event| {
+            if let Some(delay_ms) = event.payload().and_then(|p| p.parse::<u64>().ok()) {
+                auto_clickthrough.set_release_delay(delay_ms);
+                info!("auto clickthrough delay set to {}ms", delay_ms);
+            }
+        }
+    });
+
+    run.listen("fade-settings-request", {
+        let idle_fade = idle_fade.clone();
+        move |event| {
+            if let Some(settings) = event
+                .payload()
+                .and_then(|p| serde_json::from_str::<FadeSettings>(p).ok())
+            {
+                idle_fade.update_settings(settings);
+                info!("fade settings updated: {:?}", settings);
+            }
+        }
+    });
+
+    run.listen("capture-dump-request", {
+        let replay = source.is_replay();
+        move |event| {
+            if let Some(enabled) = event.payload() {
+                packet_dump::set_enabled(enabled == "true" && !replay);
+            }
+        }
+    });
+
+    run.listen("emit-details-request", {
         let emit_clone = emit_details.clone();
         move |_event| {
             let prev = emit_clone.fetch_xor(true, Ordering::Relaxed);
@@ -181,12 +474,66 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
     let mut party_freeze = false;
     let mut party_cache: Option<Vec<Vec<String>>> = None;
     let mut party_map_cache: HashMap<i32, Vec<String>> = HashMap::new();
+    let mut last_packet_time = 0;
+
+    for packet in packets {
+        // saves the in-progress encounter if it qualifies, used before shutting down.
+        // checked before anything else so it's handled while idle or paused
+        if flush.load(Ordering::Relaxed) {
+            flush.store(false, Ordering::Relaxed);
+            if !state.saved {
+                state.party_info = update_party(&party_tracker, &entity_tracker);
+                state.save_to_db(&stats_api, false);
+                state.saved = true;
+            }
+            FLUSHED.store(true, Ordering::Relaxed);
+        }
+        let Some((op, data)) = packet else {
+            continue;
+        };
 
-    while let Ok((op, data)) = rx.recv() {
+        if !source.is_replay() {
+            capture_status::on_packet();
+        }
+        metrics::on_packet();
+        if !source.is_replay() && live_delta::dump_requested() {
+            live_delta::answer_dump(&state.encounter);
+        }
         if reset.load(Ordering::Relaxed) {
             state.soft_reset(true);
             reset.store(false, Ordering::Relaxed);
         }
+
+        // nothing arrives while the pc sleeps, so a long silence mid-fight is
+        // either cut out of its duration or ends the fight there
+        let packet_time = clock::now().timestamp_millis();
+        if last_packet_time > 0
+            && packet_time - last_packet_time > clock::MAX_PACKET_GAP_MS
+            && state.encounter.fight_start > 0
+            && !state.saved
+        {
+            let split = clock::split_on_gap();
+            warn!(
+                "no packets for {}s during an encounter, split: {}",
+                (packet_time - last_packet_time) / 1000,
+                split
+            );
+            if split {
+                state.party_info = update_party(&party_tracker, &entity_tracker);
+                state.split_at_clock_gap(last_packet_time, packet_time, &stats_api);
+                state.soft_reset(true);
+                state.saved = false;
+                party_freeze = false;
+                party_cache = None;
+                party_map_cache = HashMap::new();
+                request_full_snapshot();
+            } else {
+                state.on_clock_gap(last_packet_time, packet_time);
+            }
+        }
+        // read again, a reset above catches up with the wall clock
+        last_packet_time = clock::now().timestamp_millis();
+
         if pause.load(Ordering::Relaxed) {
             continue;
         }
@@ -204,6 +551,11 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
             state.boss_only_damage = false;
             state.encounter.boss_only_damage = false;
         }
+        state.auto_favorite_pbs = auto_favorite_pbs.load(Ordering::Relaxed);
+        state.next_performance =
+            PerformanceFlags::new(low_performance_mode.load(Ordering::Relaxed));
+        emit_scheduler.set_low_performance_mode(state.performance.low_performance_mode);
+        state.min_duration_ms = min_duration_ms.load(Ordering::Relaxed);
 
         match op {
             Pkt::CounterAttackNotify => {
@@ -223,6 +575,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                             entity.name, entity.entity_type, entity.id
                         ));
                         state.on_death(entity);
+                        emit_scheduler.request_immediate();
                     }
                 }
             }
@@ -240,15 +593,22 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     "PKTIdentityGaugeChangeNotify",
                 ) {
                     state.on_identity_gain(&pkt);
-                    if emit_details.load(Ordering::Relaxed) {
-                        window.emit(
-                            "identity-update",
-                            Identity {
-                                gauge1: pkt.identity_gauge1,
-                                gauge2: pkt.identity_gauge2,
-                                gauge3: pkt.identity_gauge3,
+                    // feeds the details view and the gauge widget, time is 0 outside of a fight
+                    let fight_start = state.encounter.fight_start;
+                    if emit_details.load(Ordering::Relaxed) || fight_start > 0 {
+                        let update = IdentityUpdate {
+                            time: if fight_start > 0 {
+                                clock::now().timestamp_millis() - fight_start
+                            } else {
+                                0
                             },
-                        )?;
+                            gauge1: pkt.identity_gauge1,
+                            gauge2: pkt.identity_gauge2,
+                            gauge3: pkt.identity_gauge3,
+                        };
+                        if let Err(e) = window.emit("identity-update", update) {
+                            warn!("failed to emit identity-update: {}", e);
+                        }
                     }
                 }
             }
@@ -288,6 +648,10 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
             }
             Pkt::InitPC => {
                 if let Some(pkt) = parse_pkt(&data, PKTInitPC::new, "PKTInitPC") {
+                    if !clients::on_init_pc(&pkt.name, pkt.character_id, pkt.player_id) {
+                        info!("ignoring local player {} of another game client", pkt.name);
+                        continue;
+                    }
                     let (hp, max_hp) = get_current_and_max_hp(&pkt.stat_pairs);
                     let entity = entity_tracker.init_pc(pkt);
                     info!(
@@ -370,6 +734,13 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
             Pkt::NewProjectile => {
                 if let Some(pkt) = parse_pkt(&data, PKTNewProjectile::new, "PKTNewProjectile") {
                     entity_tracker.new_projectile(&pkt);
+                    if let Some(item) = BATTLE_ITEMS.get(&pkt.projectile_info.skill_effect) {
+                        let owner = entity_tracker.get_source_entity(pkt.projectile_info.owner_id);
+                        if owner.entity_type == EntityType::PLAYER {
+                            let timestamp = clock::now().timestamp_millis();
+                            state.on_item_used(&owner, item, "item", timestamp);
+                        }
+                    }
                     if entity_tracker.id_is_player(pkt.projectile_info.owner_id)
                         && pkt.projectile_info.skill_id > 0
                     {
@@ -386,6 +757,13 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
             Pkt::NewTrap => {
                 if let Some(pkt) = parse_pkt(&data, PKTNewTrap::new, "PKTNewTrap") {
                     entity_tracker.new_trap(&pkt);
+                    if let Some(item) = BATTLE_ITEMS.get(&pkt.trap_struct.skill_effect) {
+                        let owner = entity_tracker.get_source_entity(pkt.trap_struct.owner_id);
+                        if owner.entity_type == EntityType::PLAYER {
+                            let timestamp = clock::now().timestamp_millis();
+                            state.on_item_used(&owner, item, "item", timestamp);
+                        }
+                    }
                     if entity_tracker.id_is_player(pkt.trap_struct.owner_id)
                         && pkt.trap_struct.skill_id > 0
                     {
@@ -443,6 +821,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
             Pkt::RaidBossKillNotify => {
                 state.on_phase_transition(1, &mut stats_api);
                 state.raid_clear = true;
+                emit_scheduler.request_immediate();
                 debug_print(format_args!("phase: 1 - RaidBossKillNotify"));
             }
             Pkt::RaidResult => {
@@ -453,6 +832,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     update_party(&party_tracker, &entity_tracker)
                 };
                 state.on_phase_transition(0, &mut stats_api);
+                emit_scheduler.request_immediate();
                 raid_end_cd = Instant::now();
                 debug_print(format_args!("phase: 0 - RaidResult"));
             }
@@ -460,6 +840,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                 if let Some(pkt) = parse_pkt(&data, PKTRemoveObject::new, "PKTRemoveObject") {
                     for upo in pkt.unpublished_objects {
                         entity_tracker.entities.remove(&upo.object_id);
+                        state.on_object_removed(upo.object_id);
                         status_tracker
                             .borrow_mut()
                             .remove_local_object(upo.object_id);
@@ -476,7 +857,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                             pkt.skill_id,
                             None,
                             None,
-                            Utc::now().timestamp_millis(),
+                            clock::now().timestamp_millis(),
                         );
                     }
                 }
@@ -486,6 +867,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                 {
                     let mut entity = entity_tracker.get_source_entity(pkt.source_id);
                     entity_tracker.guess_is_player(&mut entity, pkt.skill_id);
+                    clients::on_skill_start(pkt.source_id, entity.character_id);
                     let tripod_index =
                         pkt.skill_option_data
                             .tripod_index
@@ -502,7 +884,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                                 second: tripod_level.second,
                                 third: tripod_level.third,
                             });
-                    let timestamp = Utc::now().timestamp_millis();
+                    let timestamp = clock::now().timestamp_millis();
                     let (skill_id, summon_source) = state.on_skill_start(
                         &entity,
                         pkt.skill_id,
@@ -516,6 +898,20 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                             .skill_tracker
                             .new_cast(entity.id, skill_id, summon_source, timestamp);
                     }
+                    if entity.entity_type == EntityType::BOSS {
+                        state.on_boss_cast(pkt.skill_id, timestamp);
+                    }
+                    // sidereals are cast by the esther, counted for the player who summoned it
+                    if let Some(sidereal) = SIDEREAL_SKILLS.get(&pkt.skill_id) {
+                        let owner = if entity.entity_type == EntityType::ESTHER {
+                            entity_tracker.entities.get(&entity.owner_id).cloned()
+                        } else {
+                            Some(entity.clone())
+                        };
+                        if let Some(owner) = owner.filter(|o| o.entity_type == EntityType::PLAYER) {
+                            state.on_item_used(&owner, sidereal, "sidereal", timestamp);
+                        }
+                    }
                 }
             }
             // Pkt::SkillStageNotify => {
@@ -533,7 +929,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     PKTSkillDamageAbnormalMoveNotify::new,
                     "PKTSkillDamageAbnormalMoveNotify",
                 ) {
-                    let now = Utc::now().timestamp_millis();
+                    let now = clock::now().timestamp_millis();
                     let owner = entity_tracker.get_source_entity(pkt.source_id);
                     let local_character_id = id_tracker
                         .borrow()
@@ -586,7 +982,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                 if let Some(pkt) =
                     parse_pkt(&data, PKTSkillDamageNotify::new, "PktSkillDamageNotify")
                 {
-                    let now = Utc::now().timestamp_millis();
+                    let now = clock::now().timestamp_millis();
                     let owner = entity_tracker.get_source_entity(pkt.source_id);
                     let local_character_id = id_tracker
                         .borrow()
@@ -689,8 +1085,20 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     "PKTPartyStatusEffectRemoveNotify",
                 ) {
                     let character_id = pkt.character_id;
-                    let (is_shield, shields_broken, _left_workshop) =
+                    let (is_shield, shields_broken, _left_workshop, debuffs_cleansed) =
                         entity_tracker.party_status_effect_remove(pkt);
+                    if !debuffs_cleansed.is_empty() {
+                        let entity_id = id_tracker.borrow().get_entity_id(character_id);
+                        if let Some(target) =
+                            entity_id.and_then(|id| entity_tracker.get_entity_ref(id))
+                        {
+                            state.on_debuffs_cleansed(
+                                target,
+                                debuffs_cleansed,
+                                clock::now().timestamp_millis(),
+                            );
+                        }
+                    }
                     if is_shield {
                         for status_effect in shields_broken {
                             let change = status_effect.value;
@@ -730,7 +1138,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     let status_effect = entity_tracker.build_and_register_status_effect(
                         &pkt.status_effect_data,
                         pkt.object_id,
-                        Utc::now(),
+                        clock::now(),
                         Some(&state.encounter.entities),
                     );
                     if status_effect.status_effect_type == StatusEffectType::Shield {
@@ -753,6 +1161,10 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                             status_effect.value,
                         );
                     }
+                    if is_paralyzation_effect(status_effect.status_effect_id) {
+                        let target = entity_tracker.get_source_entity(pkt.object_id);
+                        state.on_boss_paralyzed(&target, clock::now().timestamp_millis());
+                    }
                 }
             }
             // Pkt::StatusEffectDurationNotify => {
@@ -775,13 +1187,22 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     PKTStatusEffectRemoveNotify::new,
                     "PKTStatusEffectRemoveNotify",
                 ) {
-                    let (is_shield, shields_broken, _left_workshop) =
+                    let (is_shield, shields_broken, _left_workshop, debuffs_cleansed) =
                         status_tracker.borrow_mut().remove_status_effects(
                             pkt.object_id,
                             pkt.status_effect_instance_ids,
                             pkt.reason,
                             StatusEffectTargetType::Local,
                         );
+                    if !debuffs_cleansed.is_empty() {
+                        if let Some(target) = entity_tracker.get_entity_ref(pkt.object_id) {
+                            state.on_debuffs_cleansed(
+                                target,
+                                debuffs_cleansed,
+                                clock::now().timestamp_millis(),
+                            );
+                        }
+                    }
                     if is_shield {
                         if shields_broken.is_empty() {
                             let target = entity_tracker.get_source_entity(pkt.object_id);
@@ -808,6 +1229,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     || state.encounter.current_boss_name == "Saydon"
                 {
                     state.on_phase_transition(3, &mut stats_api);
+                    emit_scheduler.request_immediate();
                     debug_print(format_args!(
                         "phase: 3 - resetting encounter - TriggerBossBattleStatus"
                     ));
@@ -827,6 +1249,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                             };
                             state.raid_clear = true;
                             state.on_phase_transition(2, &mut stats_api);
+                            emit_scheduler.request_immediate();
                             raid_end_cd = Instant::now();
                             debug_print(format_args!("phase: 2 - clear - TriggerStartNotify"));
                         }
@@ -839,6 +1262,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                             };
                             state.raid_clear = false;
                             state.on_phase_transition(4, &mut stats_api);
+                            emit_scheduler.request_immediate();
                             raid_end_cd = Instant::now();
                             debug_print(format_args!("phase: 4 - wipe - TriggerStartNotify"));
                         }
@@ -856,6 +1280,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     "PKTZoneMemberLoadStatusNotify",
                 ) {
                     stats_api.valid_zone = VALID_ZONES.contains(&pkt.zone_id);
+                    state.on_zone_load(pkt.zone_id, &stats_api);
 
                     if state.raid_difficulty_id >= pkt.zone_id && !state.raid_difficulty.is_empty()
                     {
@@ -987,14 +1412,53 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
             _ => {}
         }
 
-        if last_update.elapsed() >= duration || state.resetting || state.boss_dead_update {
+        if let Some(metrics) = metrics::check_outdated() {
+            warn!(
+                "{} of {} packets failed to decode, the game was probably updated",
+                metrics.decode_failures, metrics.packets_received
+            );
+            window.emit("parser-outdated", metrics).ok();
+        }
+
+        if state.encounter.fight_start != last_fight_start {
+            last_fight_start = state.encounter.fight_start;
+            emit_scheduler.request_immediate();
+        }
+        auto_clickthrough.on_combat(
+            state.encounter.fight_start,
+            state.encounter.last_combat_packet,
+        );
+        idle_fade.on_combat(
+            state.encounter.fight_start,
+            state.encounter.last_combat_packet,
+        );
+        capture_status::on_combat(
+            state.encounter.fight_start,
+            state.encounter.last_combat_packet,
+        );
+
+        if emit_scheduler.should_flush() || state.resetting || state.boss_dead_update {
             let boss_dead = state.boss_dead_update;
             if state.boss_dead_update {
                 state.boss_dead_update = false;
             }
+            state.update_uptime();
             let mut clone = state.encounter.clone();
+            if let Some(local) = clone.entities.get_mut(&clone.local_player) {
+                local.skill_stats.cooldown_efficiency = state.local_cooldown_efficiency();
+            }
             let damage_valid = state.damage_is_valid;
             let window = window.clone();
+            emit_scheduler.flush();
+            let seq = emit_scheduler.seq_handle();
+            let delta_mode = live_delta_updates.load(Ordering::Relaxed);
+            let damage_taken_sources = live_damage_taken.load(Ordering::Relaxed);
+            let session = live_session_stats
+                .load(Ordering::Relaxed)
+                .then(session_stats::get_stats);
+            if state.resetting {
+                request_full_snapshot();
+            }
 
             let party_info: Option<HashMap<i32, Vec<String>>> =
                 if last_party_update.elapsed() >= party_duration && !party_freeze {
@@ -1044,27 +1508,73 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                         || e.entity_type == EntityType::BOSS)
                         && e.damage_stats.damage_dealt > 0
                 });
-
-                if !clone.entities.is_empty() {
-                    window
-                        .emit("encounter-update", Some(clone))
-                        .expect("failed to emit encounter-update");
-
-                    if !damage_valid {
-                        window
-                            .emit("invalid-damage", "")
-                            .expect("failed to emit invalid-damage");
+                clone.local_rank = compute_local_rank(&clone);
+                // per skill synergy breakdown is only needed in saved logs
+                for entity in clone.entities.values_mut() {
+                    entity.damage_stats.dps_alive =
+                        calculate_dps_alive(entity, clone.fight_start, clone.last_combat_packet);
+                    for skill in entity.skills.values_mut() {
+                        skill.synergy_buffed_by.clear();
                     }
-
-                    if party_info.is_some() {
-                        window
-                            .emit("party-update", party_info)
-                            .expect("failed to emit party-update");
+                    if !damage_taken_sources {
+                        entity.damage_stats.damage_taken_by.clear();
                     }
                 }
+
+                let mut party_info = party_info;
+                name_mask::mask_encounter(&mut clone, party_info.as_mut());
+
+                if delta_mode {
+                    emit_live_delta(&window, clone, party_info, !damage_valid, session);
+                } else if !clone.entities.is_empty() {
+                    let update = LiveUpdate {
+                        seq: seq.fetch_add(1, Ordering::Relaxed) + 1,
+                        encounter: clone,
+                        party: party_info,
+                        invalid_damage: !damage_valid,
+                        session,
+                    };
+                    pipe_server::publish_live_update(&update);
+                    window
+                        .emit("live-update", update)
+                        .expect("failed to emit live-update");
+                }
             });
+        }
 
-            last_update = Instant::now();
+        if last_series_update.elapsed() >= series_duration {
+            last_series_update = Instant::now();
+            if state.encounter.fight_start > 0 {
+                let update = DpsSeriesUpdate {
+                    fight_start: state.encounter.fight_start,
+                    samples: state.dps_series.samples(),
+                };
+                if let Err(e) = window.emit("dps-series", update) {
+                    warn!("failed to emit dps-series: {}", e);
+                }
+            }
+        }
+
+        // only sent when it changed, none once the encounter is reset
+        if last_boss_hp_update.elapsed() >= boss_hp_duration {
+            last_boss_hp_update = Instant::now();
+            let boss_hp = state
+                .encounter
+                .entities
+                .get(&state.encounter.current_boss_name)
+                .map(|boss| BossHpUpdate {
+                    name: boss.name.clone(),
+                    current_hp: boss.current_hp.max(0),
+                    max_hp: boss.max_hp,
+                    current_shield: boss.current_shield,
+                    is_dead: boss.is_dead || boss.current_hp <= 0,
+                });
+            if boss_hp != last_boss_hp {
+                if let Err(e) = window.emit_to(BOSS_HP_WINDOW_LABEL, "boss-hp", &boss_hp) {
+                    warn!("failed to emit boss-hp: {}", e);
+                }
+                last_boss_hp = boss_hp;
+            }
         }
 
         if state.resetting {
@@ -1109,6 +1619,15 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
         // }
     }
 
+    // a replay ends without the zone change or reset that would save it live
+    if source.is_replay() && !state.saved {
+        state.party_info = update_party(&party_tracker, &entity_tracker);
+        state.save_to_db(&stats_api, false);
+        while encounter_state::saves_in_flight() > 0 {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
     Ok(())
 }
 
@@ -1167,12 +1686,18 @@ fn write_local_players(local_info: &LocalInfo, path: &PathBuf) -> Result<()> {
 fn get_and_set_region(path: &str, state: &mut EncounterState) {
     match std::fs::read_to_string(path) {
         Ok(region) => {
-            state.region = Some(region);
+            let region = region.trim();
+            state.region = if region.is_empty() {
+                None
+            } else {
+                Some(region.to_string())
+            };
         }
         Err(_) => {
             // warn!("failed to read region file. {}", e);
         }
     }
+    capture_status::set_region(state.region.clone());
 }
 
 fn parse_pkt<T, F>(data: &[u8], new_fn: F, pkt_name: &str) -> Option<T>
@@ -1183,6 +1708,7 @@ where
         Ok(packet) => Some(packet),
         Err(e) => {
             warn!("Error parsing {}: {}", pkt_name, e);
+            metrics::on_decode_failure();
             None
         }
     }
@@ -1,7 +1,12 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
+use crate::app::payload_guard::DEFAULT_PAYLOAD_LIMIT_MB;
+use crate::parser::auto_clickthrough::DEFAULT_RELEASE_DELAY_MS;
+use crate::parser::emit_scheduler::DEFAULT_EMIT_INTERVAL_MS;
 use crate::parser::entity_tracker::Entity;
+use crate::parser::idle_fade::{DEFAULT_FADE_DELAY_S, DEFAULT_FADE_OPACITY};
+use crate::parser::prepull::DEFAULT_MAX_TRIM_PERCENT;
 use bitflags::bitflags;
 use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
@@ -11,6 +16,14 @@ use serde_with::serde_as;
 use serde_with::DefaultOnError;
 
 pub const DB_VERSION: i32 = 5;
+// format of encounter_preview.players, 1 = class:name, 2 = class:name:dps
+pub const PREVIEW_PLAYERS_VERSION: i32 = 2;
+// pseudo skill collecting the damage of every damaging battle item
+pub const BATTLE_ITEM_SKILL_ID: u32 = u32::MAX - 1;
+pub const BATTLE_ITEM_SKILL_NAME: &str = "Battle Items";
+pub const UNKNOWN_CLASS_COLOR: &str = "#808080";
+// casts kept per player skill in low performance mode
+pub const LOW_PERFORMANCE_CAST_LOG_LIMIT: usize = 200;
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Copy, Clone)]
 #[allow(non_camel_case_types)]
@@ -61,8 +74,8 @@ impl FromStr for EntityType {
     }
 }
 
-#[derive(Debug, Serialize, Clone, Default)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Encounter {
     pub last_combat_packet: i64,
     pub fight_start: i64,
@@ -77,10 +90,34 @@ pub struct Encounter {
     pub cleared: bool,
     pub boss_only_damage: bool,
     pub sync: Option<String>,
+    // only set on live updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_rank: Option<LocalRank>,
+    // set when skill details were left out to keep the ipc payload small
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub truncated_entities: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Clone, Default)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
+pub struct LocalRank {
+    pub rank: i32,
+    pub party_size: i32,
+    // damage gap to the player ranked above and below, percent is of the local player's damage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_ahead: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_ahead_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_behind: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_behind_percent: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct EncounterDamageStats {
     pub total_damage_dealt: i64,
     pub top_damage_dealt: i64,
@@ -93,6 +130,9 @@ pub struct EncounterDamageStats {
     pub total_shielding: u64,
     pub total_effective_shielding: u64,
     pub applied_shield_buffs: HashMap<u32, StatusEffect>,
+    // party damage absorbed by shields, and damage taken including absorbed damage
+    pub total_damage_absorbed: u64,
+    pub total_damage_taken_unmitigated: i64,
     #[serde(skip)]
     pub unknown_buffs: HashSet<u32>,
     #[serde(skip)]
@@ -106,8 +146,8 @@ pub struct EncounterDamageStats {
     pub stagger_stats: Option<StaggerStats>,
 }
 
-#[derive(Debug, Serialize, Clone, Default)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct MostDamageTakenEntity {
     pub name: String,
     pub damage_taken: i64,
@@ -136,6 +176,9 @@ pub struct EncounterEntity {
     pub ark_passive_active: Option<bool>,
     pub ark_passive_data: Option<ArkPassiveData>,
     pub spec: Option<String>,
+    // npc id -> damage dealt to it, for the boss/adds split at save time
+    #[serde(skip)]
+    pub damage_by_npc: HashMap<u32, i64>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -153,6 +196,8 @@ pub struct Skill {
     pub buffed_by_identity: i64,
     pub buffed_by_hat: i64,
     pub debuffed_by_support: i64,
+    // damage dealt under each support synergy buff/debuff, only kept in saved logs
+    pub synergy_buffed_by: HashMap<u32, i64>,
     pub casts: i64,
     pub hits: i64,
     pub crits: i64,
@@ -178,10 +223,29 @@ pub struct Skill {
     pub rdps_damage_received_support: i64,
     pub rdps_damage_given: i64,
     pub skill_cast_log: Vec<SkillCast>,
+    pub max_hit: Option<MaxHit>,
+    // average damage of the hits that didn't crit and of the crits
+    pub avg_hit: i64,
+    pub avg_crit_hit: i64,
     #[serde(skip)]
     pub last_timestamp: i64,
 }
 
+// the biggest single hit of a skill and what it was landed under
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MaxHit {
+    pub damage: i64,
+    // ms since fight start
+    pub timestamp: i64,
+    pub crit: bool,
+    // support synergy buffs on the player and debuffs on the target
+    pub synergies: Vec<u32>,
+    // the local player topped their best hit with the skill from earlier
+    // saved fights
+    pub personal_record: bool,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase", default)]
 pub struct TripodData {
@@ -242,6 +306,8 @@ pub struct ArkPassiveNode {
 pub struct DamageStats {
     pub damage_dealt: i64,
     pub hyper_awakening_damage: i64,
+    pub damage_to_boss: i64,
+    pub damage_to_adds: i64,
     pub damage_taken: i64,
     pub buffed_by: HashMap<u32, i64>,
     pub debuffed_by: HashMap<u32, i64>,
@@ -260,9 +326,21 @@ pub struct DamageStats {
     pub shields_received_by: HashMap<u32, u64>,
     pub damage_absorbed_by: HashMap<u32, u64>,
     pub damage_absorbed_on_others_by: HashMap<u32, u64>,
+    // damage that would have been taken without shields
+    pub damage_taken_unmitigated: i64,
+    pub hits_taken: i64,
+    // "source: skill" -> damage and hits taken from it
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub damage_taken_by: HashMap<String, DamageTakenSource>,
     pub deaths: i64,
     pub death_time: i64,
+    // total ms spent dead, excluding a death that is still ongoing
+    pub dead_for: i64,
     pub dps: i64,
+    pub dps_alive: i64,
+    // ms spent dealing damage or casting, gaps under 2s are merged
+    pub active_time: i64,
+    pub uptime_percent: f64,
     #[serde_as(deserialize_as = "DefaultOnError")]
     pub dps_average: Vec<i64>,
     #[serde_as(deserialize_as = "DefaultOnError")]
@@ -270,6 +348,29 @@ pub struct DamageStats {
     pub rdps_damage_received: i64,
     pub rdps_damage_received_support: i64,
     pub rdps_damage_given: i64,
+    // debuff id -> number of times this player cleansed it
+    pub cleansed_debuffs: HashMap<u32, i64>,
+    pub awakening: AwakeningStats,
+    // damage dealt while the boss was at 100-90%, 90-80% ... 10-0% hp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub damage_by_hp_segment: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DamageTakenSource {
+    pub damage: i64,
+    pub hits: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AwakeningStats {
+    // cast times relative to fight start
+    pub awakening_casts: Vec<i32>,
+    pub hyper_awakening_casts: Vec<i32>,
+    pub awakening_damage: i64,
+    pub hyper_awakening_damage: i64,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -281,8 +382,18 @@ pub struct SkillStats {
     pub back_attacks: i64,
     pub front_attacks: i64,
     pub counters: i64,
+    #[serde(default)]
+    pub cleanses: i64,
+    #[serde(default)]
+    pub self_cleanses: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub identity_stats: Option<String>,
+    // battle item or sidereal name -> ms since fight start of each use
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub item_uses: HashMap<String, Vec<i64>>,
+    // local player only, skill id -> percent of possible casts, see cooldown_efficiency
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub cooldown_efficiency: HashMap<u32, Option<f64>>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -321,21 +432,103 @@ pub struct DamageData {
 
 #[derive(Debug, Serialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
-pub struct Identity {
-    pub gauge1: u32,
-    pub gauge2: u32,
-    pub gauge3: u32,
+pub struct Stagger {
+    pub current: u32,
+    pub max: u32,
 }
 
 #[derive(Debug, Serialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
-pub struct Stagger {
-    pub current: u32,
-    pub max: u32,
+pub struct LiveUpdate {
+    pub seq: u64,
+    pub encounter: Encounter,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub party: Option<HashMap<i32, Vec<String>>>,
+    pub invalid_damage: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionStats>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveDelta {
+    pub seq: u64,
+    pub full: bool,
+    // set only on full snapshots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Encounter>,
+    // changed encounter fields, excluding entities
+    pub fields: serde_json::Map<String, Value>,
+    // changed fields per entity, skills only with the skill entries that
+    // changed. new entities are sent whole
+    pub entities: HashMap<String, serde_json::Map<String, Value>>,
+    pub removed: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub party: Option<HashMap<i32, Vec<String>>>,
+    pub invalid_damage: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionStats>,
+}
+
+// saved pulls since the app started, times are ms
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    pub started: i64,
+    pub pulls: i64,
+    pub total_damage: i64,
+    pub combat_time: i64,
+    pub players: Vec<SessionPlayerStats>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPlayerStats {
+    pub name: String,
+    pub class_id: u32,
+    pub pulls: i64,
+    pub damage: i64,
+    pub combat_time: i64,
+    pub average_damage: i64,
+    pub dps: i64,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DpsSample {
+    // seconds since fight start
+    pub time: i64,
+    pub party_damage: i64,
+    pub local_damage: i64,
+    pub boss_hp_percent: f32,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DpsSeriesUpdate {
+    pub fight_start: i64,
+    pub samples: Vec<DpsSample>,
 }
 
 pub type IdentityLog = Vec<(i64, (u32, u32, u32))>;
 
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalIdentity {
+    pub class_id: u32,
+    // ms since fight start, (gauge1, gauge2, gauge3), at most one sample per second
+    pub log: Vec<(i32, (u32, u32, u32))>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityUpdate {
+    pub time: i64,
+    pub gauge1: u32,
+    pub gauge2: u32,
+    pub gauge3: u32,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IdentityArcanist {
@@ -394,6 +587,139 @@ pub struct EncounterMisc {
     pub ntp_fight_start: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub manual_save: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boss_casts: Option<Vec<BossCast>>,
+    // npc ids counted as boss damage for the boss/adds split
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bosses: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_identity: Option<LocalIdentity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stagger_checks: Option<Vec<StaggerCheck>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_rank: Option<LocalRank>,
+    // (award, player, value) for the best player in each category
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<Vec<(String, String, f64)>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hp_splits: Option<Vec<HpSplit>>,
+    // app session that recorded the encounter, see get_sessions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<i64>,
+    // boss name as recorded when it differs from the english one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub localized_boss_name: Option<String>,
+    // set when a chaos dungeon or cube run was recorded as one encounter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dungeon: Option<DungeonRun>,
+    // stretches without packets, cut out of the duration or where the
+    // encounter was split
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_gaps: Option<Vec<ClockGap>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_killer: Option<TopKiller>,
+    // set when several game clients were running, see parser::clients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_index: Option<usize>,
+    // copied from an unfinished fight by dump_live_encounter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub live_dump: Option<bool>,
+    // player damage before the boss was pulled, left out of the totals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepull_damage: Option<i64>,
+    // fields written by newer versions, kept so re-saving doesn't drop them
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+// a boss pattern that was a stagger check or was countered.
+// the required stagger isn't in the packets, so only contributions are kept
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StaggerCheck {
+    // ms since fight start
+    pub start: i64,
+    pub end: i64,
+    pub skill_id: u32,
+    pub skill_name: String,
+    pub stagger_check: bool,
+    // player name -> stagger dealt during the pattern
+    pub stagger: HashMap<String, i64>,
+    // player name -> counters landed during the pattern
+    pub counters: HashMap<String, i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BossCast {
+    pub offset_ms: i64,
+    pub skill_id: u32,
+    pub skill_name: String,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HpSegmentRow {
+    pub name: String,
+    pub class_id: u32,
+    pub damage: Vec<i64>,
+}
+
+// what copy_encounter_to_clipboard packs into a LOAM1: string
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SharedEncounter {
+    pub meter_version: String,
+    pub boss: String,
+    pub difficulty: Option<String>,
+    pub cleared: bool,
+    pub fight_start: i64,
+    pub duration: i64,
+    pub local_player: String,
+    pub total_damage: i64,
+    pub players: Vec<SharedPlayer>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SharedPlayer {
+    pub name: String,
+    pub class_id: u32,
+    pub gear_score: f32,
+    pub max_hp: i64,
+    pub damage: i64,
+    pub dps: i64,
+    pub damage_taken: i64,
+    pub deaths: i64,
+    pub skills: Vec<SharedSkill>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SharedSkill {
+    pub id: u32,
+    pub name: String,
+    pub damage: i64,
+    pub max_damage: i64,
+    pub casts: i64,
+    pub hits: i64,
+    pub crits: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PastedEncounter {
+    pub encounter: SharedEncounter,
+    // set when the paste was imported, or matched an encounter already saved
+    pub id: Option<i64>,
+}
+
+// first second the current boss was at or below percent hp
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HpSplit {
+    pub percent: i32,
+    pub time: i32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -449,7 +775,7 @@ pub struct SkillData {
 pub struct SkillEffectData {
     pub id: i32,
     pub comment: String,
-    #[serde(skip)]
+    #[serde(default)]
     pub stagger: i32,
     pub source_skills: Option<Vec<u32>>,
     pub directional_mask: Option<i32>,
@@ -626,17 +952,30 @@ pub struct EngravingData {
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct EncounterPreview {
-    pub id: i32,
+    pub id: i64,
     pub fight_start: i64,
     pub boss_name: String,
     pub duration: i64,
     pub classes: Vec<i32>,
     pub names: Vec<String>,
+    pub players_detail: Vec<PreviewPlayer>,
     pub difficulty: Option<String>,
     pub local_player: String,
     pub my_dps: i64,
+    pub my_dps_alive: i64,
+    pub local_rank: Option<i32>,
+    pub party_size: Option<i32>,
     pub favorite: bool,
     pub cleared: bool,
+    pub dungeon: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewPlayer {
+    pub name: String,
+    pub class_id: i32,
+    pub dps: i64,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -644,6 +983,210 @@ pub struct EncounterPreview {
 pub struct EncountersOverview {
     pub encounters: Vec<EncounterPreview>,
     pub total_encounters: i32,
+    // the page and size actually used after clamping
+    pub page: i32,
+    pub page_size: i32,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInstalled {
+    pub old_version: String,
+    pub new_version: String,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDownloadFailed {
+    pub version: String,
+    pub download_url: String,
+    pub error: String,
+}
+
+// bump when the exported encounter changes shape and add a converter
+// from the previous version in export::native
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedEncounter {
+    pub schema_version: u32,
+    pub meter_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    pub encounter: Encounter,
+    // counters at the time of a live dump, informational only
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub parser_metrics: Option<ParserMetrics>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub total: usize,
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DungeonRun {
+    pub name: String,
+    pub stages: Vec<DungeonStage>,
+}
+
+// one floor or stage of a dungeon run. start and end are timestamps of the
+// first and last combat in it, damage is what players dealt there
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DungeonStage {
+    pub zone_id: u32,
+    pub start: i64,
+    pub end: i64,
+    pub damage: i64,
+}
+
+// causes counts the players that died shortly after being hit by each
+// "source: skill", skill is the most common one once it killed a few
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TopKiller {
+    pub skill: Option<String>,
+    pub deaths: i64,
+    pub causes: HashMap<String, i64>,
+}
+
+// a killer skill summed over the wipes get_death_causes looked at
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeathCause {
+    pub skill: String,
+    pub deaths: i64,
+    pub encounters: i64,
+}
+
+// start and end are wall clock times of the packets around the gap
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockGap {
+    pub start: i64,
+    pub end: i64,
+    pub split: bool,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildProgress {
+    pub total: usize,
+    pub rebuilt: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupEstimate {
+    pub encounters: usize,
+    pub scanned: usize,
+    pub reclaimable_bytes: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageCategory {
+    pub category: String,
+    pub files: usize,
+    pub bytes: u64,
+    // what cleanup_storage may remove, the live database and files still in
+    // use are counted in bytes only
+    pub deletable_bytes: u64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageFallback {
+    // the data dir that wasn't writable
+    pub preferred: String,
+    pub path: String,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageFreed {
+    pub category: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IgnoredEntities {
+    pub names: Vec<String>,
+    pub npc_ids: Vec<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureStatus {
+    pub capturing: bool,
+    pub paused: bool,
+    pub region: Option<String>,
+    // adapter picked in the settings, None when it's picked automatically or
+    // the saved one wasn't found
+    pub interface: Option<String>,
+    pub last_packet: i64,
+    pub error: Option<ParserError>,
+    // game clients seen since the parser started, see parser::clients
+    pub clients: Vec<CaptureClient>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureClient {
+    pub index: usize,
+    pub name: String,
+    pub character_id: u64,
+    pub last_seen: i64,
+    pub active: bool,
+    // the client the meter follows, manual when picked with
+    // set_tracked_connection
+    pub tracked: bool,
+    pub manual: bool,
+}
+
+// counted since the last encounter reset
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ParserMetrics {
+    pub packets_received: u64,
+    pub decode_failures: u64,
+    pub unknown_opcodes_total: u64,
+    pub unknown_opcodes: Vec<UnknownOpcode>,
+    pub entities_created: u64,
+    pub entities_merged: u64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownOpcode {
+    pub opcode: u16,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterLoadError {
+    pub message: String,
+    // not_found, corrupt, database or masked
+    pub category: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ParserError {
+    pub message: String,
+    // driver, permission, interface or unknown
+    pub category: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -654,10 +1197,14 @@ pub struct SearchFilter {
     pub max_duration: i32,
     pub cleared: bool,
     pub favorite: bool,
-    pub difficulty: String,
+    // "Unknown" matches encounters without a difficulty
+    #[serde(alias = "difficulty", deserialize_with = "string_or_vec")]
+    pub difficulties: Vec<String>,
     pub boss_only_damage: bool,
     pub sort: String,
     pub order: u8,
+    pub region: Option<String>,
+    pub top_rank_only: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -669,6 +1216,221 @@ pub struct Settings {
     pub logs: LogTabs,
     pub buffs: BuffSettings,
     pub sync: SyncSettings,
+    pub ui: UiSettings,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsState {
+    pub settings: Option<Settings>,
+    pub load_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterSaved {
+    pub id: i64,
+    pub boss_name: String,
+    pub cleared: bool,
+    pub duration: i64,
+    pub my_dps: i64,
+    pub manual: bool,
+}
+
+// one run of the app. current is this run, crashed is an earlier run that
+// never reached a clean shutdown
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSession {
+    pub id: i64,
+    pub start_time: i64,
+    pub end_time: Option<i64>,
+    pub version: String,
+    pub capture_mode: String,
+    pub interface_ip: String,
+    pub encounters_saved: i64,
+    pub current: bool,
+    pub crashed: bool,
+}
+
+// strategy memo shown whenever a fight against this boss starts
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BossNote {
+    pub boss: String,
+    pub note: String,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RaidStart {
+    pub timestamp: i64,
+    pub boss_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boss_note: Option<String>,
+}
+
+// config is only restored after the user confirms, the database is swapped
+// in on the next start
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    pub restart_required: bool,
+    pub config_available: bool,
+    pub backup_version: Option<u32>,
+    pub app_version: Option<String>,
+}
+
+// one week of a character's encounters, combat_time is in ms
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityWeek {
+    // monday of the week, yyyy-mm-dd
+    pub week_start: String,
+    pub raids: Vec<BossActivity>,
+    pub encounters: i64,
+    pub clears: i64,
+    pub wipes: i64,
+    pub combat_time: i64,
+    pub average_dps: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BossActivity {
+    pub boss: String,
+    pub difficulty: String,
+    pub encounters: i64,
+    pub clears: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassMetadata {
+    pub id: u32,
+    pub name: String,
+    pub color: String,
+    // image under images/
+    pub icon: String,
+}
+
+// work the parser can skip on slow machines, read on every hit so it's kept
+// to plain copies of bools
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerformanceFlags {
+    pub low_performance_mode: bool,
+    pub rolling_dps: bool,
+    pub skill_buff_attribution: bool,
+    pub boss_hp_log: bool,
+    // 0 keeps every cast
+    pub cast_log_limit: usize,
+}
+
+impl PerformanceFlags {
+    pub fn new(low_performance_mode: bool) -> Self {
+        Self {
+            low_performance_mode,
+            rolling_dps: !low_performance_mode,
+            skill_buff_attribution: !low_performance_mode,
+            boss_hp_log: !low_performance_mode,
+            cast_log_limit: if low_performance_mode {
+                LOW_PERFORMANCE_CAST_LOG_LIMIT
+            } else {
+                0
+            },
+        }
+    }
+}
+
+impl Default for PerformanceFlags {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoFavoriteSettings {
+    pub enabled: bool,
+    pub min_duration_s: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterPb {
+    pub id: i64,
+    pub boss_name: String,
+    pub difficulty: String,
+    pub old_dps: i64,
+    pub new_dps: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemUsed {
+    pub player: String,
+    pub item: String,
+    // "item" or "sidereal"
+    pub kind: String,
+    // uses of this item by the player so far this encounter
+    pub count: usize,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSuggestion {
+    pub value: String,
+    // "boss" or "player"
+    pub kind: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BossHpUpdate {
+    pub name: String,
+    pub current_hp: i64,
+    pub max_hp: i64,
+    pub current_shield: u64,
+    pub is_dead: bool,
+}
+
+// best time to reach a split and the fastest the segment ending there was
+// ever done, the segment bests add up to the sum of best
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BestSplit {
+    pub percent: i32,
+    pub time: i32,
+    pub encounter_id: i64,
+    pub best_segment: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentEncounter {
+    pub id: i64,
+    pub boss_name: String,
+    pub duration: i64,
+    pub cleared: bool,
+    pub my_dps: i64,
+    pub fight_start: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FavoriteChanged {
+    pub id: i64,
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbVersionTooNew {
+    pub db_version: i32,
+    pub app_version: i32,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -680,6 +1442,8 @@ pub struct GeneralSettings {
     pub show_names: bool,
     pub show_gear_score: bool,
     pub hide_names: bool,
+    // other players get class placeholders in what the meter and logs window show
+    pub mask_names: bool,
     #[serde(default = "default_true")]
     pub show_esther: bool,
     #[serde(default = "default_true")]
@@ -701,9 +1465,13 @@ pub struct GeneralSettings {
     pub auto_iface: bool,
     pub if_desc: String,
     pub ip: String,
+    // npf device name of the capture adapter, preferred over if_desc and ip
+    pub device_id: Option<String>,
     pub port: u16,
     pub blur: bool,
     pub blur_win11: bool,
+    // "blur", "acrylic", "mica" or "none", empty falls back to the blur flags
+    pub backdrop: String,
     pub transparent: bool,
     #[serde(default = "default_scale")]
     pub scale: String,
@@ -717,11 +1485,65 @@ pub struct GeneralSettings {
     pub keep_favorites: bool,
     pub hide_meter_on_start: bool,
     pub hide_logs_on_start: bool,
+    pub close_to_tray: bool,
     pub constant_local_player_color: bool,
     #[serde(default = "default_true")]
     pub boss_only_damage_default_on: bool,
     pub start_on_boot: bool,
     pub logs_per_page: i32,
+    #[serde(default = "default_emit_interval")]
+    pub emit_interval_ms: u64,
+    pub live_delta_updates: bool,
+    // send the per source damage taken breakdown with live updates
+    pub live_damage_taken: bool,
+    // send the session totals with live updates
+    pub live_session_stats: bool,
+    pub auto_clickthrough_in_combat: bool,
+    #[serde(default = "default_auto_clickthrough_delay")]
+    pub auto_clickthrough_delay_ms: u64,
+    pub capture_dump: bool,
+    pub pipe_server: bool,
+    pub fade_out_of_combat: bool,
+    #[serde(default = "default_fade_delay")]
+    pub fade_delay_s: u64,
+    #[serde(default = "default_fade_opacity")]
+    pub fade_opacity: f64,
+    // small always on top window with only the boss hp bar
+    pub boss_hp_window: bool,
+    #[serde(default = "default_true")]
+    pub boss_hp_clickthrough: bool,
+    #[serde(default = "default_boss_hp_hide_delay")]
+    pub boss_hp_hide_delay_s: u64,
+    // favorite cleared runs that beat the local player's best dps
+    pub auto_favorite_pbs: bool,
+    // load_encounter responses over this many mb have skill details left out
+    #[serde(default = "default_ipc_payload_limit")]
+    pub ipc_payload_limit_mb: u64,
+    // "auto" downloads updates, "notify" only shows them, "off" never checks.
+    // empty is treated as auto
+    pub update_mode: String,
+    // "aggregate" records a chaos dungeon or cube run as one encounter,
+    // "ignore" doesn't record them. empty records them like any other zone
+    pub dungeon_mode: String,
+    // "split" saves the fight at a long stretch without packets and starts a
+    // new one, anything else cuts the stretch out of the fight
+    pub clock_gap_mode: String,
+    // followed when several game clients run at once, empty follows the one
+    // that loaded a character last
+    pub main_character: String,
+    // left out of the meter and new logs, see parser::ignore_list
+    pub ignored_names: Vec<String>,
+    pub ignored_npc_ids: Vec<u32>,
+    // starts a fight at the first hit on the boss, unless that cuts off more
+    // than trim_prepull_max_percent of it
+    pub trim_prepull: bool,
+    #[serde(default = "default_trim_prepull_max_percent")]
+    pub trim_prepull_max_percent: f64,
+    // deletes backups, rotated logs and dumps older than auto_cleanup_days
+    // once a day
+    pub auto_cleanup_storage: bool,
+    #[serde(default = "default_auto_cleanup_days")]
+    pub auto_cleanup_days: u32,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -734,6 +1556,9 @@ pub struct Shortcuts {
     pub pause_session: Shortcut,
     pub manual_save: Shortcut,
     pub disable_clickthrough: Shortcut,
+    #[serde(default = "default_toggle_clickthrough")]
+    pub toggle_clickthrough: Shortcut,
+    pub peek: Shortcut,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -862,6 +1687,41 @@ pub struct BuffSettings {
     pub default: bool,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UiSettings {
+    // class id -> color overriding CLASS_METADATA
+    pub class_colors: HashMap<u32, String>,
+    pub number_format: NumberFormat,
+}
+
+// how numbers in backend generated text are written, abbreviated is 1.2m,
+// otherwise 1,234,567 with separator between thousands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NumberFormat {
+    pub abbreviate: bool,
+    pub separator: String,
+    pub decimals: u8,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            abbreviate: true,
+            separator: ",".to_string(),
+            decimals: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NumberFormatExample {
+    pub value: i64,
+    pub formatted: String,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 pub struct SyncSettings {
@@ -1048,6 +1908,78 @@ lazy_static! {
         let json_str = include_str!("../../meter-data/Esther.json");
         serde_json::from_str(json_str).unwrap()
     };
+    // the one place class names and colors are defined on the backend,
+    // base classes that can't be played have no color of their own
+    pub static ref CLASS_METADATA: HashMap<u32, ClassMetadata> = {
+        let classes = [
+            (101, "Warrior (Male)", UNKNOWN_CLASS_COLOR),
+            (102, "Berserker", "#ee2e48"),
+            (103, "Destroyer", "#7b9aa2"),
+            (104, "Gunlancer", "#E1907E"),
+            (105, "Paladin", "#ff9900"),
+            (111, "Female Warrior", UNKNOWN_CLASS_COLOR),
+            (112, "Slayer", "#db6a42"),
+            (201, "Mage", UNKNOWN_CLASS_COLOR),
+            (202, "Arcanist", "#b38915"),
+            (203, "Summoner", "#22aa99"),
+            (204, "Bard", "#674598"),
+            (205, "Sorceress", "#66aa00"),
+            (301, "Martial Artist (Female)", UNKNOWN_CLASS_COLOR),
+            (302, "Wardancer", "#aaaa11"),
+            (303, "Scrapper", "#990099"),
+            (304, "Soulfist", "#316395"),
+            (305, "Glaivier", "#f6da6a"),
+            (311, "Martial Artist (Male)", UNKNOWN_CLASS_COLOR),
+            (312, "Striker", "#994499"),
+            (313, "Breaker", "#4de3d1"),
+            (401, "Assassin", UNKNOWN_CLASS_COLOR),
+            (402, "Deathblade", "#a91a16"),
+            (403, "Shadowhunter", "#0099c6"),
+            (404, "Reaper", "#109618"),
+            (405, "Souleater", "#c16ed0"),
+            (501, "Gunner (Male)", UNKNOWN_CLASS_COLOR),
+            (502, "Sharpshooter", "#dd4477"),
+            (503, "Deadeye", "#4442a8"),
+            (504, "Artillerist", "#33670b"),
+            (505, "Machinist", "#3b4292"),
+            (511, "Gunner (Female)", UNKNOWN_CLASS_COLOR),
+            (512, "Gunslinger", "#6bcec2"),
+            (601, "Specialist", UNKNOWN_CLASS_COLOR),
+            (602, "Artist", "#a34af0"),
+            (603, "Aeromancer", "#084ba3"),
+            (604, "Alchemist", "#3a945e"),
+        ];
+        classes
+            .into_iter()
+            .map(|(id, name, color)| {
+                let class = ClassMetadata {
+                    id,
+                    name: name.to_string(),
+                    color: color.to_string(),
+                    icon: format!("classes/{}.png", id),
+                };
+                (id, class)
+            })
+            .collect()
+    };
+    // skill effect id -> name of the battle item it belongs to
+    pub static ref BATTLE_ITEMS: HashMap<u32, String> = {
+        SKILL_EFFECT_DATA
+            .iter()
+            .filter(|(_, effect)| effect.item_type.as_deref() == Some("useup"))
+            .map(|(id, effect)| {
+                let name = effect.item_name.clone().unwrap_or_else(|| effect.comment.clone());
+                (*id, name)
+            })
+            .collect()
+    };
+    // skill id -> name of the sidereal (esther) it summons
+    pub static ref SIDEREAL_SKILLS: HashMap<u32, String> = {
+        ESTHER_DATA
+            .iter()
+            .flat_map(|esther| esther.skills.iter().map(|id| (*id as u32, esther.name.clone())))
+            .collect()
+    };
     pub static ref VALID_ZONES: HashSet<u32> = {
         let valid_zones = [
             30801, 30802, 30803, 30804, 30805, 30806, 30807, 30835, 37001, 37002, 37003, 37011,
@@ -1062,6 +1994,42 @@ lazy_static! {
 
         valid_zones.iter().cloned().collect()
     };
+    // zone id -> chaos dungeon or cube it belongs to, floors of one dungeon
+    // share the name. filled in as zone ids are confirmed
+    pub static ref DUNGEON_ZONES: HashMap<u32, &'static str> = {
+        let dungeon_zones: [(u32, &str); 0] = [];
+
+        dungeon_zones.iter().cloned().collect()
+    };
+    // awakening skill ids, hyper awakenings are tracked separately
+    pub static ref AWAKENING_SKILLS: HashSet<u32> = {
+        let awakening_skills = [
+            16700, 16710, 17170, 17220, 18120, 18230, 19110, 19330, 20290, 20300, 21230, 21280,
+            22310, 22330, 23250, 23270, 24050, 24270, 25300, 25350, 26900, 26910, 27035, 27890,
+            28230, 28240, 28260, 28270, 29250, 29280, 30230, 30280, 31900, 31910, 32270, 32280,
+            32300, 34600, 34610, 35930, 35940, 36210, 36220, 37360, 37370, 38250, 38280, 39310,
+            39330, 45800, 45810, 46600, 46610, 47910, 47920
+        ];
+
+        awakening_skills.iter().cloned().collect()
+    };
+    // support skill ids with a purify tripod, the only casts credited with cleanses
+    pub static ref CLEANSE_SKILLS: HashSet<u32> = {
+        let cleanse_skills = [17130, 20040, 24250, 31220, 36140];
+
+        cleanse_skills.iter().cloned().collect()
+    };
+    pub static ref HYPER_AWAKENING_SKILLS: HashSet<u32> = {
+        let hyper_awakening_skills = [
+            16720, 16730, 18240, 18250, 17250, 17260, 36230, 36240, 45820, 45830, 19360, 19370,
+            20370, 20350, 21320, 21330, 37380, 37390, 22360, 22370, 23400, 23410, 24300, 24310,
+            34620, 34630, 39340, 39350, 47300, 47310, 25410, 25420, 27910, 27920, 26940, 26950,
+            46620, 46630, 29360, 29370, 30320, 30330, 35810, 35820, 38320, 38330, 31920, 31930,
+            32290, 32230
+        ];
+
+        hyper_awakening_skills.iter().cloned().collect()
+    };
     pub static ref STAT_TYPE_MAP: HashMap<&'static str, u32> = {
         let mut map = HashMap::new();
         map.insert("none", 0);
@@ -1300,6 +2268,62 @@ fn default_scale() -> String {
     "1".to_string()
 }
 
+fn default_emit_interval() -> u64 {
+    DEFAULT_EMIT_INTERVAL_MS
+}
+
+fn default_auto_clickthrough_delay() -> u64 {
+    DEFAULT_RELEASE_DELAY_MS
+}
+
+fn default_fade_delay() -> u64 {
+    DEFAULT_FADE_DELAY_S
+}
+
+fn default_fade_opacity() -> f64 {
+    DEFAULT_FADE_OPACITY
+}
+
+fn default_boss_hp_hide_delay() -> u64 {
+    30
+}
+
+fn default_ipc_payload_limit() -> u64 {
+    DEFAULT_PAYLOAD_LIMIT_MB
+}
+
+fn default_trim_prepull_max_percent() -> f64 {
+    DEFAULT_MAX_TRIM_PERCENT
+}
+
+fn default_auto_cleanup_days() -> u32 {
+    30
+}
+
+pub fn default_toggle_clickthrough() -> Shortcut {
+    Shortcut {
+        modifier: "Ctrl+Alt".to_string(),
+        key: "c".to_string(),
+    }
+}
+
+// older frontends send a single difficulty string, empty for no filter
+fn string_or_vec<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    match value {
+        Value::String(s) if s.is_empty() => Ok(vec![]),
+        Value::String(s) => Ok(vec![s]),
+        Value::Array(_) => serde_json::from_value(value).map_err(serde::de::Error::custom),
+        Value::Null => Ok(vec![]),
+        _ => Err(serde::de::Error::custom(
+            "Expected a string or a list of strings",
+        )),
+    }
+}
+
 fn int_or_string_as_string<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
@@ -1311,3 +2335,38 @@ where
         _ => Err(serde::de::Error::custom("Expected a string or an integer")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn misc_keeps_fields_it_does_not_know() {
+        let stored = json!({
+            "region": "EUC",
+            "raidClear": true,
+            "futureField": {"nested": [1, 2, 3]},
+            "futureFlag": false,
+        });
+        let misc: EncounterMisc = serde_json::from_value(stored.clone()).unwrap();
+        assert_eq!(misc.region.as_deref(), Some("EUC"));
+        assert_eq!(misc.extra.len(), 2);
+        assert_eq!(misc.extra["futureField"], json!({"nested": [1, 2, 3]}));
+
+        let resaved = serde_json::to_value(&misc).unwrap();
+        assert_eq!(resaved, stored);
+    }
+
+    #[test]
+    fn misc_without_unknown_fields_serializes_as_before() {
+        let misc = EncounterMisc {
+            region: Some("NAE".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&misc).unwrap(),
+            json!({"region": "NAE"})
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::parser::models::{Encounter, EncounterPreview, EntityType, StaggerCheck};
+use crate::parser::utils::get_class_from_id;
+use hashbrown::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// replaces other players' names in what the meter and logs window show, the db
+// and uploads keep the real ones
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct Placeholders {
+    names: HashMap<String, String>,
+    class_counts: HashMap<String, u32>,
+}
+
+// kept for the whole session so a player keeps their placeholder across fights
+fn get_placeholders() -> &'static Mutex<Placeholders> {
+    static PLACEHOLDERS: OnceLock<Mutex<Placeholders>> = OnceLock::new();
+    PLACEHOLDERS.get_or_init(|| Mutex::new(Placeholders::default()))
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// "Deathblade 1", numbered per class in the order players are first seen
+fn placeholder(placeholders: &mut Placeholders, name: &str, class: &str) -> String {
+    if let Some(placeholder) = placeholders.names.get(name) {
+        return placeholder.clone();
+    }
+    let class = if class.is_empty() { "Player" } else { class };
+    let count = placeholders
+        .class_counts
+        .entry(class.to_string())
+        .or_default();
+    *count += 1;
+    let placeholder = format!("{} {}", class, count);
+    placeholders
+        .names
+        .insert(name.to_string(), placeholder.clone());
+    placeholder
+}
+
+fn rename_keys<V>(map: &mut HashMap<String, V>, renames: &HashMap<String, String>) {
+    let renamed = map
+        .drain()
+        .map(|(name, value)| (renames.get(&name).cloned().unwrap_or(name), value))
+        .collect();
+    *map = renamed;
+}
+
+fn rename(name: &mut String, renames: &HashMap<String, String>) {
+    if let Some(placeholder) = renames.get(name) {
+        name.clone_from(placeholder);
+    }
+}
+
+pub fn mask_encounter(encounter: &mut Encounter, party: Option<&mut HashMap<i32, Vec<String>>>) {
+    if !is_enabled() {
+        return;
+    }
+
+    let renames: HashMap<String, String> = {
+        let mut placeholders = get_placeholders().lock().unwrap();
+        encounter
+            .entities
+            .values()
+            .filter(|e| e.entity_type == EntityType::PLAYER && e.name != encounter.local_player)
+            .map(|e| {
+                (
+                    e.name.clone(),
+                    placeholder(&mut placeholders, &e.name, &e.class),
+                )
+            })
+            .collect()
+    };
+    if renames.is_empty() {
+        return;
+    }
+
+    rename_keys(&mut encounter.entities, &renames);
+    for entity in encounter.entities.values_mut() {
+        rename(&mut entity.name, &renames);
+    }
+    rename(
+        &mut encounter
+            .encounter_damage_stats
+            .most_damage_taken_entity
+            .name,
+        &renames,
+    );
+
+    for members in party.into_iter().flat_map(|party| party.values_mut()) {
+        members.iter_mut().for_each(|name| rename(name, &renames));
+    }
+    if let Some(misc) = encounter.encounter_damage_stats.misc.as_mut() {
+        for members in misc
+            .party_info
+            .iter_mut()
+            .flat_map(|party| party.values_mut())
+        {
+            members.iter_mut().for_each(|name| rename(name, &renames));
+        }
+        for check in misc.stagger_checks.iter_mut().flatten() {
+            rename_keys(&mut check.stagger, &renames);
+            rename_keys(&mut check.counters, &renames);
+        }
+        for (_, player, _) in misc.highlights.iter_mut().flatten() {
+            rename(player, &renames);
+        }
+    }
+}
+
+pub fn mask_player(name: &str, class: &str, local_player: &str) -> String {
+    if !is_enabled() || name == local_player {
+        return name.to_string();
+    }
+    placeholder(&mut get_placeholders().lock().unwrap(), name, class)
+}
+
+// stagger results are sent on their own before the encounter is, so classes
+// come from the encounter's entities
+pub fn mask_stagger_check(check: &mut StaggerCheck, encounter: &Encounter) {
+    if !is_enabled() {
+        return;
+    }
+
+    let renames: HashMap<String, String> = {
+        let mut placeholders = get_placeholders().lock().unwrap();
+        check
+            .stagger
+            .keys()
+            .chain(check.counters.keys())
+            .filter(|name| **name != encounter.local_player)
+            .map(|name| {
+                let class = encounter
+                    .entities
+                    .get(name)
+                    .map_or("", |entity| entity.class.as_str());
+                (name.clone(), placeholder(&mut placeholders, name, class))
+            })
+            .collect()
+    };
+    rename_keys(&mut check.stagger, &renames);
+    rename_keys(&mut check.counters, &renames);
+}
+
+pub fn mask_previews(previews: &mut [EncounterPreview]) {
+    if !is_enabled() {
+        return;
+    }
+
+    let mut placeholders = get_placeholders().lock().unwrap();
+    for preview in previews.iter_mut() {
+        for player in preview.players_detail.iter_mut() {
+            if player.name != preview.local_player {
+                let class = get_class_from_id(&(player.class_id as u32));
+                player.name = placeholder(&mut placeholders, &player.name, &class);
+            }
+        }
+        preview.names = preview
+            .players_detail
+            .iter()
+            .map(|player| player.name.clone())
+            .collect();
+    }
+}
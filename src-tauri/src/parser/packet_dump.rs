@@ -0,0 +1,169 @@
+use crate::parser::packet_source::CAPTURE_MAGIC;
+use chrono::Utc;
+use log::{info, warn};
+use meter_core::packets::opcodes::Pkt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+pub const DUMP_DIR: &str = "packet_dumps";
+const DUMP_FILE: &str = "capture.bin";
+const PREVIOUS_DUMP_FILE: &str = "capture.prev.bin";
+// the current and the previous file are kept, so dumps stay under 100mb
+const MAX_DUMP_FILE_SIZE: u64 = 50 * 1024 * 1024;
+// timestamp + opcode + length
+const RECORD_HEADER_SIZE: u64 = 14;
+
+// checked before locking so packets aren't slowed down while dumping is off
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// tees the already filtered game packets into a rotating file in the
+// capture format, so a bug report can be replayed later
+struct PacketDump {
+    dir: Option<PathBuf>,
+    writer: Option<BufWriter<File>>,
+    written: u64,
+}
+
+fn get_dump() -> &'static Mutex<PacketDump> {
+    static PACKET_DUMP: OnceLock<Mutex<PacketDump>> = OnceLock::new();
+    PACKET_DUMP.get_or_init(|| {
+        Mutex::new(PacketDump {
+            dir: None,
+            writer: None,
+            written: 0,
+        })
+    })
+}
+
+pub fn init(dir: PathBuf, enabled: bool) {
+    get_dump().lock().unwrap().dir = Some(dir);
+    set_enabled(enabled);
+}
+
+pub fn set_enabled(enabled: bool) {
+    let prev = ENABLED.swap(enabled, Ordering::Relaxed);
+    if prev && !enabled {
+        get_dump().lock().unwrap().close();
+    }
+    if prev != enabled {
+        info!(
+            "packet dump {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+pub fn record(op: Pkt, data: &[u8]) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut dump = get_dump().lock().unwrap();
+    if let Err(e) = dump.write(op as u16, data) {
+        warn!("failed to write packet dump, disabling it: {}", e);
+        dump.close();
+        ENABLED.store(false, Ordering::Relaxed);
+    }
+}
+
+// whether the file is one dumping is still writing to or rotating into
+pub fn in_use(path: &Path) -> bool {
+    ENABLED.load(Ordering::Relaxed)
+        && path
+            .file_name()
+            .is_some_and(|name| name == DUMP_FILE || name == PREVIOUS_DUMP_FILE)
+}
+
+// stops dumping and joins the previous and the current file into one capture
+// so it can be attached to a report
+pub fn stop_and_collect() -> Result<PathBuf, String> {
+    ENABLED.store(false, Ordering::Relaxed);
+    let mut dump = get_dump().lock().unwrap();
+    dump.close();
+
+    let dir = dump.dir.clone().ok_or("packet dump not initialized")?;
+    let parts: Vec<PathBuf> = [PREVIOUS_DUMP_FILE, DUMP_FILE]
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists())
+        .collect();
+    if parts.is_empty() {
+        return Err("no packets have been dumped".to_string());
+    }
+    let collected = dir.join(format!(
+        "capture-{}.bin",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    join_captures(&parts, &collected).map_err(|e| e.to_string())?;
+    for part in parts {
+        fs::remove_file(&part).ok();
+    }
+    info!("collected packet dump {:?}", collected);
+    Ok(collected)
+}
+
+// every part starts with the capture magic, only the first one is kept
+fn join_captures(parts: &[PathBuf], path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(CAPTURE_MAGIC)?;
+    for part in parts {
+        let mut reader = File::open(part)?;
+        reader.seek(SeekFrom::Start(CAPTURE_MAGIC.len() as u64))?;
+        io::copy(&mut reader, &mut writer)?;
+    }
+    writer.flush()
+}
+
+impl PacketDump {
+    fn write(&mut self, opcode: u16, data: &[u8]) -> std::io::Result<()> {
+        let size = RECORD_HEADER_SIZE + data.len() as u64;
+        if self.writer.is_some() && self.written + size > MAX_DUMP_FILE_SIZE {
+            self.close();
+        }
+        if self.writer.is_none() {
+            self.open()?;
+        }
+
+        let writer = self.writer.as_mut().unwrap();
+        writer.write_all(&Utc::now().timestamp_millis().to_le_bytes())?;
+        writer.write_all(&opcode.to_le_bytes())?;
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(data)?;
+        self.written += size;
+        Ok(())
+    }
+
+    // the file left by the last session or the last rotation becomes the
+    // previous one instead of being truncated
+    fn open(&mut self) -> std::io::Result<()> {
+        self.rotate()?;
+        let Some(dir) = self.dir.as_ref() else {
+            return Err(std::io::Error::other("packet dump not initialized"));
+        };
+        fs::create_dir_all(dir)?;
+        let mut writer = BufWriter::new(File::create(dir.join(DUMP_FILE))?);
+        writer.write_all(CAPTURE_MAGIC)?;
+        self.written = CAPTURE_MAGIC.len() as u64;
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.close();
+        if let Some(dir) = self.dir.as_ref() {
+            let current = dir.join(DUMP_FILE);
+            if current.exists() {
+                fs::rename(current, dir.join(PREVIOUS_DUMP_FILE))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush().ok();
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::parser::clock;
+use crate::parser::metrics;
+use crate::parser::packet_dump;
+use anyhow::{bail, Result};
+use log::{info, warn};
+use meter_core::packets::opcodes::Pkt;
+use meter_core::start_capture;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::PathBuf;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+
+// capture files start with CAPTURE_MAGIC followed by one record per packet:
+// [timestamp ms: i64][opcode: u16][payload length: u32][payload], little endian
+pub const CAPTURE_MAGIC: &[u8; 8] = b"LOAPCAP1";
+// longest pause a real-time replay waits between two packets
+const MAX_REPLAY_GAP: Duration = Duration::from_secs(5);
+// how often an idle capture wakes up the parser loop to handle requests
+const IDLE_TICK: Duration = Duration::from_millis(250);
+
+// None is an idle tick, the stream ends when the capture stops
+pub type Packets = Box<dyn Iterator<Item = Option<(Pkt, Vec<u8>)>>>;
+
+pub enum PacketSource {
+    Live { port: u16 },
+    Replay { path: PathBuf, realtime: bool },
+}
+
+impl PacketSource {
+    pub fn is_replay(&self) -> bool {
+        matches!(self, PacketSource::Replay { .. })
+    }
+
+    pub fn open(&self, region_file_path: String) -> Result<Packets> {
+        match self {
+            PacketSource::Live { port } => {
+                let rx = start_capture(*port, region_file_path)?;
+                Ok(Box::new(std::iter::from_fn(move || {
+                    match rx.recv_timeout(IDLE_TICK) {
+                        Ok((op, data)) => {
+                            packet_dump::record(op, &data);
+                            Some(Some((op, data)))
+                        }
+                        Err(RecvTimeoutError::Timeout) => Some(None),
+                        Err(RecvTimeoutError::Disconnected) => None,
+                    }
+                })))
+            }
+            PacketSource::Replay { path, realtime } => {
+                info!("replaying capture {:?}, real-time: {}", path, realtime);
+                let mut reader = BufReader::new(File::open(path)?);
+                let mut magic = [0u8; 8];
+                reader.read_exact(&mut magic)?;
+                if &magic != CAPTURE_MAGIC {
+                    bail!("{:?} is not a packet capture", path);
+                }
+                Ok(Box::new(ReplayReader {
+                    reader,
+                    realtime: *realtime,
+                    last_timestamp: None,
+                }))
+            }
+        }
+    }
+}
+
+struct ReplayReader {
+    reader: BufReader<File>,
+    realtime: bool,
+    last_timestamp: Option<i64>,
+}
+
+impl ReplayReader {
+    fn read_record(&mut self) -> std::io::Result<(i64, u16, Vec<u8>)> {
+        let mut timestamp = [0u8; 8];
+        let mut opcode = [0u8; 2];
+        let mut len = [0u8; 4];
+        self.reader.read_exact(&mut timestamp)?;
+        self.reader.read_exact(&mut opcode)?;
+        self.reader.read_exact(&mut len)?;
+        let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok((
+            i64::from_le_bytes(timestamp),
+            u16::from_le_bytes(opcode),
+            data,
+        ))
+    }
+}
+
+impl Iterator for ReplayReader {
+    type Item = Option<(Pkt, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (timestamp, opcode, data) = match self.read_record() {
+                Ok(record) => record,
+                Err(e) => {
+                    if e.kind() != ErrorKind::UnexpectedEof {
+                        warn!("failed to read capture: {}", e);
+                    }
+                    info!("replay finished");
+                    clock::set_replay_time(0);
+                    return None;
+                }
+            };
+
+            if self.realtime {
+                if let Some(last) = self.last_timestamp {
+                    let gap = Duration::from_millis((timestamp - last).max(0) as u64);
+                    thread::sleep(gap.min(MAX_REPLAY_GAP));
+                }
+            }
+            self.last_timestamp = Some(timestamp);
+            clock::set_replay_time(timestamp);
+
+            match Pkt::try_from(opcode) {
+                Ok(op) => return Some(Some((op, data))),
+                // recorded by a different client version
+                Err(_) => metrics::on_unknown_opcode(opcode),
+            }
+        }
+    }
+}
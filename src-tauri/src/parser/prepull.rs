@@ -0,0 +1,127 @@
+use crate::parser::models::{BossCast, BossHpLog, Encounter, EntityType};
+use crate::parser::utils::calculate_dps;
+use hashbrown::HashMap;
+use log::info;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+pub const DEFAULT_MAX_TRIM_PERCENT: f64 = 10.0;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// f64 bits of the largest share of the fight a trim may cut off
+static MAX_TRIM_FRACTION: AtomicU64 = AtomicU64::new(0);
+
+// from GeneralSettings trim_prepull and trim_prepull_max_percent
+pub fn set(enabled: bool, max_percent: f64) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    let fraction = if max_percent.is_finite() {
+        max_percent.clamp(0.0, 100.0) / 100.0
+    } else {
+        DEFAULT_MAX_TRIM_PERCENT / 100.0
+    };
+    MAX_TRIM_FRACTION.store(fraction.to_bits(), Ordering::Relaxed);
+}
+
+// whether the time before the first hit on the boss is short enough to be
+// cut off. a manual save mid fight sees less of it than the final save, so
+// the first save's answer is kept for the rest of the fight
+pub fn should_trim(encounter: &Encounter, first_boss_hit: i64) -> bool {
+    if !ENABLED.load(Ordering::Relaxed) || encounter.boss_only_damage {
+        return false;
+    }
+    let offset = first_boss_hit - encounter.fight_start;
+    let duration = encounter.last_combat_packet - encounter.fight_start;
+    if offset <= 0 || duration <= 0 {
+        return false;
+    }
+    let max_fraction = f64::from_bits(MAX_TRIM_FRACTION.load(Ordering::Relaxed));
+    if offset as f64 > duration as f64 * max_fraction {
+        info!(
+            "not trimming {}ms before the pull, over {}% of the fight",
+            offset,
+            max_fraction * 100.0
+        );
+        return false;
+    }
+    true
+}
+
+// moves the start of a fight that was opened by stray hits on trash to the
+// first hit on the boss and takes the damage before it out of the totals.
+// returns the removed damage, none when nothing was trimmed. skill breakdowns
+// keep the trimmed hits, they aren't stored with timestamps, and stagger
+// intervals are durations so the shift doesn't apply to them
+pub fn trim(
+    encounter: &mut Encounter,
+    first_boss_hit: i64,
+    damage_log: &mut HashMap<String, Vec<(i64, i64)>>,
+    boss_hp_log: &mut HashMap<String, Vec<BossHpLog>>,
+    cast_log: &mut HashMap<String, HashMap<u32, Vec<i32>>>,
+    boss_casts: &mut Vec<BossCast>,
+    stagger_log: &mut Vec<(i32, f32)>,
+) -> Option<i64> {
+    let offset = first_boss_hit - encounter.fight_start;
+    // without rolling dps there is no damage log to trim
+    if offset <= 0 || damage_log.is_empty() {
+        return None;
+    }
+
+    let mut removed = 0;
+    for (name, log) in damage_log.iter_mut() {
+        let before: i64 = log
+            .iter()
+            .filter(|(timestamp, _)| *timestamp < first_boss_hit)
+            .map(|(_, damage)| damage)
+            .sum();
+        if before == 0 {
+            continue;
+        }
+        log.retain(|(timestamp, _)| *timestamp >= first_boss_hit);
+        if let Some(entity) = encounter.entities.get_mut(name) {
+            // the pull itself is the first damage to the boss, so everything
+            // before it went to adds
+            let stats = &mut entity.damage_stats;
+            stats.damage_dealt = (stats.damage_dealt - before).max(0);
+            stats.damage_to_adds = (stats.damage_to_adds - before).max(0);
+            if entity.entity_type == EntityType::PLAYER {
+                removed += before;
+            }
+        }
+    }
+
+    let offset_s = (offset / 1000) as i32;
+    for log in boss_hp_log.values_mut() {
+        log.retain(|entry| entry.time >= offset_s);
+        log.iter_mut().for_each(|entry| entry.time -= offset_s);
+    }
+    stagger_log.retain(|(time, _)| *time >= offset_s);
+    stagger_log
+        .iter_mut()
+        .for_each(|(time, _)| *time -= offset_s);
+    boss_casts.retain(|cast| cast.offset_ms >= offset);
+    boss_casts
+        .iter_mut()
+        .for_each(|cast| cast.offset_ms -= offset);
+    let offset_ms = offset as i32;
+    for casts in cast_log.values_mut().flat_map(|skills| skills.values_mut()) {
+        casts.retain(|&time| time >= offset_ms);
+        casts.iter_mut().for_each(|time| *time -= offset_ms);
+    }
+
+    encounter.fight_start = first_boss_hit;
+    let stats = &mut encounter.encounter_damage_stats;
+    stats.total_damage_dealt = (stats.total_damage_dealt - removed).max(0);
+    let duration = encounter.last_combat_packet - first_boss_hit;
+    let mut top_damage = 0;
+    for entity in encounter.entities.values_mut() {
+        entity.damage_stats.dps = calculate_dps(entity.damage_stats.damage_dealt, duration);
+        if entity.entity_type == EntityType::PLAYER {
+            top_damage = top_damage.max(entity.damage_stats.damage_dealt);
+        }
+    }
+    stats.top_damage_dealt = top_damage;
+    info!(
+        "trimmed {}ms and {} damage before the pull",
+        offset, removed
+    );
+    Some(removed)
+}
@@ -0,0 +1,170 @@
+use crate::parser::clock;
+use crate::parser::models::{Encounter, EntityType, SessionPlayerStats, SessionStats};
+use crate::parser::name_mask;
+use hashbrown::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+// totals over every pull saved since the app started or reset_session_stats,
+// nothing of it is written to the db
+#[derive(Default)]
+struct Session {
+    started: i64,
+    pulls: i64,
+    combat_time: i64,
+    local_player: String,
+    players: HashMap<String, SessionPlayer>,
+    // fight start -> what the fight added, taken back out when a fight that
+    // was saved manually is saved again at its end
+    saved: HashMap<i64, SavedFight>,
+}
+
+struct SavedFight {
+    duration: i64,
+    damage: Vec<(String, i64)>,
+}
+
+#[derive(Default)]
+struct SessionPlayer {
+    class: String,
+    class_id: u32,
+    pulls: i64,
+    damage: i64,
+    combat_time: i64,
+}
+
+fn get_session() -> &'static Mutex<Session> {
+    static SESSION: OnceLock<Mutex<Session>> = OnceLock::new();
+    SESSION.get_or_init(|| Mutex::new(Session::default()))
+}
+
+// called with each encounter that gets saved, so short resets and trash
+// pulls the db wouldn't keep don't count either
+pub fn on_encounter(encounter: &Encounter) {
+    let duration = encounter.last_combat_packet - encounter.fight_start;
+    if duration <= 0 {
+        return;
+    }
+    let mut session = get_session().lock().unwrap();
+    let session = &mut *session;
+    if session.started == 0 {
+        session.started = encounter.fight_start;
+    }
+    if let Some(previous) = session.saved.remove(&encounter.fight_start) {
+        session.pulls -= 1;
+        session.combat_time -= previous.duration;
+        for (name, damage) in previous.damage {
+            if let Some(player) = session.players.get_mut(&name) {
+                player.pulls -= 1;
+                player.damage -= damage;
+                player.combat_time -= previous.duration;
+            }
+        }
+        session.players.retain(|_, player| player.pulls > 0);
+    }
+    session.pulls += 1;
+    session.combat_time += duration;
+    session.local_player.clone_from(&encounter.local_player);
+    let mut saved = SavedFight {
+        duration,
+        damage: Vec::new(),
+    };
+    for entity in encounter.entities.values() {
+        if entity.entity_type != EntityType::PLAYER || entity.damage_stats.damage_dealt == 0 {
+            continue;
+        }
+        let player = session.players.entry(entity.name.clone()).or_default();
+        player.class.clone_from(&entity.class);
+        player.class_id = entity.class_id;
+        player.pulls += 1;
+        player.damage += entity.damage_stats.damage_dealt;
+        player.combat_time += duration;
+        saved
+            .damage
+            .push((entity.name.clone(), entity.damage_stats.damage_dealt));
+    }
+    session.saved.insert(encounter.fight_start, saved);
+}
+
+pub fn reset() {
+    *get_session().lock().unwrap() = Session {
+        started: clock::now().timestamp_millis(),
+        ..Default::default()
+    };
+}
+
+pub fn get_stats() -> SessionStats {
+    let session = get_session().lock().unwrap();
+    let mut players: Vec<SessionPlayerStats> = session
+        .players
+        .iter()
+        .map(|(name, player)| SessionPlayerStats {
+            name: name_mask::mask_player(name, &player.class, &session.local_player),
+            class_id: player.class_id,
+            pulls: player.pulls,
+            damage: player.damage,
+            combat_time: player.combat_time,
+            average_damage: player.damage / player.pulls.max(1),
+            dps: player.damage * 1000 / player.combat_time.max(1),
+        })
+        .collect();
+    players.sort_by(|a, b| b.damage.cmp(&a.damage));
+
+    SessionStats {
+        started: session.started,
+        pulls: session.pulls,
+        combat_time: session.combat_time,
+        total_damage: players.iter().map(|player| player.damage).sum(),
+        players,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::models::{DamageStats, EncounterEntity};
+
+    fn encounter(fight_start: i64, damage: &[(&str, i64)]) -> Encounter {
+        Encounter {
+            fight_start,
+            last_combat_packet: fight_start + 60_000,
+            entities: damage
+                .iter()
+                .map(|(name, damage)| {
+                    let entity = EncounterEntity {
+                        name: name.to_string(),
+                        entity_type: EntityType::PLAYER,
+                        damage_stats: DamageStats {
+                            damage_dealt: *damage,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+                    (entity.name.clone(), entity)
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn saving_a_fight_again_replaces_it() {
+        reset();
+        on_encounter(&encounter(1_000, &[("Alice", 100), ("Bob", 50)]));
+        // the manual save was mid fight, the natural one has more damage
+        on_encounter(&encounter(1_000, &[("Alice", 300)]));
+
+        let stats = get_stats();
+        assert_eq!(stats.pulls, 1);
+        assert_eq!(stats.combat_time, 60_000);
+        assert_eq!(stats.total_damage, 300);
+        assert_eq!(stats.players.len(), 1);
+        assert_eq!(stats.players[0].pulls, 1);
+
+        on_encounter(&encounter(100_000, &[("Alice", 200)]));
+        let stats = get_stats();
+        assert_eq!(stats.pulls, 2);
+        assert_eq!(stats.combat_time, 120_000);
+        assert_eq!(stats.players[0].damage, 500);
+        assert_eq!(stats.players[0].average_damage, 250);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::parser::clock;
 use crate::parser::entity_tracker::Entity;
+use crate::parser::interner::{intern_with, Name, NameKind};
 use crate::parser::models::{EncounterEntity, EntityType, SKILL_BUFF_DATA};
 use crate::parser::party_tracker::PartyTracker;
 use crate::parser::status_tracker::StatusEffectBuffCategory::{BattleItem, Bracelet, Elixir, Etc};
@@ -14,6 +16,11 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 const TIMEOUT_DELAY_MS: i64 = 1000;
+// debuffs removed this long before they expire are treated as cleansed
+const CLEANSE_EXPIRY_MARGIN_MS: i64 = 500;
+// removal reasons, see statuseffectexpiredreasontype
+const REMOVE_REASON_CANCEL: u8 = 1;
+const REMOVE_REASON_BEATTACKED: u8 = 4;
 const WORKSHOP_BUFF_ID: u32 = 9701;
 
 pub type StatusEffectRegistry = HashMap<u32, StatusEffectDetails>;
@@ -46,7 +53,7 @@ impl StatusTracker {
         } else {
             (pkt.pc_struct.player_id, StatusEffectTargetType::Local)
         };
-        let timestamp = Utc::now();
+        let timestamp = clock::now();
         for sed in pkt.pc_struct.status_effect_datas.into_iter() {
             let source_id = sed.source_id;
             let status_effect =
@@ -81,7 +88,7 @@ impl StatusTracker {
         instance_id: Vec<u32>,
         reason: u8,
         sett: StatusEffectTargetType,
-    ) -> (bool, Vec<StatusEffectDetails>, bool) {
+    ) -> (bool, Vec<StatusEffectDetails>, bool, Vec<StatusEffectDetails>) {
         let registry = match sett {
             StatusEffectTargetType::Local => &mut self.local_status_effect_registry,
             StatusEffectTargetType::Party => &mut self.party_status_effect_registry,
@@ -90,6 +97,8 @@ impl StatusTracker {
         let mut has_shield_buff = false;
         let mut shields_broken: Vec<StatusEffectDetails> = Vec::new();
         let mut left_workshop = false;
+        let mut debuffs_cleansed: Vec<StatusEffectDetails> = Vec::new();
+        let now = clock::now();
 
         if let Some(ser) = registry.get_mut(&target_id) {
             for id in instance_id {
@@ -99,15 +108,24 @@ impl StatusTracker {
                     }
                     if se.status_effect_type == StatusEffectType::Shield {
                         has_shield_buff = true;
-                        if reason == 4 {
+                        if reason == REMOVE_REASON_BEATTACKED {
                             shields_broken.push(se);
                         }
+                    } else if se.category == Debuff
+                        && reason == REMOVE_REASON_CANCEL
+                        // permanent debuffs only go away with a mechanic or on death
+                        && se.expire_at.is_some_and(|expire_at| {
+                            (expire_at - now).num_milliseconds()
+                                > TIMEOUT_DELAY_MS + CLEANSE_EXPIRY_MARGIN_MS
+                        })
+                    {
+                        debuffs_cleansed.push(se);
                     }
                 }
             }
         }
 
-        (has_shield_buff, shields_broken, left_workshop)
+        (has_shield_buff, shields_broken, left_workshop, debuffs_cleansed)
     }
 
     pub fn update_status_duration(
@@ -186,7 +204,7 @@ impl StatusTracker {
         target_entity: &Entity,
         local_character_id: u64,
     ) -> (Vec<StatusEffectDetails>, Vec<StatusEffectDetails>) {
-        let timestamp = Utc::now();
+        let timestamp = clock::now();
 
         let use_party_for_source = if source_entity.entity_type == EntityType::PLAYER {
             self.should_use_party_status_effect(source_entity.character_id, local_character_id)
@@ -358,11 +376,14 @@ pub fn build_status_effect(
     let mut buff_category = StatusEffectBuffCategory::Other;
     let mut show_type = StatusEffectShowType::Other;
     let mut status_effect_type = StatusEffectType::Other;
-    let mut name = "Unknown".to_string();
-    let mut db_target_type = "".to_string();
+    let effect = SKILL_BUFF_DATA.get(&se_data.status_effect_id);
+    let name = intern_with(NameKind::Buff, se_data.status_effect_id, || match effect {
+        Some(effect) => effect.name.clone().unwrap_or_default(),
+        None => "Unknown".to_string(),
+    });
+    let mut db_target_type = Name::default();
     let mut custom_id = 0;
-    if let Some(effect) = SKILL_BUFF_DATA.get(&se_data.status_effect_id) {
-        name = effect.name.clone().unwrap_or_default();
+    if let Some(effect) = effect {
         if effect.category.as_str() == "debuff" {
             status_effect_category = Debuff
         }
@@ -379,7 +400,9 @@ pub fn build_status_effect(
         if effect.buff_type.as_str() == "shield" {
             status_effect_type = StatusEffectType::Shield
         }
-        db_target_type = effect.target.to_string();
+        db_target_type = intern_with(NameKind::BuffTarget, se_data.status_effect_id, || {
+            effect.target.to_string()
+        });
 
         if let Some(source_skills) = effect.source_skills.as_ref() {
             if source_skills.len() > 1 {
@@ -503,7 +526,7 @@ pub struct StatusEffectDetails {
     pub target_id: u64,
     pub source_id: u64,
     pub target_type: StatusEffectTargetType,
-    pub db_target_type: String,
+    pub db_target_type: Name,
     pub value: u64,
     pub stack_count: u8,
     pub category: StatusEffectCategory,
@@ -514,5 +537,5 @@ pub struct StatusEffectDetails {
     pub expire_at: Option<DateTime<Utc>>,
     pub end_tick: u64,
     pub timestamp: DateTime<Utc>,
-    pub name: String,
+    pub name: Name,
 }
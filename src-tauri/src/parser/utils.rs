@@ -1,4 +1,6 @@
+use crate::app::number_format;
 use crate::parser::entity_tracker::Entity;
+use crate::parser::metrics;
 use crate::parser::models::*;
 use crate::parser::skill_tracker::SkillTracker;
 use crate::parser::stats_api::PlayerStats;
@@ -6,8 +8,9 @@ use crate::parser::status_tracker::StatusEffectDetails;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use hashbrown::{HashMap, HashSet};
+use log::{info, warn};
 use moka::sync::Cache;
-use rusqlite::{params, Transaction};
+use rusqlite::{params, OptionalExtension, Transaction};
 use serde::Serialize;
 use serde_json::json;
 use std::cmp::{max, Ordering, Reverse};
@@ -15,6 +18,7 @@ use std::collections::BTreeMap;
 use std::io::Write;
 
 pub fn encounter_entity_from_entity(entity: &Entity) -> EncounterEntity {
+    metrics::on_entity_created();
     let mut e = EncounterEntity {
         id: entity.id,
         name: entity.name.clone(),
@@ -436,53 +440,26 @@ pub fn get_skill_name(skill_id: &u32) -> String {
         })
 }
 
-pub fn get_skill(skill_id: &u32) -> Option<SkillData> {
-    SKILL_DATA.get(skill_id).cloned()
+// unknown ids get a grey placeholder instead of failing
+pub fn get_class_metadata(class_id: u32) -> ClassMetadata {
+    CLASS_METADATA
+        .get(&class_id)
+        .cloned()
+        .unwrap_or_else(|| ClassMetadata {
+            id: class_id,
+            name: "Unknown".to_string(),
+            color: UNKNOWN_CLASS_COLOR.to_string(),
+            icon: "classes/0.png".to_string(),
+        })
 }
 
 pub fn get_class_from_id(class_id: &u32) -> String {
-    let class = match class_id {
-        0 => "",
-        101 => "Warrior (Male)",
-        102 => "Berserker",
-        103 => "Destroyer",
-        104 => "Gunlancer",
-        105 => "Paladin",
-        111 => "Female Warrior",
-        112 => "Slayer",
-        201 => "Mage",
-        202 => "Arcanist",
-        203 => "Summoner",
-        204 => "Bard",
-        205 => "Sorceress",
-        301 => "Martial Artist (Female)",
-        302 => "Wardancer",
-        303 => "Scrapper",
-        304 => "Soulfist",
-        305 => "Glaivier",
-        311 => "Martial Artist (Male)",
-        312 => "Striker",
-        313 => "Breaker",
-        401 => "Assassin",
-        402 => "Deathblade",
-        403 => "Shadowhunter",
-        404 => "Reaper",
-        405 => "Souleater",
-        501 => "Gunner (Male)",
-        502 => "Sharpshooter",
-        503 => "Deadeye",
-        504 => "Artillerist",
-        505 => "Machinist",
-        511 => "Gunner (Female)",
-        512 => "Gunslinger",
-        601 => "Specialist",
-        602 => "Artist",
-        603 => "Aeromancer",
-        604 => "Alchemist",
-        _ => "Unknown",
-    };
-
-    class.to_string()
+    if *class_id == 0 {
+        return String::new();
+    }
+    CLASS_METADATA
+        .get(class_id)
+        .map_or_else(|| "Unknown".to_string(), |class| class.name.clone())
 }
 
 fn damage_gem_value_to_level(value: u32, tier: u8) -> u8 {
@@ -660,66 +637,40 @@ fn is_class_engraving(class_id: u32, engraving_id: u32) -> bool {
     }
 }
 
+pub fn is_awakening_skill(skill_id: u32) -> bool {
+    AWAKENING_SKILLS.contains(&skill_id)
+}
+
 pub fn is_hyper_awakening_skill(skill_id: u32) -> bool {
-    matches!(
-        skill_id,
-        16720
-            | 16730
-            | 18240
-            | 18250
-            | 17250
-            | 17260
-            | 36230
-            | 36240
-            | 45820
-            | 45830
-            | 19360
-            | 19370
-            | 20370
-            | 20350
-            | 21320
-            | 21330
-            | 37380
-            | 37390
-            | 22360
-            | 22370
-            | 23400
-            | 23410
-            | 24300
-            | 24310
-            | 34620
-            | 34630
-            | 39340
-            | 39350
-            | 47300
-            | 47310
-            | 25410
-            | 25420
-            | 27910
-            | 27920
-            | 26940
-            | 26950
-            | 46620
-            | 46630
-            | 29360
-            | 29370
-            | 30320
-            | 30330
-            | 35810
-            | 35820
-            | 38320
-            | 38330
-            | 31920
-            | 31930
-            | 32290
-            | 32230
-    )
+    HYPER_AWAKENING_SKILLS.contains(&skill_id)
+}
+
+// the debuff a boss gets when its stagger bar is broken
+pub fn is_paralyzation_effect(status_effect_id: u32) -> bool {
+    SKILL_BUFF_DATA
+        .get(&status_effect_id)
+        .is_some_and(|buff| buff.buff_type == "paralyzation")
 }
 
 pub fn is_hat_buff(buff_id: &u32) -> bool {
     matches!(buff_id, 362600 | 212305 | 319503)
 }
 
+// support party damage buffs (attack buff, identity) and debuffs (brand)
+pub fn is_support_synergy(status_effect: &StatusEffect) -> bool {
+    if let Some(skill) = status_effect.source.skill.as_ref() {
+        is_support_class_id(skill.class_id)
+            && status_effect.buff_type & StatusEffectBuffTypeFlags::DMG.bits() != 0
+            && status_effect.target == StatusEffectTarget::PARTY
+            && (status_effect.buff_category == "classskill"
+                || status_effect.buff_category == "arkpassive"
+                || status_effect.buff_category == "identity"
+                || status_effect.category == "debuff")
+    } else {
+        false
+    }
+}
+
 fn generate_intervals(start: i64, end: i64) -> Vec<i64> {
     if start >= end {
         return Vec::new();
@@ -808,33 +759,93 @@ pub fn check_tripod_level_change(before: Option<TripodLevel>, after: Option<Trip
 
 const WINDOW_MS: i64 = 5_000;
 const WINDOW_S: i64 = 5;
+// fewer casts can't tell a cooldown apart from a pause
+const MIN_EFFICIENCY_CASTS: usize = 3;
+// a single unlucky death doesn't make a skill the top killer
+const MIN_KILLER_DEATHS: i64 = 2;
+
+// arguments of insert_data
+#[derive(Default)]
+pub struct EncounterSave {
+    pub encounter: Encounter,
+    pub prev_stagger: i32,
+    pub damage_log: HashMap<String, Vec<(i64, i64)>>,
+    pub identity_log: HashMap<String, IdentityLog>,
+    pub cast_log: HashMap<String, HashMap<u32, Vec<i32>>>,
+    pub boss_hp_log: HashMap<String, Vec<BossHpLog>>,
+    pub stagger_log: Vec<(i32, f32)>,
+    pub stagger_intervals: Vec<(i32, i32)>,
+    pub raid_clear: bool,
+    pub party_info: Vec<Vec<String>>,
+    pub raid_difficulty: String,
+    pub region: Option<String>,
+    pub player_info: Option<HashMap<String, PlayerStats>>,
+    pub meter_version: String,
+    pub ntp_fight_start: i64,
+    pub rdps_valid: bool,
+    pub manual: bool,
+    pub skill_cast_log: HashMap<u64, HashMap<u32, BTreeMap<i64, SkillCast>>>,
+    pub boss_casts: Vec<BossCast>,
+    pub stagger_checks: Vec<StaggerCheck>,
+    pub session_id: Option<i64>,
+    pub dungeon: Option<DungeonRun>,
+    pub clock_gaps: Vec<ClockGap>,
+    pub death_causes: HashMap<String, i64>,
+    pub client_index: Option<usize>,
+    pub prepull_damage: Option<i64>,
+}
+
+pub fn insert_data(tx: &Transaction, save: EncounterSave) -> i64 {
+    let EncounterSave {
+        mut encounter,
+        prev_stagger,
+        damage_log,
+        identity_log,
+        cast_log,
+        mut boss_hp_log,
+        stagger_log,
+        mut stagger_intervals,
+        raid_clear,
+        party_info,
+        raid_difficulty,
+        region,
+        player_info,
+        meter_version,
+        ntp_fight_start,
+        rdps_valid,
+        manual,
+        skill_cast_log,
+        boss_casts,
+        stagger_checks,
+        session_id,
+        dungeon,
+        clock_gaps,
+        death_causes,
+        client_index,
+        prepull_damage,
+    } = save;
+    // a manual save followed by the natural fight end stores the same fight
+    // twice, the second save overwrites the first and keeps its id and favorite
+    let existing_id: Option<i64> = tx
+        .prepare_cached(
+            "SELECT id FROM encounter_preview
+            WHERE fight_start = ? AND local_player = ?
+            ORDER BY id DESC LIMIT 1",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_row(
+                params![encounter.fight_start, encounter.local_player],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+        .expect("failed to look up previous save");
 
-#[allow(clippy::too_many_arguments)]
-pub fn insert_data(
-    tx: &Transaction,
-    mut encounter: Encounter,
-    prev_stagger: i32,
-    damage_log: HashMap<String, Vec<(i64, i64)>>,
-    identity_log: HashMap<String, IdentityLog>,
-    cast_log: HashMap<String, HashMap<u32, Vec<i32>>>,
-    boss_hp_log: HashMap<String, Vec<BossHpLog>>,
-    stagger_log: Vec<(i32, f32)>,
-    mut stagger_intervals: Vec<(i32, i32)>,
-    raid_clear: bool,
-    party_info: Vec<Vec<String>>,
-    raid_difficulty: String,
-    region: Option<String>,
-    player_info: Option<HashMap<String, PlayerStats>>,
-    meter_version: String,
-    ntp_fight_start: i64,
-    rdps_valid: bool,
-    manual: bool,
-    skill_cast_log: HashMap<u64, HashMap<u32, BTreeMap<i64, SkillCast>>>,
-) -> i64 {
     let mut encounter_stmt = tx
         .prepare_cached(
             "
     INSERT INTO encounter (
+        id,
         last_combat_packet,
         total_damage_dealt,
         top_damage_dealt,
@@ -849,15 +860,101 @@ pub fn insert_data(
         misc,
         version,
         boss_hp_log,
-        stagger_log
-    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        stagger_log,
+        total_damage_absorbed,
+        total_damage_taken_unmitigated
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+    ON CONFLICT(id) DO UPDATE SET
+        last_combat_packet = excluded.last_combat_packet,
+        total_damage_dealt = excluded.total_damage_dealt,
+        top_damage_dealt = excluded.top_damage_dealt,
+        total_damage_taken = excluded.total_damage_taken,
+        top_damage_taken = excluded.top_damage_taken,
+        dps = excluded.dps,
+        buffs = excluded.buffs,
+        debuffs = excluded.debuffs,
+        total_shielding = excluded.total_shielding,
+        total_effective_shielding = excluded.total_effective_shielding,
+        applied_shield_buffs = excluded.applied_shield_buffs,
+        misc = excluded.misc,
+        version = excluded.version,
+        boss_hp_log = excluded.boss_hp_log,
+        stagger_log = excluded.stagger_log,
+        total_damage_absorbed = excluded.total_damage_absorbed,
+        total_damage_taken_unmitigated = excluded.total_damage_taken_unmitigated",
         )
         .expect("failed to prepare encounter statement");
 
-    encounter.duration = encounter.last_combat_packet - encounter.fight_start;
-    let duration_seconds = max(encounter.duration / 1000, 1);
-    encounter.encounter_damage_stats.dps =
-        encounter.encounter_damage_stats.total_damage_dealt / duration_seconds;
+    let localized_boss_name = normalize_boss_name(&mut encounter, &mut boss_hp_log);
+    let is_dungeon = dungeon.is_some();
+
+    // a clock change mid fight can put the last packet before the start
+    encounter.duration = max(encounter.last_combat_packet - encounter.fight_start, 0);
+    encounter.encounter_damage_stats.dps = calculate_dps(
+        encounter.encounter_damage_stats.total_damage_dealt,
+        encounter.duration,
+    );
+
+    let mut bosses = encounter
+        .entities
+        .values()
+        .filter(|e| e.entity_type == EntityType::BOSS)
+        .map(|e| e.npc_id)
+        .collect::<Vec<_>>();
+    bosses.sort_unstable();
+    bosses.dedup();
+    // an npc can turn into a boss after it was first hit, so the split
+    // counted while fighting is only an estimate
+    for entity in encounter.entities.values_mut() {
+        if entity.damage_by_npc.is_empty() {
+            continue;
+        }
+        entity.damage_stats.damage_to_boss = 0;
+        entity.damage_stats.damage_to_adds = 0;
+        for (npc_id, damage) in entity.damage_by_npc.iter() {
+            if bosses.binary_search(npc_id).is_ok() {
+                entity.damage_stats.damage_to_boss += damage;
+            } else {
+                entity.damage_stats.damage_to_adds += damage;
+            }
+        }
+    }
+
+    let local_identity = encounter
+        .entities
+        .get(&encounter.local_player)
+        .zip(identity_log.get(&encounter.local_player))
+        .and_then(|(entity, log)| {
+            let log = downsample_identity_log(log, encounter.fight_start);
+            // classes without a meaningful identity resource never move the gauge
+            if log.windows(2).all(|w| w[0].1 == w[1].1) {
+                None
+            } else {
+                Some(LocalIdentity {
+                    class_id: entity.class_id,
+                    log,
+                })
+            }
+        });
+
+    let local_rank = compute_local_rank(&encounter);
+    let highlights = compute_highlights(&encounter);
+    let hp_splits = boss_hp_log
+        .get(&encounter.current_boss_name)
+        .map(|log| compute_hp_splits(log))
+        .unwrap_or_default();
+    let hp_segments: HashMap<String, Vec<i64>> = boss_hp_log
+        .get(&encounter.current_boss_name)
+        .map(|log| {
+            damage_log
+                .iter()
+                .filter_map(|(name, damage)| {
+                    let segments = damage_by_hp_segment(damage, log, encounter.fight_start)?;
+                    Some((name.clone(), segments))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
 
     let misc: EncounterMisc = EncounterMisc {
         raid_clear: if raid_clear { Some(true) } else { None },
@@ -872,7 +969,7 @@ pub fn insert_data(
                     .collect(),
             )
         },
-        region,
+        region: region.clone(),
         version: Some(meter_version),
         rdps_valid: Some(rdps_valid),
         rdps_message: if rdps_valid {
@@ -882,6 +979,47 @@ pub fn insert_data(
         },
         ntp_fight_start: Some(ntp_fight_start),
         manual_save: Some(manual),
+        boss_casts: if boss_casts.is_empty() {
+            None
+        } else {
+            Some(boss_casts)
+        },
+        bosses: if bosses.is_empty() { None } else { Some(bosses) },
+        local_identity,
+        local_rank: local_rank.clone(),
+        highlights: if highlights.is_empty() {
+            None
+        } else {
+            Some(highlights)
+        },
+        stagger_checks: if stagger_checks.is_empty() {
+            None
+        } else {
+            Some(stagger_checks)
+        },
+        hp_splits: if hp_splits.is_empty() {
+            None
+        } else {
+            Some(hp_splits)
+        },
+        session_id,
+        localized_boss_name,
+        dungeon,
+        clock_gaps: if clock_gaps.is_empty() {
+            None
+        } else {
+            Some(clock_gaps)
+        },
+        top_killer: top_killer(death_causes),
+        client_index,
+        prepull_damage,
+        // set when an exported encounter from a newer version is imported
+        extra: encounter
+            .encounter_damage_stats
+            .misc
+            .as_ref()
+            .map(|misc| misc.extra.clone())
+            .unwrap_or_default(),
         ..Default::default()
     };
 
@@ -889,9 +1027,9 @@ pub fn insert_data(
     if !stagger_log.is_empty() {
         if prev_stagger > 0 && prev_stagger != encounter.encounter_damage_stats.max_stagger {
             // never finished staggering the boss, calculate average from whatever stagger has been done
-            let stagger_start_s = ((encounter.encounter_damage_stats.stagger_start
-                - encounter.fight_start)
-                / 1000) as i32;
+            let stagger_start_s = saturating_i32(
+                (encounter.encounter_damage_stats.stagger_start - encounter.fight_start) / 1000,
+            );
             let stagger_duration = stagger_log.last().unwrap().0 - stagger_start_s;
             if stagger_duration > 0 {
                 stagger_intervals.push((stagger_duration, prev_stagger));
@@ -925,6 +1063,7 @@ pub fn insert_data(
 
     encounter_stmt
         .execute(params![
+            existing_id,
             encounter.last_combat_packet,
             encounter.encounter_damage_stats.total_damage_dealt,
             encounter.encounter_damage_stats.top_damage_dealt,
@@ -940,10 +1079,22 @@ pub fn insert_data(
             DB_VERSION,
             compressed_boss_hp,
             json!(stagger_stats),
+            encounter.encounter_damage_stats.total_damage_absorbed,
+            encounter
+                .encounter_damage_stats
+                .total_damage_taken_unmitigated,
         ])
         .expect("failed to insert encounter");
 
-    let last_insert_id = tx.last_insert_rowid();
+    let last_insert_id = match existing_id {
+        Some(id) => {
+            tx.prepare_cached("DELETE FROM entity WHERE encounter_id = ?")
+                .and_then(|mut stmt| stmt.execute([id]))
+                .expect("failed to clear previous entities");
+            id
+        }
+        None => tx.last_insert_rowid(),
+    };
 
     let mut entity_stmt = tx
         .prepare_cached(
@@ -968,13 +1119,18 @@ pub fn insert_data(
         gear_hash,
         ark_passive_active,
         spec,
-        ark_passive_data
-    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+        ark_passive_data,
+        entity_index
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
         )
         .expect("failed to prepare entity statement");
 
     let fight_start = encounter.fight_start;
     let fight_end = encounter.last_combat_packet;
+    // entities sharing a name are stored with increasing indexes
+    let mut name_counts: HashMap<String, i32> = HashMap::new();
+    let mut skills_compressor = JsonCompressor::default();
+    let mut damage_stats_compressor = JsonCompressor::default();
 
     for (_key, entity) in encounter.entities.iter_mut().filter(|(_, e)| {
         ((e.entity_type == EntityType::PLAYER && e.class_id != 0 && e.max_hp > 0)
@@ -1003,6 +1159,7 @@ pub fn insert_data(
                 entity.damage_stats.dps_average =
                     calculate_average_dps(damage_log, fight_start_sec, fight_end_sec);
             }
+            entity.damage_stats.damage_by_hp_segment = hp_segments.get(&entity.name).cloned();
 
             entity.spec = Some(get_player_spec(
                 entity,
@@ -1064,11 +1221,17 @@ pub fn insert_data(
                 }
             }
 
-            entity.damage_stats.dps = entity.damage_stats.damage_dealt / duration_seconds;
+            entity.damage_stats.dps =
+                calculate_dps(entity.damage_stats.damage_dealt, encounter.duration);
+            entity.damage_stats.dps_alive = calculate_dps_alive(
+                entity,
+                encounter.fight_start,
+                encounter.last_combat_packet,
+            );
         }
 
         for (_, skill) in entity.skills.iter_mut() {
-            skill.dps = skill.total_damage / duration_seconds;
+            skill.dps = calculate_dps(skill.total_damage, encounter.duration);
         }
 
         for (_, cast_log) in cast_log.iter().filter(|&(s, _)| *s == entity.name) {
@@ -1144,7 +1307,7 @@ pub fn insert_data(
                                 total_identity_gain += curr.0 - prev.0;
                             }
 
-                            let relative_time = ((t2 - fight_start) as f32 / 1000.0) as i32;
+                            let relative_time = saturating_i32((t2 - fight_start) / 1000);
                             // calculate percentage, round to 2 decimal places
                             let percentage = if curr.0 >= max as u32 {
                                 100.0
@@ -1180,7 +1343,7 @@ pub fn insert_data(
                                 total_identity_gain += i2.0 - i1.0;
                             }
 
-                            let relative_time = ((t2 - fight_start) as f32 / 1000.0) as i32;
+                            let relative_time = saturating_i32((t2 - fight_start) / 1000);
                             // since bard and artist have 3 bubbles, i.1 is the number of bubbles
                             // we scale percentage to 3 bubbles
                             // current bubble + max * number of bubbles
@@ -1214,7 +1377,7 @@ pub fn insert_data(
                                 total_identity_gain += i2.0 - i1.0;
                             }
 
-                            let relative_time = ((t2 - fight_start) as f32 / 1000.0) as i32;
+                            let relative_time = saturating_i32((t2 - fight_start) / 1000);
                             let percentage =
                                 (((i2.0 as f32 / max) * 100.0) * 100.0).round() / 100.0;
                             log.push((relative_time, percentage));
@@ -1235,8 +1398,19 @@ pub fn insert_data(
             }
         }
 
-        let compressed_skills = compress_json(&entity.skills);
-        let compressed_damage_stats = compress_json(&entity.damage_stats);
+        if entity.name == encounter.local_player {
+            if let Some(log) = cast_log.get(&entity.name) {
+                entity.skill_stats.cooldown_efficiency =
+                    cooldown_efficiency(log, encounter.duration);
+            }
+        }
+
+        let compressed_skills = skills_compressor.compress(&entity.skills);
+        let compressed_damage_stats = damage_stats_compressor.compress(&entity.damage_stats);
+
+        let name_count = name_counts.entry(entity.name.clone()).or_insert(0);
+        let entity_index = *name_count;
+        *name_count += 1;
 
         entity_stmt
             .execute(params![
@@ -1259,31 +1433,13 @@ pub fn insert_data(
                 entity.gear_hash,
                 entity.ark_passive_active,
                 entity.spec,
-                json!(entity.ark_passive_data)
+                json!(entity.ark_passive_data),
+                entity_index
             ])
             .expect("failed to insert entity");
     }
 
-    let mut players = encounter
-        .entities
-        .values()
-        .filter(|e| {
-            ((e.entity_type == EntityType::PLAYER && e.class_id != 0 && e.max_hp > 0)
-                || e.name == encounter.local_player)
-                && e.damage_stats.damage_dealt > 0
-        })
-        .collect::<Vec<_>>();
-    let local_player_dps = players
-        .iter()
-        .find(|e| e.name == encounter.local_player)
-        .map(|e| e.damage_stats.dps)
-        .unwrap_or_default();
-    players.sort_unstable_by_key(|e| Reverse(e.damage_stats.damage_dealt));
-    let preview_players = players
-        .into_iter()
-        .map(|e| format!("{}:{}", e.class_id, e.name))
-        .collect::<Vec<_>>()
-        .join(",");
+    let (preview_players, local_player_dps, local_player_dps_alive) = preview_players(&encounter);
 
     let mut encounter_preview_stmt = tx
         .prepare_cached(
@@ -1298,8 +1454,30 @@ pub fn insert_data(
         local_player,
         my_dps,
         cleared,
-        boss_only_damage
-    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        boss_only_damage,
+        players_version,
+        my_dps_alive,
+        region,
+        local_rank,
+        party_size,
+        dungeon
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+    ON CONFLICT(id) DO UPDATE SET
+        fight_start = excluded.fight_start,
+        current_boss = excluded.current_boss,
+        duration = excluded.duration,
+        players = excluded.players,
+        difficulty = excluded.difficulty,
+        local_player = excluded.local_player,
+        my_dps = excluded.my_dps,
+        cleared = excluded.cleared,
+        boss_only_damage = excluded.boss_only_damage,
+        players_version = excluded.players_version,
+        my_dps_alive = excluded.my_dps_alive,
+        region = excluded.region,
+        local_rank = excluded.local_rank,
+        party_size = excluded.party_size,
+        dungeon = excluded.dungeon",
         )
         .expect("failed to prepare encounter preview statement");
     encounter_preview_stmt
@@ -1313,13 +1491,367 @@ pub fn insert_data(
             encounter.local_player,
             local_player_dps,
             raid_clear,
-            encounter.boss_only_damage
+            encounter.boss_only_damage,
+            PREVIEW_PLAYERS_VERSION,
+            local_player_dps_alive,
+            region,
+            local_rank.as_ref().map(|r| r.rank),
+            local_rank.as_ref().map(|r| r.party_size),
+            is_dungeon
         ])
         .expect("failed to insert encounter preview");
 
     last_insert_id
 }
 
+// players column of the preview, class:name:dps by damage dealt, along with
+// the local player's dps and dps while alive
+pub fn preview_players(encounter: &Encounter) -> (String, i64, i64) {
+    let mut players = encounter
+        .entities
+        .values()
+        .filter(|e| {
+            ((e.entity_type == EntityType::PLAYER && e.class_id != 0 && e.max_hp > 0)
+                || e.name == encounter.local_player)
+                && e.damage_stats.damage_dealt > 0
+        })
+        .collect::<Vec<_>>();
+    let (local_player_dps, local_player_dps_alive) = players
+        .iter()
+        .find(|e| e.name == encounter.local_player)
+        .map(|e| (e.damage_stats.dps, e.damage_stats.dps_alive))
+        .unwrap_or_default();
+    players.sort_unstable_by_key(|e| Reverse(e.damage_stats.damage_dealt));
+    let players = players
+        .into_iter()
+        .map(|e| format!("{}:{}:{}", e.class_id, e.name, e.damage_stats.dps))
+        .collect::<Vec<_>>()
+        .join(",");
+    (players, local_player_dps, local_player_dps_alive)
+}
+
+// local player's damage rank among players, with the gap to their neighbours
+pub fn compute_local_rank(encounter: &Encounter) -> Option<LocalRank> {
+    let mut players = encounter
+        .entities
+        .values()
+        .filter(|e| {
+            ((e.entity_type == EntityType::PLAYER && e.class_id != 0)
+                || e.name == encounter.local_player)
+                && e.damage_stats.damage_dealt > 0
+        })
+        .map(|e| (e.name.as_str(), e.damage_stats.damage_dealt))
+        .collect::<Vec<_>>();
+    players.sort_unstable_by_key(|(_, damage)| Reverse(*damage));
+
+    let index = players
+        .iter()
+        .position(|(name, _)| *name == encounter.local_player)?;
+    let damage = players[index].1;
+    let gap_percent = |gap: i64| (gap as f64 / damage as f64 * 100.0 * 100.0).round() / 100.0;
+    let gap_ahead = index
+        .checked_sub(1)
+        .map(|ahead| players[ahead].1 - damage);
+    let gap_behind = players.get(index + 1).map(|behind| damage - behind.1);
+
+    Some(LocalRank {
+        rank: index as i32 + 1,
+        party_size: players.len() as i32,
+        gap_ahead,
+        gap_ahead_percent: gap_ahead.map(gap_percent),
+        gap_behind,
+        gap_behind_percent: gap_behind.map(gap_percent),
+    })
+}
+
+// favorites a cleared run that beats the local player's best dps on the same
+// boss and difficulty. wipes and runs under min_duration_ms never count, and
+// a first clear has nothing to beat. nothing is ever unfavorited
+pub fn favorite_if_personal_best(
+    tx: &Transaction,
+    encounter_id: i64,
+    min_duration_ms: i64,
+) -> Option<EncounterPb> {
+    let (boss_name, difficulty, local_player, duration, cleared, new_dps) = tx
+        .query_row(
+            "SELECT current_boss, difficulty, local_player, duration, cleared, my_dps
+            FROM encounter_preview WHERE id = ?",
+            [encounter_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<bool>>(4)?.unwrap_or_default(),
+                    row.get::<_, Option<i64>>(5)?.unwrap_or_default(),
+                ))
+            },
+        )
+        .map_err(|e| warn!("pb check failed for {}: {}", encounter_id, e))
+        .ok()?;
+    if !cleared || duration < min_duration_ms || difficulty.is_empty() {
+        return None;
+    }
+
+    let old_dps: Option<i64> = tx
+        .query_row(
+            "SELECT MAX(en.dps)
+            FROM encounter_preview ep
+            JOIN entity en ON en.encounter_id = ep.id AND en.name = ep.local_player
+            WHERE ep.current_boss = ? AND ep.difficulty = ? AND ep.local_player = ?
+                AND ep.cleared = 1 AND ep.duration >= ? AND ep.id <> ?",
+            params![
+                boss_name,
+                difficulty,
+                local_player,
+                min_duration_ms,
+                encounter_id
+            ],
+            |row| row.get(0),
+        )
+        .map_err(|e| warn!("could not read previous best: {}", e))
+        .ok()?;
+    let old_dps = old_dps?;
+    if new_dps <= old_dps {
+        return None;
+    }
+
+    tx.execute(
+        "UPDATE encounter_preview SET favorite = 1 WHERE id = ?",
+        [encounter_id],
+    )
+    .map_err(|e| warn!("could not favorite encounter {}: {}", encounter_id, e))
+    .ok()?;
+    info!(
+        "new personal best on {} [{}]: {} -> {} dps",
+        boss_name,
+        difficulty,
+        number_format::format(old_dps),
+        number_format::format(new_dps)
+    );
+
+    Some(EncounterPb {
+        id: encounter_id,
+        boss_name,
+        difficulty,
+        old_dps,
+        new_dps,
+    })
+}
+
+// best player per award, awards where every player is at zero are left out.
+// ties go to the player that sorts first by name so re-saving is stable.
+// entity dps isn't filled in yet when misc is built, so it's derived here
+pub fn compute_highlights(encounter: &Encounter) -> Vec<(String, String, f64)> {
+    let duration_seconds = max(encounter.duration / 1000, 1) as f64;
+    let dps = |e: &EncounterEntity| e.damage_stats.damage_dealt as f64 / duration_seconds;
+    let players = encounter
+        .entities
+        .values()
+        .filter(|e| {
+            (e.entity_type == EntityType::PLAYER && e.class_id != 0)
+                || e.name == encounter.local_player
+        })
+        .collect::<Vec<_>>();
+
+    let crit_rate = |e: &EncounterEntity| {
+        if e.skill_stats.hits > 0 {
+            e.skill_stats.crits as f64 / e.skill_stats.hits as f64
+        } else {
+            0.0
+        }
+    };
+    let awards: [(&str, &dyn Fn(&EncounterEntity) -> f64); 5] = [
+        ("highestDps", &dps),
+        ("mostDamageTaken", &|e| e.damage_stats.damage_taken as f64),
+        ("mostDeaths", &|e| e.damage_stats.deaths as f64),
+        ("highestCritRate", &crit_rate),
+        ("mostCounters", &|e| e.skill_stats.counters as f64),
+    ];
+
+    awards
+        .iter()
+        .filter_map(|(award, stat)| {
+            players
+                .iter()
+                .map(|e| (e.name.as_str(), stat(e)))
+                .filter(|(_, value)| value.is_finite() && *value > 0.0)
+                .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(name, value)| (award.to_string(), name.to_string(), value))
+        })
+        .collect()
+}
+
+// time the boss first reached each 10% step, steps it never reached are left out
+pub fn compute_hp_splits(log: &[BossHpLog]) -> Vec<HpSplit> {
+    // the boss max hp wasn't known yet, every entry would read as 0%
+    if log.first().map_or(true, |entry| entry.p <= 0.0) {
+        return Vec::new();
+    }
+    let mut splits = Vec::new();
+    let mut percent = 90;
+    for entry in log {
+        while percent >= 0 && entry.p * 100.0 <= percent as f32 {
+            splits.push(HpSplit {
+                percent,
+                time: entry.time,
+            });
+            percent -= 10;
+        }
+    }
+    splits
+}
+
+// buckets damage by the boss hp decile it was dealt at, the hp log is sampled
+// so each hit takes the latest entry before it. none without a usable log
+pub fn damage_by_hp_segment(
+    damage_log: &[(i64, i64)],
+    hp_log: &[BossHpLog],
+    fight_start: i64,
+) -> Option<Vec<i64>> {
+    // same as compute_hp_splits, without a max hp every entry reads as 0%
+    if hp_log.first().map_or(true, |entry| entry.p <= 0.0) {
+        return None;
+    }
+    let mut segments = vec![0; 10];
+    for &(timestamp, damage) in damage_log {
+        let time = saturating_i32((timestamp - fight_start) / 1000);
+        let index = hp_log
+            .partition_point(|entry| entry.time <= time)
+            .saturating_sub(1);
+        let lost = (1.0 - hp_log[index].p.clamp(0.0, 1.0)) * 10.0;
+        segments[(lost as usize).min(9)] += damage;
+    }
+    Some(segments)
+}
+
+// keeps the last gauge value of every second
+pub fn downsample_identity_log(
+    log: &IdentityLog,
+    fight_start: i64,
+) -> Vec<(i32, (u32, u32, u32))> {
+    let mut samples: Vec<(i32, (u32, u32, u32))> = Vec::new();
+    for (timestamp, gauge) in log {
+        let time = saturating_i32(max(timestamp - fight_start, 0));
+        match samples.last_mut() {
+            Some(last) if last.0 / 1000 == time / 1000 => *last = (time, *gauge),
+            _ => samples.push((time, *gauge)),
+        }
+    }
+    samples
+}
+
+// english name from the bundled npc data, the same one the parser uses for
+// bosses. names with placeholders or other scripts are never canonical
+pub fn canonical_boss_name(npc_id: u32) -> Option<&'static str> {
+    let name = NPC_DATA.get(&npc_id)?.name.as_deref()?;
+    if name.is_empty() || name.contains('_') || !name.is_ascii() {
+        return None;
+    }
+    Some(name)
+}
+
+// renames a boss recorded under a localized name, e.g. by a client in
+// another language, to its english name and returns the original.
+// the entity and its hp log are moved along so they still match
+pub fn normalize_boss_name(
+    encounter: &mut Encounter,
+    boss_hp_log: &mut HashMap<String, Vec<BossHpLog>>,
+) -> Option<String> {
+    let boss = encounter.entities.get(&encounter.current_boss_name)?;
+    let canonical = canonical_boss_name(boss.npc_id)?;
+    if canonical == encounter.current_boss_name || encounter.entities.contains_key(canonical) {
+        return None;
+    }
+
+    let localized = std::mem::replace(&mut encounter.current_boss_name, canonical.to_string());
+    if let Some(mut boss) = encounter.entities.remove(&localized) {
+        boss.name = canonical.to_string();
+        encounter.entities.insert(canonical.to_string(), boss);
+    }
+    if let Some(log) = boss_hp_log.remove(&localized) {
+        boss_hp_log.insert(canonical.to_string(), log);
+    }
+    if let Some(boss) = encounter.current_boss.as_mut() {
+        boss.name = canonical.to_string();
+    }
+    Some(localized)
+}
+
+// skill id -> casts as a percent of the casts that fit between the first one
+// and the fight end. the client doesn't send cooldowns, the shortest gap
+// between two casts stands in for it. skills with too few casts are null
+pub fn cooldown_efficiency(
+    cast_log: &HashMap<u32, Vec<i32>>,
+    duration: i64,
+) -> HashMap<u32, Option<f64>> {
+    cast_log
+        .iter()
+        .map(|(&skill_id, casts)| (skill_id, skill_efficiency(casts, duration)))
+        .collect()
+}
+
+fn skill_efficiency(casts: &[i32], duration: i64) -> Option<f64> {
+    if casts.len() < MIN_EFFICIENCY_CASTS {
+        return None;
+    }
+    let min_interval = casts
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]) as i64)
+        .filter(|&gap| gap > 0)
+        .min()?;
+    let possible = 1 + (duration - casts[0] as i64).max(0) / min_interval;
+    let efficiency = (casts.len() as f64 / possible as f64 * 100.0).min(100.0);
+    Some((efficiency * 10.0).round() / 10.0)
+}
+
+pub fn top_killer(causes: HashMap<String, i64>) -> Option<TopKiller> {
+    if causes.is_empty() {
+        return None;
+    }
+    let (skill, deaths) = causes
+        .iter()
+        .filter(|(_, &deaths)| deaths >= MIN_KILLER_DEATHS)
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map_or((None, 0), |(skill, &deaths)| (Some(skill.clone()), deaths));
+    Some(TopKiller {
+        skill,
+        deaths,
+        causes,
+    })
+}
+
+// offsets stored as i32 wrap around after 24 days of ms, a meter left
+// running that long keeps the last representable value instead
+pub fn saturating_i32(value: i64) -> i32 {
+    value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+// no fight gets anywhere near this, a higher dps comes from broken damage values
+const MAX_DPS: i64 = 100_000_000_000;
+
+// fights shorter than a second count as one second, so a single hit doesn't
+// divide by zero
+pub fn calculate_dps(damage: i64, duration_ms: i64) -> i64 {
+    let seconds = max(duration_ms / 1000, 1);
+    (damage / seconds).clamp(0, MAX_DPS)
+}
+
+// dps over the time the entity was alive, an entity that is still dead
+// at the end of the fight is counted as dead until the last combat packet
+pub fn calculate_dps_alive(entity: &EncounterEntity, fight_start: i64, fight_end: i64) -> i64 {
+    let duration = max(fight_end - fight_start, 0);
+    let mut dead_for = entity.damage_stats.dead_for;
+    if entity.is_dead && entity.damage_stats.death_time > 0 {
+        dead_for += max(fight_end - entity.damage_stats.death_time, 0);
+    }
+    calculate_dps(
+        entity.damage_stats.damage_dealt,
+        duration - dead_for.clamp(0, duration),
+    )
+}
+
 pub fn map_status_effect(se: &StatusEffectDetails, custom_id_map: &mut HashMap<u32, u32>) -> u32 {
     if se.custom_id > 0 {
         custom_id_map.insert(se.custom_id, se.status_effect_id);
@@ -1361,6 +1893,28 @@ where
     e.finish().expect("unable to compress json")
 }
 
+// compress_json with an output buffer that is kept between calls, the json is
+// written straight into the encoder. saving a big raid otherwise allocates and
+// drops a json string and a gzip buffer for every entity column, which shows
+// up as a hitch at fight end
+#[derive(Default)]
+pub struct JsonCompressor {
+    compressed: Vec<u8>,
+}
+
+impl JsonCompressor {
+    pub fn compress<T>(&mut self, value: &T) -> &[u8]
+    where
+        T: ?Sized + Serialize,
+    {
+        self.compressed.clear();
+        let mut e = GzEncoder::new(&mut self.compressed, Compression::default());
+        serde_json::to_writer(&mut e, value).expect("unable to serialize json");
+        e.finish().expect("unable to compress json");
+        &self.compressed
+    }
+}
+
 pub fn update_current_boss_name(boss_name: &str) -> String {
     match boss_name {
         "Chaos Lightning Dragon Jade" => "Argeos",
@@ -1655,3 +2209,92 @@ fn get_spec_from_ark_passive(node: &ArkPassiveNode) -> String {
     }
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::io::Read;
+
+    const DAY_MS: i64 = 24 * 3600 * 1000;
+
+    // counts allocations per thread, so tests running in parallel don't
+    // show up in each other's numbers
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.try_with(|n| n.set(n.get() + 1)).ok();
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations_in(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(|n| n.get());
+        f();
+        ALLOCATIONS.with(|n| n.get()) - before
+    }
+
+    #[test]
+    fn compressor_allocations_dont_grow_with_the_value() {
+        let small: Vec<i64> = (0..10).collect();
+        let large: Vec<i64> = (0..100_000).collect();
+        let mut compressor = JsonCompressor::default();
+        compressor.compress(&large);
+
+        let for_large = allocations_in(|| {
+            compressor.compress(&large);
+        });
+        let for_small = allocations_in(|| {
+            compressor.compress(&small);
+        });
+        assert_eq!(for_large, for_small);
+
+        let mut json = String::new();
+        GzDecoder::new(compressor.compress(&large))
+            .read_to_string(&mut json)
+            .unwrap();
+        assert_eq!(json, serde_json::to_string(&large).unwrap());
+    }
+
+    #[test]
+    fn offsets_saturate_instead_of_wrapping() {
+        assert_eq!(saturating_i32(0), 0);
+        assert_eq!(saturating_i32(DAY_MS), 86_400_000);
+        // 25 days of ms don't fit in an i32
+        assert_eq!(saturating_i32(25 * DAY_MS), i32::MAX);
+        assert_eq!(saturating_i32(-25 * DAY_MS), i32::MIN);
+    }
+
+    #[test]
+    fn zero_duration_counts_as_a_second() {
+        assert_eq!(calculate_dps(1_000_000, 0), 1_000_000);
+        assert_eq!(calculate_dps(1_000_000, 999), 1_000_000);
+        assert_eq!(calculate_dps(1_000_000, -5_000), 1_000_000);
+    }
+
+    #[test]
+    fn day_long_fight() {
+        assert_eq!(calculate_dps(86_400_000_000, DAY_MS), 1_000_000);
+        assert_eq!(calculate_dps(1_000_000, DAY_MS), 11);
+    }
+
+    #[test]
+    fn absurd_dps_is_clamped() {
+        assert_eq!(calculate_dps(i64::MAX, 0), MAX_DPS);
+        assert_eq!(calculate_dps(-1_000_000, 60_000), 0);
+    }
+}
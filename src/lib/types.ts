@@ -8,6 +8,50 @@ export interface PartyEvent {
     payload?: PartyInfo;
 }
 
+export interface LiveUpdate {
+    seq: number;
+    encounter: Encounter;
+    party?: PartyInfo;
+    invalidDamage: boolean;
+    session?: SessionStats;
+}
+
+// saved pulls since the app started or reset_session_stats, times are ms
+export interface SessionStats {
+    started: number;
+    pulls: number;
+    totalDamage: number;
+    combatTime: number;
+    players: Array<SessionPlayerStats>;
+}
+
+export interface SessionPlayerStats {
+    name: string;
+    classId: number;
+    pulls: number;
+    damage: number;
+    combatTime: number;
+    averageDamage: number;
+    dps: number;
+}
+
+export interface DpsSample {
+    time: number;
+    partyDamage: number;
+    localDamage: number;
+    bossHpPercent: number;
+}
+
+export interface DpsSeriesUpdate {
+    fightStart: number;
+    samples: Array<DpsSample>;
+}
+
+export interface LiveUpdateEvent {
+    event: string;
+    payload: LiveUpdate;
+}
+
 export interface Encounter {
     lastCombatPacket: number;
     fightStart: number;
@@ -23,11 +67,26 @@ export interface Encounter {
     cleared: boolean;
     bossOnlyDamage: boolean;
     sync?: string;
+    localRank?: LocalRank;
+    // skill details were left out of a very large log
+    truncated?: boolean;
+    truncatedEntities?: string[];
+}
+
+export interface LocalRank {
+    rank: number;
+    partySize: number;
+    gapAhead?: number;
+    gapAheadPercent?: number;
+    gapBehind?: number;
+    gapBehindPercent?: number;
 }
 
 export interface EncountersOverview {
     encounters: Array<EncounterPreview>;
     totalEncounters: number;
+    page: number;
+    pageSize: number;
 }
 
 export interface EncounterPreview {
@@ -37,11 +96,22 @@ export interface EncounterPreview {
     duration: number;
     classes: Array<number>;
     names: Array<string>;
+    playersDetail: Array<PreviewPlayer>;
     difficulty?: string;
     localPlayer: string;
     myDps: number;
+    myDpsAlive: number;
+    localRank?: number;
+    partySize?: number;
     favorite: boolean;
     cleared: boolean;
+    dungeon: boolean;
+}
+
+export interface PreviewPlayer {
+    name: string;
+    classId: number;
+    dps: number;
 }
 
 export interface EncounterDamageStats {
@@ -55,6 +125,8 @@ export interface EncounterDamageStats {
     debuffs: { [key: number]: StatusEffect };
     totalShielding: number;
     totalEffectiveShielding: number;
+    totalDamageAbsorbed?: number;
+    totalDamageTakenUnmitigated?: number;
     appliedShieldBuffs: { [key: number]: StatusEffect };
     misc?: EncounterMisc;
     bossHpLog: { [key: string]: Array<BossHpLog> };
@@ -67,6 +139,98 @@ export interface EncounterMisc {
     partyInfo?: PartyInfo;
     rdpsValid?: boolean;
     rdpsMessage?: string;
+    bossCasts?: Array<BossCast>;
+    bosses?: Array<number>;
+    localIdentity?: LocalIdentity;
+    staggerChecks?: Array<StaggerCheck>;
+    localRank?: LocalRank;
+    // [award, player, value]
+    highlights?: Array<[string, string, number]>;
+    hpSplits?: Array<HpSplit>;
+    sessionId?: number;
+    localizedBossName?: string;
+    dungeon?: DungeonRun;
+    clockGaps?: Array<ClockGap>;
+    topKiller?: TopKiller;
+    clientIndex?: number;
+    liveDump?: boolean;
+    prepullDamage?: number;
+}
+
+// causes maps "source: skill" to the players it killed, skill is only set
+// once the top one killed more than one player
+export interface TopKiller {
+    skill?: string;
+    deaths: number;
+    causes: { [key: string]: number };
+}
+
+// summed over wipes by get_death_causes
+export interface DeathCause {
+    skill: string;
+    deaths: number;
+    encounters: number;
+}
+
+// no packets from start to end, split when the encounter ended there
+export interface ClockGap {
+    start: number;
+    end: number;
+    split: boolean;
+}
+
+export interface DungeonRun {
+    name: string;
+    stages: Array<DungeonStage>;
+}
+
+// start and end are timestamps, zero for a stage without combat
+export interface DungeonStage {
+    zoneId: number;
+    start: number;
+    end: number;
+    damage: number;
+}
+
+// time is seconds since fight start
+export interface HpSplit {
+    percent: number;
+    time: number;
+}
+
+export interface BestSplit {
+    percent: number;
+    time: number;
+    encounterId: number;
+    bestSegment: number;
+}
+
+export interface StaggerCheck {
+    start: number;
+    end: number;
+    skillId: number;
+    skillName: string;
+    staggerCheck: boolean;
+    stagger: { [key: string]: number };
+    counters: { [key: string]: number };
+}
+
+export interface LocalIdentity {
+    classId: number;
+    log: Array<[number, [number, number, number]]>;
+}
+
+export interface IdentityUpdate {
+    time: number;
+    gauge1: number;
+    gauge2: number;
+    gauge3: number;
+}
+
+export interface BossCast {
+    offsetMs: number;
+    skillId: number;
+    skillName: string;
 }
 
 export interface PartyInfo {
@@ -142,6 +306,7 @@ export interface Skill {
     buffedByIdentity: number;
     buffedByHat: number;
     debuffedBySupport: number;
+    synergyBuffedBy?: { [key: number]: number };
     casts: number;
     hits: number;
     crits: number;
@@ -163,6 +328,17 @@ export interface Skill {
     rdpsDamageReceivedSupport: number;
     rdpsDamageGiven: number;
     skillCastLog: Array<SkillCast>;
+    maxHit?: MaxHit;
+    avgHit: number;
+    avgCritHit: number;
+}
+
+export interface MaxHit {
+    damage: number;
+    timestamp: number;
+    crit: boolean;
+    synergies: Array<number>;
+    personalRecord: boolean;
 }
 
 export interface SkillCast {
@@ -193,6 +369,8 @@ export interface DamageStats {
     damageDealt: number;
     damageTaken: number;
     hyperAwakeningDamage?: number;
+    damageToBoss?: number;
+    damageToAdds?: number;
     buffedBy: { [key: number]: number };
     debuffedBy: { [key: number]: number };
     buffedBySupport: number;
@@ -205,6 +383,9 @@ export interface DamageStats {
     shieldsGiven: number;
     shieldsReceived: number;
     damageAbsorbed: number;
+    damageTakenUnmitigated?: number;
+    hitsTaken?: number;
+    damageTakenBy?: { [key: string]: DamageTakenSource };
     damageAbsorbedOnOthers: number;
     shieldsGivenBy: { [key: number]: number };
     shieldsReceivedBy: { [key: number]: number };
@@ -212,15 +393,75 @@ export interface DamageStats {
     damageAbsorbedOnOthersBy: { [key: number]: number };
     deaths: number;
     deathTime: number;
+    deadFor: number;
+    dpsAlive: number;
+    activeTime: number;
+    uptimePercent: number;
     dps: number;
     dpsAverage: [number, number];
     dpsRolling10sAvg: [number, number];
     rdpsDamageReceived: number;
     rdpsDamageReceivedSupport: number;
     rdpsDamageGiven: number;
+    cleansedDebuffs?: { [key: number]: number };
+    awakening?: AwakeningStats;
+    // ten entries from 100-90% boss hp down to 10-0%
+    damageByHpSegment?: Array<number>;
     [key: string]: any;
 }
 
+export interface HpSegmentRow {
+    name: string;
+    classId: number;
+    damage: Array<number>;
+}
+
+export interface SharedSkill {
+    id: number;
+    name: string;
+    damage: number;
+    maxDamage: number;
+    casts: number;
+    hits: number;
+    crits: number;
+}
+
+export interface SharedPlayer {
+    name: string;
+    classId: number;
+    gearScore: number;
+    maxHp: number;
+    damage: number;
+    dps: number;
+    damageTaken: number;
+    deaths: number;
+    skills: Array<SharedSkill>;
+}
+
+export interface SharedEncounter {
+    meterVersion: string;
+    boss: string;
+    difficulty?: string;
+    cleared: boolean;
+    fightStart: number;
+    duration: number;
+    localPlayer: string;
+    totalDamage: number;
+    players: Array<SharedPlayer>;
+}
+
+export interface PastedEncounter {
+    encounter: SharedEncounter;
+    id?: number;
+}
+
+export interface AwakeningStats {
+    awakeningCasts: Array<number>;
+    hyperAwakeningCasts: Array<number>;
+    awakeningDamage: number;
+    hyperAwakeningDamage: number;
+}
+
 export interface SkillStats {
     casts: number;
     hits: number;
@@ -228,7 +469,12 @@ export interface SkillStats {
     backAttacks: number;
     frontAttacks: number;
     counters: number;
+    cleanses?: number;
+    selfCleanses?: number;
     identityStats?: string;
+    itemUses?: { [key: string]: number[] };
+    // local player only, skill id -> percent of possible casts, null under 3 casts
+    cooldownEfficiency?: { [skillId: number]: number | null };
 }
 
 export type IdentityLogTypeValue = number | [number, number] | [number, number, number];
@@ -364,6 +610,44 @@ export enum ShieldTab {
     E_RECEIVED
 }
 
+// crashed sessions have no endTime and aren't the current one
+export interface AppSession {
+    id: number;
+    startTime: number;
+    endTime?: number;
+    version: string;
+    captureMode: string;
+    interfaceIp: string;
+    encountersSaved: number;
+    current: boolean;
+    crashed: boolean;
+}
+
+// combatTime is in ms
+export interface ActivityWeek {
+    weekStart: string;
+    raids: Array<BossActivity>;
+    encounters: number;
+    clears: number;
+    wipes: number;
+    combatTime: number;
+    averageDps: number;
+}
+
+export interface BossActivity {
+    boss: string;
+    difficulty: string;
+    encounters: number;
+    clears: number;
+}
+
+export interface ClassMetadata {
+    id: number;
+    name: string;
+    color: string;
+    icon: string;
+}
+
 export interface ClassMap {
     [key: number]: string;
 }
@@ -460,16 +744,148 @@ export interface EncounterDbInfo {
     totalEncountersFiltered: number;
 }
 
+export interface SettingsState {
+    settings?: any;
+    loadError?: string;
+}
+
+export interface EncounterLoadError {
+    message: string;
+    // not_found, corrupt or database
+    category: string;
+}
+
+export interface BossNote {
+    boss: string;
+    note: string;
+    updatedAt: number;
+}
+
+export interface RaidStart {
+    timestamp: number;
+    bossName: string;
+    bossNote?: string;
+}
+
+export interface EncounterPb {
+    id: number;
+    bossName: string;
+    difficulty: string;
+    oldDps: number;
+    newDps: number;
+}
+
+export interface EncounterSaved {
+    id: number;
+    bossName: string;
+    cleared: boolean;
+    duration: number;
+    myDps: number;
+    manual: boolean;
+}
+
+export interface DamageTakenSource {
+    damage: number;
+    hits: number;
+}
+
+export interface SearchSuggestion {
+    value: string;
+    kind: "boss" | "player";
+    count: number;
+}
+
+export interface BossHpUpdate {
+    name: string;
+    currentHp: number;
+    maxHp: number;
+    currentShield: number;
+    isDead: boolean;
+}
+
+export interface ItemUsed {
+    player: string;
+    item: string;
+    kind: "item" | "sidereal";
+    count: number;
+    timestamp: number;
+}
+
+export interface RecentEncounter {
+    id: number;
+    bossName: string;
+    duration: number;
+    cleared: boolean;
+    myDps: number;
+    fightStart: number;
+}
+
+export interface FavoriteChanged {
+    id: number;
+    favorite: boolean;
+}
+
+export interface DbVersionTooNew {
+    dbVersion: number;
+    appVersion: number;
+}
+
+export interface RestoreResult {
+    restartRequired: boolean;
+    configAvailable: boolean;
+    backupVersion?: number;
+    appVersion?: string;
+}
+
+export interface ImportProgress {
+    total: number;
+    imported: number;
+    skipped: number;
+    failed: number;
+}
+
+export interface RebuildProgress {
+    total: number;
+    rebuilt: number;
+    failed: number;
+}
+
+export interface CleanupEstimate {
+    encounters: number;
+    scanned: number;
+    reclaimableBytes: number;
+}
+
+export interface StorageCategory {
+    category: string;
+    files: number;
+    bytes: number;
+    deletableBytes: number;
+}
+
+export interface StorageFallback {
+    preferred: string;
+    path: string;
+}
+
+export interface StorageFreed {
+    category: string;
+    files: number;
+    bytes: number;
+}
+
 export class SearchFilter {
     bosses: Set<string>;
     encounters: Set<string>;
     minDuration: number;
     favorite: boolean;
     cleared: boolean;
-    difficulty: string;
+    difficulties: Set<string>;
     bossOnlyDamage: boolean;
     sort: string;
     order: number;
+    region: string;
+    topRankOnly: boolean;
 
     constructor(minDuration = -1) {
         this.bosses = new Set();
@@ -477,16 +893,74 @@ export class SearchFilter {
         this.minDuration = minDuration;
         this.favorite = false;
         this.cleared = false;
-        this.difficulty = "";
+        this.difficulties = new Set();
         this.bossOnlyDamage = false;
         this.sort = "id";
         this.order = 2;
+        this.region = "";
+        this.topRankOnly = false;
     }
 }
 
+export interface NumberFormatExample {
+    value: number;
+    formatted: string;
+}
+
+export interface ParserMetrics {
+    packetsReceived: number;
+    decodeFailures: number;
+    unknownOpcodesTotal: number;
+    unknownOpcodes: { opcode: number; count: number }[];
+    entitiesCreated: number;
+    entitiesMerged: number;
+}
+
+export interface CaptureStatus {
+    capturing: boolean;
+    paused: boolean;
+    region?: string;
+    interface?: string;
+    lastPacket: number;
+    error?: ParserError;
+    clients: Array<CaptureClient>;
+}
+
+export interface IgnoredEntities {
+    names: Array<string>;
+    npcIds: Array<number>;
+}
+
+// a game client told apart by its local player, see parser/clients.rs
+export interface CaptureClient {
+    index: number;
+    name: string;
+    characterId: number;
+    lastSeen: number;
+    active: boolean;
+    tracked: boolean;
+    manual: boolean;
+}
+
+export interface ParserError {
+    message: string;
+    category: "driver" | "permission" | "interface" | "unknown";
+}
+
 export interface PartyBuffs {
     parties: Array<Array<Entity>>;
     partyGroupedSynergies: Map<string, Set<string>>;
     partyPercentages: Array<number[]>;
     partyBuffs: Map<string, Map<string, Array<BuffDetails>>>;
 }
+
+// deviceId is the npf device name, only known on windows
+export interface NetworkInterface {
+    name: string;
+    ips: Array<string>;
+    up: boolean;
+    isVirtual: boolean;
+    hasTraffic: boolean;
+    deviceId?: string;
+    description?: string;
+}
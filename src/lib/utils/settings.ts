@@ -2,9 +2,8 @@ import { classColors } from "$lib/constants/colors";
 import { invoke } from "@tauri-apps/api";
 import { emit } from "@tauri-apps/api/event";
 import { register, unregisterAll } from "@tauri-apps/api/globalShortcut";
-import { get, writable } from "svelte/store";
+import { writable } from "svelte/store";
 import { hideAll } from "tippy.js";
-import { clickthroughStore } from "$lib/utils/stores";
 
 export const defaultSettings = {
     general: {
@@ -13,6 +12,7 @@ export const defaultSettings = {
         showNames: true,
         showGearScore: false,
         hideNames: false,
+        maskNames: false,
         showEsther: true,
         hideLogo: false,
         showDate: true,
@@ -29,9 +29,11 @@ export const defaultSettings = {
         autoIface: true,
         ifDesc: "",
         ip: "",
+        deviceId: "",
         port: 6040,
         blur: true,
         blurWin11: false,
+        backdrop: "",
         isWin11: false,
         transparent: true,
         scale: "1",
@@ -41,10 +43,37 @@ export const defaultSettings = {
         keepFavorites: true,
         hideMeterOnStart: false,
         hideLogsOnStart: false,
+        closeToTray: false,
         constantLocalPlayerColor: false,
         bossOnlyDamageDefaultOn: true,
         startOnBoot: false,
-        logsPerPage: 10
+        logsPerPage: 10,
+        emitIntervalMs: 200,
+        liveDeltaUpdates: false,
+        liveDamageTaken: false,
+        liveSessionStats: false,
+        autoClickthroughInCombat: false,
+        autoClickthroughDelayMs: 3000,
+        captureDump: false,
+        pipeServer: false,
+        fadeOutOfCombat: false,
+        fadeDelayS: 30,
+        fadeOpacity: 0.3,
+        bossHpWindow: false,
+        bossHpClickthrough: true,
+        bossHpHideDelayS: 30,
+        autoFavoritePbs: false,
+        ipcPayloadLimitMb: 30,
+        updateMode: "auto",
+        dungeonMode: "",
+        clockGapMode: "",
+        mainCharacter: "",
+        ignoredNames: [],
+        ignoredNpcIds: [],
+        trimPrepull: false,
+        trimPrepullMaxPercent: 10,
+        autoCleanupStorage: false,
+        autoCleanupDays: 30
     },
     shortcuts: {
         hideMeter: {
@@ -74,6 +103,14 @@ export const defaultSettings = {
         disableClickthrough: {
             modifier: "",
             key: ""
+        },
+        toggleClickthrough: {
+            modifier: "Ctrl+Alt",
+            key: "c"
+        },
+        peek: {
+            modifier: "",
+            key: ""
         }
     },
     meter: {
@@ -185,6 +222,16 @@ export const defaultSettings = {
         auto: false,
         username: "",
         visibility: "0"
+    },
+    ui: {
+        // class id -> color, read by the backend
+        classColors: {},
+        // used for text the backend writes out itself
+        numberFormat: {
+            abbreviate: true,
+            separator: ",",
+            decimals: 1
+        }
     }
 };
 
@@ -192,7 +239,9 @@ export const update = {
     available: false,
     manifest: undefined,
     dismissed: false,
-    isNotice: false
+    isNotice: false,
+    installedVersion: undefined,
+    downloadFailedUrl: undefined
 };
 
 const settingsStore = (key: string, defaultSettings: object) => {
@@ -212,7 +261,8 @@ const settingsStore = (key: string, defaultSettings: object) => {
         set: (value: object) => {
             localStorage.setItem(key, JSON.stringify(value));
             if (key === "settings") {
-                invoke("save_settings", { settings: value });
+                // fails when the data dir is read-only, the backend logs why
+                invoke("save_settings", { settings: value }).catch(() => {});
             }
             store.set(value);
         },
@@ -226,9 +276,20 @@ export const updateSettings = settingsStore("updateSettings", update);
 
 export const miscSettings = settingsStore("miscSettings", {});
 
+// settings saved before the clickthrough toggle existed only have the disable shortcut
+export function migrateSettings(stored: any) {
+    const shortcuts = stored?.shortcuts;
+    if (shortcuts && !shortcuts.toggleClickthrough && shortcuts.disableClickthrough?.key) {
+        shortcuts.toggleClickthrough = { ...shortcuts.disableClickthrough };
+    }
+    return stored;
+}
+
 export async function registerShortcuts(shortcuts: any) {
     try {
         await unregisterAll();
+        // the clickthrough toggle is registered by the backend and was just dropped
+        await invoke("restore_clickthrough_shortcut", { shortcut: shortcuts.toggleClickthrough });
 
         if (shortcuts.hideMeter.modifier && shortcuts.hideMeter.key) {
             await register(shortcuts.hideMeter.modifier + "+" + shortcuts.hideMeter.key, async () => {
@@ -265,25 +326,8 @@ export async function registerShortcuts(shortcuts: any) {
             });
         }
 
-        if (shortcuts.disableClickthrough.modifier && shortcuts.disableClickthrough.key) {
-            await register(
-                shortcuts.disableClickthrough.modifier + "+" + shortcuts.disableClickthrough.key,
-                async () => {
-                    // if meter is clickthrough, disable it
-                    if (get(clickthroughStore)) {
-                        await invoke("set_clickthrough", { set: false });
-                        await invoke("write_log", { message: "disabling clickthrough" });
-                        clickthroughStore.update(() => false);
-                    } else {
-                        await invoke("set_clickthrough", { set: true });
-                        await invoke("write_log", { message: "enabling clickthrough" });
-                        clickthroughStore.update(() => true);
-                    }
-                }
-            );
-        }
     } catch (error) {
-        await invoke("write_log", { message: "[live_meter::register_shortcuts]" + error });
+        await invoke("write_log", { level: "error", message: "[live_meter::register_shortcuts]" + error });
     }
 }
 
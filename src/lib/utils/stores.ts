@@ -15,6 +15,8 @@ export const backNavStore = writable(false);
 export const ifaceChangedStore = writable(false);
 export const uploadErrorStore = writable(false);
 export const uploadErrorMessage = writable("");
+export const settingsLoadError = writable("");
+export const dbVersionTooNew = writable(false);
 
 export const searchFilter = writable(new SearchFilter());
 
@@ -62,6 +62,15 @@ export function getEstherFromNpcId(npcId: number): string {
     return "Unknown";
 }
 
+// encounter ids arrive as strings from routes and events, the backend only takes integers
+export function parseEncounterId(id: string | number): number {
+    const parsed = Number(id);
+    if (!Number.isSafeInteger(parsed) || parsed <= 0) {
+        throw new Error("invalid encounter id: " + id);
+    }
+    return parsed;
+}
+
 // this is used to invalidate caches when loading images
 // change this value when images are updated
 export const queryParam: string = "?194";
@@ -2,6 +2,7 @@ import { invoke } from "@tauri-apps/api";
 import type { Encounter } from "$lib/types";
 import pako from "pako";
 
+export const ACCESS_TOKEN_SECRET = "sync_access_token";
 export const LOG_SITE_URL = "https://logs.snow.xyz";
 export const API_URL = "https://api.snow.xyz";
 // export const API_URL = "http://localhost:5180";
@@ -58,7 +59,7 @@ export async function uploadLog(id: string | number, encounter: Encounter, setti
     const resp = await fetch(API_URL + "/logs/upload", {
         method: "POST",
         headers: {
-            access_token: settings.accessToken,
+            access_token: await getAccessToken(),
             "Content-Encoding": "gzip",
             "Content-Type": "application/json",
             visibility: settings.visibility ?? ""
@@ -75,6 +76,7 @@ export async function uploadLog(id: string | number, encounter: Encounter, setti
         }
 
         await invoke("write_log", {
+            level: "warn",
             message: "couldn't upload encounter " + id + " (" + encounter.currentBossName + ") - error: " + error
         });
         return { id: 0, error: error };
@@ -84,6 +86,7 @@ export async function uploadLog(id: string | number, encounter: Encounter, setti
         if (body.error === "duplicate log" && body.duplicate) {
             const duplicate = body.duplicate;
             await invoke("write_log", {
+                level: "info",
                 message:
                     "did not upload duplicate encounter " +
                     id +
@@ -97,6 +100,7 @@ export async function uploadLog(id: string | number, encounter: Encounter, setti
         }
 
         await invoke("write_log", {
+            level: "warn",
             message:
                 "couldn't upload encounter " +
                 id +
@@ -110,6 +114,7 @@ export async function uploadLog(id: string | number, encounter: Encounter, setti
     }
     if (resp.status === 400) {
         await invoke("write_log", {
+            level: "warn",
             message: "couldn't upload encounter " + id + " (" + encounter.currentBossName + ") - error: unknown error"
         });
         return { id: 0, error: body.error };
@@ -121,12 +126,48 @@ export async function uploadLog(id: string | number, encounter: Encounter, setti
     }
 
     await invoke("write_log", {
+        level: "info",
         message: "uploaded encounter " + id + " (" + encounter.currentBossName + ") upstream: " + upstream
     });
     await invoke("sync", { encounter: Number(id), upstream: upstream.toString(), failed: false });
     return { id: upstream, error: "" };
 }
 
+// the token lives in the os credential store, not in settings
+export async function getAccessToken(): Promise<string> {
+    try {
+        return ((await invoke("get_secret", { name: ACCESS_TOKEN_SECRET })) as string | null) ?? "";
+    } catch (e) {
+        await invoke("write_log", { level: "error", message: String(e) });
+        return "";
+    }
+}
+
+export async function setAccessToken(accessToken: string) {
+    if (accessToken) {
+        await invoke("set_secret", { name: ACCESS_TOKEN_SECRET, value: accessToken });
+    } else {
+        await invoke("delete_secret", { name: ACCESS_TOKEN_SECRET });
+    }
+}
+
+// moves a token saved in settings before the credential store was used,
+// it's only cleared from the stored settings once it's been moved
+export async function migrateAccessToken(stored: any) {
+    const accessToken = stored?.sync?.accessToken;
+    if (!accessToken) {
+        return;
+    }
+    try {
+        if (!(await getAccessToken())) {
+            await setAccessToken(accessToken);
+        }
+        stored.sync.accessToken = "";
+    } catch (e) {
+        await invoke("write_log", { level: "error", message: String(e) });
+    }
+}
+
 export async function checkAccessToken(accessToken: string) {
     if (!accessToken) {
         return false;
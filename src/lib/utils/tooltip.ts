@@ -136,6 +136,24 @@ export function generateClassTooltip(player: Entity) {
     return str;
 }
 
+export function generateDamageTakenTooltip(player: Entity) {
+    let str = `<div class="py-0.5">${player.damageStats.damageTaken.toLocaleString()}`;
+    if (player.damageStats.hitsTaken) {
+        str += ` <span class="text-gray-300">(${player.damageStats.hitsTaken} hits)</span>`;
+    }
+    str += "</div>";
+
+    const sources = Object.entries(player.damageStats.damageTakenBy ?? {})
+        .sort(([, a], [, b]) => b.damage - a.damage)
+        .slice(0, 10);
+    for (const [source, taken] of sources) {
+        const damage = abbreviateNumberSplit(taken.damage);
+        str += `<div class="flex justify-between space-x-2 text-xs"><div>${source}</div>`;
+        str += `<div>${damage[0]}${damage[1]} <span class="text-gray-300">x${taken.hits}</span></div></div>`;
+    }
+    return str;
+}
+
 export function generateSkillTooltip(skill: Skill) {
     let str = `<div class="py-0.5">${skill.name}</div>`;
     str += "<div class='text-gray-300'>";
@@ -1,11 +1,17 @@
 import { invoke } from "@tauri-apps/api/tauri";
-import type { Encounter } from "$lib/types";
+import type { Encounter, EncounterLoadError } from "$lib/types";
 import type { PageLoad } from "./$types";
+import { parseEncounterId } from "$lib/utils/strings";
 
 export const prerender: boolean = false;
 
 export const load: PageLoad = async ({ params }) => {
-    // If there's no such id this return `Encounter` with all fields zeroed
-    const encounter = (await invoke("load_encounter", { id: params.id })) as Encounter;
-    return { id: params.id, encounter };
+    const id = parseEncounterId(params.id);
+    try {
+        const encounter = (await invoke("load_encounter", { id })) as Encounter;
+        return { id: params.id, encounter, loadError: undefined };
+    } catch (e) {
+        // deleted from another window or unreadable
+        return { id: params.id, encounter: undefined, loadError: e as EncounterLoadError };
+    }
 };